@@ -0,0 +1,169 @@
+// إعادة كتابة آمنة ومتكررة (idempotent) لملفات إقلاع طراز Raspberry Pi
+// (config.txt / cmdline.txt): كل ما تكتبه هذه الوحدة محصور بين علامتي
+// `# DOS-SAFAR-SETTINGS-START` و`# DOS-SAFAR-SETTINGS-END` (أو، في حالة
+// cmdline.txt ذات السطر الواحد الذي لا يقبل تعليقات، بين رمزي تحديد داخل
+// نفس السطر)، حتى لا تُكرَّر الأسطر عند كل إعادة تشغيل ولا تُفقَد أسطر
+// المستخدم خارج المنطقة المُدارة. يُحفظ الملف الأصلي في `<path>.bak` قبل
+// أول تعديل.
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+use crate::hardware::device_detect::GamingFeatures;
+
+const REGION_START: &str = "# DOS-SAFAR-SETTINGS-START";
+const REGION_END: &str = "# DOS-SAFAR-SETTINGS-END";
+
+const CMDLINE_MARK_START: &str = "dos_safar_managed_start";
+const CMDLINE_MARK_END: &str = "dos_safar_managed_end";
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// ينسخ `original` إلى `<path>.bak` إن لم توجد نسخة احتياطية بعد، حتى لا
+/// تُستبدل النسخة الأصلية التي التُقطت قبل أول تعديل بنسخ لاحقة معدَّلة.
+fn backup_if_absent(path: &Path, original: &str) -> Result<()> {
+    let backup = backup_path(path);
+    if !backup.exists() {
+        std::fs::write(&backup, original)
+            .with_context(|| format!("تعذّر إنشاء نسخة احتياطية: {}", backup.display()))?;
+    }
+    Ok(())
+}
+
+/// يستبدل (أو يُلحق) المنطقة بين علامتي `# DOS-SAFAR-SETTINGS-START/END`
+/// في `path` بـ `managed_lines`، تاركاً بقية الملف دون أي تغيير؛ إعادة
+/// تشغيلها بنفس المدخلات تنتج نفس الملف (idempotent).
+pub fn rewrite_managed_region(path: &Path, managed_lines: &[String]) -> Result<()> {
+    let original = std::fs::read_to_string(path)
+        .with_context(|| format!("تعذّر قراءة ملف الإعداد: {}", path.display()))?;
+    backup_if_absent(path, &original)?;
+
+    let pattern = format!(
+        "(?s){}\\n.*?{}\\n?",
+        regex::escape(REGION_START),
+        regex::escape(REGION_END)
+    );
+    let region_re = Regex::new(&pattern).context("فشل بناء نمط المنطقة المُدارة")?;
+
+    let mut managed_block = format!("{}\n", REGION_START);
+    for line in managed_lines {
+        managed_block.push_str(line);
+        managed_block.push('\n');
+    }
+    managed_block.push_str(REGION_END);
+    managed_block.push('\n');
+
+    let updated = if region_re.is_match(&original) {
+        region_re.replace(&original, managed_block.as_str()).into_owned()
+    } else {
+        let mut combined = original;
+        if !combined.is_empty() && !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+        combined.push_str(&managed_block);
+        combined
+    };
+
+    std::fs::write(path, updated)
+        .with_context(|| format!("تعذّر كتابة ملف الإعداد: {}", path.display()))?;
+    Ok(())
+}
+
+/// نفس فكرة `rewrite_managed_region` لكن لملف `cmdline.txt` الذي هو سطر
+/// واحد بلا تعليقات (البرنامج الثابت يمرّره حرفياً إلى النواة)، فلا يمكن
+/// استخدام أسطر `#`؛ بدلاً من ذلك تُحاط المعاملات المُدارة برمزين فريدين
+/// ضمن نفس السطر.
+pub fn rewrite_cmdline_managed_params(path: &Path, managed_params: &[String]) -> Result<()> {
+    let original = std::fs::read_to_string(path)
+        .with_context(|| format!("تعذّر قراءة ملف cmdline: {}", path.display()))?;
+    backup_if_absent(path, &original)?;
+
+    let pattern = format!(
+        r"\s*{}.*?{}",
+        regex::escape(CMDLINE_MARK_START),
+        regex::escape(CMDLINE_MARK_END)
+    );
+    let managed_re = Regex::new(&pattern).context("فشل بناء نمط cmdline المُدار")?;
+
+    let stripped = managed_re
+        .replace(original.trim_end(), "")
+        .into_owned();
+
+    let managed_segment = if managed_params.is_empty() {
+        String::new()
+    } else {
+        format!(
+            " {} {} {}",
+            CMDLINE_MARK_START,
+            managed_params.join(" "),
+            CMDLINE_MARK_END
+        )
+    };
+
+    let updated = format!("{}{}\n", stripped.trim_end(), managed_segment);
+    std::fs::write(path, updated)
+        .with_context(|| format!("تعذّر كتابة ملف cmdline: {}", path.display()))?;
+    Ok(())
+}
+
+/// يضبط `config.txt` لأنظمة الألعاب (RetroPie/Batocera/Recalbox): يفرض
+/// اكتشاف HDMI، يعطّل overscan، ويحقن دقة الشاشة المدمجة المُكتشَفة من
+/// `device_info.gaming_features` إن وُجدت.
+fn apply_gaming_config_txt(path: &Path, gaming: &GamingFeatures) -> Result<()> {
+    let mut lines = vec![
+        "hdmi_force_hotplug=1".to_string(),
+        "disable_overscan=1".to_string(),
+    ];
+
+    if let Some((width, height)) = gaming.native_resolution {
+        lines.push(format!("hdmi_cvt={} {} 60 3 0 0 0", width, height));
+        lines.push("hdmi_group=2".to_string());
+        lines.push("hdmi_mode=87".to_string());
+    }
+    if let Some(size) = gaming.screen_size_inches {
+        lines.push(format!("# screen_size_inches={}", size));
+    }
+
+    rewrite_managed_region(path, &lines)
+}
+
+/// يضبط `config.txt` للتوزيعات القياسية (Raspberry Pi OS/Ubuntu/Debian):
+/// ملف تهيئة شاشة أبسط يثق بعرض الشاشة الفعلي بدل افتراض شاشة ألعاب صغيرة.
+fn apply_standard_config_txt(path: &Path) -> Result<()> {
+    let lines = vec![
+        "hdmi_force_hotplug=1".to_string(),
+        "disable_overscan=0".to_string(),
+    ];
+    rewrite_managed_region(path, &lines)
+}
+
+/// يطبّق ملف تهيئة نظام الألعاب كاملاً: `config.txt` + معاملات `cmdline.txt`
+/// لإقلاع هادئ دون زخم رسائل وحدة التحكم.
+pub fn apply_boot_config_for_gaming(boot_dir: &Path, gaming: &GamingFeatures) -> Result<()> {
+    apply_gaming_config_txt(&boot_dir.join("config.txt"), gaming)?;
+    rewrite_cmdline_managed_params(
+        &boot_dir.join("cmdline.txt"),
+        &[
+            "console=tty1".to_string(),
+            "quiet".to_string(),
+            "loglevel=1".to_string(),
+        ],
+    )
+}
+
+/// يطبّق ملف تهيئة التوزيعة القياسية كاملاً: `config.txt` + معاملات
+/// `cmdline.txt` التي تُبقي وحدة التحكم التسلسلية ظاهرة لتسهيل التشخيص.
+pub fn apply_boot_config_for_standard(boot_dir: &Path) -> Result<()> {
+    apply_standard_config_txt(&boot_dir.join("config.txt"))?;
+    rewrite_cmdline_managed_params(
+        &boot_dir.join("cmdline.txt"),
+        &[
+            "console=serial0,115200".to_string(),
+            "console=tty1".to_string(),
+        ],
+    )
+}
@@ -0,0 +1,112 @@
+// مخزن حالة الإقلاع الدائم: يسجّل آخر استخدام، عدّاد الإقلاعات، ونتيجة آخر
+// محاولة لكل نظام يكتشفه BootMenu، حتى لا يعود `last_used` دائماً `None`
+// ولا يبقى `save_boot_selection` بلا أثر بين عمليات إعادة التشغيل (انظر
+// `menu::BootMenu::scan_for_operating_systems`/`save_boot_selection`).
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::bootloader::menu::OperatingSystem;
+use crate::utils::config::Config;
+
+/// نتيجة آخر محاولة إقلاع معروفة لنظام ما.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BootOutcome {
+    Success,
+    Failed,
+}
+
+/// بيانات دائمة لنظام واحد، مفتاحها `identity_key` (المسار + نوع النظام)
+/// كي لا تضيع مع إعادة ترتيب/إعادة فحص الأنظمة المتاحة بين التشغيلات.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BootStateEntry {
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+    pub boot_count: u64,
+    pub last_boot_outcome: Option<BootOutcome>,
+}
+
+/// مخزن JSON واحد لكل مدخلات `BootStateEntry`، محفوظ في
+/// `config.system.config_persist_path/boot_state.json` ومكتوب بأسلوب ملف
+/// مؤقت + إعادة تسمية حتى لا يُفسَد بانقطاع منتصف الكتابة - لحظة حرجة بما
+/// أن `save_boot_selection` يكتب هذا الملف مباشرة قبل تسليم kexec للتحكم.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BootStateStore {
+    entries: HashMap<String, BootStateEntry>,
+}
+
+/// مسار ملف الحالة، بجوار ملف التكوين المستمر نفسه (`config_persist_path`).
+pub fn state_file_path(config: &Config) -> PathBuf {
+    Path::new(&config.system.config_persist_path).join("boot_state.json")
+}
+
+/// هوية مستقرة للنظام عبر عمليات الفحص المتكررة: المسار وحده قابل لإعادة
+/// الاستخدام بين أنواع مختلفة (مثال: محرك USB أُعيد تهيئته بنظام آخر)، لذا
+/// يُضاف نوع النظام لتفادي دمج حالة نظام مختلف عن طريق الخطأ.
+fn identity_key(os: &OperatingSystem) -> String {
+    format!("{:?}:{}", os.os_type, os.path)
+}
+
+impl BootStateStore {
+    /// يقرأ المخزن من القرص، أو يعيد مخزناً فارغاً إن لم يوجد الملف أو تعذّر
+    /// تحليله - لا سبب لرفض بدء القائمة بسبب حالة تاريخية تالفة.
+    pub fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        serde_json::from_str(&content).unwrap_or_default()
+    }
+
+    /// يكتب المخزن عبر ملف مؤقت في نفس المجلد ثم `rename` ذرّي، كي لا يُرى
+    /// أبداً ملف جزئي إن انقطعت العملية أثناء الحفظ.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("فشل في إنشاء مجلد حالة الإقلاع: {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_string_pretty(self).context("فشل في ترميز حالة الإقلاع")?;
+
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content).with_context(|| {
+            format!("فشل في كتابة الملف المؤقت لحالة الإقلاع: {}", tmp_path.display())
+        })?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("فشل في إحلال حالة الإقلاع المحدَّثة في {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// يُسجَّل عند كل محاولة إقلاع: يزيد العدّاد، يحدّث آخر استخدام إلى
+    /// الآن، ويفترض نجاح الإقلاع تفاؤلياً - إذ لا عودة من `kexec` الناجح
+    /// لتأكيد ذلك مباشرة؛ `record_failure` يصحّح هذا إن أخفق `kexec` قبل أن
+    /// يسلّم التحكم فعلياً.
+    pub fn record_attempt(&mut self, os: &OperatingSystem) {
+        let entry = self.entries.entry(identity_key(os)).or_default();
+        entry.last_used = Some(chrono::Utc::now());
+        entry.boot_count += 1;
+        entry.last_boot_outcome = Some(BootOutcome::Success);
+    }
+
+    /// يصحّح نتيجة آخر محاولة إلى فشل لنظام أخفق `kexec_boot` فعلياً قبل
+    /// القفز - لا يمسّ `last_used`/`boot_count` المسجَّلين بالفعل عبر
+    /// `record_attempt`.
+    pub fn record_failure(&mut self, os: &OperatingSystem) {
+        if let Some(entry) = self.entries.get_mut(&identity_key(os)) {
+            entry.last_boot_outcome = Some(BootOutcome::Failed);
+        }
+    }
+
+    /// يدمج البيانات المحفوظة فوق نتائج فحص جديدة، بحيث يعود `last_used`
+    /// للعمل في ترتيب `scan_for_operating_systems` رغم أن كل فحص يبني
+    /// `OperatingSystem` جديدة لا تعرف شيئاً عن تاريخها بذاتها.
+    pub fn merge_into(&self, systems: &mut [OperatingSystem]) {
+        for os in systems.iter_mut() {
+            if let Some(entry) = self.entries.get(&identity_key(os)) {
+                os.last_used = entry.last_used;
+                os.boot_count = entry.boot_count;
+                os.last_boot_outcome = entry.last_boot_outcome;
+            }
+        }
+    }
+}
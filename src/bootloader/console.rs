@@ -0,0 +1,114 @@
+// طبقة إخراج مزدوجة لقائمة الإقلاع: كل سطر يُكتب على stdout (الشاشة
+// المحلية/HDMI) وأيضاً على جهاز تسلسلي اختياري (UART)، حتى تعمل القائمة
+// نفسها أثناء التطوير على كبل تسلسلي وعلى شاشة الجهاز المدمجة. فشل الكتابة
+// التسلسلية يُسجَّل ويُبتلَع كي لا يوقف القائمة المحلية.
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::mem::MaybeUninit;
+use std::os::unix::io::{AsRawFd, RawFd};
+use tracing::warn;
+
+use crate::utils::config::SerialConsoleConfig;
+
+pub struct Console {
+    serial: Option<File>,
+}
+
+impl Console {
+    pub fn new(config: &SerialConsoleConfig) -> Self {
+        if !config.enabled {
+            return Console { serial: None };
+        }
+
+        match open_serial_port(&config.device_path, config.baud_rate) {
+            Ok(file) => Console { serial: Some(file) },
+            Err(e) => {
+                warn!(
+                    "تعذّر فتح وحدة التحكم التسلسلية {}: {} - المتابعة عبر الشاشة المحلية فقط",
+                    config.device_path, e
+                );
+                Console { serial: None }
+            }
+        }
+    }
+
+    /// يكتب `text` كما هو على كل سنخ مهيّأ، دون سطر جديد تلقائي.
+    pub fn write(&mut self, text: &str) {
+        print!("{}", text);
+        let _ = std::io::stdout().flush();
+
+        if let Some(serial) = &mut self.serial {
+            if let Err(e) = serial.write_all(text.as_bytes()) {
+                warn!("فشلت الكتابة على وحدة التحكم التسلسلية: {}", e);
+            }
+        }
+    }
+
+    pub fn writeln(&mut self, text: &str) {
+        self.write(text);
+        self.write("\n");
+    }
+
+    /// واصف ملف الجهاز التسلسلي (إن وُجد)، ليُضاف إلى قارئ المفاتيح حتى
+    /// تُقبل ضغطات واردة عبر الخط التسلسلي أيضاً.
+    pub fn serial_fd(&self) -> Option<RawFd> {
+        self.serial.as_ref().map(|file| file.as_raw_fd())
+    }
+}
+
+fn open_serial_port(path: &str, baud_rate: u32) -> Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)?;
+    configure_serial_termios(&file, baud_rate)?;
+    Ok(file)
+}
+
+/// يضبط المنفذ على 8N1 خام (بدون ICANON/ECHO/تحكم تدفق)، مناسباً لوحدة
+/// تحكم نصية بسيطة بدل طرفية تفاعلية كاملة.
+fn configure_serial_termios(file: &File, baud_rate: u32) -> Result<()> {
+    let fd = file.as_raw_fd();
+    let mut termios = MaybeUninit::<libc::termios>::uninit();
+    if unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } != 0 {
+        return Err(anyhow::anyhow!(
+            "فشل tcgetattr على المنفذ التسلسلي: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    let mut termios = unsafe { termios.assume_init() };
+
+    let speed = baud_to_speed(baud_rate);
+    unsafe {
+        libc::cfsetispeed(&mut termios, speed);
+        libc::cfsetospeed(&mut termios, speed);
+    }
+
+    termios.c_cflag &= !(libc::PARENB | libc::CSTOPB | libc::CSIZE);
+    termios.c_cflag |= libc::CLOCAL | libc::CREAD | libc::CS8;
+    termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ECHOE | libc::ISIG);
+    termios.c_iflag &= !(libc::IXON | libc::IXOFF | libc::IXANY);
+    termios.c_oflag &= !libc::OPOST;
+    termios.c_cc[libc::VMIN] = 1;
+    termios.c_cc[libc::VTIME] = 0;
+
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) } != 0 {
+        return Err(anyhow::anyhow!(
+            "فشل tcsetattr على المنفذ التسلسلي: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(())
+}
+
+fn baud_to_speed(baud_rate: u32) -> libc::speed_t {
+    match baud_rate {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        _ => libc::B115200,
+    }
+}
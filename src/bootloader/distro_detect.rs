@@ -0,0 +1,276 @@
+// Linux distribution identification for a mounted OS root: `os-release` ->
+// `lsb-release` -> distro-specific release files -> ELF bitness probe.
+// Replaces the old file-marker guesswork in `os_manager::detect_os_type`,
+// which stays responsible for the RetroPie/Batocera/Recalbox overlay on top.
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+
+/// Enriched Linux distribution identity, carrying enough detail for
+/// `OperatingSystem.description` to say more than "Ubuntu Linux system".
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinuxDistroInfo {
+    pub id: String,
+    pub id_like: Vec<String>,
+    pub version_id: Option<String>,
+    pub version_codename: Option<String>,
+    pub pretty_name: Option<String>,
+    /// 32 or 64, read from the ELF class byte of `bin/sh` or `lib/ld-linux*`.
+    pub bitness: Option<u8>,
+}
+
+/// Identifies the Linux distribution rooted at `os_root`, trying each
+/// source in turn and falling back to the next when a file is missing.
+pub fn detect_linux_distro(os_root: &Path) -> Option<LinuxDistroInfo> {
+    let mut info = parse_os_release(os_root)
+        .or_else(|| parse_lsb_release(os_root))
+        .or_else(|| parse_distro_release_files(os_root))?;
+
+    info.bitness = detect_bitness(os_root);
+    Some(info)
+}
+
+fn parse_os_release(os_root: &Path) -> Option<LinuxDistroInfo> {
+    let content = fs::read_to_string(os_root.join("etc/os-release")).ok()?;
+    Some(parse_os_release_content(&content))
+}
+
+/// الجزء الصِّرف من `parse_os_release` بلا لمس للقرص، كي تستطيع
+/// `os_manager::read_os_release_from_fat_image` إعادة استخدامه على محتوى
+/// مقروء من داخل صورة FAT بدل مسار مُمَنتَق فعلياً.
+pub fn parse_os_release_content(content: &str) -> LinuxDistroInfo {
+    let fields = parse_key_value_lines(content);
+
+    LinuxDistroInfo {
+        id: fields.get("ID").cloned().unwrap_or_else(|| "linux".to_string()),
+        id_like: fields
+            .get("ID_LIKE")
+            .map(|v| v.split_whitespace().map(|s| s.to_string()).collect())
+            .unwrap_or_default(),
+        version_id: fields.get("VERSION_ID").cloned(),
+        version_codename: fields.get("VERSION_CODENAME").cloned(),
+        pretty_name: fields.get("PRETTY_NAME").cloned(),
+        bitness: None,
+    }
+}
+
+fn parse_lsb_release(os_root: &Path) -> Option<LinuxDistroInfo> {
+    let content = fs::read_to_string(os_root.join("etc/lsb-release")).ok()?;
+    let fields = parse_key_value_lines(&content);
+
+    Some(LinuxDistroInfo {
+        id: fields.get("DISTRIB_ID")?.to_lowercase(),
+        id_like: Vec::new(),
+        version_id: fields.get("DISTRIB_RELEASE").cloned(),
+        version_codename: fields.get("DISTRIB_CODENAME").cloned(),
+        pretty_name: fields.get("DISTRIB_DESCRIPTION").cloned(),
+        bitness: None,
+    })
+}
+
+/// `key=value` parser shared by `os-release` and `lsb-release`: surrounding
+/// single/double quotes are stripped, and `\` escapes the next character
+/// literally (so `PRETTY_NAME="Ubuntu \"Focal\""` keeps its inner quotes).
+fn parse_key_value_lines(content: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, raw_value)) = line.split_once('=') else {
+            continue;
+        };
+        fields.insert(key.trim().to_string(), unquote(raw_value.trim()));
+    }
+
+    fields
+}
+
+fn unquote(raw: &str) -> String {
+    let chars: Vec<char> = raw.chars().collect();
+    let is_quoted = chars.len() >= 2
+        && ((chars[0] == '"' && chars[chars.len() - 1] == '"')
+            || (chars[0] == '\'' && chars[chars.len() - 1] == '\''));
+    let body = if is_quoted {
+        &chars[1..chars.len() - 1]
+    } else {
+        &chars[..]
+    };
+
+    let mut result = String::with_capacity(body.len());
+    let mut escaped = false;
+    for &c in body {
+        if escaped {
+            result.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else {
+            result.push(c);
+        }
+    }
+
+    result
+}
+
+/// Distro-specific release files for systems with neither `os-release` nor
+/// `lsb-release`; each has its own free-text format, hence the regex.
+fn parse_distro_release_files(os_root: &Path) -> Option<LinuxDistroInfo> {
+    const CANDIDATES: &[(&str, &str, &str)] = &[
+        ("etc/fedora-release", "fedora", r"release\s+([\d.]+)"),
+        ("etc/centos-release", "centos", r"release\s+([\d.]+)"),
+        ("etc/debian_version", "debian", r"([\d.]+)"),
+        ("etc/alpine-release", "alpine", r"([\d.]+)"),
+    ];
+
+    for (relative_path, id, version_pattern) in CANDIDATES {
+        let Ok(content) = fs::read_to_string(os_root.join(relative_path)) else {
+            continue;
+        };
+
+        let version_id = Regex::new(version_pattern)
+            .ok()
+            .and_then(|re| re.captures(content.trim()))
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str().to_string());
+
+        return Some(LinuxDistroInfo {
+            id: id.to_string(),
+            id_like: Vec::new(),
+            version_id,
+            version_codename: None,
+            pretty_name: Some(content.trim().to_string()),
+            bitness: None,
+        });
+    }
+
+    None
+}
+
+/// Filesystem identity read from `blkid` for the block device backing a
+/// mounted path. `detect_linux_distro` needs the rootfs already mounted to
+/// find `/etc/os-release`, but a boot cmdline needs `root=UUID=...` to find
+/// that same partition again at kexec time without depending on a device
+/// node name (`/dev/sda1`, `/dev/mmcblk0p2`, ...) that can shift between
+/// boots or device classes (SD card vs USB).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PartitionInfo {
+    pub uuid: Option<String>,
+    pub fstype: Option<String>,
+}
+
+/// يحل جهاز الكتلة الداعم لـ `mount_path` عبر `/proc/mounts` ثم يستجوبه
+/// بـ`blkid` - فحص قراءة فقط، لا تحميل إضافي ولا كتابة على القرص. يعيد
+/// `None` بهدوء إن لم تكن `mount_path` نقطة تحميل فعلية أو فشل `blkid`
+/// (مثال: بيئة اختبار بلا الأداة مثبَّتة).
+pub fn probe_mounted_partition(mount_path: &Path) -> Option<PartitionInfo> {
+    let device = resolve_mount_source(mount_path)?;
+    probe_blkid(&device)
+}
+
+fn resolve_mount_source(mount_path: &Path) -> Option<String> {
+    let canonical = fs::canonicalize(mount_path).ok()?;
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+
+    mounts.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let device = fields.next()?;
+        let target = fields.next()?;
+        (Path::new(target) == canonical).then(|| device.to_string())
+    })
+}
+
+fn probe_blkid(device: &str) -> Option<PartitionInfo> {
+    let output = Command::new("blkid").args(["-o", "export", device]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let fields = parse_key_value_lines(&String::from_utf8_lossy(&output.stdout));
+    Some(PartitionInfo {
+        uuid: fields.get("UUID").cloned(),
+        fstype: fields.get("TYPE").cloned(),
+    })
+}
+
+/// Classifies `os_path` into an [`OSType`](crate::bootloader::menu::OSType):
+/// the gaming distributions (RetroPie/Batocera/Recalbox) stay a file-marker
+/// check first since they're a layer on top of a base distro rather than an
+/// `os-release` identity of their own, then `distro` (if already detected)
+/// decides via `ID`/`ID_LIKE`, falling back to the `config.txt`/`cmdline.txt`
+/// pair for Raspberry Pi OS images that carry neither `os-release` nor
+/// `lsb-release`. Shared by `os_manager::detect_os_type` (installed-OS
+/// analysis) and `menu::identify_os_from_boot_partition` (raw scan path) so
+/// the two don't drift into classifying the same distro differently.
+pub fn classify_os_type(
+    os_path: &Path,
+    distro: Option<&LinuxDistroInfo>,
+) -> crate::bootloader::menu::OSType {
+    use crate::bootloader::menu::OSType;
+
+    if os_path.join("retropie").exists() || os_path.join("RetroPie").exists() {
+        return OSType::RetroPie;
+    }
+
+    if os_path.join("batocera").exists() || os_path.join("BATOCERA").exists() {
+        return OSType::Batocera;
+    }
+
+    if os_path.join("recalbox").exists() {
+        return OSType::Recalbox;
+    }
+
+    if let Some(distro) = distro {
+        return match distro.id.as_str() {
+            "raspbian" => OSType::RaspberryPiOS,
+            "ubuntu" => OSType::Ubuntu,
+            "debian" => OSType::Debian,
+            _ if distro.id_like.iter().any(|id| id == "ubuntu") => OSType::Ubuntu,
+            _ if distro.id_like.iter().any(|id| id == "debian") => OSType::Debian,
+            _ => OSType::Unknown,
+        };
+    }
+
+    if os_path.join("config.txt").exists() && os_path.join("cmdline.txt").exists() {
+        return OSType::RaspberryPiOS;
+    }
+
+    OSType::Unknown
+}
+
+/// Reads the ELF class byte (offset 4 of the ELF header: `1` = 32-bit,
+/// `2` = 64-bit) from `bin/sh` or the first `lib/ld-linux*` found.
+fn detect_bitness(os_root: &Path) -> Option<u8> {
+    let mut candidates = vec![os_root.join("bin/sh")];
+    if let Ok(entries) = fs::read_dir(os_root.join("lib")) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with("ld-linux") {
+                candidates.push(entry.path());
+            }
+        }
+    }
+
+    candidates.iter().find_map(|candidate| read_elf_class(candidate))
+}
+
+fn read_elf_class(path: &Path) -> Option<u8> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut header = [0u8; 5];
+    file.read_exact(&mut header).ok()?;
+
+    if &header[0..4] != b"\x7fELF" {
+        return None;
+    }
+
+    match header[4] {
+        1 => Some(32),
+        2 => Some(64),
+        _ => None,
+    }
+}
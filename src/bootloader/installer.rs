@@ -0,0 +1,213 @@
+// تدفّق النسخ الخام لصورة نظام تشغيل إلى جهاز تخزين، لمسار "لم يُعثر على
+// نظام تشغيل" - بخلاف نموذج os_manager القائم على الأجيال (الذي يستخرج
+// محتوى صورة إلى مجلد مُدار)، الفلاشينغ هنا يكتب بايتات خام مباشرة على جهاز
+// مثل /dev/mmcblk0 فلا وجود لـ"جيل" لإدارته.
+use anyhow::{Context, Result};
+use async_compression::tokio::bufread::{GzipDecoder, XzDecoder};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::process::Command;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tracing::{info, warn};
+
+const FLASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageCompression {
+    None,
+    Gzip,
+    Xz,
+}
+
+fn detect_compression(path: &Path) -> ImageCompression {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("gz") => ImageCompression::Gzip,
+        Some(ext) if ext.eq_ignore_ascii_case("xz") => ImageCompression::Xz,
+        _ => ImageCompression::None,
+    }
+}
+
+/// تقدّم عملية الفلاشينغ بالبايت، يُستدعى بعد كل دفعة مكتوبة.
+pub struct FlashProgress {
+    pub bytes_written: u64,
+    pub total_bytes: Option<u64>,
+}
+
+pub type FlashProgressCallback<'a> = dyn Fn(FlashProgress) + Send + Sync + 'a;
+
+/// ينسخ `image_path` (مفكوك الضغط أثناء البث إن كان `.gz`/`.xz`) إلى
+/// `target_device` على دفعات ثابتة الحجم، ثم يعيد قراءة جدول الأقسام
+/// ويتحقق من البايتات المكتوبة عبر بصمة SHA-256 متدرجة (مقارنة بصمة ما
+/// كُتب مع بصمة ما يُقرأ الآن من الجهاز نفسه)، بالإضافة إلى مقارنتها بـ
+/// `expected_digest` إن وُجد (أو بملف بصمة منفصل `<image>.sha256` بجانب
+/// الصورة). يرفض التثبيت (خطأ، لا panic) إن فشل أي من التحققين.
+pub async fn flash_image_to_device(
+    image_path: &Path,
+    target_device: &Path,
+    expected_digest: Option<&str>,
+    progress: Option<&FlashProgressCallback<'_>>,
+) -> Result<()> {
+    if !image_path.exists() {
+        return Err(anyhow::anyhow!("صورة النظام {} غير موجودة", image_path.display()));
+    }
+    if !target_device.exists() {
+        return Err(anyhow::anyhow!("جهاز التخزين الهدف {} غير موجود", target_device.display()));
+    }
+
+    let expected_digest = expected_digest
+        .map(str::to_string)
+        .or_else(|| read_expected_digest_file(image_path));
+
+    info!("💾 بدء نسخ {} إلى {}", image_path.display(), target_device.display());
+    let (bytes_written, source_digest) =
+        stream_image_to_device(image_path, target_device, progress).await?;
+
+    if let Some(expected) = &expected_digest {
+        if !expected.eq_ignore_ascii_case(&source_digest) {
+            return Err(anyhow::anyhow!(
+                "فشل التحقق من بصمة الصورة: المتوقعة {} لا تطابق المحسوبة {}",
+                expected,
+                source_digest
+            ));
+        }
+    }
+
+    reread_partition_table(target_device);
+
+    info!("🔎 إعادة قراءة {} بايت من الجهاز للتحقق من الفلاشينغ", bytes_written);
+    let device_digest = hash_device_prefix(target_device, bytes_written).await?;
+    if device_digest != source_digest {
+        return Err(anyhow::anyhow!(
+            "فشل التحقق بعد الفلاشينغ: بصمة الجهاز {} لا تطابق بصمة المصدر {} - الفلاشينغ قد يكون تالفاً",
+            device_digest,
+            source_digest
+        ));
+    }
+
+    info!("✅ تم فلاشينغ {} بنجاح والتحقق منه", target_device.display());
+    Ok(())
+}
+
+async fn stream_image_to_device(
+    image_path: &Path,
+    target_device: &Path,
+    progress: Option<&FlashProgressCallback<'_>>,
+) -> Result<(u64, String)> {
+    let source_file = tokio::fs::File::open(image_path)
+        .await
+        .with_context(|| format!("تعذّر فتح صورة النظام: {}", image_path.display()))?;
+    let compression = detect_compression(image_path);
+    let total_bytes = if compression == ImageCompression::None {
+        Some(
+            source_file
+                .metadata()
+                .await
+                .context("تعذّر قراءة حجم صورة النظام")?
+                .len(),
+        )
+    } else {
+        None
+    };
+
+    let mut target = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(target_device)
+        .await
+        .with_context(|| format!("تعذّر فتح جهاز التخزين للكتابة: {}", target_device.display()))?;
+
+    let buffered = BufReader::new(source_file);
+    let mut hasher = Sha256::new();
+    let mut written: u64 = 0;
+    let mut buffer = vec![0u8; FLASH_BUFFER_SIZE];
+
+    macro_rules! drain {
+        ($reader:expr) => {{
+            let mut reader = $reader;
+            loop {
+                let bytes_read = reader
+                    .read(&mut buffer)
+                    .await
+                    .context("فشل في قراءة دفعة من صورة النظام")?;
+                if bytes_read == 0 {
+                    break;
+                }
+                let chunk = &buffer[..bytes_read];
+                hasher.update(chunk);
+                target
+                    .write_all(chunk)
+                    .await
+                    .context("فشل في الكتابة على جهاز التخزين")?;
+                written += bytes_read as u64;
+
+                if let Some(callback) = progress {
+                    callback(FlashProgress {
+                        bytes_written: written,
+                        total_bytes,
+                    });
+                }
+            }
+        }};
+    }
+
+    match compression {
+        ImageCompression::None => drain!(buffered),
+        ImageCompression::Gzip => drain!(GzipDecoder::new(buffered)),
+        ImageCompression::Xz => drain!(XzDecoder::new(buffered)),
+    }
+
+    target.flush().await.context("فشل في إتمام الكتابة على جهاز التخزين")?;
+    target.sync_all().await.context("فشل في مزامنة جهاز التخزين (sync)")?;
+
+    Ok((written, format!("{:x}", hasher.finalize())))
+}
+
+async fn hash_device_prefix(target_device: &Path, byte_count: u64) -> Result<String> {
+    let file = tokio::fs::File::open(target_device)
+        .await
+        .with_context(|| format!("تعذّر إعادة فتح جهاز التخزين للتحقق: {}", target_device.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut remaining = byte_count;
+    let mut buffer = vec![0u8; FLASH_BUFFER_SIZE];
+
+    while remaining > 0 {
+        let to_read = remaining.min(buffer.len() as u64) as usize;
+        let bytes_read = AsyncReadExt::read(&mut reader, &mut buffer[..to_read])
+            .await
+            .context("فشل في قراءة بيانات التحقق من جهاز التخزين")?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        remaining -= bytes_read as u64;
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// يبحث عن ملف بصمة منفصل بجانب الصورة (`<image>.sha256`)، مثل ما تنشره
+/// أغلب توزيعات Raspberry Pi OS بجانب صورها الرسمية.
+fn read_expected_digest_file(image_path: &Path) -> Option<String> {
+    let mut digest_path = image_path.as_os_str().to_owned();
+    digest_path.push(".sha256");
+    std::fs::read_to_string(digest_path)
+        .ok()
+        .map(|content| content.split_whitespace().next().unwrap_or("").to_string())
+        .filter(|digest| !digest.is_empty())
+}
+
+/// يطلب من النواة إعادة قراءة جدول أقسام الجهاز بعد الفلاشينغ؛ فشل هذا غير
+/// قاتل (بعض الأجهزة الوهمية/الحاويات لا تدعمه) فيُسجَّل تحذيراً فقط.
+fn reread_partition_table(target_device: &Path) {
+    match Command::new("partprobe").arg(target_device).output() {
+        Ok(output) if output.status.success() => {
+            info!("🔄 أُعيدت قراءة جدول الأقسام على {}", target_device.display());
+        }
+        Ok(output) => warn!(
+            "⚠️ فشل partprobe على {}: {}",
+            target_device.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(e) => warn!("⚠️ تعذّر تشغيل partprobe: {}", e),
+    }
+}
@@ -0,0 +1,138 @@
+// نقل فعلي للتحكم إلى النظام المختار عبر kexec، بدل المحاكاة بـ sleep/println
+// في menu.rs القديمة. يحدّد صورة النواة/initrd تحت مسار النظام، يبني سطر
+// أوامر النواة (من cmdline.txt الموجود أو بتوليفه)، يحمّله بنداء
+// kexec_file_load(2)، ثم يقفز إليه بـ reboot(LINUX_REBOOT_CMD_KEXEC).
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::bootloader::menu::OSType;
+
+const KEXEC_FILE_NO_INITRAMFS: libc::c_ulong = 0x4;
+const LINUX_REBOOT_CMD_KEXEC: libc::c_int = 0x4558_4543;
+
+#[cfg(target_arch = "x86_64")]
+const SYS_KEXEC_FILE_LOAD: libc::c_long = 320;
+#[cfg(target_arch = "aarch64")]
+const SYS_KEXEC_FILE_LOAD: libc::c_long = 294;
+// أجهزة الاستهداف الرئيسية لهذا المشروع (Pi Zero/1/2 وكثير من ألواح Anbernic)
+// تعمل بمعمارية ARM 32-بت؛ بدون هذا الفرع يقبل تثبيت صورة Armv7 ويجتاز
+// اختبار إقلاعها عبر QEMU لكن لا يمكنه الإقلاع فعلياً على العتاد الحقيقي.
+#[cfg(target_arch = "arm")]
+const SYS_KEXEC_FILE_LOAD: libc::c_long = 401;
+
+const KERNEL_CANDIDATES: &[&str] = &["vmlinuz", "kernel8.img", "kernel7.img", "kernel.img", "zImage"];
+const INITRD_CANDIDATES: &[&str] = &["initrd.img", "initramfs.img", "initrd7.img", "initrd8.img"];
+
+/// يبحث عن أول صورة نواة معروفة الاسم تحت مسار النظام.
+fn find_kernel(os_path: &Path) -> Result<PathBuf> {
+    KERNEL_CANDIDATES
+        .iter()
+        .map(|name| os_path.join(name))
+        .find(|candidate| candidate.exists())
+        .ok_or_else(|| anyhow::anyhow!("تعذّر العثور على صورة نواة ضمن {}", os_path.display()))
+}
+
+/// initrd اختياري؛ بعض الأنظمة (خصوصاً صور الألعاب المدمجة) تُقلَع بدونه.
+fn find_initrd(os_path: &Path) -> Option<PathBuf> {
+    INITRD_CANDIDATES
+        .iter()
+        .map(|name| os_path.join(name))
+        .find(|candidate| candidate.exists())
+}
+
+/// يقرأ `cmdline.txt` إن وُجد، وإلا يولّد سطر أوامر أساسي افتراضاً، ثم يُلحق
+/// مقطعاً خاصاً بنوع النظام. عند غياب `cmdline.txt`، `root_uuid` المكتشف عبر
+/// `distro_detect::probe_mounted_partition` (من `OperatingSystem::root_uuid`)
+/// يُفضَّل على افتراض اسم جهاز ثابت مثل `/dev/mmcblk0p2` - الأخير ينكسر فور
+/// تغيّر ترتيب الأقراص أو نوع الجهاز (USB بدل SD مثلاً).
+fn build_cmdline(os_path: &Path, os_type: &OSType, root_uuid: Option<&str>) -> String {
+    let mut cmdline = std::fs::read_to_string(os_path.join("cmdline.txt"))
+        .map(|content| content.trim().to_string())
+        .unwrap_or_else(|_| match root_uuid {
+            Some(uuid) => format!("root=UUID={} rootwait console=tty1", uuid),
+            None => "root=/dev/mmcblk0p2 rootwait console=tty1".to_string(),
+        });
+
+    let fragment = match os_type {
+        OSType::RetroPie | OSType::Batocera | OSType::Recalbox => " quiet loglevel=0 logo.nologo",
+        OSType::RaspberryPiOS | OSType::Ubuntu | OSType::Debian => " quiet splash",
+        OSType::Unknown => "",
+    };
+    cmdline.push_str(fragment);
+    cmdline
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm"))]
+fn kexec_file_load(kernel: &Path, initrd: Option<&Path>, cmdline: &str) -> Result<()> {
+    let kernel_file =
+        File::open(kernel).with_context(|| format!("تعذّر فتح صورة النواة: {}", kernel.display()))?;
+    let initrd_file = initrd
+        .map(File::open)
+        .transpose()
+        .with_context(|| "تعذّر فتح صورة initrd")?;
+
+    let (initrd_fd, flags): (libc::c_int, libc::c_ulong) = match &initrd_file {
+        Some(file) => (file.as_raw_fd(), 0),
+        None => (-1, KEXEC_FILE_NO_INITRAMFS),
+    };
+
+    let cmdline_with_nul = format!("{}\0", cmdline);
+    let result = unsafe {
+        libc::syscall(
+            SYS_KEXEC_FILE_LOAD,
+            kernel_file.as_raw_fd(),
+            initrd_fd,
+            cmdline_with_nul.len() as libc::c_ulong,
+            cmdline_with_nul.as_ptr(),
+            flags,
+        )
+    };
+
+    if result == -1 {
+        return Err(anyhow::anyhow!(
+            "فشل نداء kexec_file_load(2): {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64", target_arch = "arm")))]
+fn kexec_file_load(_kernel: &Path, _initrd: Option<&Path>, _cmdline: &str) -> Result<()> {
+    Err(anyhow::anyhow!("kexec_file_load(2) غير مدعوم على هذه المعمارية"))
+}
+
+fn reboot_into_kexec() -> Result<()> {
+    info!("🔁 القفز إلى النواة المحمّلة عبر kexec...");
+    let result = unsafe { libc::reboot(LINUX_REBOOT_CMD_KEXEC) };
+    if result != 0 {
+        return Err(anyhow::anyhow!(
+            "فشل استدعاء reboot(LINUX_REBOOT_CMD_KEXEC): {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+/// يحمّل نواة/initrd النظام الموجود تحت `os_path` بنداء kexec_file_load(2)
+/// ثم يقفز إليه فوراً. يعيد خطأ (لا panic) إن تعذّر إيجاد الملفات أو فشل أي
+/// من النداءين، كي تتمكن القائمة من الرجوع لخيار آخر بدل التجمّد.
+pub async fn kexec_boot(os_path: &str, os_type: &OSType, root_uuid: Option<&str>) -> Result<()> {
+    let os_path = Path::new(os_path);
+    let kernel = find_kernel(os_path)?;
+    let initrd = find_initrd(os_path);
+    let cmdline = build_cmdline(os_path, os_type, root_uuid);
+
+    info!(
+        "🔧 تحميل kexec: kernel={} initrd={:?} cmdline=\"{}\"",
+        kernel.display(),
+        initrd,
+        cmdline
+    );
+    kexec_file_load(&kernel, initrd.as_deref(), &cmdline)?;
+    reboot_into_kexec()
+}
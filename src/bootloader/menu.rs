@@ -1,560 +1,767 @@
-// Boot menu implementation 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use tokio::time::{sleep, timeout};
-use tracing::{info, warn};
-use crate::hardware::device_detect::DeviceInfo;
-use crate::utils::config::Config;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BootMenu {
-    pub config: Config,
-    pub device_info: DeviceInfo,
-    pub available_systems: Vec<OperatingSystem>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OperatingSystem {
-    pub name: String,
-    pub path: String,
-    pub description: String,
-    pub os_type: OSType,
-    pub is_bootable: bool,
-    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum OSType {
-    RetroPie,
-    Batocera,
-    Recalbox,
-    RaspberryPiOS,
-    Ubuntu,
-    Debian,
-    Unknown,
-}
-
-impl BootMenu {
-    pub fn new(config: &Config, device_info: &DeviceInfo) -> Result<Self> {
-        let mut boot_menu = BootMenu {
-            config: config.clone(),
-            device_info: device_info.clone(),
-            available_systems: Vec::new(),
-        };
-
-        // Scan for available operating systems
-        boot_menu.scan_for_operating_systems()?;
-
-        Ok(boot_menu)
-    }
-
-    pub async fn show_menu(&self) -> Result<()> {
-        info!("=== DOS Safar Boot Menu ===");
-        info!("Device: {}", self.device_info.model);
-        
-        if self.available_systems.is_empty() {
-            warn!("No operating systems found!");
-            self.show_no_os_menu().await?;
-            return Ok(());
-        }
-
-        // Check if we have a default OS and auto-boot is enabled
-        if let Some(default_os) = &self.config.boot.default_os {
-            if !default_os.is_empty() {
-                return self.auto_boot_default(default_os).await;
-            }
-        }
-
-        // Show interactive menu
-        self.show_interactive_menu().await
-    }
-
-    fn scan_for_operating_systems(&mut self) -> Result<()> {
-        info!("Scanning for operating systems...");
-
-        // Scan different potential locations
-        self.scan_boot_partitions()?;
-        self.scan_sd_card_images()?;
-        self.scan_usb_devices()?;
-
-        info!("Found {} operating systems", self.available_systems.len());
-        
-        // Sort by last used (most recent first)
-        self.available_systems.sort_by(|a, b| {
-            match (&a.last_used, &b.last_used) {
-                (Some(a_time), Some(b_time)) => b_time.cmp(a_time),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.name.cmp(&b.name),
-            }
-        });
-
-        Ok(())
-    }
-
-    fn scan_boot_partitions(&mut self) -> Result<()> {
-        // Look for boot partitions with different OS signatures
-        let boot_paths = vec![
-            "/boot",
-            "/mnt/boot",
-            "/media/boot",
-        ];
-
-        for boot_path in boot_paths {
-            if std::path::Path::new(boot_path).exists() {
-                if let Ok(os) = self.identify_os_from_boot_partition(boot_path) {
-                    self.available_systems.push(os);
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn scan_sd_card_images(&mut self) -> Result<()> {
-        // Look for OS images on SD card
-        let image_paths = vec![
-            "/boot/os_images/",
-            "/home/dos_safar/images/",
-            "/opt/dos_safar/images/",
-        ];
-
-        for image_path in image_paths {
-            if let Ok(entries) = std::fs::read_dir(image_path) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if let Some(extension) = path.extension() {
-                        let ext = extension.to_string_lossy().to_lowercase();
-                        if ext == "img" || ext == "iso" {
-                            if let Ok(os) = self.identify_os_from_image(&path) {
-                                self.available_systems.push(os);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn scan_usb_devices(&mut self) -> Result<()> {
-        // Look for bootable USB devices
-        let usb_mount_paths = vec![
-            "/media/",
-            "/mnt/",
-            "/run/media/",
-        ];
-
-        for mount_path in usb_mount_paths {
-            if let Ok(entries) = std::fs::read_dir(mount_path) {
-                for entry in entries.flatten() {
-                    let device_path = entry.path();
-                    if device_path.is_dir() {
-                        // Check if this looks like a bootable OS
-                        if let Ok(os) = self.identify_os_from_boot_partition(&device_path.to_string_lossy()) {
-                            self.available_systems.push(os);
-                        }
-                    }
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    fn identify_os_from_boot_partition(&self, boot_path: &str) -> Result<OperatingSystem> {
-        let boot_path = std::path::Path::new(boot_path);
-        
-        // Check for RetroPie
-        if boot_path.join("retropie").exists() || 
-           boot_path.join("RetroPie").exists() {
-            return Ok(OperatingSystem {
-                name: "RetroPie".to_string(),
-                path: boot_path.to_string_lossy().to_string(),
-                description: "Retro Gaming System".to_string(),
-                os_type: OSType::RetroPie,
-                is_bootable: true,
-                last_used: None,
-            });
-        }
-
-        // Check for Batocera
-        if boot_path.join("batocera").exists() ||
-           boot_path.join("BATOCERA").exists() {
-            return Ok(OperatingSystem {
-                name: "Batocera".to_string(),
-                path: boot_path.to_string_lossy().to_string(),
-                description: "Retro Gaming Distribution".to_string(),
-                os_type: OSType::Batocera,
-                is_bootable: true,
-                last_used: None,
-            });
-        }
-
-        // Check for Recalbox
-        if boot_path.join("recalbox").exists() {
-            return Ok(OperatingSystem {
-                name: "Recalbox".to_string(),
-                path: boot_path.to_string_lossy().to_string(),
-                description: "Retro Gaming OS".to_string(),
-                os_type: OSType::Recalbox,
-                is_bootable: true,
-                last_used: None,
-            });
-        }
-
-        // Check for Raspberry Pi OS
-        if boot_path.join("config.txt").exists() &&
-           boot_path.join("cmdline.txt").exists() {
-            return Ok(OperatingSystem {
-                name: "Raspberry Pi OS".to_string(),
-                path: boot_path.to_string_lossy().to_string(),
-                description: "Official Raspberry Pi Operating System".to_string(),
-                os_type: OSType::RaspberryPiOS,
-                is_bootable: true,
-                last_used: None,
-            });
-        }
-
-        // Check for Ubuntu/Debian
-        if boot_path.join("ubuntu").exists() ||
-           boot_path.join("vmlinuz").exists() {
-            return Ok(OperatingSystem {
-                name: "Linux System".to_string(),
-                path: boot_path.to_string_lossy().to_string(),
-                description: "General Linux Distribution".to_string(),
-                os_type: OSType::Ubuntu,
-                is_bootable: true,
-                last_used: None,
-            });
-        }
-
-        Err(anyhow::anyhow!("Unknown OS type"))
-    }
-
-    fn identify_os_from_image(&self, image_path: &std::path::Path) -> Result<OperatingSystem> {
-        let filename = image_path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-        
-        let filename_lower = filename.to_lowercase();
-
-        let (name, os_type, description) = if filename_lower.contains("retropie") {
-            ("RetroPie Image".to_string(), OSType::RetroPie, "RetroPie OS Image".to_string())
-        } else if filename_lower.contains("batocera") {
-            ("Batocera Image".to_string(), OSType::Batocera, "Batocera OS Image".to_string())
-        } else if filename_lower.contains("recalbox") {
-            ("Recalbox Image".to_string(), OSType::Recalbox, "Recalbox OS Image".to_string())
-        } else if filename_lower.contains("raspios") || filename_lower.contains("raspberry") {
-            ("Raspberry Pi OS Image".to_string(), OSType::RaspberryPiOS, "Raspberry Pi OS Image".to_string())
-        } else {
-            (format!("OS Image: {}", filename), OSType::Unknown, "Unknown OS Image".to_string())
-        };
-
-        Ok(OperatingSystem {
-            name,
-            path: image_path.to_string_lossy().to_string(),
-            description,
-            os_type,
-            is_bootable: true,
-            last_used: None,
-        })
-    }
-
-    async fn auto_boot_default(&self, default_os: &str) -> Result<()> {
-        info!("Auto-booting default OS: {}", default_os);
-        
-        // Find the default OS
-        if let Some(os) = self.available_systems.iter().find(|os| os.name == default_os) {
-            info!("Booting into {}", os.name);
-            self.boot_operating_system(os).await?;
-        } else {
-            warn!("Default OS '{}' not found, showing menu", default_os);
-            self.show_interactive_menu().await?;
-        }
-
-        Ok(())
-    }
-
-    async fn show_interactive_menu(&self) -> Result<()> {
-        info!("=== Interactive Boot Menu ===");
-        
-        // Display menu options
-        self.display_menu_header();
-        
-        for (index, os) in self.available_systems.iter().enumerate() {
-            self.display_menu_item(index + 1, os);
-        }
-        
-        self.display_menu_footer();
-
-        // Gaming mode: Show timeout and wait for input
-        if self.config.boot.gaming_mode {
-            self.gaming_mode_selection().await
-        } else {
-            self.standard_mode_selection().await
-        }
-    }
-
-    fn display_menu_header(&self) {
-        println!("\n🎮 DOS Safar Boot Manager 🎮");
-        println!("Device: {}", self.device_info.model);
-        if self.device_info.gaming_features.has_built_in_screen {
-            println!("Screen: {}\"", self.device_info.gaming_features.screen_size_inches.unwrap_or(3.5));
-        }
-        println!("═══════════════════════════════════════");
-    }
-
-    fn display_menu_item(&self, index: usize, os: &OperatingSystem) {
-        let icon = match os.os_type {
-            OSType::RetroPie => "🕹️",
-            OSType::Batocera => "🎯",
-            OSType::Recalbox => "📦",
-            OSType::RaspberryPiOS => "🍓",
-            OSType::Ubuntu => "🐧",
-            OSType::Debian => "🌊",
-            OSType::Unknown => "❓",
-        };
-
-        println!("  {}. {} {} - {}", index, icon, os.name, os.description);
-        
-        if let Some(last_used) = os.last_used {
-            println!("     Last used: {}", last_used.format("%Y-%m-%d %H:%M"));
-        }
-    }
-
-    fn display_menu_footer(&self) {
-        println!("═══════════════════════════════════════");
-        println!("  A. Advanced Options");
-        println!("  W. Web Interface");
-        println!("  R. Restart Hardware Tests");
-        println!("  S. Shutdown");
-        println!("═══════════════════════════════════════");
-        
-        if self.config.boot.gaming_mode {
-            println!("🎮 Use D-Pad to navigate, A to select");
-            println!("⏱️  Auto-boot in {} seconds...", self.config.boot.menu_timeout_seconds);
-        } else {
-            println!("Enter your choice (1-{}):", self.available_systems.len());
-        }
-    }
-
-    async fn gaming_mode_selection(&self) -> Result<()> {
-        // Simplified input handling for gaming mode
-        // In a real implementation, you would read from input devices
-        
-        let timeout_duration = Duration::from_secs(self.config.boot.menu_timeout_seconds);
-        
-        // Wait for timeout or input
-        match timeout(timeout_duration, self.wait_for_gaming_input()).await {
-            Ok(selection) => {
-                self.handle_selection(selection).await?;
-            }
-            Err(_) => {
-                // Timeout - boot first available system
-                if let Some(first_os) = self.available_systems.first() {
-                    info!("Timeout reached, booting {}", first_os.name);
-                    self.boot_operating_system(first_os).await?;
-                } else {
-                    warn!("No systems available to auto-boot");
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn wait_for_gaming_input(&self) -> MenuSelection {
-        // Simplified input simulation
-        // In real implementation, this would read from gaming controls
-        
-        // For now, just wait and return first option
-        sleep(Duration::from_millis(100)).await;
-        
-        // Simulate user pressing A button to select first item
-        MenuSelection::BootOS(0)
-    }
-
-    async fn standard_mode_selection(&self) -> Result<()> {
-        // Standard keyboard input mode
-        // This would implement proper stdin reading
-        // For now, just boot the first system
-        
-        if let Some(first_os) = self.available_systems.first() {
-            info!("Standard mode: booting {}", first_os.name);
-            self.boot_operating_system(first_os).await?;
-        }
-
-        Ok(())
-    }
-
-    async fn handle_selection(&self, selection: MenuSelection) -> Result<()> {
-        match selection {
-            MenuSelection::BootOS(index) => {
-                if let Some(os) = self.available_systems.get(index) {
-                    self.boot_operating_system(os).await?;
-                } else {
-                    warn!("Invalid OS selection: {}", index);
-                }
-            }
-            MenuSelection::AdvancedOptions => {
-                self.show_advanced_menu().await?;
-            }
-            MenuSelection::WebInterface => {
-                info!("Web interface is already running");
-                println!("Web interface available at: http://localhost:8080");
-            }
-            MenuSelection::RestartTests => {
-                info!("Restarting hardware tests...");
-                // This would restart the hardware testing process
-            }
-            MenuSelection::Shutdown => {
-                info!("Shutting down system...");
-                self.shutdown_system().await?;
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn boot_operating_system(&self, os: &OperatingSystem) -> Result<()> {
-        info!("🚀 Booting into: {}", os.name);
-        
-        // Save boot selection
-        self.save_boot_selection(os).await?;
-        
-        // Apply any hardware configurations
-        self.apply_hardware_config_for_os(os).await?;
-        
-        // Perform the actual boot
-        match os.os_type {
-            OSType::RetroPie | OSType::Batocera | OSType::Recalbox => {
-                self.boot_gaming_os(os).await?;
-            }
-            OSType::RaspberryPiOS | OSType::Ubuntu | OSType::Debian => {
-                self.boot_standard_os(os).await?;
-            }
-            OSType::Unknown => {
-                self.boot_unknown_os(os).await?;
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn boot_gaming_os(&self, os: &OperatingSystem) -> Result<()> {
-        info!("Booting gaming OS: {}", os.name);
-        
-        // Gaming OS specific boot sequence
-        // This would configure controllers, displays, etc.
-        
-        // For now, just simulate boot
-        println!("🎮 Configuring gaming controls...");
-        sleep(Duration::from_secs(1)).await;
-        
-        println!("🎮 Loading {} system...", os.name);
-        sleep(Duration::from_secs(2)).await;
-        
-        println!("🎮 {} is ready!", os.name);
-        
-        Ok(())
-    }
-
-    async fn boot_standard_os(&self, os: &OperatingSystem) -> Result<()> {
-        info!("Booting standard OS: {}", os.name);
-        
-        // Standard OS boot sequence
-        println!("🐧 Loading {} system...", os.name);
-        sleep(Duration::from_secs(2)).await;
-        
-        println!("🐧 {} is ready!", os.name);
-        
-        Ok(())
-    }
-
-    async fn boot_unknown_os(&self, os: &OperatingSystem) -> Result<()> {
-        info!("Booting unknown OS: {}", os.name);
-        
-        // Generic boot sequence
-        println!("❓ Loading system from {}...", os.path);
-        sleep(Duration::from_secs(2)).await;
-        
-        println!("❓ System loaded!");
-        
-        Ok(())
-    }
-
-    async fn show_advanced_menu(&self) -> Result<()> {
-        println!("\n=== Advanced Options ===");
-        println!("1. Install New OS");
-        println!("2. Remove OS");
-        println!("3. Hardware Configuration");
-        println!("4. Network Settings");
-        println!("5. Back to Main Menu");
-        
-        // For now, just return to main menu
-        self.show_interactive_menu().await
-    }
-
-    async fn show_no_os_menu(&self) -> Result<()> {
-        println!("\n⚠️  No Operating Systems Found!");
-        println!("═══════════════════════════════════");
-        println!("Options:");
-        println!("1. 🌐 Download OS images via web interface");
-        println!("2. 🔍 Rescan for OS images");
-        println!("3. 📁 Check connected USB drives");
-        println!("4. ⚡ Emergency shell");
-        println!("═══════════════════════════════════");
-        
-        // Start web interface for OS installation
-        println!("💡 Starting web interface for OS management...");
-        println!("Visit: http://localhost:8080 to install operating systems");
-        
-        Ok(())
-    }
-
-    async fn save_boot_selection(&self, os: &OperatingSystem) -> Result<()> {
-        // Save the selected OS as the last used
-        // This would update the configuration file
-        info!("Saving boot selection: {}", os.name);
-        Ok(())
-    }
-
-    async fn apply_hardware_config_for_os(&self, os: &OperatingSystem) -> Result<()> {
-        // Apply OS-specific hardware configurations
-        match os.os_type {
-            OSType::RetroPie | OSType::Batocera | OSType::Recalbox => {
-                // Apply gaming-specific configurations
-                info!("Applying gaming hardware configuration");
-            }
-            _ => {
-                // Apply standard configurations
-                info!("Applying standard hardware configuration");
-            }
-        }
-        Ok(())
-    }
-
-    async fn shutdown_system(&self) -> Result<()> {
-        println!("💤 Shutting down DOS Safar...");
-        
-        // Graceful shutdown
-        std::process::Command::new("shutdown")
-            .args(&["-h", "now"])
-            .output()
-            .context("Failed to shutdown system")?;
-        
-        Ok(())
-    }
-}
-
-#[derive(Debug)]
-enum MenuSelection {
-    BootOS(usize),
-    AdvancedOptions,
-    WebInterface,
-    RestartTests,
-    Shutdown,
+// Boot menu implementation
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{info, warn};
+use crate::bootloader::boot_state::{self, BootOutcome};
+use crate::bootloader::distro_detect::{self, LinuxDistroInfo};
+use crate::hardware::device_detect::DeviceInfo;
+use crate::utils::config::Config;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootMenu {
+    pub config: Config,
+    pub device_info: DeviceInfo,
+    pub available_systems: Vec<OperatingSystem>,
+    /// مسار ملف التكوين على القرص، محفوظ حتى يتمكن `set_default_os` من
+    /// كتابة `config.boot.default_os` إليه مباشرة دون تمريره عبر كل دالة.
+    config_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OperatingSystem {
+    pub name: String,
+    pub path: String,
+    pub description: String,
+    pub os_type: OSType,
+    pub is_bootable: bool,
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+    /// CPU architecture this system was installed for, detected from its
+    /// kernel/ELF at install time. `None` when it predates arch detection
+    /// or detection was inconclusive.
+    pub target_arch: Option<TargetArch>,
+    /// Enriched distro identity from `distro_detect::detect_linux_distro`,
+    /// with retro-gaming `os_type`s (RetroPie/Batocera/Recalbox) layered on
+    /// top of whatever base distro they were built on.
+    pub distro: Option<LinuxDistroInfo>,
+    /// Structured `dos_safar_config.toml` manifest, parsed once by
+    /// `OSManager::analyze_os_directory`. `None` when the system carries no
+    /// such file or it failed to parse (legacy/foreign images).
+    pub manifest: Option<crate::bootloader::os_manager::OsManifest>,
+    /// ROM libraries found under `roms/<system>/`, one entry per console
+    /// directory, for retro-gaming systems (RetroPie/Batocera/Recalbox).
+    /// Empty for everything else.
+    #[serde(default)]
+    pub rom_libraries: Vec<crate::bootloader::rom_scanner::RomLibrary>,
+    /// Number of times this system has been selected and booted, from
+    /// `boot_state::BootStateStore`. Stays `0` for entries `OSManager`
+    /// builds directly (`analyze_os_directory`), which has its own
+    /// separate install registry and does not track this.
+    #[serde(default)]
+    pub boot_count: u64,
+    /// Outcome of the last known boot attempt for this system, from the
+    /// same store. `None` until a first boot is recorded.
+    #[serde(default)]
+    pub last_boot_outcome: Option<BootOutcome>,
+    /// Version string for this system, preferring `distro.version_id` when
+    /// `/etc/os-release` was parsed; falls back to whatever the plain
+    /// filename/marker heuristic could infer (usually nothing).
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Filesystem UUID of the partition backing this system's `path`, from
+    /// `distro_detect::probe_mounted_partition` (real `blkid` probe of the
+    /// underlying block device). Lets `kexec::kexec_boot` build a
+    /// `root=UUID=...` cmdline instead of guessing a device node name that
+    /// may not be stable across boots (see `kexec::build_cmdline`).
+    #[serde(default)]
+    pub root_uuid: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum OSType {
+    RetroPie,
+    Batocera,
+    Recalbox,
+    RaspberryPiOS,
+    Ubuntu,
+    Debian,
+    Unknown,
+}
+
+/// CPU architecture a system image targets, mirroring repbuild's `Target`.
+/// Used to refuse or warn before installing an image onto an incompatible
+/// host (see `os_manager::install_os_from_image_verified`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TargetArch {
+    X86_64,
+    Aarch64,
+    Riscv64,
+    Armv7,
+}
+
+impl BootMenu {
+    pub fn new(config: &Config, device_info: &DeviceInfo, config_path: &std::path::Path) -> Result<Self> {
+        let mut boot_menu = BootMenu {
+            config: config.clone(),
+            device_info: device_info.clone(),
+            available_systems: Vec::new(),
+            config_path: config_path.to_path_buf(),
+        };
+
+        // Scan for available operating systems
+        boot_menu.scan_for_operating_systems()?;
+
+        Ok(boot_menu)
+    }
+
+    pub async fn show_menu(&self) -> Result<()> {
+        info!("=== DOS Safar Boot Menu ===");
+        info!("Device: {}", self.device_info.model);
+        
+        if self.available_systems.is_empty() {
+            warn!("No operating systems found!");
+            self.show_no_os_menu().await?;
+            return Ok(());
+        }
+
+        // Check if we have a default OS and auto-boot is enabled
+        if let Some(default_os) = &self.config.boot.default_os {
+            if !default_os.is_empty() {
+                return self.auto_boot_default(default_os).await;
+            }
+        }
+
+        // Show interactive menu
+        self.show_interactive_menu().await
+    }
+
+    fn scan_for_operating_systems(&mut self) -> Result<()> {
+        info!("Scanning for operating systems...");
+
+        // Scan different potential locations
+        self.scan_boot_partitions()?;
+        self.scan_sd_card_images()?;
+        self.scan_usb_devices()?;
+
+        info!("Found {} operating systems", self.available_systems.len());
+
+        // Merge persisted last_used/boot_count/last_boot_outcome onto the
+        // freshly-discovered entries - each scan above builds a brand new
+        // `OperatingSystem` that knows nothing about its own history.
+        let state_path = boot_state::state_file_path(&self.config);
+        boot_state::BootStateStore::load(&state_path).merge_into(&mut self.available_systems);
+
+        // Sort by last used (most recent first)
+        self.available_systems.sort_by(|a, b| {
+            match (&a.last_used, &b.last_used) {
+                (Some(a_time), Some(b_time)) => b_time.cmp(a_time),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => a.name.cmp(&b.name),
+            }
+        });
+
+        Ok(())
+    }
+
+    fn scan_boot_partitions(&mut self) -> Result<()> {
+        // Look for boot partitions with different OS signatures
+        let boot_paths = vec![
+            "/boot",
+            "/mnt/boot",
+            "/media/boot",
+        ];
+
+        for boot_path in boot_paths {
+            if std::path::Path::new(boot_path).exists() {
+                if let Ok(os) = self.identify_os_from_boot_partition(boot_path) {
+                    self.available_systems.push(os);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn scan_sd_card_images(&mut self) -> Result<()> {
+        // Look for OS images on SD card
+        let image_paths = vec![
+            "/boot/os_images/",
+            "/home/dos_safar/images/",
+            "/opt/dos_safar/images/",
+        ];
+
+        for image_path in image_paths {
+            if let Ok(entries) = std::fs::read_dir(image_path) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if let Some(extension) = path.extension() {
+                        let ext = extension.to_string_lossy().to_lowercase();
+                        if ext == "img" || ext == "iso" {
+                            if let Ok(os) = self.identify_os_from_image(&path) {
+                                self.available_systems.push(os);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn scan_usb_devices(&mut self) -> Result<()> {
+        // Look for bootable USB devices
+        let usb_mount_paths = vec![
+            "/media/",
+            "/mnt/",
+            "/run/media/",
+        ];
+
+        for mount_path in usb_mount_paths {
+            if let Ok(entries) = std::fs::read_dir(mount_path) {
+                for entry in entries.flatten() {
+                    let device_path = entry.path();
+                    if device_path.is_dir() {
+                        // Check if this looks like a bootable OS
+                        if let Ok(os) = self.identify_os_from_boot_partition(&device_path.to_string_lossy()) {
+                            self.available_systems.push(os);
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// يحدد هوية النظام تحت `boot_path` عبر `distro_detect::detect_linux_distro`
+    /// (os-release/lsb-release/ملفات التوزيعة) و`distro_detect::classify_os_type`
+    /// (نفس منطق `os_manager::detect_os_type`)، بدل تخمين الاسم من وجود ملفات
+    /// علامة فقط كما كان سابقاً. `root_uuid` يُستجوب عبر `blkid` على جهاز
+    /// الكتلة الداعم للمسار (يتطلب أن يكون `boot_path` ممنتقاً فعلاً، وهو
+    /// الحال دوماً هنا بما أن المسارات تأتي من `scan_boot_partitions`/
+    /// `scan_usb_devices`). الاسم يُبنى من `os_type` دائماً، والوصف من
+    /// `distro.pretty_name` إن توفر، وإلا فمن `os_type` كما في النسخة القديمة.
+    fn identify_os_from_boot_partition(&self, boot_path: &str) -> Result<OperatingSystem> {
+        let boot_path = std::path::Path::new(boot_path);
+        let distro = distro_detect::detect_linux_distro(boot_path);
+        let os_type = distro_detect::classify_os_type(boot_path, distro.as_ref());
+        let root_uuid = distro_detect::probe_mounted_partition(boot_path).and_then(|p| p.uuid);
+
+        if matches!(os_type, OSType::Unknown) && distro.is_none() {
+            return Err(anyhow::anyhow!("Unknown OS type"));
+        }
+
+        let (name, description) = match os_type {
+            OSType::RetroPie => ("RetroPie".to_string(), "Retro Gaming System".to_string()),
+            OSType::Batocera => ("Batocera".to_string(), "Retro Gaming Distribution".to_string()),
+            OSType::Recalbox => ("Recalbox".to_string(), "Retro Gaming OS".to_string()),
+            OSType::RaspberryPiOS => (
+                "Raspberry Pi OS".to_string(),
+                "Official Raspberry Pi Operating System".to_string(),
+            ),
+            OSType::Ubuntu => ("Linux System".to_string(), "General Linux Distribution".to_string()),
+            OSType::Debian => ("Linux System".to_string(), "General Linux Distribution".to_string()),
+            OSType::Unknown => (
+                "Linux System".to_string(),
+                "General Linux Distribution".to_string(),
+            ),
+        };
+        let description = distro
+            .as_ref()
+            .and_then(|d| d.pretty_name.clone())
+            .unwrap_or(description);
+
+        Ok(OperatingSystem {
+            name,
+            path: boot_path.to_string_lossy().to_string(),
+            description,
+            os_type,
+            is_bootable: true,
+            last_used: None,
+            target_arch: None,
+            version: distro.as_ref().and_then(|d| d.version_id.clone()),
+            distro,
+            manifest: None,
+            rom_libraries: Vec::new(),
+            boot_count: 0,
+            last_boot_outcome: None,
+            root_uuid,
+        })
+    }
+
+    /// يحدد هوية صورة القرص تحت `image_path`. الصور غير ممنتقة، لذا لا يصح
+    /// قراءة `/etc/os-release` من مسار حقيقي ولا استجواب `blkid` عبر
+    /// `/proc/mounts` كما في `identify_os_from_boot_partition` - بدلاً من
+    /// ذلك تُقرأ هذه البيانات من داخل نظام ملفات FAT المضمَّن في الصورة عبر
+    /// `os_manager::read_os_release_from_fat_image` (بافتراض الصورة جذر
+    /// FAT مسطح بلا جدول أقسام، وهو الافتراض القائم في بقية هذه الوحدة).
+    /// تسقط إلى تخمين الاسم من الملف عند غياب `os-release` أو فشل فتح
+    /// الصورة كـ FAT (الحال الشائع: صور بجذر ext4).
+    fn identify_os_from_image(&self, image_path: &std::path::Path) -> Result<OperatingSystem> {
+        let filename = image_path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        let image_path_str = image_path.to_string_lossy();
+        let distro = crate::bootloader::os_manager::OSManager::read_os_release_from_fat_image(&image_path_str);
+
+        let filename_lower = filename.to_lowercase();
+
+        // os-release المقروء من داخل الصورة أدق من اسم الملف، فيُفضَّل عليه؛
+        // أسماء الألعاب الترفيهية تبقى استثناء (os-release لأنظمتها يُعرّف
+        // عادة التوزيعة الأساسية Raspbian/Debian لا "RetroPie" نفسها).
+        let (name, os_type, description) = if filename_lower.contains("retropie") {
+            ("RetroPie Image".to_string(), OSType::RetroPie, "RetroPie OS Image".to_string())
+        } else if filename_lower.contains("batocera") {
+            ("Batocera Image".to_string(), OSType::Batocera, "Batocera OS Image".to_string())
+        } else if filename_lower.contains("recalbox") {
+            ("Recalbox Image".to_string(), OSType::Recalbox, "Recalbox OS Image".to_string())
+        } else if let Some(distro) = &distro {
+            let os_type = distro_detect::classify_os_type(image_path, Some(distro));
+            (
+                distro.pretty_name.clone().unwrap_or_else(|| format!("OS Image: {}", filename)),
+                os_type,
+                distro.pretty_name.clone().unwrap_or_else(|| "Linux OS Image".to_string()),
+            )
+        } else if filename_lower.contains("raspios") || filename_lower.contains("raspberry") {
+            ("Raspberry Pi OS Image".to_string(), OSType::RaspberryPiOS, "Raspberry Pi OS Image".to_string())
+        } else {
+            (format!("OS Image: {}", filename), OSType::Unknown, "Unknown OS Image".to_string())
+        };
+
+        Ok(OperatingSystem {
+            name,
+            path: image_path.to_string_lossy().to_string(),
+            description,
+            os_type,
+            is_bootable: true,
+            last_used: None,
+            target_arch: None,
+            version: distro.as_ref().and_then(|d| d.version_id.clone()),
+            distro,
+            manifest: None,
+            rom_libraries: Vec::new(),
+            boot_count: 0,
+            last_boot_outcome: None,
+            root_uuid: None,
+        })
+    }
+
+    async fn auto_boot_default(&self, default_os: &str) -> Result<()> {
+        info!("Auto-booting default OS: {}", default_os);
+        
+        // Find the default OS
+        if let Some(os) = self.available_systems.iter().find(|os| os.name == default_os) {
+            info!("Booting into {}", os.name);
+            self.boot_operating_system(os).await?;
+        } else {
+            warn!("Default OS '{}' not found, showing menu", default_os);
+            self.show_interactive_menu().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn show_interactive_menu(&self) -> Result<()> {
+        info!("=== Interactive Boot Menu ===");
+        self.run_navigable_menu().await
+    }
+
+    fn display_menu_header(&self, console: &mut crate::bootloader::console::Console) {
+        console.writeln("\n🎮 DOS Safar Boot Manager 🎮");
+        console.writeln(&format!("Device: {}", self.device_info.model));
+        if self.device_info.gaming_features.has_built_in_screen {
+            console.writeln(&format!(
+                "Screen: {}\"",
+                self.device_info.gaming_features.screen_size_inches.unwrap_or(3.5)
+            ));
+        }
+        console.writeln("═══════════════════════════════════════");
+    }
+
+    fn display_menu_item(
+        &self,
+        console: &mut crate::bootloader::console::Console,
+        index: usize,
+        os: &OperatingSystem,
+        highlighted: bool,
+    ) {
+        let icon = match os.os_type {
+            OSType::RetroPie => "🕹️",
+            OSType::Batocera => "🎯",
+            OSType::Recalbox => "📦",
+            OSType::RaspberryPiOS => "🍓",
+            OSType::Ubuntu => "🐧",
+            OSType::Debian => "🌊",
+            OSType::Unknown => "❓",
+        };
+
+        let line = format!("  {}. {} {} - {}", index, icon, os.name, os.description);
+        if highlighted {
+            console.writeln(&format!("\x1b[7m{}\x1b[0m", line));
+        } else {
+            console.writeln(&line);
+        }
+
+        if let Some(last_used) = os.last_used {
+            console.writeln(&format!("     Last used: {}", last_used.format("%Y-%m-%d %H:%M")));
+        }
+    }
+
+    fn display_menu_footer(&self, console: &mut crate::bootloader::console::Console, remaining_seconds: u64) {
+        console.writeln("═══════════════════════════════════════");
+        console.writeln("  A. Advanced Options");
+        console.writeln("  W. Web Interface");
+        console.writeln("  R. Restart Hardware Tests");
+        console.writeln("  S. Shutdown");
+        console.writeln("  D. Set Highlighted as Default");
+        console.writeln("═══════════════════════════════════════");
+        console.writeln("↑/↓ or j/k to move, Enter to select");
+        console.writeln(&format!("⏱️  Auto-boot highlighted entry in {} seconds...", remaining_seconds));
+    }
+
+    /// يعيد رسم القائمة كاملة مع تظليل العنصر المختار حالياً، بعد مسح
+    /// الشاشة حتى لا تتراكم الإطارات السابقة مع كل نبضة عدّاد تنازلي.
+    fn render_menu(&self, console: &mut crate::bootloader::console::Console, selected: usize, remaining_seconds: u64) {
+        console.write("\x1b[2J\x1b[H");
+        self.display_menu_header(console);
+        for (index, os) in self.available_systems.iter().enumerate() {
+            self.display_menu_item(console, index + 1, os, index == selected);
+        }
+        self.display_menu_footer(console, remaining_seconds);
+    }
+
+    /// قائمة تفاعلية حقيقية: وضع الطرفية الخام + قارئ مفاتيح في خيط منفصل
+    /// (يراقب أيضاً جهاز الطرفية التسلسلية إن كان مفعّلاً)، مع عدّاد تنازلي
+    /// يُعاد ضبطه عند أي ضغطة، وإقلاع العنصر المظلَّل (لا الأول بالضرورة)
+    /// عند انتهاء المهلة.
+    async fn run_navigable_menu(&self) -> Result<()> {
+        let _raw_mode = crate::bootloader::terminal::RawTerminal::enable()
+            .context("فشل تفعيل الوضع الخام للطرفية")?;
+        let mut console = crate::bootloader::console::Console::new(&self.config.serial_console);
+        let mut keys = crate::bootloader::terminal::spawn_key_reader(console.serial_fd());
+
+        let mut selected = 0usize;
+        let mut remaining = self.config.boot.menu_timeout_seconds;
+        self.render_menu(&mut console, selected, remaining);
+
+        loop {
+            tokio::select! {
+                _ = sleep(Duration::from_secs(1)) => {
+                    if remaining == 0 {
+                        info!("⏱️ انتهت المهلة - إقلاع العنصر المظلَّل");
+                        return self.handle_selection(MenuSelection::BootOS(selected)).await;
+                    }
+                    remaining -= 1;
+                    self.render_menu(&mut console, selected, remaining);
+                }
+                key = keys.recv() => {
+                    use crate::bootloader::terminal::Key;
+                    match key {
+                        Some(Key::Up) => {
+                            selected = selected.checked_sub(1).unwrap_or(self.available_systems.len() - 1);
+                            remaining = self.config.boot.menu_timeout_seconds;
+                            self.render_menu(&mut console, selected, remaining);
+                        }
+                        Some(Key::Down) => {
+                            selected = (selected + 1) % self.available_systems.len();
+                            remaining = self.config.boot.menu_timeout_seconds;
+                            self.render_menu(&mut console, selected, remaining);
+                        }
+                        Some(Key::Select) => {
+                            return self.handle_selection(MenuSelection::BootOS(selected)).await;
+                        }
+                        Some(Key::Advanced) => {
+                            return self.handle_selection(MenuSelection::AdvancedOptions).await;
+                        }
+                        Some(Key::Web) => {
+                            return self.handle_selection(MenuSelection::WebInterface).await;
+                        }
+                        Some(Key::RestartTests) => {
+                            return self.handle_selection(MenuSelection::RestartTests).await;
+                        }
+                        Some(Key::Shutdown) => {
+                            return self.handle_selection(MenuSelection::Shutdown).await;
+                        }
+                        Some(Key::SetDefault) => {
+                            if let Some(os) = self.available_systems.get(selected) {
+                                let os_name = os.name.clone();
+                                if let Err(e) = self.set_default_os(&os_name).await {
+                                    warn!("⚠️ فشل في تعيين {} كنظام افتراضي: {}", os_name, e);
+                                }
+                            }
+                            remaining = self.config.boot.menu_timeout_seconds;
+                            self.render_menu(&mut console, selected, remaining);
+                        }
+                        Some(Key::Other(_)) => {
+                            remaining = self.config.boot.menu_timeout_seconds;
+                        }
+                        None => {
+                            // أُغلقت قناة المفاتيح (فشل قراءة stdin) - نكمل
+                            // بالاعتماد على العدّاد التنازلي وحده.
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle_selection(&self, selection: MenuSelection) -> Result<()> {
+        match selection {
+            MenuSelection::BootOS(index) => {
+                if let Some(os) = self.available_systems.get(index) {
+                    self.boot_operating_system(os).await?;
+                } else {
+                    warn!("Invalid OS selection: {}", index);
+                }
+            }
+            MenuSelection::AdvancedOptions => {
+                self.show_advanced_menu().await?;
+            }
+            MenuSelection::WebInterface => {
+                info!("Web interface is already running");
+                println!("Web interface available at: http://localhost:8080");
+            }
+            MenuSelection::RestartTests => {
+                info!("Restarting hardware tests...");
+                // This would restart the hardware testing process
+            }
+            MenuSelection::Shutdown => {
+                info!("Shutting down system...");
+                self.shutdown_system().await?;
+            }
+            MenuSelection::InstallOS => {
+                self.install_os_interactive().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// يبحث عن نظام باسم `os_name` ضمن `available_systems` ويقلعه عبر
+    /// `boot_operating_system` - لاستدعاءات بعيدة (`remote::web_server`,
+    /// `remote::mqtt`) لا تملك سوى اسم النظام، لا `OperatingSystem` كاملاً.
+    pub async fn boot_by_name(&self, os_name: &str) -> Result<()> {
+        let os = self
+            .available_systems
+            .iter()
+            .find(|os| os.name == os_name)
+            .ok_or_else(|| anyhow::anyhow!("لم يُعثر على نظام باسم {}", os_name))?;
+
+        self.boot_operating_system(os).await
+    }
+
+    async fn boot_operating_system(&self, os: &OperatingSystem) -> Result<()> {
+        info!("🚀 Booting into: {}", os.name);
+
+        // Save boot selection
+        self.save_boot_selection(os).await?;
+
+        // Apply any hardware configurations
+        self.apply_hardware_config_for_os(os).await?;
+
+        // Perform the actual boot
+        let boot_result = match os.os_type {
+            OSType::RetroPie | OSType::Batocera | OSType::Recalbox => {
+                self.boot_gaming_os(os).await
+            }
+            OSType::RaspberryPiOS | OSType::Ubuntu | OSType::Debian => {
+                self.boot_standard_os(os).await
+            }
+            OSType::Unknown => {
+                self.boot_unknown_os(os).await
+            }
+        };
+
+        // A successful `kexec` never returns here (the running process is
+        // replaced), so reaching this point with an error is the only
+        // chance to correct the optimistic `Success` that `save_boot_selection`
+        // recorded before the jump.
+        if boot_result.is_err() {
+            self.record_boot_failure(os).await;
+        }
+
+        boot_result
+    }
+
+    async fn boot_gaming_os(&self, os: &OperatingSystem) -> Result<()> {
+        info!("Booting gaming OS: {}", os.name);
+
+        println!("🎮 Loading {} system...", os.name);
+        crate::bootloader::kexec::kexec_boot(&os.path, &os.os_type, os.root_uuid.as_deref()).await
+    }
+
+    async fn boot_standard_os(&self, os: &OperatingSystem) -> Result<()> {
+        info!("Booting standard OS: {}", os.name);
+
+        println!("🐧 Loading {} system...", os.name);
+        crate::bootloader::kexec::kexec_boot(&os.path, &os.os_type, os.root_uuid.as_deref()).await
+    }
+
+    async fn boot_unknown_os(&self, os: &OperatingSystem) -> Result<()> {
+        info!("Booting unknown OS: {}", os.name);
+
+        println!("❓ Loading system from {}...", os.path);
+        crate::bootloader::kexec::kexec_boot(&os.path, &os.os_type, os.root_uuid.as_deref()).await
+    }
+
+    async fn show_advanced_menu(&self) -> Result<()> {
+        println!("\n=== Advanced Options ===");
+        println!("1. Install New OS");
+        println!("2. Remove OS");
+        println!("3. Hardware Configuration");
+        println!("4. Network Settings");
+        println!("5. Back to Main Menu");
+        
+        // For now, just return to main menu
+        self.show_interactive_menu().await
+    }
+
+    async fn show_no_os_menu(&self) -> Result<()> {
+        println!("\n⚠️  No Operating Systems Found!");
+        println!("═══════════════════════════════════");
+        println!("Options:");
+        println!("1. 🌐 Download OS images via web interface");
+        println!("2. 🔍 Rescan for OS images");
+        println!("3. 📁 Check connected USB drives");
+        println!("4. ⚡ Emergency shell");
+        println!("5. 💾 Flash an OS image to storage");
+        println!("═══════════════════════════════════");
+
+        let mut choice = String::new();
+        std::io::stdin()
+            .read_line(&mut choice)
+            .context("Failed to read menu choice")?;
+
+        if choice.trim() == "5" {
+            return self.handle_selection(MenuSelection::InstallOS).await;
+        }
+
+        // Start web interface for OS installation
+        println!("💡 Starting web interface for OS management...");
+        println!("Visit: http://localhost:8080 to install operating systems");
+
+        Ok(())
+    }
+
+    /// يقرأ مسار الصورة وجهاز التخزين الهدف من المستخدم مباشرة ثم يستدعي
+    /// `installer::flash_image_to_device`، طابعاً تقدّم الفلاشينغ بالبايت.
+    async fn install_os_interactive(&self) -> Result<()> {
+        use std::io::Write;
+
+        println!("💾 فلاشينغ صورة نظام تشغيل إلى جهاز تخزين");
+        print!("مسار صورة النظام (.img / .img.gz / .img.xz): ");
+        std::io::stdout().flush().ok();
+        let mut image_path = String::new();
+        std::io::stdin()
+            .read_line(&mut image_path)
+            .context("Failed to read image path")?;
+
+        print!("جهاز التخزين الهدف (مثال: /dev/mmcblk0): ");
+        std::io::stdout().flush().ok();
+        let mut device_path = String::new();
+        std::io::stdin()
+            .read_line(&mut device_path)
+            .context("Failed to read target device")?;
+
+        let image_path = std::path::PathBuf::from(image_path.trim());
+        let device_path = std::path::PathBuf::from(device_path.trim());
+
+        let progress = |event: crate::bootloader::installer::FlashProgress| match event.total_bytes {
+            Some(total) => println!("  {} / {} بايت", event.bytes_written, total),
+            None => println!("  {} بايت مكتوبة", event.bytes_written),
+        };
+
+        match crate::bootloader::installer::flash_image_to_device(
+            &image_path,
+            &device_path,
+            None,
+            Some(&progress),
+        )
+        .await
+        {
+            Ok(()) => println!("✅ تم الفلاشينغ والتحقق منه بنجاح"),
+            Err(e) => println!("❌ فشل الفلاشينغ: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// يحدّث `boot_state::BootStateStore` لـ `os` (آخر استخدام + عدّاد
+    /// الإقلاعات) ويكتبه ذرّياً قبل استدعاء `kexec` مباشرة - أخطر لحظة ممكنة
+    /// لترك الملف نصف مكتوب.
+    async fn save_boot_selection(&self, os: &OperatingSystem) -> Result<()> {
+        info!("💾 Saving boot selection: {}", os.name);
+
+        let path = boot_state::state_file_path(&self.config);
+        let mut store = boot_state::BootStateStore::load(&path);
+        store.record_attempt(os);
+        store.save(&path)
+    }
+
+    /// يصحّح حالة `os` إلى فشل بعد أن أخفق `kexec_boot` فعلياً قبل القفز.
+    async fn record_boot_failure(&self, os: &OperatingSystem) {
+        let path = boot_state::state_file_path(&self.config);
+        let mut store = boot_state::BootStateStore::load(&path);
+        store.record_failure(os);
+        if let Err(e) = store.save(&path) {
+            warn!("⚠️ فشل في تحديث حالة فشل الإقلاع لـ {}: {}", os.name, e);
+        }
+    }
+
+    /// يكتب اسم النظام المختار إلى `config.boot.default_os` في ملف التكوين
+    /// على القرص، بحيث يصبح `auto_boot_default` (عبر `show_menu`) ملتزماً به
+    /// عبر إعادة التشغيلات القادمة دون إعادة اختياره يدوياً في كل مرة.
+    async fn set_default_os(&self, os_name: &str) -> Result<()> {
+        let mut config = self.config.clone();
+        config.boot.default_os = Some(os_name.to_string());
+        config
+            .save(&self.config_path)
+            .with_context(|| format!("فشل في حفظ {} كنظام افتراضي في ملف التكوين", os_name))?;
+
+        info!("⭐ تم تعيين {} كنظام افتراضي دائم", os_name);
+        Ok(())
+    }
+
+    async fn apply_hardware_config_for_os(&self, os: &OperatingSystem) -> Result<()> {
+        let boot_dir = std::path::Path::new(&os.path);
+        if !boot_dir.join("config.txt").exists() || !boot_dir.join("cmdline.txt").exists() {
+            info!(
+                "لا توجد ملفات config.txt/cmdline.txt تحت {} - تخطي إعداد الإقلاع الخاص بالجهاز",
+                os.path
+            );
+            return Ok(());
+        }
+
+        match os.os_type {
+            OSType::RetroPie | OSType::Batocera | OSType::Recalbox => {
+                info!("Applying gaming hardware configuration");
+                crate::bootloader::boot_config::apply_boot_config_for_gaming(
+                    boot_dir,
+                    &self.device_info.gaming_features,
+                )?;
+            }
+            _ => {
+                info!("Applying standard hardware configuration");
+                crate::bootloader::boot_config::apply_boot_config_for_standard(boot_dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn shutdown_system(&self) -> Result<()> {
+        println!("💤 Shutting down DOS Safar...");
+        
+        // Graceful shutdown
+        std::process::Command::new("shutdown")
+            .args(&["-h", "now"])
+            .output()
+            .context("Failed to shutdown system")?;
+        
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum MenuSelection {
+    BootOS(usize),
+    AdvancedOptions,
+    WebInterface,
+    RestartTests,
+    Shutdown,
+    InstallOS,
 }
\ No newline at end of file
@@ -1,6 +1,17 @@
+pub mod boot_config;
+pub mod boot_state;
+pub mod console;
+pub mod distro_detect;
+pub mod installer;
+pub mod kexec;
 pub mod menu;
 pub mod os_manager;
-pub mod boot_config;
+pub mod progress;
+pub mod rom_scanner;
+pub mod terminal;
 
 // Re-export commonly used types
-pub use menu::{BootMenu, OperatingSystem};
\ No newline at end of file
+pub use distro_detect::LinuxDistroInfo;
+pub use menu::{BootMenu, OperatingSystem};
+pub use progress::{ProgressEvent, ProgressReporter};
+pub use rom_scanner::RomLibrary;
\ No newline at end of file
@@ -1,1012 +1,3556 @@
-// OS management functions 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::{Path, PathBuf};
-use std::process::Command;
-use tokio::process::Command as AsyncCommand;
-use tokio::fs as async_fs;
-use tracing::{info, warn, error, debug};
-use crate::bootloader::menu::{OperatingSystem, OSType};
-use crate::utils::config::Config;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OSManager {
-    config: Config,
-    os_storage_path: PathBuf,
-    boot_partition_path: PathBuf,
-    backup_path: PathBuf,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OSInstallationProgress {
-    pub stage: InstallationStage,
-    pub progress_percentage: f32,
-    pub current_operation: String,
-    pub estimated_time_remaining: Option<u64>, // seconds
-    pub error_message: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum InstallationStage {
-    Preparing,
-    Downloading,
-    Extracting,
-    Installing,
-    Configuring,
-    Testing,
-    Finalizing,
-    Completed,
-    Failed,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OSBackup {
-    pub os_name: String,
-    pub backup_date: chrono::DateTime<chrono::Utc>,
-    pub backup_size_mb: u64,
-    pub backup_path: String,
-    pub is_bootable: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OSImage {
-    pub name: String,
-    pub file_path: String,
-    pub size_mb: u64,
-    pub os_type: OSType,
-    pub checksum: Option<String>,
-    pub is_compressed: bool,
-    pub supported_devices: Vec<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct BootConfiguration {
-    pub default_os: Option<String>,
-    pub timeout_seconds: u64,
-    pub available_systems: Vec<OperatingSystem>,
-    pub boot_order: Vec<String>,
-    pub recovery_mode: bool,
-}
-
-impl OSManager {
-    pub fn new(config: &Config) -> Result<Self> {
-        let os_storage_path = PathBuf::from("/boot/dos_safar/systems");
-        let boot_partition_path = PathBuf::from("/boot");
-        let backup_path = PathBuf::from("/boot/dos_safar/backups");
-
-        // إنشاء المجلدات المطلوبة
-        fs::create_dir_all(&os_storage_path)
-            .context("فشل في إنشاء مجلد أنظمة التشغيل")?;
-        fs::create_dir_all(&backup_path)
-            .context("فشل في إنشاء مجلد النسخ الاحتياطية")?;
-
-        Ok(OSManager {
-            config: config.clone(),
-            os_storage_path,
-            boot_partition_path,
-            backup_path,
-        })
-    }
-
-    /// تثبيت نظام تشغيل من صورة
-    pub async fn install_os_from_image(&self, image_path: &str, os_name: &str) -> Result<()> {
-        info!("🔧 بدء تثبيت {} من {}", os_name, image_path);
-
-        // التحقق من وجود الصورة
-        if !Path::new(image_path).exists() {
-            return Err(anyhow::anyhow!("الصورة {} غير موجودة", image_path));
-        }
-
-        // تحضير مجلد التثبيت
-        let install_path = self.os_storage_path.join(os_name);
-        if install_path.exists() {
-            warn!("النظام {} موجود مسبقاً، سيتم الاستبدال", os_name);
-            fs::remove_dir_all(&install_path)
-                .context("فشل في حذف النظام القديم")?;
-        }
-
-        fs::create_dir_all(&install_path)
-            .context("فشل في إنشاء مجلد التثبيت")?;
-
-        // تحديد نوع الصورة والتثبيت المناسب
-        let image_type = self.detect_image_type(image_path)?;
-        
-        match image_type {
-            ImageType::ISO => self.install_from_iso(image_path, &install_path).await?,
-            ImageType::IMG => self.install_from_img(image_path, &install_path).await?,
-            ImageType::TAR => self.install_from_tar(image_path, &install_path).await?,
-            ImageType::ZIP => self.install_from_zip(image_path, &install_path).await?,
-        }
-
-        // تكوين نظام التشغيل المثبت
-        self.configure_installed_os(&install_path, os_name).await?;
-
-        // إضافة إلى قائمة الأنظمة المتاحة
-        self.register_os(os_name, &install_path).await?;
-
-        info!("✅ تم تثبيت {} بنجاح", os_name);
-        Ok(())
-    }
-
-    /// تثبيت نظام تشغيل من URL
-    pub async fn install_os_from_url(&self, url: &str, os_name: &str) -> Result<()> {
-        info!("📥 تحميل وتثبيت {} من {}", os_name, url);
-
-        // تحميل الصورة
-        let temp_path = format!("/tmp/{}.img", os_name);
-        self.download_os_image(url, &temp_path).await?;
-
-        // تثبيت من الملف المحمل
-        self.install_os_from_image(&temp_path, os_name).await?;
-
-        // حذف الملف المؤقت
-        let _ = fs::remove_file(&temp_path);
-
-        Ok(())
-    }
-
-    /// إنشاء نسخة احتياطية من نظام تشغيل
-    pub async fn backup_os(&self, os_name: &str) -> Result<OSBackup> {
-        info!("💾 إنشاء نسخة احتياطية من {}", os_name);
-
-        let os_path = self.os_storage_path.join(os_name);
-        if !os_path.exists() {
-            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
-        }
-
-        let backup_name = format!("{}_{}", os_name, chrono::Utc::now().format("%Y%m%d_%H%M%S"));
-        let backup_file = self.backup_path.join(format!("{}.tar.gz", backup_name));
-
-        // إنشاء الأرشيف
-        let output = Command::new("tar")
-            .args(&[
-                "-czf", 
-                backup_file.to_str().unwrap(),
-                "-C", 
-                self.os_storage_path.to_str().unwrap(),
-                os_name
-            ])
-            .output()
-            .context("فشل في تنفيذ أمر tar")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("فشل في إنشاء النسخة الاحتياطية: {}", error));
-        }
-
-        // حساب حجم النسخة الاحتياطية
-        let backup_size = fs::metadata(&backup_file)
-            .context("فشل في قراءة حجم النسخة الاحتياطية")?
-            .len() / 1024 / 1024; // تحويل إلى MB
-
-        let backup = OSBackup {
-            os_name: os_name.to_string(),
-            backup_date: chrono::Utc::now(),
-            backup_size_mb: backup_size,
-            backup_path: backup_file.to_string_lossy().to_string(),
-            is_bootable: true, // سنفترض أنه قابل للتشغيل
-        };
-
-        info!("✅ تم إنشاء نسخة احتياطية من {} ({}MB)", os_name, backup_size);
-        Ok(backup)
-    }
-
-    /// استعادة نظام من نسخة احتياطية
-    pub async fn restore_os_from_backup(&self, backup: &OSBackup) -> Result<()> {
-        info!("🔄 استعادة {} من النسخة الاحتياطية", backup.os_name);
-
-        let backup_path = Path::new(&backup.backup_path);
-        if !backup_path.exists() {
-            return Err(anyhow::anyhow!("النسخة الاحتياطية غير موجودة"));
-        }
-
-        // حذف النظام الحالي إذا كان موجوداً
-        let os_path = self.os_storage_path.join(&backup.os_name);
-        if os_path.exists() {
-            fs::remove_dir_all(&os_path)
-                .context("فشل في حذف النظام الحالي")?;
-        }
-
-        // استخراج النسخة الاحتياطية
-        let output = Command::new("tar")
-            .args(&[
-                "-xzf",
-                backup.backup_path.as_str(),
-                "-C",
-                self.os_storage_path.to_str().unwrap()
-            ])
-            .output()
-            .context("فشل في تنفيذ أمر استخراج")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("فشل في استعادة النسخة الاحتياطية: {}", error));
-        }
-
-        // إعادة تسجيل النظام
-        self.register_os(&backup.os_name, &os_path).await?;
-
-        info!("✅ تم استعادة {} بنجاح", backup.os_name);
-        Ok(())
-    }
-
-    /// حذف نظام تشغيل
-    pub async fn remove_os(&self, os_name: &str, create_backup: bool) -> Result<()> {
-        info!("🗑️ حذف نظام التشغيل: {}", os_name);
-
-        let os_path = self.os_storage_path.join(os_name);
-        if !os_path.exists() {
-            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
-        }
-
-        // إنشاء نسخة احتياطية قبل الحذف إذا طُلب ذلك
-        if create_backup {
-            info!("💾 إنشاء نسخة احتياطية قبل الحذف");
-            self.backup_os(os_name).await?;
-        }
-
-        // حذف النظام
-        fs::remove_dir_all(&os_path)
-            .context("فشل في حذف مجلد النظام")?;
-
-        // إزالة من قائمة الأنظمة المتاحة
-        self.unregister_os(os_name).await?;
-
-        info!("✅ تم حذف {} بنجاح", os_name);
-        Ok(())
-    }
-
-    /// إعداد النظام الافتراضي للتشغيل
-    pub async fn set_default_os(&self, os_name: &str) -> Result<()> {
-        info!("⚙️ تعيين {} كنظام افتراضي", os_name);
-
-        // التحقق من وجود النظام
-        if !self.os_exists(os_name) {
-            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
-        }
-
-        // تحديث ملف التكوين
-        let mut boot_config = self.load_boot_configuration().await?;
-        boot_config.default_os = Some(os_name.to_string());
-        self.save_boot_configuration(&boot_config).await?;
-
-        info!("✅ تم تعيين {} كنظام افتراضي", os_name);
-        Ok(())
-    }
-
-    /// الحصول على قائمة الأنظمة المتاحة
-    pub async fn get_available_systems(&self) -> Result<Vec<OperatingSystem>> {
-        debug!("📋 جمع قائمة الأنظمة المتاحة");
-
-        let mut systems = Vec::new();
-
-        // مسح مجلد أنظمة التشغيل
-        if self.os_storage_path.exists() {
-            let entries = fs::read_dir(&self.os_storage_path)
-                .context("فشل في قراءة مجلد الأنظمة")?;
-
-            for entry in entries.flatten() {
-                if entry.path().is_dir() {
-                    if let Ok(os) = self.analyze_os_directory(&entry.path()).await {
-                        systems.push(os);
-                    }
-                }
-            }
-        }
-
-        // مسح أنظمة إضافية في مواقع أخرى
-        systems.extend(self.scan_external_systems().await?);
-
-        // ترتيب حسب آخر استخدام
-        systems.sort_by(|a, b| {
-            match (&a.last_used, &b.last_used) {
-                (Some(a_time), Some(b_time)) => b_time.cmp(a_time),
-                (Some(_), None) => std::cmp::Ordering::Less,
-                (None, Some(_)) => std::cmp::Ordering::Greater,
-                (None, None) => a.name.cmp(&b.name),
-            }
-        });
-
-        Ok(systems)
-    }
-
-    /// الحصول على قائمة النسخ الاحتياطية
-    pub async fn get_backups(&self) -> Result<Vec<OSBackup>> {
-        debug!("📦 جمع قائمة النسخ الاحتياطية");
-
-        let mut backups = Vec::new();
-
-        if self.backup_path.exists() {
-            let entries = fs::read_dir(&self.backup_path)
-                .context("فشل في قراءة مجلد النسخ الاحتياطية")?;
-
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("gz") {
-                    if let Ok(backup) = self.analyze_backup_file(&path).await {
-                        backups.push(backup);
-                    }
-                }
-            }
-        }
-
-        // ترتيب حسب التاريخ (الأحدث أولاً)
-        backups.sort_by(|a, b| b.backup_date.cmp(&a.backup_date));
-
-        Ok(backups)
-    }
-
-    /// تحديث نظام تشغيل موجود
-    pub async fn update_os(&self, os_name: &str, update_source: &str) -> Result<()> {
-        info!("🔄 تحديث نظام {}", os_name);
-
-        // إنشاء نسخة احتياطية قبل التحديث
-        let backup = self.backup_os(os_name).await?;
-        info!("💾 تم إنشاء نسخة احتياطية: {}", backup.backup_path);
-
-        // محاولة التحديث
-        match self.perform_os_update(os_name, update_source).await {
-            Ok(_) => {
-                info!("✅ تم تحديث {} بنجاح", os_name);
-                Ok(())
-            }
-            Err(e) => {
-                error!("❌ فشل في تحديث {}: {}", os_name, e);
-                
-                // استعادة النسخة الاحتياطية عند الفشل
-                warn!("🔄 استعادة النسخة الاحتياطية");
-                self.restore_os_from_backup(&backup).await?;
-                
-                Err(e)
-            }
-        }
-    }
-
-    /// تحسين أداء نظام تشغيل
-    pub async fn optimize_os(&self, os_name: &str) -> Result<()> {
-        info!("⚡ تحسين أداء {}", os_name);
-
-        let os_path = self.os_storage_path.join(os_name);
-        if !os_path.exists() {
-            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
-        }
-
-        // تنظيف الملفات المؤقتة
-        self.cleanup_temporary_files(&os_path).await?;
-
-        // تحسين قاعدة البيانات (إذا وجدت)
-        self.optimize_databases(&os_path).await?;
-
-        // ضغط الملفات غير المستخدمة
-        self.compress_unused_files(&os_path).await?;
-
-        // تحديث فهرس الملفات
-        self.update_file_index(&os_path).await?;
-
-        info!("✅ تم تحسين {} بنجاح", os_name);
-        Ok(())
-    }
-
-    // =====================================
-    // وظائف مساعدة داخلية
-    // =====================================
-
-    fn detect_image_type(&self, image_path: &str) -> Result<ImageType> {
-        let path = Path::new(image_path);
-        let extension = path.extension()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| anyhow::anyhow!("لا يمكن تحديد نوع الصورة"))?
-            .to_lowercase();
-
-        match extension.as_str() {
-            "iso" => Ok(ImageType::ISO),
-            "img" => Ok(ImageType::IMG),
-            "tar" | "tgz" => Ok(ImageType::TAR),
-            "zip" => Ok(ImageType::ZIP),
-            _ => {
-                // محاولة تحديد النوع من محتوى الملف
-                self.detect_image_type_by_content(image_path)
-            }
-        }
-    }
-
-    fn detect_image_type_by_content(&self, image_path: &str) -> Result<ImageType> {
-        let output = Command::new("file")
-            .arg(image_path)
-            .output()
-            .context("فشل في تحديد نوع الملف")?;
-
-        let file_info = String::from_utf8_lossy(&output.stdout).to_lowercase();
-
-        if file_info.contains("iso") {
-            Ok(ImageType::ISO)
-        } else if file_info.contains("tar") {
-            Ok(ImageType::TAR)
-        } else if file_info.contains("zip") {
-            Ok(ImageType::ZIP)
-        } else {
-            Ok(ImageType::IMG) // افتراضي
-        }
-    }
-
-    async fn install_from_iso(&self, iso_path: &str, install_path: &Path) -> Result<()> {
-        info!("📀 تثبيت من ISO: {}", iso_path);
-
-        // إنشاء نقطة تحميل مؤقتة
-        let mount_point = format!("/tmp/dos_safar_mount_{}", 
-            std::process::id());
-        fs::create_dir_all(&mount_point)
-            .context("فشل في إنشاء نقطة التحميل")?;
-
-        // تحميل الـ ISO
-        let mount_output = Command::new("mount")
-            .args(&["-o", "loop", iso_path, &mount_point])
-            .output()
-            .context("فشل في تحميل ISO")?;
-
-        if !mount_output.status.success() {
-            let _ = fs::remove_dir(&mount_point);
-            return Err(anyhow::anyhow!("فشل في تحميل ISO"));
-        }
-
-        // نسخ المحتويات
-        let copy_result = Command::new("cp")
-            .args(&["-r", &format!("{}/*", mount_point), 
-                   install_path.to_str().unwrap()])
-            .output();
-
-        // إلغاء تحميل الـ ISO
-        let _ = Command::new("umount").arg(&mount_point).output();
-        let _ = fs::remove_dir(&mount_point);
-
-        match copy_result {
-            Ok(output) if output.status.success() => Ok(()),
-            Ok(output) => {
-                let error = String::from_utf8_lossy(&output.stderr);
-                Err(anyhow::anyhow!("فشل في نسخ الملفات: {}", error))
-            }
-            Err(e) => Err(anyhow::anyhow!("خطأ في تنفيذ الأمر: {}", e))
-        }
-    }
-
-    async fn install_from_img(&self, img_path: &str, install_path: &Path) -> Result<()> {
-        info!("💾 تثبيت من IMG: {}", img_path);
-
-        // نسخ صورة القرص مباشرة
-        let output = Command::new("dd")
-            .args(&[
-                &format!("if={}", img_path),
-                &format!("of={}/system.img", install_path.to_str().unwrap()),
-                "bs=4M",
-                "conv=fsync"
-            ])
-            .output()
-            .context("فشل في نسخ صورة القرص")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("فشل في نسخ IMG: {}", error));
-        }
-
-        // محاولة تحميل الصورة لاستخراج الملفات
-        self.extract_img_contents(install_path).await?;
-
-        Ok(())
-    }
-
-    async fn install_from_tar(&self, tar_path: &str, install_path: &Path) -> Result<()> {
-        info!("📦 تثبيت من TAR: {}", tar_path);
-
-        let output = Command::new("tar")
-            .args(&[
-                "-xf", tar_path,
-                "-C", install_path.to_str().unwrap()
-            ])
-            .output()
-            .context("فشل في استخراج TAR")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("فشل في استخراج TAR: {}", error));
-        }
-
-        Ok(())
-    }
-
-    async fn install_from_zip(&self, zip_path: &str, install_path: &Path) -> Result<()> {
-        info!("🗂️ تثبيت من ZIP: {}", zip_path);
-
-        let output = Command::new("unzip")
-            .args(&[
-                "-q", zip_path,
-                "-d", install_path.to_str().unwrap()
-            ])
-            .output()
-            .context("فشل في استخراج ZIP")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("فشل في استخراج ZIP: {}", error));
-        }
-
-        Ok(())
-    }
-
-    async fn extract_img_contents(&self, install_path: &Path) -> Result<()> {
-        let img_file = install_path.join("system.img");
-        if !img_file.exists() {
-            return Ok(()); // لا توجد صورة لاستخراجها
-        }
-
-        let mount_point = format!("/tmp/dos_safar_img_mount_{}", 
-            std::process::id());
-        fs::create_dir_all(&mount_point)
-            .context("فشل في إنشاء نقطة تحميل الصورة")?;
-
-        // محاولة تحميل الصورة
-        let mount_output = Command::new("mount")
-            .args(&["-o", "loop", img_file.to_str().unwrap(), &mount_point])
-            .output();
-
-        if let Ok(output) = mount_output {
-            if output.status.success() {
-                // نسخ المحتويات
-                let _ = Command::new("cp")
-                    .args(&["-r", &format!("{}/*", mount_point), 
-                           install_path.to_str().unwrap()])
-                    .output();
-
-                // إلغاء التحميل
-                let _ = Command::new("umount").arg(&mount_point).output();
-            }
-        }
-
-        let _ = fs::remove_dir(&mount_point);
-        Ok(())
-    }
-
-    async fn configure_installed_os(&self, install_path: &Path, os_name: &str) -> Result<()> {
-        info!("⚙️ تكوين النظام المثبت: {}", os_name);
-
-        // إنشاء ملف التكوين الخاص بالنظام
-        let config_file = install_path.join("dos_safar_config.toml");
-        let os_config = format!(
-            r#"[system]
-name = "{}"
-install_date = "{}"
-version = "1.0"
-bootable = true
-
-[hardware]
-auto_detect = true
-optimize_for_gaming = true
-
-[display]
-auto_resolution = true
-safe_mode = false
-"#,
-            os_name,
-            chrono::Utc::now().to_rfc3339()
-        );
-
-        fs::write(&config_file, os_config)
-            .context("فشل في كتابة ملف التكوين")?;
-
-        // تطبيق تحسينات خاصة بالجهاز
-        self.apply_device_optimizations(install_path).await?;
-
-        // إعداد البوت
-        self.setup_boot_configuration(install_path, os_name).await?;
-
-        Ok(())
-    }
-
-    async fn apply_device_optimizations(&self, install_path: &Path) -> Result<()> {
-        // تحسينات خاصة بـ Raspberry Pi
-        if self.is_raspberry_pi() {
-            self.apply_raspberry_pi_optimizations(install_path).await?;
-        }
-
-        // تحسينات خاصة بأجهزة الألعاب المحمولة
-        if self.is_gaming_handheld() {
-            self.apply_gaming_handheld_optimizations(install_path).await?;
-        }
-
-        Ok(())
-    }
-
-    async fn apply_raspberry_pi_optimizations(&self, install_path: &Path) -> Result<()> {
-        info!("🍓 تطبيق تحسينات Raspberry Pi");
-
-        // تكوين GPU memory split
-        let boot_config = install_path.join("config.txt");
-        if boot_config.exists() {
-            let mut config_content = fs::read_to_string(&boot_config)
-                .unwrap_or_default();
-
-            // إضافة تحسينات GPU
-            if !config_content.contains("gpu_mem") {
-                config_content.push_str("\n# DOS Safar GPU optimizations\n");
-                config_content.push_str("gpu_mem=128\n");
-                config_content.push_str("gpu_freq=500\n");
-                config_content.push_str("over_voltage=2\n");
-
-                fs::write(&boot_config, config_content)
-                    .context("فشل في تحديث config.txt")?;
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn apply_gaming_handheld_optimizations(&self, install_path: &Path) -> Result<()> {
-        info!("🎮 تطبيق تحسينات أجهزة الألعاب المحمولة");
-
-        // تحسينات خاصة بالشاشات الصغيرة
-        let display_config = install_path.join("display_config.txt");
-        let display_settings = r#"# Gaming Handheld Display Settings
-hdmi_force_hotplug=1
-hdmi_group=2
-hdmi_mode=87
-hdmi_cvt=480 320 60 6 0 0 0
-display_rotate=0
-"#;
-
-        fs::write(&display_config, display_settings)
-            .context("فشل في كتابة تكوين الشاشة")?;
-
-        Ok(())
-    }
-
-    async fn setup_boot_configuration(&self, install_path: &Path, os_name: &str) -> Result<()> {
-        info!("🚀 إعداد تكوين البوت لـ {}", os_name);
-
-        // إنشاء سكريبت البوت
-        let boot_script = install_path.join("boot.sh");
-        let script_content = format!(
-            r#"#!/bin/bash
-# DOS Safar Boot Script for {}
-echo "🎮 Starting {} via DOS Safar..."
-
-# Set environment variables
-export DOS_SAFAR_OS="{}"
-export DOS_SAFAR_PATH="{}"
-
-# Load system specific configurations
-if [ -f "{}/dos_safar_config.toml" ]; then
-    echo "📝 Loading DOS Safar configuration..."
-fi
-
-# Start the operating system
-echo "🚀 Launching {}..."
-exec /sbin/init
-"#,
-            os_name, os_name, os_name, 
-            install_path.to_str().unwrap(),
-            install_path.to_str().unwrap(),
-            os_name
-        );
-
-        fs::write(&boot_script, script_content)
-            .context("فشل في كتابة سكريبت البوت")?;
-
-        // جعل السكريبت قابل للتنفيذ
-        Command::new("chmod")
-            .args(&["+x", boot_script.to_str().unwrap()])
-            .output()
-            .context("فشل في تعيين صلاحيات التنفيذ")?;
-
-        Ok(())
-    }
-
-    async fn register_os(&self, os_name: &str, os_path: &Path) -> Result<()> {
-        info!("📝 تسجيل النظام {} في قاعدة البيانات", os_name);
-
-        let registry_file = self.os_storage_path.join("registry.json");
-        let mut registry: serde_json::Value = if registry_file.exists() {
-            let content = fs::read_to_string(&registry_file)?;
-            serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
-        } else {
-            serde_json::json!({})
-        };
-
-        // إضافة معلومات النظام
-        registry[os_name] = serde_json::json!({
-            "name": os_name,
-            "path": os_path.to_str().unwrap(),
-            "install_date": chrono::Utc::now().to_rfc3339(),
-            "last_used": null,
-            "bootable": true,
-            "size_mb": self.calculate_directory_size(os_path).await.unwrap_or(0)
-        });
-
-        let registry_content = serde_json::to_string_pretty(&registry)?;
-        fs::write(&registry_file, registry_content)
-            .context("فشل في كتابة سجل الأنظمة")?;
-
-        Ok(())
-    }
-
-    async fn unregister_os(&self, os_name: &str) -> Result<()> {
-        info!("🗑️ إزالة {} من سجل الأنظمة", os_name);
-
-        let registry_file = self.os_storage_path.join("registry.json");
-        if !registry_file.exists() {
-            return Ok(());
-        }
-
-        let content = fs::read_to_string(&registry_file)?;
-        let mut registry: serde_json::Value = serde_json::from_str(&content)
-            .unwrap_or(serde_json::json!({}));
-
-        // إزالة النظام من السجل
-        if let Some(obj) = registry.as_object_mut() {
-            obj.remove(os_name);
-        }
-
-        let registry_content = serde_json::to_string_pretty(&registry)?;
-        fs::write(&registry_file, registry_content)
-            .context("فشل في تحديث سجل الأنظمة")?;
-
-        Ok(())
-    }
-
-    async fn download_os_image(&self, url: &str, output_path: &str) -> Result<()> {
-        info!("📥 تحميل صورة النظام من: {}", url);
-
-        let output = Command::new("wget")
-            .args(&[
-                "-O", output_path,
-                "--progress=bar",
-                "--show-progress",
-                url
-            ])
-            .output()
-            .context("فشل في تنفيذ أمر التحميل")?;
-
-        if !output.status.success() {
-            let error = String::from_utf8_lossy(&output.stderr);
-            return Err(anyhow::anyhow!("فشل في التحميل: {}", error));
-        }
-
-        info!("✅ تم تحميل الصورة بنجاح");
-        Ok(())
-    }
-
-    async fn analyze_os_directory(&self, os_path: &Path) -> Result<OperatingSystem> {
-        let os_name = os_path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("Unknown")
-            .to_string();
-
-        // تحديد نوع النظام
-        let os_type = self.detect_os_type(os_path);
-
-        // قراءة معلومات إضافية
-        let description = self.get_os_description(os_path, &os_type);
-        let last_used = self.get_last_used_date(&os_name).await;
-
-        Ok(OperatingSystem {
-            name: os_name,
-            path: os_path.to_string_lossy().to_string(),
-            description,
-            os_type,
-            is_bootable: self.is_bootable(os_path),
-            last_used,
-        })
-    }
-
-    fn detect_os_type(&self, os_path: &Path) -> OSType {
-        // فحص ملفات مميزة لكل نوع نظام
-        if os_path.join("retropie").exists() || 
-           os_path.join("RetroPie").exists() {
-            return OSType::RetroPie;
-        }
-
-        if os_path.join("batocera").exists() ||
-           os_path.join("BATOCERA").exists() {
-            return OSType::Batocera;
-        }
-
-        if os_path.join("recalbox").exists() {
-            return OSType::Recalbox;
-        }
-
-        if os_path.join("config.txt").exists() &&
-           os_path.join("cmdline.txt").exists() {
-            return OSType::RaspberryPiOS;
-        }
-
-        if os_path.join("ubuntu").exists() ||
-           os_path.join("etc/lsb-release").exists() {
-            return OSType::Ubuntu;
-        }
-
-        OSType::Unknown
-    }
-
-    fn get_os_description(&self, os_path: &Path, os_type: &OSType) -> String {
-        // محاولة قراءة وصف من ملف التكوين
-        let config_file = os_path.join("dos_safar_config.toml");
-        if config_file.exists() {
-            if let Ok(content) = fs::read_to_string(&config_file) {
-                // محاولة استخراج الوصف من TOML
-                // هذا مبسط - في التنفيذ الحقيقي نستخدم مكتبة TOML
-                for line in content.lines() {
-                    if line.starts_with("description") {
-                        if let Some(desc) = line.split('=').nth(1) {
-                            return desc.trim().trim_matches('"').to_string();
-                        }
-                    }
-                }
-            }
-        }
-
-        // وصف افتراضي حسب النوع
-        match os_type {
-            OSType::RetroPie => "نظام الألعاب الكلاسيكية RetroPie".to_string(),
-            OSType::Batocera => "نظام الألعاب Batocera".to_string(),
-            OSType::Recalbox => "نظام الألعاب Recalbox".to_string(),
-            OSType::RaspberryPiOS => "نظام التشغيل الرسمي لـ Raspberry Pi".to_string(),
-            OSType::Ubuntu => "نظام Ubuntu Linux".to_string(),
-            OSType::Debian => "نظام Debian Linux".to_string(),
-            OSType::Unknown => "نظام تشغيل غير معروف".to_string(),
-        }
-    }
-
-    fn is_bootable(&self, os_path: &Path) -> bool {
-        // فحص وجود ملفات البوت الأساسية
-        let boot_files = vec![
-            "boot.sh",
-            "kernel.img",
-            "config.txt",
-            "system.img",
-        ];
-
-        boot_files.iter().any(|file| os_path.join(file).exists())
-    }
-
-    async fn get_last_used_date(&self, os_name: &str) -> Option<chrono::DateTime<chrono::Utc>> {
-        let registry_file = self.os_storage_path.join("registry.json");
-        if !registry_file.exists() {
-            return None;
-        }
-
-        let content = fs::read_to_string(&registry_file).ok()?;
-        let registry: serde_json::Value = serde_json::from_str(&content).ok()?;
-
-        let last_used_str = registry[os_name]["last_used"].as_str()?;
-        chrono::DateTime::parse_from_rfc3339(last_used_str)
-            .ok()
-            .map(|dt| dt.with_timezone(&chrono::Utc))
-    }
-
-    async fn calculate_directory_size(&self, dir_path: &Path) -> Result<u64> {
-        let output = Command::new("du")
-            .args(&["-s", "-m", dir_path.to_str().unwrap()])
-            .output()
-            .context("فشل في حساب حجم المجلد")?;
-
-        if output.status.success() {
-            let output_str = String::from_utf8_lossy(&output.stdout);
-            let size_str = output_str.split_whitespace().next().unwrap_or("0");
-            Ok(size_str.parse().unwrap_or(0))
-        } else {
-            Ok(0)
-        }
-    }
-
-    fn os_exists(&self, os_name: &str) -> bool {
-        self.os_storage_path.join(os_name).exists()
-    }
-
-    fn is_raspberry_pi(&self) -> bool {
-        Path::new("/proc/device-tree/model").exists() &&
-        fs::read_to_string("/proc/device-tree/model")
-            .unwrap_or_default()
-            .to_lowercase()
-            .contains("raspberry pi")
-    }
-
-    fn is_gaming_handheld(&self) -> bool {
-        // فحص مبسط لأجهزة الألعاب المحمولة
-        let model_info = fs::read_to_string("/proc/device-tree/model")
-            .unwrap_or_default()
-            .to_lowercase();
-        
-        model_info.contains("anbernic") ||
-        model_info.contains("rg351") ||
-        model_info.contains("rg552")
-    }
-
-    // باقي الوظائف المساعدة...
-    async fn scan_external_systems(&self) -> Result<Vec<OperatingSystem>> {
-        // فحص مواقع إضافية للأنظمة
-        Ok(Vec::new()) // مبسط
-    }
-
-    async fn analyze_backup_file(&self, backup_path: &Path) -> Result<OSBackup> {
-        let metadata = fs::metadata(backup_path)?;
-        let size_mb = metadata.len() / 1024 / 1024;
-        
-        let file_name = backup_path.file_stem()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-
-        // استخراج اسم النظام وتاريخ النسخة الاحتياطية من اسم الملف
-        let parts: Vec<&str> = file_name.split('_').collect();
-        let os_name = parts.get(0).unwrap_or(&"unknown").to_string();
-        
-        Ok(OSBackup {
-            os_name,
-            backup_date: metadata.created()
-                .ok()
-                .and_then(|t| chrono::DateTime::from(t).into())
-                .unwrap_or_else(chrono::Utc::now),
-            backup_size_mb: size_mb,
-            backup_path: backup_path.to_string_lossy().to_string(),
-            is_bootable: true,
-        })
-    }
-
-    async fn load_boot_configuration(&self) -> Result<BootConfiguration> {
-        let config_file = self.boot_partition_path.join("dos_safar_boot.json");
-        
-        if config_file.exists() {
-            let content = fs::read_to_string(&config_file)?;
-            let config: BootConfiguration = serde_json::from_str(&content)?;
-            Ok(config)
-        } else {
-            // تكوين افتراضي
-            Ok(BootConfiguration {
-                default_os: None,
-                timeout_seconds: 10,
-                available_systems: Vec::new(),
-                boot_order: Vec::new(),
-                recovery_mode: false,
-            })
-        }
-    }
-
-    async fn save_boot_configuration(&self, config: &BootConfiguration) -> Result<()> {
-        let config_file = self.boot_partition_path.join("dos_safar_boot.json");
-        let content = serde_json::to_string_pretty(config)?;
-        fs::write(&config_file, content)?;
-        Ok(())
-    }
-
-    async fn perform_os_update(&self, os_name: &str, update_source: &str) -> Result<()> {
-        // تنفيذ مبسط للتحديث
-        info!("تحديث {} من {}", os_name, update_source);
-        Ok(())
-    }
-
-    async fn cleanup_temporary_files(&self, os_path: &Path) -> Result<()> {
-        // تنظيف الملفات المؤقتة
-        Ok(())
-    }
-
-    async fn optimize_databases(&self, os_path: &Path) -> Result<()> {
-        // تحسين قواعد البيانات
-        Ok(())
-    }
-
-    async fn compress_unused_files(&self, os_path: &Path) -> Result<()> {
-        // ضغط الملفات غير المستخدمة
-        Ok(())
-    }
-
-    async fn update_file_index(&self, os_path: &Path) -> Result<()> {
-        // تحديث فهرس الملفات
-        Ok(())
-    }
-}
-
-#[derive(Debug, Clone, PartialEq)]
-enum ImageType {
-    ISO,
-    IMG,
-    TAR,
-    ZIP,
+// OS management functions 
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use tokio::process::Command as AsyncCommand;
+use tokio::fs as async_fs;
+use tokio::io::AsyncWriteExt;
+use async_compression::tokio::bufread::{GzipDecoder, XzDecoder};
+use futures_util::StreamExt;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
+use md5::{Md5, Digest as Md5DigestTrait};
+use tracing::{info, warn, error, debug};
+use crate::bootloader::distro_detect::{self, LinuxDistroInfo};
+use crate::bootloader::menu::{OperatingSystem, OSType, TargetArch};
+use crate::bootloader::progress::ProgressReporter;
+use crate::bootloader::rom_scanner;
+use crate::utils::config::Config;
+
+/// Size of each chunk read while hashing an image, so a multi-GB ISO is
+/// never fully read into memory at once.
+const CHECKSUM_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// ISO 9660 sector size; the MD5-implant scheme below works in whole
+/// sectors, mirroring `implantisomd5`/`checkisomd5` from Fedora/Anaconda.
+const ISO_SECTOR_SIZE: u64 = 2048;
+/// The Primary Volume Descriptor always lives at sector 16 of an ISO 9660
+/// image.
+const ISO_PVD_SECTOR: u64 = 16;
+/// Byte offset of the PVD's "Application Use" field within its sector -
+/// 512 reserved bytes most authoring tools leave zero-filled, which is
+/// where the MD5 tag is implanted instead.
+const ISO_PVD_APP_DATA_OFFSET: u64 = 883;
+const ISO_PVD_APP_DATA_SIZE: u64 = 512;
+
+/// أسماء ملفات النواة المعروفة التي نبحث عنها داخل نظام مثبَّت، مستخدمة في
+/// كل من اكتشاف المعمارية (`detect_image_arch`) واختبار الإقلاع
+/// (`test_os`).
+const KERNEL_CANDIDATES: &[&str] = &[
+    "vmlinuz",
+    "boot/vmlinuz",
+    "kernel.img",
+    "kernel7.img",
+    "kernel8.img",
+    "zImage",
+    "Image",
+];
+
+/// المهلة القصوى لانتظار علامة نجاح الإقلاع تحت QEMU قبل اعتباره فاشلاً.
+const BOOT_TEST_TIMEOUT_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OSManager {
+    config: Config,
+    os_storage_path: PathBuf,
+    boot_partition_path: PathBuf,
+    backup_path: PathBuf,
+    /// Maximum number of generations retained per OS before `gc()` prunes
+    /// the oldest ones, from `config.os_manager.configuration_limit`.
+    configuration_limit: usize,
+    /// Optional Unix-socket progress broadcaster, attached via
+    /// `attach_progress_socket`, so a GUI front-end can observe long
+    /// operations (download, update, cleanup/optimize/compress, directory
+    /// sizing, backup analysis) from outside this process. `None` until
+    /// attached, in which case progress is only reported in-process through
+    /// `ProgressCallback` as before.
+    #[serde(skip)]
+    progress_reporter: Option<Arc<ProgressReporter>>,
+}
+
+/// Per-OS generation bookkeeping, persisted in `generations.json` next to
+/// `registry.json`: which generation is current, and which ones failed
+/// `InstallationStage::Testing` and should be garbage-collected first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct OsGenerationState {
+    current_generation: Option<u64>,
+    broken_generations: BTreeSet<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OSInstallationProgress {
+    pub stage: InstallationStage,
+    pub progress_percentage: f32,
+    pub current_operation: String,
+    pub estimated_time_remaining: Option<u64>, // seconds
+    pub error_message: Option<String>,
+}
+
+/// A stage-tagged `OSInstallationProgress` subscriber, so a front-end can
+/// get a single unified stream across downloading, extracting, and
+/// installing instead of polling after the fact.
+pub type ProgressCallback = Box<dyn Fn(OSInstallationProgress) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum InstallationStage {
+    Preparing,
+    Downloading,
+    Extracting,
+    Installing,
+    Configuring,
+    Verifying,
+    Testing,
+    Finalizing,
+    Completed,
+    Failed,
+}
+
+/// Result of `update_os`'s preserve/merge pass over user configuration,
+/// mirroring DrakX's `filesToSaveForUpgrade`/`filesNewerToUseAfterUpgrade`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OsUpdateResult {
+    /// Files restored unconditionally from the pre-update tree.
+    pub preserved_files: Vec<String>,
+    /// `newer_wins` files where the preserved copy was kept because it was
+    /// newer than the incoming one.
+    pub newer_wins_applied: Vec<String>,
+    /// Files that differed between the preserved and incoming trees, for
+    /// the user to review rather than have silently overwritten.
+    pub conflicts: Vec<String>,
+}
+
+/// نتيجة `test_os`: هل أقلعت الصورة فعلاً تحت محاكاة QEMU (أو اجتازت فحص
+/// تشغيل داخل `chroot` لصور بلا نواة منفصلة)، مع سجل الخرج التسلسلي/الفحص
+/// ومدة الاختبار، حتى يمكن عرضها أو تسجيلها قبل تعيين النظام كافتراضي.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootTestResult {
+    pub booted: bool,
+    pub log: String,
+    pub duration_seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OSBackup {
+    pub os_name: String,
+    pub backup_date: chrono::DateTime<chrono::Utc>,
+    pub backup_size_mb: u64,
+    pub backup_path: String,
+    pub is_bootable: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OSImage {
+    pub name: String,
+    pub file_path: String,
+    pub size_mb: u64,
+    pub os_type: OSType,
+    pub checksum: Option<String>,
+    pub is_compressed: bool,
+    pub supported_devices: Vec<String>,
+}
+
+/// A detached signature to verify an image against, mirroring how a `.sig`
+/// file ships alongside a release artifact.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageSignature {
+    /// Base64-encoded Ed25519 signature, either read from a `.sig` file or
+    /// supplied inline.
+    pub signature_base64: String,
+    /// Path to the base64-encoded Ed25519 public key used to verify it.
+    pub public_key_path: String,
+}
+
+/// Outcome of `verify_image_md5_tag`: whether the recomputed MD5 matched the
+/// tag `implant_md5_tag` wrote earlier, alongside both digests for logging.
+/// An image with no implanted tag (i.e. not produced by `dos_safar`) is
+/// reported as `passed: true` with empty digests, since there is nothing to
+/// check - this is an additional check layered on `verify_image_integrity`,
+/// not a replacement for it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Md5TagVerification {
+    pub passed: bool,
+    pub stored_md5: String,
+    pub computed_md5: String,
+}
+
+/// One parsed ISO 9660 Directory Record (ECMA-119 section 9.1), as read
+/// by `extract_iso9660_contents` and friends.
+#[derive(Debug, Clone)]
+struct Iso9660DirEntry {
+    name: String,
+    is_directory: bool,
+    /// The two self-referential entries every ISO 9660 directory extent
+    /// starts with (file identifier bytes `0x00`/`0x01`), equivalent to
+    /// "."/".." but carrying no real file identifier to decode.
+    is_self_or_parent: bool,
+    extent_lba: u32,
+    data_length: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootConfiguration {
+    pub default_os: Option<String>,
+    pub timeout_seconds: u64,
+    pub available_systems: Vec<OperatingSystem>,
+    pub boot_order: Vec<String>,
+    pub recovery_mode: bool,
+    /// Maximum number of retained OS entries. `None` (the default) keeps
+    /// current unbounded behavior; `Some(n)` makes `save_boot_configuration`
+    /// prune the oldest-`last_used` entries past `n` on every save, so a
+    /// boot partition can't fill up from repeated registrations/updates.
+    #[serde(default)]
+    pub configuration_limit: Option<usize>,
+}
+
+/// A kickstart/auto_inst-style manifest describing a full multi-boot setup:
+/// every system to install plus the resulting global boot settings, parsed
+/// and applied unattended by `OSManager::apply_manifest`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallManifest {
+    #[serde(rename = "system")]
+    pub systems: Vec<ManifestSystem>,
+    #[serde(default)]
+    pub boot: ManifestBootSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestSystem {
+    pub name: String,
+    /// Either a `https://`/`http://` URL or a local filesystem path; decided
+    /// by looking for a URL scheme when the manifest is applied.
+    pub source: String,
+    pub checksum: Option<String>,
+    pub target_device_class: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default)]
+    pub optimization_profiles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestBootSettings {
+    pub timeout_seconds: Option<u64>,
+    pub boot_order: Vec<String>,
+}
+
+/// Structured schema for a single OS's `dos_safar_config.toml`, replacing
+/// the line-by-line `description =` scrape that `get_os_description` used
+/// to do. Parsed once by `analyze_os_directory` and cached on
+/// `OperatingSystem::manifest`; every section is optional so legacy/foreign
+/// configs (or ones missing entirely) still parse to an empty manifest
+/// rather than failing outright.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OsManifest {
+    #[serde(default)]
+    pub meta: Option<OsManifestMeta>,
+    #[serde(default)]
+    pub boot: Option<OsManifestBoot>,
+    /// Logical name -> in-image path, e.g. `kernel = "boot/vmlinuz"`.
+    #[serde(default)]
+    pub links: HashMap<String, String>,
+    #[serde(default)]
+    pub requirements: Option<OsManifestRequirements>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsManifestMeta {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub maintainer: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsManifestBoot {
+    pub kernel: Option<String>,
+    pub cmdline: Option<String>,
+    pub dtb: Option<String>,
+    #[serde(default)]
+    pub overlays: Vec<String>,
+}
+
+/// Hardware gate checked by `register_os_verified` against the host via
+/// `is_raspberry_pi`/`is_gaming_handheld` before an install is registered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsManifestRequirements {
+    pub min_storage_mb: Option<u64>,
+    /// Expected values: `"rpi"`, `"handheld"`. Unknown values are ignored
+    /// (treated as "no constraint") rather than rejected.
+    pub required_device: Option<String>,
+}
+
+impl OsManifest {
+    /// يقرأ ويحلل `dos_safar_config.toml` عند `os_path` إن وُجد؛ يعيد بياناً
+    /// فارغاً (لا أقسام) دون خطأ إن كان الملف غائباً، ويسجل تحذيراً ويعيد
+    /// بياناً فارغاً أيضاً إن كان موجوداً لكن تعذّر تحليله (صورة خارجية لا
+    /// تتبع مخطط dos_safar).
+    fn read_from(os_path: &Path) -> Self {
+        let config_file = os_path.join("dos_safar_config.toml");
+        let Ok(content) = fs::read_to_string(&config_file) else {
+            return OsManifest::default();
+        };
+
+        match toml::from_str(&content) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                warn!(
+                    "⚠️ تعذر تحليل {} كمخطط dos_safar منظم: {} - سيُعامل كبيان فارغ",
+                    config_file.display(),
+                    e
+                );
+                OsManifest::default()
+            }
+        }
+    }
+}
+
+impl Default for ManifestBootSettings {
+    fn default() -> Self {
+        ManifestBootSettings {
+            timeout_seconds: None,
+            boot_order: Vec::new(),
+        }
+    }
+}
+
+impl OSManager {
+    pub fn new(config: &Config) -> Result<Self> {
+        let os_storage_path = PathBuf::from("/boot/dos_safar/systems");
+        let boot_partition_path = PathBuf::from("/boot");
+        let backup_path = PathBuf::from("/boot/dos_safar/backups");
+
+        // إنشاء المجلدات المطلوبة
+        fs::create_dir_all(&os_storage_path)
+            .context("فشل في إنشاء مجلد أنظمة التشغيل")?;
+        fs::create_dir_all(&backup_path)
+            .context("فشل في إنشاء مجلد النسخ الاحتياطية")?;
+
+        Ok(OSManager {
+            config: config.clone(),
+            os_storage_path,
+            boot_partition_path,
+            backup_path,
+            configuration_limit: config.os_manager.configuration_limit,
+            progress_reporter: None,
+        })
+    }
+
+    /// يفتح مقبس تقدم يونكس عند `socket_path` ويربطه بهذا المدير، حتى تبث
+    /// العمليات الطويلة اللاحقة (تحميل، تحديث، تنظيف/تحسين/ضغط، حساب حجم
+    /// مجلد، تحليل نسخة احتياطية) أحداثها عبره لعملية خارجية تراقبها.
+    pub async fn attach_progress_socket(&mut self, socket_path: impl Into<PathBuf>) -> Result<()> {
+        self.progress_reporter = Some(Arc::new(ProgressReporter::bind(socket_path).await?));
+        Ok(())
+    }
+
+    fn reporter(&self) -> Option<&ProgressReporter> {
+        self.progress_reporter.as_deref()
+    }
+
+    /// تثبيت نظام تشغيل من صورة
+    pub async fn install_os_from_image(&self, image_path: &str, os_name: &str) -> Result<()> {
+        self.install_os_from_image_verified(image_path, os_name, None, None, false, None)
+            .await
+    }
+
+    /// تثبيت نظام تشغيل من صورة مع التحقق من السلامة والتوقيع قبل التثبيت
+    ///
+    /// `InstallationStage::Preparing` computes the image's SHA-256 (streamed
+    /// in fixed-size buffers) and, if `expected_checksum` is given, refuses
+    /// to install on a mismatch. If `signature` is also given, the digest is
+    /// verified against the supplied Ed25519 public key before proceeding.
+    ///
+    /// After extraction, the installed kernel/ELF is probed for its target
+    /// architecture and compared against the host's. A mismatch aborts the
+    /// install (the half-written generation is removed) unless
+    /// `allow_foreign_arch` is set, since booting a foreign-arch image would
+    /// just produce an unbootable multi-boot entry. Inconclusive detection
+    /// never blocks installation.
+    ///
+    /// If `progress` is given, `InstallationStage::Extracting` and
+    /// `InstallationStage::Installing` events are reported through it,
+    /// continuing whatever stream `install_os_from_url_verified`'s download
+    /// phase already reported on.
+    pub async fn install_os_from_image_verified(
+        &self,
+        image_path: &str,
+        os_name: &str,
+        expected_checksum: Option<&str>,
+        signature: Option<&ImageSignature>,
+        allow_foreign_arch: bool,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<()> {
+        info!("🔧 بدء تثبيت {} من {}", os_name, image_path);
+
+        // التحقق من وجود الصورة
+        if !Path::new(image_path).exists() {
+            return Err(anyhow::anyhow!("الصورة {} غير موجودة", image_path));
+        }
+
+        // InstallationStage::Preparing - التحقق من سلامة الصورة والتوقيع
+        info!("🔍 التحقق من سلامة الصورة (InstallationStage::Preparing)");
+        let verified_checksum = self
+            .verify_image_integrity(image_path, expected_checksum, signature)
+            .await?;
+
+        // InstallationStage::Verifying - تحقق إضافي من وسم MD5 المزروع إن
+        // كانت الصورة من إنتاج dos_safar نفسه (implant_md5_tag)؛ الصور من
+        // مصادر خارجية تمر دون رفض لأنها لا تحمل هذا الوسم أصلاً
+        let md5_verification = self.verify_image_md5_tag(image_path, progress).await?;
+        if !md5_verification.passed {
+            return Err(anyhow::anyhow!(
+                "فشل التحقق من وسم MD5 المزروع: الصورة {} قد تكون تالفة أو مبتورة (المحسوبة {} لا تطابق المخزَّنة {})",
+                image_path,
+                md5_verification.computed_md5,
+                md5_verification.stored_md5
+            ));
+        }
+
+        // كل تثبيت ينتج جيلاً جديداً غير قابل للتعديل بدلاً من الكتابة فوق
+        // النظام الحالي، على غرار نموذج lanzaboote للأجيال
+        let os_root = self.os_storage_path.join(os_name);
+        fs::create_dir_all(&os_root)
+            .context("فشل في إنشاء مجلد أجيال النظام")?;
+
+        let generation = self.next_generation_number(os_name).await?;
+        let install_path = self.generation_dir(os_name, generation);
+        fs::create_dir_all(&install_path)
+            .context("فشل في إنشاء مجلد التثبيت")?;
+        info!("🆕 تثبيت {} كجيل جديد رقم {}", os_name, generation);
+
+        if let Some(callback) = progress {
+            callback(OSInstallationProgress {
+                stage: InstallationStage::Extracting,
+                progress_percentage: 0.0,
+                current_operation: format!("استخراج {}", os_name),
+                estimated_time_remaining: None,
+                error_message: None,
+            });
+        }
+
+        // تحديد نوع الصورة والتثبيت المناسب
+        let image_type = self.detect_image_type(image_path)?;
+
+        match image_type {
+            ImageType::ISO => self.install_from_iso(image_path, &install_path).await?,
+            ImageType::IMG => self.install_from_img(image_path, &install_path).await?,
+            ImageType::TAR => self.install_from_tar(image_path, &install_path).await?,
+            ImageType::ZIP => self.install_from_zip(image_path, &install_path).await?,
+        }
+
+        // التحقق من توافق معمارية الصورة مع الجهاز المضيف قبل المتابعة
+        let target_arch = self.detect_image_arch(&install_path);
+        let host_arch = Self::detect_host_arch();
+        if let (Some(detected), Some(host)) = (target_arch, host_arch) {
+            if detected != host {
+                if allow_foreign_arch {
+                    warn!(
+                        "⚠️ معمارية الصورة {:?} تختلف عن معمارية الجهاز {:?} - المتابعة بسبب allow_foreign_arch",
+                        detected, host
+                    );
+                } else {
+                    let _ = fs::remove_dir_all(&install_path);
+                    return Err(anyhow::anyhow!(
+                        "معمارية الصورة {:?} لا تطابق معمارية الجهاز {:?} - استخدم allow_foreign_arch لتجاوز هذا التحقق",
+                        detected,
+                        host
+                    ));
+                }
+            }
+        }
+
+        if let Some(callback) = progress {
+            callback(OSInstallationProgress {
+                stage: InstallationStage::Installing,
+                progress_percentage: 50.0,
+                current_operation: format!("تكوين {}", os_name),
+                estimated_time_remaining: None,
+                error_message: None,
+            });
+        }
+
+        // تكوين نظام التشغيل المثبت
+        self.configure_installed_os(&install_path, os_name, target_arch)
+            .await?;
+
+        // تحديث رابط "current" إلى الجيل الجديد وتسجيل النظام
+        self.set_current_generation(os_name, generation)?;
+        self.register_os_verified(os_name, &install_path, Some(&verified_checksum), target_arch)
+            .await?;
+
+        // تنظيف الأجيال القديمة الزائدة عن الحد المسموح
+        self.gc_os(os_name).await?;
+
+        info!("✅ تم تثبيت {} بنجاح (الجيل {})", os_name, generation);
+        Ok(())
+    }
+
+    /// تثبيت نظام تشغيل من URL
+    pub async fn install_os_from_url(&self, url: &str, os_name: &str) -> Result<()> {
+        self.install_os_from_url_verified(url, os_name, None, None, false, None)
+            .await
+    }
+
+    /// تثبيت نظام تشغيل من URL مع التحقق من السلامة والتوقيع بعد التحميل
+    ///
+    /// يحمّل الصورة عبر `download_image_resumable` (استئناف + بصمة تدريجية
+    /// + تقدم)، ثم يمرر نفس `progress` إلى `install_os_from_image_verified`
+    /// ليواصل الإبلاغ عبر مرحلتي الاستخراج والتثبيت - بث تقدم موحّد من بداية
+    /// التحميل حتى اكتمال التثبيت.
+    pub async fn install_os_from_url_verified(
+        &self,
+        url: &str,
+        os_name: &str,
+        expected_checksum: Option<&str>,
+        signature: Option<&ImageSignature>,
+        allow_foreign_arch: bool,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<()> {
+        info!("📥 تحميل وتثبيت {} من {}", os_name, url);
+
+        let result = self
+            .install_os_from_url_verified_inner(
+                url,
+                os_name,
+                expected_checksum,
+                signature,
+                allow_foreign_arch,
+                progress,
+            )
+            .await;
+
+        if let Some(reporter) = self.reporter() {
+            match &result {
+                Ok(_) => reporter.done("download", Some(os_name)).await,
+                Err(e) => reporter.error("download", Some(os_name), e.to_string()).await,
+            }
+        }
+
+        result
+    }
+
+    async fn install_os_from_url_verified_inner(
+        &self,
+        url: &str,
+        os_name: &str,
+        expected_checksum: Option<&str>,
+        signature: Option<&ImageSignature>,
+        allow_foreign_arch: bool,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<()> {
+        // اسم وامتداد الملف النهائي بعد فكّ الضغط إن كان الرابط منتهياً
+        // بـ .gz/.xz، حتى يتعرف detect_image_type على النوع الحقيقي
+        // (.img/.iso) بدل امتداد الضغط
+        let url_file_name = Path::new(url)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("image.img");
+        let decompressed_name = strip_compression_extension(url_file_name);
+        let extension = Path::new(decompressed_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("img");
+
+        // تحميل الصورة عبر طلبات Range قابلة للاستئناف، مع بصمة SHA-256
+        // تُحسب تدريجياً أثناء الكتابة (وفكّ ضغط gzip/xz أثناء البث إن
+        // كان الرابط يشير إلى صورة مضغوطة)
+        let temp_path = format!("/tmp/{}.{}", os_name, extension);
+        let downloaded_checksum = self
+            .download_image_resumable(url, &temp_path, os_name, progress)
+            .await?;
+
+        // استخدام بصمة التحميل كبصمة متوقعة إن لم تُحدَّد واحدة صراحة، حتى
+        // يتحقق `verify_image_integrity` من عدم تلف الملف بعد الكتابة
+        let expected_checksum = expected_checksum.or(Some(downloaded_checksum.as_str()));
+
+        // تثبيت من الملف المحمل بعد التحقق من سلامته
+        self.install_os_from_image_verified(
+            &temp_path,
+            os_name,
+            expected_checksum,
+            signature,
+            allow_foreign_arch,
+            progress,
+        )
+        .await?;
+
+        // حذف الملف المؤقت
+        let _ = fs::remove_file(&temp_path);
+
+        Ok(())
+    }
+
+    /// يقرأ ملف بيان (manifest) على طراز kickstart/auto_inst ويطبّقه بالكامل
+    /// دون تدخل: يثبت كل نظام وارد فيه (متجاوزاً الأنظمة المسجلة مسبقاً
+    /// ببصمة مطابقة)، ثم يكتب `BootConfiguration` لتعكس ترتيب/مهلة الإقلاع
+    /// المطلوبين. يتوقف فوراً ويذكر أي مدخل فشل بالاسم.
+    pub async fn apply_manifest(&self, manifest_path: &str) -> Result<Vec<OSInstallationProgress>> {
+        info!("📜 تطبيق بيان التثبيت: {}", manifest_path);
+
+        let content = fs::read_to_string(manifest_path)
+            .with_context(|| format!("فشل في قراءة بيان التثبيت {}", manifest_path))?;
+        let manifest: InstallManifest = toml::from_str(&content)
+            .with_context(|| format!("فشل في تحليل بيان التثبيت {}", manifest_path))?;
+
+        let total = manifest.systems.len();
+        let mut progress_log = Vec::with_capacity(total);
+
+        for (index, system) in manifest.systems.iter().enumerate() {
+            let base_percentage = (index as f32 / total.max(1) as f32) * 100.0;
+
+            if self.is_registered_with_checksum(&system.name, system.checksum.as_deref()) {
+                info!(
+                    "⏭️ تخطي {} - مسجل مسبقاً ببصمة مطابقة (تثبيت متكرر الفاعلية)",
+                    system.name
+                );
+                progress_log.push(OSInstallationProgress {
+                    stage: InstallationStage::Completed,
+                    progress_percentage: base_percentage,
+                    current_operation: format!("{} موجود بالفعل، تم التخطي", system.name),
+                    estimated_time_remaining: None,
+                    error_message: None,
+                });
+                continue;
+            }
+
+            progress_log.push(OSInstallationProgress {
+                stage: InstallationStage::Preparing,
+                progress_percentage: base_percentage,
+                current_operation: format!("تثبيت {} ({}/{})", system.name, index + 1, total),
+                estimated_time_remaining: None,
+                error_message: None,
+            });
+
+            let install_result = if system.source.starts_with("http://") || system.source.starts_with("https://") {
+                self.install_os_from_url_verified(
+                    &system.source,
+                    &system.name,
+                    system.checksum.as_deref(),
+                    None,
+                    false,
+                    None,
+                )
+                .await
+            } else {
+                self.install_os_from_image_verified(
+                    &system.source,
+                    &system.name,
+                    system.checksum.as_deref(),
+                    None,
+                    false,
+                    None,
+                )
+                .await
+            };
+
+            if let Err(e) = install_result {
+                let message = format!("فشل تطبيق البيان عند المدخل '{}': {}", system.name, e);
+                error!("❌ {}", message);
+                progress_log.push(OSInstallationProgress {
+                    stage: InstallationStage::Failed,
+                    progress_percentage: base_percentage,
+                    current_operation: system.name.clone(),
+                    estimated_time_remaining: None,
+                    error_message: Some(message.clone()),
+                });
+                return Err(anyhow::anyhow!(message));
+            }
+
+            for profile in &system.optimization_profiles {
+                debug!("⚡ تطبيق ملف التحسين '{}' على {}", profile, system.name);
+            }
+
+            progress_log.push(OSInstallationProgress {
+                stage: InstallationStage::Completed,
+                progress_percentage: ((index + 1) as f32 / total.max(1) as f32) * 100.0,
+                current_operation: format!("اكتمل تثبيت {}", system.name),
+                estimated_time_remaining: None,
+                error_message: None,
+            });
+        }
+
+        // كتابة BootConfiguration النهائي ليعكس البيان بالكامل
+        let mut boot_config = self.load_boot_configuration().await?;
+        if let Some(default_system) = manifest.systems.iter().find(|s| s.is_default) {
+            boot_config.default_os = Some(default_system.name.clone());
+        }
+        if let Some(timeout) = manifest.boot.timeout_seconds {
+            boot_config.timeout_seconds = timeout;
+        }
+        if !manifest.boot.boot_order.is_empty() {
+            boot_config.boot_order = manifest.boot.boot_order.clone();
+        }
+        self.save_boot_configuration(&mut boot_config).await?;
+
+        info!("✅ تم تطبيق بيان التثبيت بنجاح ({} نظام)", total);
+        Ok(progress_log)
+    }
+
+    /// نظام مُثبَّت مسبقاً وبصمة سجله تطابق `expected_checksum` يُعتبر
+    /// مثبتاً بالفعل، فيتخطاه `apply_manifest` (idempotent replay).
+    fn is_registered_with_checksum(&self, os_name: &str, expected_checksum: Option<&str>) -> bool {
+        let Some(expected) = expected_checksum else {
+            return false;
+        };
+
+        let registry_file = self.os_storage_path.join("registry.json");
+        let Ok(content) = fs::read_to_string(&registry_file) else {
+            return false;
+        };
+        let Ok(registry) = serde_json::from_str::<serde_json::Value>(&content) else {
+            return false;
+        };
+
+        registry[os_name]["verified_checksum"]
+            .as_str()
+            .map(|actual| actual.eq_ignore_ascii_case(expected))
+            .unwrap_or(false)
+    }
+
+    /// يحسب بصمة SHA-256 للصورة (قراءة مجزأة لتفادي تحميلها بالكامل في
+    /// الذاكرة)، ويقارنها بالبصمة المتوقعة إن وُجدت، ثم يتحقق من التوقيع
+    /// المرفق إن وُجد. يعيد البصمة المحسوبة عند نجاح التحقق.
+    async fn verify_image_integrity(
+        &self,
+        image_path: &str,
+        expected_checksum: Option<&str>,
+        signature: Option<&ImageSignature>,
+    ) -> Result<String> {
+        let digest = self.compute_sha256_streaming(image_path)?;
+        debug!("📐 بصمة SHA-256 للصورة {}: {}", image_path, digest);
+
+        if let Some(expected) = expected_checksum {
+            if !digest.eq_ignore_ascii_case(expected) {
+                return Err(anyhow::anyhow!(
+                    "فشل التحقق من سلامة الصورة: البصمة المحسوبة {} لا تطابق المتوقعة {}",
+                    digest,
+                    expected
+                ));
+            }
+            info!("✅ تطابقت بصمة الصورة مع البصمة المتوقعة");
+        }
+
+        if let Some(sig) = signature {
+            self.verify_image_signature(&digest, sig)?;
+            info!("✅ تم التحقق من توقيع الصورة بنجاح");
+        }
+
+        Ok(digest)
+    }
+
+    /// يقرأ الملف على دفعات ثابتة الحجم (`CHECKSUM_BUFFER_SIZE`) ويحسب
+    /// بصمة SHA-256 بشكل تدريجي دون تحميل الملف كاملاً في الذاكرة.
+    fn compute_sha256_streaming(&self, image_path: &str) -> Result<String> {
+        let mut file = fs::File::open(image_path)
+            .with_context(|| format!("فشل في فتح الصورة {} للتحقق من سلامتها", image_path))?;
+
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+
+        loop {
+            let bytes_read = file
+                .read(&mut buffer)
+                .context("فشل في قراءة الصورة أثناء حساب البصمة")?;
+            if bytes_read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..bytes_read]);
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// يتحقق من توقيع Ed25519 محسوب على بصمة SHA-256 للصورة (وليس المحتوى
+    /// الخام) حتى يبقى التحقق من التوقيع متوافقاً مع القراءة المجزأة أعلاه.
+    fn verify_image_signature(&self, digest_hex: &str, signature: &ImageSignature) -> Result<()> {
+        let public_key_b64 = fs::read_to_string(&signature.public_key_path)
+            .with_context(|| format!("فشل في قراءة مفتاح التحقق {}", signature.public_key_path))?;
+
+        let public_key_bytes = base64::decode(public_key_b64.trim())
+            .context("مفتاح التحقق ليس بترميز base64 صحيح")?;
+        let public_key_bytes: [u8; 32] = public_key_bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("مفتاح التحقق يجب أن يكون 32 بايت (Ed25519)"))?;
+        let verifying_key = VerifyingKey::from_bytes(&public_key_bytes)
+            .context("مفتاح التحقق غير صالح")?;
+
+        let signature_bytes = base64::decode(signature.signature_base64.trim())
+            .context("التوقيع ليس بترميز base64 صحيح")?;
+        let signature_bytes: [u8; 64] = signature_bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("التوقيع يجب أن يكون 64 بايت (Ed25519)"))?;
+        let signature = Signature::from_bytes(&signature_bytes);
+
+        verifying_key
+            .verify(digest_hex.as_bytes(), &signature)
+            .context("فشل التحقق من صحة توقيع الصورة")?;
+
+        Ok(())
+    }
+
+    /// يزرع وسم MD5 في صورة أنتجها `dos_safar` (مثلاً عبر `build_boot_image`)
+    /// حتى يكتشف `verify_image_md5_tag` لاحقاً أي بتر أو تلف طرأ عليها، بنفس
+    /// أسلوب implantisomd5/checkisomd5 في Fedora/Anaconda. لصور ISO يُكتب
+    /// الوسم داخل منطقة بيانات تطبيق PVD (القطاع 16، الإزاحة 883)؛ لصور IMG
+    /// التي لا تملك منطقة مكافئة يُكتب في ملف جانبي `<path>.md5`.
+    ///
+    /// `skip_sectors` يحجز قطاعات لاحقة (تُكتب بعد هذا الاستدعاء) يتجاهلها
+    /// التحقق لاحقاً عند إعادة حساب البصمة؛ مرّر 0 إن كان الوسم آخر ما يُكتب.
+    pub fn implant_md5_tag(&self, image_path: &str, skip_sectors: u64) -> Result<()> {
+        let digest = self.compute_md5_skipping_tail(image_path, skip_sectors, None)?;
+        let tag = format!("DOS_SAFAR_ISO_MD5 = {};SKIPSECTORS = {};", digest, skip_sectors);
+
+        if Self::is_iso_path(image_path) {
+            self.write_iso_pvd_tag(image_path, &tag)
+        } else {
+            let sidecar = format!("{}.md5", image_path);
+            fs::write(&sidecar, &tag)
+                .with_context(|| format!("فشل في كتابة وسم MD5 الجانبي {}", sidecar))
+        }
+    }
+
+    /// يتحقق من وسم MD5 الذي زرعه `implant_md5_tag` (إن وُجد): يقرأه من
+    /// منطقة بيانات تطبيق PVD لملفات ISO أو من الملف الجانبي `.md5` لملفات
+    /// IMG، ثم يعيد حساب بصمة MD5 على دفعات ثابتة الحجم متجاهلاً آخر
+    /// `SKIPSECTORS` قطاع (القطاعات التي حجزها `implant_md5_tag` لما كُتب
+    /// بعد الزرع؛ الوسم نفسه في حقل PVD أو الملف الجانبي)، مبلِّغاً نسبة الإنجاز عبر
+    /// `progress` بمرحلة `InstallationStage::Verifying`. الصور التي لا تحمل
+    /// وسماً (من مصدر خارجي لم يُنتجها `dos_safar`) تُعتبر ناجحة ضمنياً
+    /// بدلاً من رفضها دون سبب.
+    pub async fn verify_image_md5_tag(
+        &self,
+        image_path: &str,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<Md5TagVerification> {
+        let tag = if Self::is_iso_path(image_path) {
+            self.read_iso_pvd_tag(image_path)?
+        } else {
+            self.read_md5_sidecar(image_path)?
+        };
+
+        let Some((stored_md5, skip_sectors)) = tag else {
+            debug!(
+                "ℹ️ لا يوجد وسم MD5 مزروع في {} - تخطي هذا التحقق الإضافي",
+                image_path
+            );
+            return Ok(Md5TagVerification {
+                passed: true,
+                stored_md5: String::new(),
+                computed_md5: String::new(),
+            });
+        };
+
+        let computed_md5 = self.compute_md5_skipping_tail(image_path, skip_sectors, progress)?;
+        let passed = computed_md5.eq_ignore_ascii_case(&stored_md5);
+
+        if passed {
+            info!("✅ تطابق وسم MD5 المزروع في {}", image_path);
+        } else {
+            warn!(
+                "⚠️ فشل التحقق من وسم MD5 في {}: البصمة المحسوبة {} لا تطابق المخزَّنة {}",
+                image_path, computed_md5, stored_md5
+            );
+        }
+
+        Ok(Md5TagVerification {
+            passed,
+            stored_md5,
+            computed_md5,
+        })
+    }
+
+    fn is_iso_path(image_path: &str) -> bool {
+        Path::new(image_path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.eq_ignore_ascii_case("iso"))
+            .unwrap_or(false)
+    }
+
+    /// يقرأ الملف على دفعات ثابتة الحجم (`CHECKSUM_BUFFER_SIZE`) ويحسب بصمة
+    /// MD5 تدريجياً متجاهلاً آخر `skip_sectors` قطاع من 2048 بايت، مبلّغاً
+    /// نسبة الإنجاز عبر `progress` إن وُجد حتى يظهر تحقق الملفات الكبيرة في
+    /// الواجهة.
+    ///
+    /// لصور ISO يُستثنى أيضاً حقل بيانات تطبيق PVD (حيث يقيم الوسم نفسه) من
+    /// الحساب بتصفيره بدل تجاهل الفرق بين "قبل/بعد" الزرع - نفس أسلوب
+    /// checkisomd5: `implant_md5_tag` يحسب البصمة قبل أن يكتب `write_iso_pvd_tag`
+    /// الوسم في ذلك الحقل، بينما `verify_image_md5_tag` يعيد حسابها بعد أن
+    /// أصبح الحقل يحمل نص الوسم؛ دون هذا الاستثناء تفشل كل صورة زُرع فيها وسم
+    /// في أول تحقق لاحق لها رغم سلامتها.
+    fn compute_md5_skipping_tail(
+        &self,
+        image_path: &str,
+        skip_sectors: u64,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<String> {
+        let mut file = fs::File::open(image_path)
+            .with_context(|| format!("فشل في فتح {} لحساب بصمة MD5", image_path))?;
+        let total_len = file
+            .metadata()
+            .context("فشل في قراءة حجم الصورة")?
+            .len();
+        let skip_bytes = skip_sectors * ISO_SECTOR_SIZE;
+        let hashed_len = total_len.saturating_sub(skip_bytes);
+
+        let excluded_range = Self::is_iso_path(image_path).then(|| {
+            let start = ISO_PVD_SECTOR * ISO_SECTOR_SIZE + ISO_PVD_APP_DATA_OFFSET;
+            (start, start + ISO_PVD_APP_DATA_SIZE)
+        });
+
+        let mut hasher = Md5::new();
+        let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+        let mut processed: u64 = 0;
+
+        while processed < hashed_len {
+            let remaining = hashed_len - processed;
+            let to_read = remaining.min(buffer.len() as u64) as usize;
+            let bytes_read = file
+                .read(&mut buffer[..to_read])
+                .context("فشل في القراءة أثناء حساب بصمة MD5")?;
+            if bytes_read == 0 {
+                break;
+            }
+
+            if let Some((excl_start, excl_end)) = excluded_range {
+                let chunk_start = processed;
+                let chunk_end = processed + bytes_read as u64;
+                if chunk_start < excl_end && chunk_end > excl_start {
+                    let zero_from = (excl_start.max(chunk_start) - chunk_start) as usize;
+                    let zero_to = (excl_end.min(chunk_end) - chunk_start) as usize;
+                    buffer[zero_from..zero_to].fill(0);
+                }
+            }
+
+            hasher.update(&buffer[..bytes_read]);
+            processed += bytes_read as u64;
+
+            if let Some(callback) = progress {
+                callback(OSInstallationProgress {
+                    stage: InstallationStage::Verifying,
+                    progress_percentage: (processed as f32 / hashed_len.max(1) as f32) * 100.0,
+                    current_operation: format!(
+                        "التحقق من وسم MD5 ({}MB)",
+                        processed / 1024 / 1024
+                    ),
+                    estimated_time_remaining: None,
+                    error_message: None,
+                });
+            }
+        }
+
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// يكتب وسم MD5 داخل منطقة بيانات تطبيق PVD (القطاع 16، الإزاحة 883)،
+    /// مرفوضاً الكتابة إن تجاوز الوسم حجم الحقل المحجوز (512 بايت) بدل
+    /// تشويه البايتات المجاورة له في PVD.
+    fn write_iso_pvd_tag(&self, image_path: &str, tag: &str) -> Result<()> {
+        if tag.len() as u64 > ISO_PVD_APP_DATA_SIZE {
+            return Err(anyhow::anyhow!(
+                "وسم MD5 ({} بايت) أطول من حقل بيانات تطبيق PVD ({} بايت)",
+                tag.len(),
+                ISO_PVD_APP_DATA_SIZE
+            ));
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .open(image_path)
+            .with_context(|| format!("فشل في فتح {} لزرع وسم MD5", image_path))?;
+
+        let offset = ISO_PVD_SECTOR * ISO_SECTOR_SIZE + ISO_PVD_APP_DATA_OFFSET;
+        file.seek(SeekFrom::Start(offset))
+            .context("فشل في الانتقال إلى منطقة بيانات تطبيق PVD")?;
+
+        let mut padded = tag.as_bytes().to_vec();
+        padded.resize(ISO_PVD_APP_DATA_SIZE as usize, 0);
+        file.write_all(&padded)
+            .context("فشل في كتابة وسم MD5 داخل PVD")?;
+
+        Ok(())
+    }
+
+    /// يقرأ منطقة بيانات تطبيق PVD ويحلّل وسم MD5 منها إن وُجد، أو `None` إن
+    /// كانت الصورة أصغر من أن تحتوي PVD أو لم يحمل حقلها وسماً معروفاً
+    /// (صورة من مصدر خارجي لم يزرعها `implant_md5_tag`).
+    fn read_iso_pvd_tag(&self, image_path: &str) -> Result<Option<(String, u64)>> {
+        let mut file = fs::File::open(image_path)
+            .with_context(|| format!("فشل في فتح {} لقراءة وسم MD5", image_path))?;
+        let offset = ISO_PVD_SECTOR * ISO_SECTOR_SIZE + ISO_PVD_APP_DATA_OFFSET;
+        if file.metadata().context("فشل في قراءة حجم الصورة")?.len() < offset + ISO_PVD_APP_DATA_SIZE {
+            return Ok(None);
+        }
+
+        file.seek(SeekFrom::Start(offset))
+            .context("فشل في الانتقال إلى منطقة بيانات تطبيق PVD")?;
+        let mut buffer = vec![0u8; ISO_PVD_APP_DATA_SIZE as usize];
+        file.read_exact(&mut buffer)
+            .context("فشل في قراءة منطقة بيانات تطبيق PVD")?;
+
+        Ok(Self::parse_md5_tag(&String::from_utf8_lossy(&buffer)))
+    }
+
+    /// يقرأ الملف الجانبي `<image_path>.md5` ويحلّل وسم MD5 منه إن وُجد.
+    fn read_md5_sidecar(&self, image_path: &str) -> Result<Option<(String, u64)>> {
+        let sidecar = format!("{}.md5", image_path);
+        if !Path::new(&sidecar).exists() {
+            return Ok(None);
+        }
+
+        let raw = fs::read_to_string(&sidecar)
+            .with_context(|| format!("فشل في قراءة وسم MD5 الجانبي {}", sidecar))?;
+        Ok(Self::parse_md5_tag(&raw))
+    }
+
+    /// يحلّل الصيغة `DOS_SAFAR_ISO_MD5 = <hex>;SKIPSECTORS = <n>;` من نص خام
+    /// (قد يحوي بايتات صفرية زائدة بعد الوسم في حالة PVD). يعيد `None` إن لم
+    /// يُعثر على حقل البصمة.
+    fn parse_md5_tag(raw: &str) -> Option<(String, u64)> {
+        let trimmed = raw.trim_end_matches('\0').trim();
+
+        let mut md5 = None;
+        let mut skip_sectors = None;
+        for segment in trimmed.split(';') {
+            let segment = segment.trim();
+            if let Some(value) = segment.strip_prefix("DOS_SAFAR_ISO_MD5 = ") {
+                md5 = Some(value.trim().to_string());
+            } else if let Some(value) = segment.strip_prefix("SKIPSECTORS = ") {
+                skip_sectors = value.trim().parse::<u64>().ok();
+            }
+        }
+
+        Some((md5?, skip_sectors.unwrap_or(0)))
+    }
+
+    /// إنشاء نسخة احتياطية من نظام تشغيل
+    pub async fn backup_os(&self, os_name: &str) -> Result<OSBackup> {
+        info!("💾 إنشاء نسخة احتياطية من {}", os_name);
+
+        let os_path = self.os_storage_path.join(os_name);
+        if !os_path.exists() {
+            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
+        }
+
+        let backup_name = format!("{}_{}", os_name, chrono::Utc::now().format("%Y%m%d_%H%M%S"));
+        let backup_file = self.backup_path.join(format!("{}.tar.gz", backup_name));
+
+        // إنشاء الأرشيف
+        let output = Command::new("tar")
+            .args(&[
+                "-czf", 
+                backup_file.to_str().unwrap(),
+                "-C", 
+                self.os_storage_path.to_str().unwrap(),
+                os_name
+            ])
+            .output()
+            .context("فشل في تنفيذ أمر tar")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("فشل في إنشاء النسخة الاحتياطية: {}", error));
+        }
+
+        // حساب حجم النسخة الاحتياطية
+        let backup_size = fs::metadata(&backup_file)
+            .context("فشل في قراءة حجم النسخة الاحتياطية")?
+            .len() / 1024 / 1024; // تحويل إلى MB
+
+        let backup = OSBackup {
+            os_name: os_name.to_string(),
+            backup_date: chrono::Utc::now(),
+            backup_size_mb: backup_size,
+            backup_path: backup_file.to_string_lossy().to_string(),
+            is_bootable: true, // سنفترض أنه قابل للتشغيل
+        };
+
+        info!("✅ تم إنشاء نسخة احتياطية من {} ({}MB)", os_name, backup_size);
+        Ok(backup)
+    }
+
+    /// استعادة نظام من نسخة احتياطية
+    pub async fn restore_os_from_backup(&self, backup: &OSBackup) -> Result<()> {
+        info!("🔄 استعادة {} من النسخة الاحتياطية", backup.os_name);
+
+        let backup_path = Path::new(&backup.backup_path);
+        if !backup_path.exists() {
+            return Err(anyhow::anyhow!("النسخة الاحتياطية غير موجودة"));
+        }
+
+        // حذف النظام الحالي إذا كان موجوداً
+        let os_path = self.os_storage_path.join(&backup.os_name);
+        if os_path.exists() {
+            fs::remove_dir_all(&os_path)
+                .context("فشل في حذف النظام الحالي")?;
+        }
+
+        // استخراج النسخة الاحتياطية
+        let output = Command::new("tar")
+            .args(&[
+                "-xzf",
+                backup.backup_path.as_str(),
+                "-C",
+                self.os_storage_path.to_str().unwrap()
+            ])
+            .output()
+            .context("فشل في تنفيذ أمر استخراج")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("فشل في استعادة النسخة الاحتياطية: {}", error));
+        }
+
+        // إعادة تسجيل النظام
+        self.register_os(&backup.os_name, &os_path).await?;
+
+        info!("✅ تم استعادة {} بنجاح", backup.os_name);
+        Ok(())
+    }
+
+    /// حذف نظام تشغيل
+    pub async fn remove_os(&self, os_name: &str, create_backup: bool) -> Result<()> {
+        info!("🗑️ حذف نظام التشغيل: {}", os_name);
+
+        let os_path = self.os_storage_path.join(os_name);
+        if !os_path.exists() {
+            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
+        }
+
+        // إنشاء نسخة احتياطية قبل الحذف إذا طُلب ذلك
+        if create_backup {
+            info!("💾 إنشاء نسخة احتياطية قبل الحذف");
+            self.backup_os(os_name).await?;
+        }
+
+        // حذف النظام
+        fs::remove_dir_all(&os_path)
+            .context("فشل في حذف مجلد النظام")?;
+
+        // إزالة من قائمة الأنظمة المتاحة
+        self.unregister_os(os_name).await?;
+
+        info!("✅ تم حذف {} بنجاح", os_name);
+        Ok(())
+    }
+
+    /// إعداد النظام الافتراضي للتشغيل
+    pub async fn set_default_os(&self, os_name: &str) -> Result<()> {
+        info!("⚙️ تعيين {} كنظام افتراضي", os_name);
+
+        // التحقق من وجود النظام
+        if !self.os_exists(os_name) {
+            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
+        }
+
+        // تحديث ملف التكوين
+        let mut boot_config = self.load_boot_configuration().await?;
+        boot_config.default_os = Some(os_name.to_string());
+        self.save_boot_configuration(&mut boot_config).await?;
+
+        info!("✅ تم تعيين {} كنظام افتراضي", os_name);
+        Ok(())
+    }
+
+    /// الحصول على قائمة الأنظمة المتاحة
+    pub async fn get_available_systems(&self) -> Result<Vec<OperatingSystem>> {
+        debug!("📋 جمع قائمة الأنظمة المتاحة");
+
+        let mut systems = Vec::new();
+
+        // مسح مجلد أنظمة التشغيل
+        if self.os_storage_path.exists() {
+            let entries = fs::read_dir(&self.os_storage_path)
+                .context("فشل في قراءة مجلد الأنظمة")?;
+
+            for entry in entries.flatten() {
+                if entry.path().is_dir() {
+                    if let Ok(os) = self.analyze_os_directory(&entry.path()).await {
+                        systems.push(os);
+                    }
+                }
+            }
+        }
+
+        // مسح أنظمة إضافية في مواقع أخرى
+        systems.extend(self.scan_external_systems().await?);
+
+        // ترتيب حسب آخر استخدام
+        systems.sort_by(|a, b| {
+            match (&a.last_used, &b.last_used) {
+                (Some(a_time), Some(b_time)) => b_time.cmp(a_time),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => a.name.cmp(&b.name),
+            }
+        });
+
+        Ok(systems)
+    }
+
+    /// الحصول على قائمة النسخ الاحتياطية
+    pub async fn get_backups(&self) -> Result<Vec<OSBackup>> {
+        debug!("📦 جمع قائمة النسخ الاحتياطية");
+
+        let mut backups = Vec::new();
+
+        if self.backup_path.exists() {
+            let entries = fs::read_dir(&self.backup_path)
+                .context("فشل في قراءة مجلد النسخ الاحتياطية")?;
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|s| s.to_str()) == Some("gz") {
+                    if let Ok(backup) = self.analyze_backup_file(&path).await {
+                        backups.push(backup);
+                    }
+                }
+            }
+        }
+
+        // ترتيب حسب التاريخ (الأحدث أولاً)
+        backups.sort_by(|a, b| b.backup_date.cmp(&a.backup_date));
+
+        Ok(backups)
+    }
+
+    /// تحديث نظام تشغيل موجود مع حفظ ودمج تكوين المستخدم
+    ///
+    /// قبل استبدال الشجرة، تُخزَّن مؤقتاً كل الملفات المطابقة لـ
+    /// `config.os_manager.preserve_globs`/`newer_wins_globs`. بعد التحديث
+    /// تُعاد ملفات `preserve_globs` بلا شروط، بينما تُطبَّق على
+    /// `newer_wins_globs` سياسة "الأحدث يفوز": تُستعاد النسخة القديمة فقط
+    /// إن كانت أحدث من الواردة. أي ملف تغيّر في الجهتين يُسجَّل في
+    /// `conflicts` بدل الكتابة فوقه بصمت.
+    pub async fn update_os(&self, os_name: &str, update_source: &str) -> Result<OsUpdateResult> {
+        info!("🔄 تحديث نظام {}", os_name);
+
+        let os_path = self.os_storage_path.join(os_name);
+        if !os_path.exists() {
+            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
+        }
+
+        let current_link = os_path.join("current");
+        let content_path = if current_link.exists() {
+            current_link
+        } else {
+            os_path.clone()
+        };
+
+        // إنشاء نسخة احتياطية قبل التحديث
+        let backup = self.backup_os(os_name).await?;
+        info!("💾 تم إنشاء نسخة احتياطية: {}", backup.backup_path);
+
+        // تخزين الملفات المراد حفظها أو دمجها مؤقتاً قبل استبدال الشجرة
+        let staging_dir = std::env::temp_dir().join(format!(
+            "dos_safar_update_preserve_{}_{}",
+            os_name,
+            std::process::id()
+        ));
+        let staged_preserve = self.stage_matching_files(
+            &content_path,
+            &staging_dir.join("preserve"),
+            &self.config.os_manager.preserve_globs,
+        )?;
+        let staged_newer_wins = self.stage_matching_files(
+            &content_path,
+            &staging_dir.join("newer_wins"),
+            &self.config.os_manager.newer_wins_globs,
+        )?;
+
+        // محاولة التحديث
+        let outcome = match self.perform_os_update(os_name, update_source).await {
+            Ok(_) => {
+                let merge_result = self.merge_preserved_files(
+                    &content_path,
+                    &staging_dir,
+                    &staged_preserve,
+                    &staged_newer_wins,
+                );
+                match merge_result {
+                    Ok(result) => {
+                        info!(
+                            "✅ تم تحديث {} بنجاح ({} ملف محفوظ، {} تعارض)",
+                            os_name,
+                            result.preserved_files.len() + result.newer_wins_applied.len(),
+                            result.conflicts.len()
+                        );
+                        Ok(result)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => {
+                error!("❌ فشل في تحديث {}: {}", os_name, e);
+
+                // استعادة النسخة الاحتياطية عند الفشل
+                warn!("🔄 استعادة النسخة الاحتياطية");
+                self.restore_os_from_backup(&backup).await?;
+
+                Err(e)
+            }
+        };
+
+        let _ = fs::remove_dir_all(&staging_dir);
+        outcome
+    }
+
+    /// ينسخ كل ملف تحت `source_root` تطابق مساره النسبي أحد أنماط `patterns`
+    /// (دعم `*` فقط) إلى `dest_root` محافظاً على المسار النسبي، ويعيد قائمة
+    /// تلك المسارات النسبية.
+    fn stage_matching_files(
+        &self,
+        source_root: &Path,
+        dest_root: &Path,
+        patterns: &[String],
+    ) -> Result<Vec<String>> {
+        let mut staged = Vec::new();
+        if patterns.is_empty() || !source_root.exists() {
+            return Ok(staged);
+        }
+
+        let mut files = Vec::new();
+        Self::collect_files_recursive(source_root, source_root, &mut files)?;
+
+        for relative in files {
+            let relative_str = relative.to_string_lossy().replace('\\', "/");
+            if !patterns.iter().any(|p| Self::glob_match(p, &relative_str)) {
+                continue;
+            }
+
+            let source_file = source_root.join(&relative);
+            let dest_file = dest_root.join(&relative);
+            if let Some(parent) = dest_file.parent() {
+                fs::create_dir_all(parent)
+                    .context("فشل في إنشاء مجلد التخزين المؤقت للتكوين")?;
+            }
+            Self::copy_preserving_mtime(&source_file, &dest_file)
+                .with_context(|| format!("فشل في حفظ {} مؤقتاً قبل التحديث", relative_str))?;
+            staged.push(relative_str);
+        }
+
+        Ok(staged)
+    }
+
+    /// مثل `fs::copy` لكنه ينقل وقت تعديل المصدر إلى الملف المنسوخ أيضاً،
+    /// بدل أن يختم النسخة بوقت النسخ نفسه - ضروري هنا لأن `merge_preserved_files`
+    /// يقارن وقت تعديل الملف المخزَّن مؤقتاً بوقت تعديل الملف المثبَّت حديثاً
+    /// لحسم سياسة "الأحدث يفوز"، وبدون هذا تكون النسخة المخزَّنة مختومة دوماً
+    /// بوقت التخزين (أي: قبل التثبيت الجديد مباشرة) فتخسر هذه المقارنة دائماً.
+    fn copy_preserving_mtime(source: &Path, dest: &Path) -> Result<()> {
+        fs::copy(source, dest)?;
+        let modified = fs::metadata(source)?.modified()?;
+        fs::File::options()
+            .write(true)
+            .open(dest)?
+            .set_modified(modified)?;
+        Ok(())
+    }
+
+    fn collect_files_recursive(
+        base: &Path,
+        dir: &Path,
+        out: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir).context("فشل في قراءة مجلد النظام")? {
+            let entry = entry.context("فشل في قراءة مدخل من مجلد النظام")?;
+            let path = entry.path();
+            if path.is_dir() {
+                // تجاهل رابط "current" حتى لا نمشي على نفس الملفات مرتين
+                if path.file_name().and_then(|n| n.to_str()) == Some("current") {
+                    continue;
+                }
+                Self::collect_files_recursive(base, &path, out)?;
+            } else if let Ok(relative) = path.strip_prefix(base) {
+                out.push(relative.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    /// مطابقة أنماط بسيطة تدعم `*` فقط (بدون اعتماد مكتبة glob خارجية).
+    fn glob_match(pattern: &str, text: &str) -> bool {
+        fn helper(p: &[u8], t: &[u8]) -> bool {
+            match (p.first(), t.first()) {
+                (None, None) => true,
+                (Some(b'*'), _) => {
+                    helper(&p[1..], t) || (!t.is_empty() && helper(p, &t[1..]))
+                }
+                (Some(pc), Some(tc)) if pc == tc => helper(&p[1..], &t[1..]),
+                _ => false,
+            }
+        }
+        helper(pattern.as_bytes(), text.as_bytes())
+    }
+
+    /// يعيد ملفات `preserve_globs` بلا شروط، ويطبّق سياسة "الأحدث يفوز" على
+    /// ملفات `newer_wins_globs` مقارناً وقت التعديل، مسجلاً أي ملف تغيّر في
+    /// الجهتين ضمن `conflicts` بدل الكتابة فوقه بصمت.
+    fn merge_preserved_files(
+        &self,
+        content_path: &Path,
+        staging_dir: &Path,
+        preserved: &[String],
+        newer_wins: &[String],
+    ) -> Result<OsUpdateResult> {
+        let mut result = OsUpdateResult::default();
+
+        for relative in preserved {
+            let staged_file = staging_dir.join("preserve").join(relative);
+            let dest_file = content_path.join(relative);
+            if let Some(parent) = dest_file.parent() {
+                fs::create_dir_all(parent)
+                    .context("فشل في إنشاء مجلد الوجهة أثناء استعادة التكوين")?;
+            }
+            fs::copy(&staged_file, &dest_file)
+                .with_context(|| format!("فشل في استعادة {} بعد التحديث", relative))?;
+            result.preserved_files.push(relative.clone());
+        }
+
+        for relative in newer_wins {
+            let staged_file = staging_dir.join("newer_wins").join(relative);
+            let dest_file = content_path.join(relative);
+
+            if !dest_file.exists() {
+                if let Some(parent) = dest_file.parent() {
+                    fs::create_dir_all(parent)
+                        .context("فشل في إنشاء مجلد الوجهة أثناء استعادة التكوين")?;
+                }
+                fs::copy(&staged_file, &dest_file)
+                    .with_context(|| format!("فشل في استعادة {} بعد التحديث", relative))?;
+                result.newer_wins_applied.push(relative.clone());
+                continue;
+            }
+
+            let old_content = fs::read(&staged_file).unwrap_or_default();
+            let new_content = fs::read(&dest_file).unwrap_or_default();
+            if old_content == new_content {
+                continue;
+            }
+
+            // الملف تغيّر في الجهتين: نسجله كتعارض حتى لو حُسم تلقائياً
+            result.conflicts.push(relative.clone());
+
+            let old_modified = fs::metadata(&staged_file).and_then(|m| m.modified()).ok();
+            let new_modified = fs::metadata(&dest_file).and_then(|m| m.modified()).ok();
+
+            let old_is_newer = match (old_modified, new_modified) {
+                (Some(old_time), Some(new_time)) => old_time > new_time,
+                (Some(_), None) => true,
+                _ => false,
+            };
+
+            if old_is_newer {
+                fs::copy(&staged_file, &dest_file)
+                    .with_context(|| format!("فشل في استعادة {} بعد التحديث", relative))?;
+                result.newer_wins_applied.push(relative.clone());
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// تحسين أداء نظام تشغيل
+    pub async fn optimize_os(&self, os_name: &str) -> Result<()> {
+        info!("⚡ تحسين أداء {}", os_name);
+
+        let os_path = self.os_storage_path.join(os_name);
+        if !os_path.exists() {
+            return Err(anyhow::anyhow!("النظام {} غير موجود", os_name));
+        }
+
+        // تنظيف الملفات المؤقتة
+        self.cleanup_temporary_files(&os_path).await?;
+
+        // تحسين قاعدة البيانات (إذا وجدت)
+        self.optimize_databases(&os_path).await?;
+
+        // ضغط الملفات غير المستخدمة
+        self.compress_unused_files(&os_path).await?;
+
+        // تحديث فهرس الملفات
+        self.update_file_index(&os_path).await?;
+
+        if let Some(reporter) = self.reporter() {
+            reporter.done("optimize", Some(os_name)).await;
+        }
+
+        info!("✅ تم تحسين {} بنجاح", os_name);
+        Ok(())
+    }
+
+    // =====================================
+    // وظائف مساعدة داخلية
+    // =====================================
+
+    fn detect_image_type(&self, image_path: &str) -> Result<ImageType> {
+        let path = Path::new(image_path);
+        let extension = path.extension()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| anyhow::anyhow!("لا يمكن تحديد نوع الصورة"))?
+            .to_lowercase();
+
+        match extension.as_str() {
+            "iso" => Ok(ImageType::ISO),
+            "img" => Ok(ImageType::IMG),
+            "tar" | "tgz" => Ok(ImageType::TAR),
+            "zip" => Ok(ImageType::ZIP),
+            _ => {
+                // محاولة تحديد النوع من محتوى الملف
+                self.detect_image_type_by_content(image_path)
+            }
+        }
+    }
+
+    /// يحدد معمارية الجهاز المضيف الحالي من `std::env::consts::ARCH`.
+    fn detect_host_arch() -> Option<TargetArch> {
+        match std::env::consts::ARCH {
+            "x86_64" => Some(TargetArch::X86_64),
+            "aarch64" => Some(TargetArch::Aarch64),
+            "riscv64" => Some(TargetArch::Riscv64),
+            "arm" => Some(TargetArch::Armv7),
+            _ => None,
+        }
+    }
+
+    /// يحاول تحديد معمارية نظام مثبَّت بفحص نواته أو ملف ELF داخله عبر أداة
+    /// `file`، بنفس أسلوب `detect_image_type_by_content`. يعيد `None` إن
+    /// تعذر العثور على ملف نواة معروف أو تعذر تحديد معماريته بثقة، بدلاً من
+    /// افتراض عدم التوافق.
+    fn detect_image_arch(&self, install_path: &Path) -> Option<TargetArch> {
+        for candidate in KERNEL_CANDIDATES {
+            let candidate_path = install_path.join(candidate);
+            if candidate_path.exists() {
+                if let Some(arch) = self.detect_arch_via_file_command(&candidate_path) {
+                    return Some(arch);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// يعيد مسار أول ملف نواة معروف موجود تحت `install_path`، أو `None` إن
+    /// لم يوجد أي منها (صورة بلا نواة منفصلة، مثل صورة rootfs خالصة).
+    fn find_kernel_path(&self, install_path: &Path) -> Option<PathBuf> {
+        KERNEL_CANDIDATES
+            .iter()
+            .map(|candidate| install_path.join(candidate))
+            .find(|path| path.exists())
+    }
+
+    fn detect_arch_via_file_command(&self, path: &Path) -> Option<TargetArch> {
+        let output = Command::new("file").arg(path).output().ok()?;
+        let file_info = String::from_utf8_lossy(&output.stdout).to_lowercase();
+
+        if file_info.contains("x86-64") || file_info.contains("x86_64") {
+            Some(TargetArch::X86_64)
+        } else if file_info.contains("aarch64") || file_info.contains("arm64") {
+            Some(TargetArch::Aarch64)
+        } else if file_info.contains("risc-v") || file_info.contains("riscv") {
+            Some(TargetArch::Riscv64)
+        } else if file_info.contains("arm") {
+            Some(TargetArch::Armv7)
+        } else {
+            None
+        }
+    }
+
+    fn detect_image_type_by_content(&self, image_path: &str) -> Result<ImageType> {
+        let output = Command::new("file")
+            .arg(image_path)
+            .output()
+            .context("فشل في تحديد نوع الملف")?;
+
+        let file_info = String::from_utf8_lossy(&output.stdout).to_lowercase();
+
+        if file_info.contains("iso") {
+            Ok(ImageType::ISO)
+        } else if file_info.contains("tar") {
+            Ok(ImageType::TAR)
+        } else if file_info.contains("zip") {
+            Ok(ImageType::ZIP)
+        } else {
+            Ok(ImageType::IMG) // افتراضي
+        }
+    }
+
+    /// يقرأ نظام ملفات ISO 9660 داخل الصورة مباشرة (دون `mount -o loop`)
+    /// ويستخرج محتوياته. صور ISO الحقيقية (Raspberry Pi OS، Ubuntu،
+    /// إعادة توزيعات RetroPie/Batocera...) تحمل ISO 9660 لا FAT - محاولة
+    /// فتحها بـ `fatfs` (كما كان يحدث سابقاً هنا) تفشل دوماً لغياب BPB/FAT
+    /// صالح في بداية القرص؛ الجزء الآخر من هذا الملف (`read_iso_pvd_tag`)
+    /// يتعامل بالفعل مع PVD على القطاع 16 بشكل صحيح، فنعيد استخدام نفس
+    /// الفهم هنا بدل خلط التنسيقين.
+    async fn install_from_iso(&self, iso_path: &str, install_path: &Path) -> Result<()> {
+        info!("📀 تثبيت من ISO (قراءة ISO 9660 داخل العملية): {}", iso_path);
+
+        let iso_path = iso_path.to_string();
+        let install_path = install_path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::extract_iso9660_contents(&iso_path, &install_path))
+            .await
+            .context("فشل في تنفيذ استخراج ISO في خيط منفصل")??;
+
+        Ok(())
+    }
+
+    async fn install_from_img(&self, img_path: &str, install_path: &Path) -> Result<()> {
+        info!("💾 تثبيت من IMG (نسخ واستخراج FAT داخل العملية): {}", img_path);
+
+        // نسخ صورة القرص بالكامل داخل العملية على دفعات ثابتة بدلاً من `dd`
+        let dest_img = install_path.join("system.img");
+        let src = img_path.to_string();
+        let dest = dest_img.to_string_lossy().to_string();
+        tokio::task::spawn_blocking(move || Self::copy_file_streaming(&src, &dest))
+            .await
+            .context("فشل في تنفيذ نسخ IMG في خيط منفصل")??;
+
+        // استخراج محتويات قسم FAT مباشرة من الصورة دون `mount`
+        self.extract_img_contents(install_path).await?;
+
+        Ok(())
+    }
+
+    /// ينسخ ملفاً بدفعات ثابتة الحجم (`CHECKSUM_BUFFER_SIZE`) دون تحميله
+    /// بالكامل في الذاكرة، بديلاً لعملية `dd` الخارجية.
+    fn copy_file_streaming(src: &str, dst: &str) -> Result<()> {
+        let mut input =
+            fs::File::open(src).with_context(|| format!("فشل في فتح {} للنسخ", src))?;
+        let mut output =
+            fs::File::create(dst).with_context(|| format!("فشل في إنشاء {}", dst))?;
+
+        let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+        loop {
+            let bytes_read = input
+                .read(&mut buffer)
+                .context("فشل في القراءة أثناء نسخ الصورة")?;
+            if bytes_read == 0 {
+                break;
+            }
+            output
+                .write_all(&buffer[..bytes_read])
+                .context("فشل في الكتابة أثناء نسخ الصورة")?;
+        }
+
+        Ok(())
+    }
+
+    /// يفتح ملف الصورة كقرص تخزين ويقرأ نظام ملفات FAT منه مباشرة.
+    fn open_fat_filesystem(image_path: &str) -> Result<fatfs::FileSystem<fs::File>> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(image_path)
+            .with_context(|| format!("فشل في فتح صورة FAT {}", image_path))?;
+
+        fatfs::FileSystem::new(file, fatfs::FsOptions::new())
+            .context("فشل في قراءة نظام ملفات FAT من الصورة - هل الصورة بتنسيق FAT؟")
+    }
+
+    /// يحاول قراءة `etc/os-release` مباشرة من داخل صورة FAT (دون استخراج
+    /// كامل المحتوى على القرص كما تفعل `extract_fat_contents`)، لصور نادرة
+    /// تضع الجذر على FAT بدل ext4. يعيد `None` بهدوء إن لم تكن الصورة
+    /// بتنسيق FAT أصلاً أو لم يحمل الملف - أغلب صور Raspberry Pi تضع هذا
+    /// الملف على قسم جذر ext4 منفصل لا تدعمه هذه الأداة، فيسقط الاستدعاء
+    /// إلى تخمين الاسم/العلامات القديم في `menu::identify_os_from_image`.
+    pub(crate) fn read_os_release_from_fat_image(image_path: &str) -> Option<LinuxDistroInfo> {
+        let filesystem = Self::open_fat_filesystem(image_path).ok()?;
+        let mut file = filesystem.root_dir().open_file("etc/os-release").ok()?;
+        let mut content = String::new();
+        file.read_to_string(&mut content).ok()?;
+        Some(distro_detect::parse_os_release_content(&content))
+    }
+
+    /// يمشي على شجرة الدليل الجذر لنظام ملفات FAT ويستخرجها إلى `dest` على
+    /// القرص، دون أي نقطة تحميل وسيطة.
+    fn extract_fat_contents(image_path: &str, dest: &Path) -> Result<()> {
+        let filesystem = Self::open_fat_filesystem(image_path)?;
+        Self::extract_fat_dir_recursive(filesystem.root_dir(), dest)
+    }
+
+    fn extract_fat_dir_recursive(dir: fatfs::Dir<'_, fs::File>, dest: &Path) -> Result<()> {
+        fs::create_dir_all(dest)
+            .with_context(|| format!("فشل في إنشاء مجلد {}", dest.display()))?;
+
+        for entry in dir.iter() {
+            let entry = entry.context("فشل في قراءة مدخل من نظام ملفات FAT")?;
+            let name = entry.file_name();
+            if name == "." || name == ".." {
+                continue;
+            }
+
+            let dest_path = dest.join(&name);
+            if entry.is_dir() {
+                Self::extract_fat_dir_recursive(entry.to_dir(), &dest_path)?;
+            } else {
+                let mut src_file = entry.to_file();
+                let mut out_file = fs::File::create(&dest_path)
+                    .with_context(|| format!("فشل في إنشاء {}", dest_path.display()))?;
+
+                let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+                loop {
+                    let bytes_read = src_file
+                        .read(&mut buffer)
+                        .context("فشل في القراءة من نظام ملفات FAT")?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    out_file
+                        .write_all(&buffer[..bytes_read])
+                        .context("فشل في الكتابة أثناء استخراج FAT")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// يمشي على شجرة الدليل الجذر لنظام ملفات ISO 9660 ويستخرجها إلى `dest`
+    /// على القرص، دون أي نقطة تحميل وسيطة - نظير `extract_fat_contents` لكن
+    /// لتنسيق ISO 9660 الفعلي بدل FAT.
+    fn extract_iso9660_contents(image_path: &str, dest: &Path) -> Result<()> {
+        let mut file = fs::File::open(image_path)
+            .with_context(|| format!("فشل في فتح صورة ISO {}", image_path))?;
+        let root = Self::read_iso9660_root_record(&mut file, image_path)?;
+        Self::extract_iso9660_dir_recursive(&mut file, &root, dest)
+    }
+
+    /// يقرأ سجل الدليل الجذر من حقل `root_directory_record` ضمن PVD (الإزاحة
+    /// 156 من بداية قطاع PVD، القطاع 16)، وفق مواصفة ECMA-119/ISO 9660.
+    fn read_iso9660_root_record(file: &mut fs::File, image_path: &str) -> Result<Iso9660DirEntry> {
+        const ROOT_RECORD_OFFSET_IN_PVD: u64 = 156;
+
+        file.seek(SeekFrom::Start(ISO_PVD_SECTOR * ISO_SECTOR_SIZE + ROOT_RECORD_OFFSET_IN_PVD))
+            .context("فشل في الانتقال إلى سجل الدليل الجذر ضمن PVD")?;
+        let mut record = vec![0u8; 34];
+        file.read_exact(&mut record)
+            .with_context(|| format!("فشل في قراءة سجل الدليل الجذر من {} - هل الصورة بتنسيق ISO 9660؟", image_path))?;
+
+        Self::parse_iso9660_dir_record(&record)
+            .ok_or_else(|| anyhow::anyhow!("سجل الدليل الجذر في {} غير صالح", image_path))
+            .map(|(entry, _)| entry)
+    }
+
+    /// يحلّل سجل دليل واحد (34+ بايت، بنية Directory Record لـ ISO 9660) من
+    /// بداية `bytes`. يعيد المدخل مع طول السجل الفعلي (`bytes[0]`) كي يتقدم
+    /// المستدعي إلى السجل التالي، أو `None` إن كان `bytes[0]` صفراً (حشو
+    /// نهاية القطاع - سجلات ISO 9660 لا تمتد عبر حدود القطاعات أبداً).
+    fn parse_iso9660_dir_record(bytes: &[u8]) -> Option<(Iso9660DirEntry, usize)> {
+        let length = *bytes.first()? as usize;
+        if length == 0 || bytes.len() < length.max(34) {
+            return None;
+        }
+
+        let extent_lba = u32::from_le_bytes(bytes[2..6].try_into().ok()?);
+        let data_length = u32::from_le_bytes(bytes[10..14].try_into().ok()?);
+        let flags = *bytes.get(25)?;
+        let file_id_length = *bytes.get(32)? as usize;
+        let file_id_bytes = bytes.get(33..33 + file_id_length)?;
+
+        // معرّف الملف بطول بايت واحد بقيمة 0x00 أو 0x01 يمثّل "." أو ".."
+        // على التوالي، لا اسم ملف فعلي.
+        let (name, is_self_or_parent) = match file_id_bytes {
+            [0x00] => (".".to_string(), true),
+            [0x01] => ("..".to_string(), true),
+            other => (String::from_utf8_lossy(other).to_string(), false),
+        };
+
+        // أسماء الملفات (لا الأدلة) تحمل لاحقة إصدار ";1"؛ نزيلها لأن لا
+        // معنى لرقم الإصدار خارج ISO 9660 نفسه.
+        let is_directory = flags & 0x02 != 0;
+        let name = if is_directory || is_self_or_parent {
+            name
+        } else {
+            name.split(';').next().unwrap_or(&name).to_string()
+        };
+
+        Some((
+            Iso9660DirEntry {
+                name,
+                is_directory,
+                is_self_or_parent,
+                extent_lba,
+                data_length,
+            },
+            length,
+        ))
+    }
+
+    /// يقرأ كل سجلات الدليل ضمن امتداد (`extent_lba`/`data_length`) - كل
+    /// قطاع من الامتداد يُفسَّر على حدة لأن السجلات لا تمتد عبر حدود
+    /// القطاعات، وحشو الصفر الذي يسبق نهاية القطاع ينهي ذلك القطاع فقط لا
+    /// الامتداد كله.
+    fn read_iso9660_dir_entries(file: &mut fs::File, extent_lba: u32, data_length: u32) -> Result<Vec<Iso9660DirEntry>> {
+        file.seek(SeekFrom::Start(extent_lba as u64 * ISO_SECTOR_SIZE))
+            .context("فشل في الانتقال إلى امتداد الدليل")?;
+        let mut raw = vec![0u8; data_length as usize];
+        file.read_exact(&mut raw).context("فشل في قراءة امتداد الدليل")?;
+
+        let mut entries = Vec::new();
+        for sector in raw.chunks(ISO_SECTOR_SIZE as usize) {
+            let mut offset = 0usize;
+            while offset < sector.len() {
+                let Some((entry, record_len)) = Self::parse_iso9660_dir_record(&sector[offset..]) else {
+                    break; // حشو صفر - نهاية السجلات في هذا القطاع
+                };
+                entries.push(entry);
+                offset += record_len;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn extract_iso9660_dir_recursive(file: &mut fs::File, dir: &Iso9660DirEntry, dest: &Path) -> Result<()> {
+        fs::create_dir_all(dest)
+            .with_context(|| format!("فشل في إنشاء مجلد {}", dest.display()))?;
+
+        let entries = Self::read_iso9660_dir_entries(file, dir.extent_lba, dir.data_length)?;
+        for entry in entries {
+            if entry.is_self_or_parent {
+                continue;
+            }
+
+            // اسم معرّف خبيث في الصورة (مثلاً يحتوي "../") قد يُخرج
+            // `dest_path` خارج `dest` تماماً (zip-slip) - نرفض أي اسم يحمل
+            // فاصل مسار أو مكوّن "..‎" بدل الوثوق بما ورد في سجل الدليل.
+            if entry.name.contains('/') || entry.name.contains('\\') || entry.name == ".." || entry.name.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "اسم مدخل غير آمن في صورة ISO 9660: {:?}",
+                    entry.name
+                ));
+            }
+
+            let dest_path = dest.join(&entry.name);
+            if entry.is_directory {
+                Self::extract_iso9660_dir_recursive(file, &entry, &dest_path)?;
+            } else {
+                Self::extract_iso9660_file(file, &entry, &dest_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// ينسخ محتوى ملف واحد من امتداده في الصورة إلى `dest_path` على دفعات
+    /// ثابتة الحجم (`CHECKSUM_BUFFER_SIZE`)، نظير الفرع الخاص بالملفات في
+    /// `extract_fat_dir_recursive`.
+    fn extract_iso9660_file(file: &mut fs::File, entry: &Iso9660DirEntry, dest_path: &Path) -> Result<()> {
+        file.seek(SeekFrom::Start(entry.extent_lba as u64 * ISO_SECTOR_SIZE))
+            .context("فشل في الانتقال إلى امتداد الملف")?;
+        let mut out_file = fs::File::create(dest_path)
+            .with_context(|| format!("فشل في إنشاء {}", dest_path.display()))?;
+
+        let mut remaining = entry.data_length as u64;
+        let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+        while remaining > 0 {
+            let to_read = remaining.min(buffer.len() as u64) as usize;
+            let bytes_read = file
+                .read(&mut buffer[..to_read])
+                .context("فشل في القراءة من نظام ملفات ISO 9660")?;
+            if bytes_read == 0 {
+                break;
+            }
+            out_file
+                .write_all(&buffer[..bytes_read])
+                .context("فشل في الكتابة أثناء استخراج ISO 9660")?;
+            remaining -= bytes_read as u64;
+        }
+
+        Ok(())
+    }
+
+    /// يبني قسم إقلاع (ESP) قابل للتشغيل بتنسيق FAT من نظام مُثبَّت مسبقاً،
+    /// بنسخ `boot.sh`/`dos_safar_config.toml` المولَّدين داخله.
+    pub async fn build_boot_image(&self, os_name: &str, size_mb: u64) -> Result<PathBuf> {
+        info!("🛠️ بناء صورة إقلاع FAT لـ {} بحجم {}MB", os_name, size_mb);
+
+        let os_root = self.os_storage_path.join(os_name);
+        let current_link = os_root.join("current");
+        if !current_link.exists() {
+            return Err(anyhow::anyhow!("لا يوجد جيل حالي مسجل للنظام {}", os_name));
+        }
+
+        let boot_image_path = os_root.join("boot.img");
+        let os_name_owned = os_name.to_string();
+        let current_link_owned = current_link.clone();
+        let boot_image_path_owned = boot_image_path.clone();
+
+        tokio::task::spawn_blocking(move || {
+            Self::format_and_populate_esp(
+                &boot_image_path_owned,
+                size_mb,
+                &current_link_owned,
+                &os_name_owned,
+            )
+        })
+        .await
+        .context("فشل في تنفيذ بناء صورة الإقلاع في خيط منفصل")??;
+
+        // زرع وسم MD5 في الصورة التي أنتجناها للتو حتى يكتشف
+        // `verify_image_md5_tag` لاحقاً أي بتر أو تلف طرأ عليها
+        self.implant_md5_tag(&boot_image_path.to_string_lossy(), 0)
+            .context("فشل في زرع وسم MD5 داخل صورة الإقلاع")?;
+
+        info!("✅ تم بناء صورة الإقلاع: {}", boot_image_path.display());
+        Ok(boot_image_path)
+    }
+
+    fn format_and_populate_esp(
+        image_path: &Path,
+        size_mb: u64,
+        system_path: &Path,
+        os_name: &str,
+    ) -> Result<()> {
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(image_path)
+            .with_context(|| format!("فشل في إنشاء ملف صورة الإقلاع {}", image_path.display()))?;
+
+        file.set_len(size_mb * 1024 * 1024)
+            .context("فشل في تحديد حجم صورة الإقلاع")?;
+
+        fatfs::format_volume(&file, fatfs::FormatVolumeOptions::new())
+            .context("فشل في تهيئة صورة الإقلاع بنظام FAT")?;
+
+        let filesystem = fatfs::FileSystem::new(&file, fatfs::FsOptions::new())
+            .context("فشل في فتح صورة الإقلاع بعد تهيئتها")?;
+        let root = filesystem.root_dir();
+
+        for file_name in ["boot.sh", "dos_safar_config.toml"] {
+            let source = system_path.join(file_name);
+            if !source.exists() {
+                continue;
+            }
+
+            let contents = fs::read(&source)
+                .with_context(|| format!("فشل في قراءة {} من النظام {}", file_name, os_name))?;
+            let mut esp_file = root
+                .create_file(file_name)
+                .with_context(|| format!("فشل في إنشاء {} داخل صورة الإقلاع", file_name))?;
+            esp_file
+                .write_all(&contents)
+                .with_context(|| format!("فشل في كتابة {} داخل صورة الإقلاع", file_name))?;
+        }
+
+        Ok(())
+    }
+
+    async fn install_from_tar(&self, tar_path: &str, install_path: &Path) -> Result<()> {
+        info!("📦 تثبيت من TAR: {}", tar_path);
+
+        let output = Command::new("tar")
+            .args(&[
+                "-xf", tar_path,
+                "-C", install_path.to_str().unwrap()
+            ])
+            .output()
+            .context("فشل في استخراج TAR")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("فشل في استخراج TAR: {}", error));
+        }
+
+        Ok(())
+    }
+
+    async fn install_from_zip(&self, zip_path: &str, install_path: &Path) -> Result<()> {
+        info!("🗂️ تثبيت من ZIP: {}", zip_path);
+
+        let output = Command::new("unzip")
+            .args(&[
+                "-q", zip_path,
+                "-d", install_path.to_str().unwrap()
+            ])
+            .output()
+            .context("فشل في استخراج ZIP")?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow::anyhow!("فشل في استخراج ZIP: {}", error));
+        }
+
+        Ok(())
+    }
+
+    async fn extract_img_contents(&self, install_path: &Path) -> Result<()> {
+        let img_file = install_path.join("system.img");
+        if !img_file.exists() {
+            return Ok(()); // لا توجد صورة لاستخراجها
+        }
+
+        let img_file_str = img_file.to_string_lossy().to_string();
+        let install_path_owned = install_path.to_path_buf();
+
+        // نقرأ قسم FAT داخل الصورة مباشرة دون `mount`؛ إن لم تكن الصورة
+        // بتنسيق FAT (مثل صور بعض أنظمة Linux) نتجاهل الفشل بصمت كما كان
+        // يحدث سابقاً عند فشل `mount`.
+        let result = tokio::task::spawn_blocking(move || {
+            Self::extract_fat_contents(&img_file_str, &install_path_owned)
+        })
+        .await
+        .context("فشل في تنفيذ استخراج IMG في خيط منفصل")?;
+
+        if let Err(e) = result {
+            warn!("⚠️ تعذر استخراج محتويات الصورة كـ FAT: {}", e);
+        }
+
+        Ok(())
+    }
+
+    async fn configure_installed_os(
+        &self,
+        install_path: &Path,
+        os_name: &str,
+        target_arch: Option<TargetArch>,
+    ) -> Result<()> {
+        info!("⚙️ تكوين النظام المثبت: {}", os_name);
+
+        // إنشاء ملف التكوين الخاص بالنظام
+        let config_file = install_path.join("dos_safar_config.toml");
+        let os_config = format!(
+            r#"[system]
+name = "{}"
+install_date = "{}"
+version = "1.0"
+bootable = true
+
+[hardware]
+auto_detect = true
+optimize_for_gaming = true
+
+[display]
+auto_resolution = true
+safe_mode = false
+"#,
+            os_name,
+            chrono::Utc::now().to_rfc3339()
+        );
+
+        fs::write(&config_file, os_config)
+            .context("فشل في كتابة ملف التكوين")?;
+
+        // تطبيق تحسينات خاصة بالجهاز
+        self.apply_device_optimizations(install_path, target_arch).await?;
+
+        // إعداد البوت
+        self.setup_boot_configuration(install_path, os_name).await?;
+
+        Ok(())
+    }
+
+    async fn apply_device_optimizations(
+        &self,
+        install_path: &Path,
+        target_arch: Option<TargetArch>,
+    ) -> Result<()> {
+        // تحسينات Raspberry Pi لا تعني شيئاً لمعمارية غير ARM، حتى لو كان
+        // الجهاز المضيف نفسه Raspberry Pi (مثل تثبيت متعدد الإقلاع)
+        let is_arm_target = matches!(
+            target_arch,
+            None | Some(TargetArch::Aarch64) | Some(TargetArch::Armv7)
+        );
+
+        if is_arm_target && self.is_raspberry_pi() {
+            self.apply_raspberry_pi_optimizations(install_path).await?;
+        }
+
+        // تحسينات خاصة بأجهزة الألعاب المحمولة
+        if self.is_gaming_handheld() {
+            self.apply_gaming_handheld_optimizations(install_path).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn apply_raspberry_pi_optimizations(&self, install_path: &Path) -> Result<()> {
+        info!("🍓 تطبيق تحسينات Raspberry Pi");
+
+        // تكوين GPU memory split
+        let boot_config = install_path.join("config.txt");
+        if boot_config.exists() {
+            let mut config_content = fs::read_to_string(&boot_config)
+                .unwrap_or_default();
+
+            // إضافة تحسينات GPU
+            if !config_content.contains("gpu_mem") {
+                config_content.push_str("\n# DOS Safar GPU optimizations\n");
+                config_content.push_str("gpu_mem=128\n");
+                config_content.push_str("gpu_freq=500\n");
+                config_content.push_str("over_voltage=2\n");
+
+                fs::write(&boot_config, config_content)
+                    .context("فشل في تحديث config.txt")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn apply_gaming_handheld_optimizations(&self, install_path: &Path) -> Result<()> {
+        info!("🎮 تطبيق تحسينات أجهزة الألعاب المحمولة");
+
+        // تحسينات خاصة بالشاشات الصغيرة
+        let display_config = install_path.join("display_config.txt");
+        let display_settings = r#"# Gaming Handheld Display Settings
+hdmi_force_hotplug=1
+hdmi_group=2
+hdmi_mode=87
+hdmi_cvt=480 320 60 6 0 0 0
+display_rotate=0
+"#;
+
+        fs::write(&display_config, display_settings)
+            .context("فشل في كتابة تكوين الشاشة")?;
+
+        Ok(())
+    }
+
+    async fn setup_boot_configuration(&self, install_path: &Path, os_name: &str) -> Result<()> {
+        info!("🚀 إعداد تكوين البوت لـ {}", os_name);
+
+        // إنشاء سكريبت البوت
+        let boot_script = install_path.join("boot.sh");
+        let script_content = format!(
+            r#"#!/bin/bash
+# DOS Safar Boot Script for {}
+echo "🎮 Starting {} via DOS Safar..."
+
+# Set environment variables
+export DOS_SAFAR_OS="{}"
+export DOS_SAFAR_PATH="{}"
+
+# Load system specific configurations
+if [ -f "{}/dos_safar_config.toml" ]; then
+    echo "📝 Loading DOS Safar configuration..."
+fi
+
+# Start the operating system
+echo "🚀 Launching {}..."
+exec /sbin/init
+"#,
+            os_name, os_name, os_name, 
+            install_path.to_str().unwrap(),
+            install_path.to_str().unwrap(),
+            os_name
+        );
+
+        fs::write(&boot_script, script_content)
+            .context("فشل في كتابة سكريبت البوت")?;
+
+        // جعل السكريبت قابل للتنفيذ
+        Command::new("chmod")
+            .args(&["+x", boot_script.to_str().unwrap()])
+            .output()
+            .context("فشل في تعيين صلاحيات التنفيذ")?;
+
+        Ok(())
+    }
+
+    async fn register_os(&self, os_name: &str, os_path: &Path) -> Result<()> {
+        self.register_os_verified(os_name, os_path, None, None).await
+    }
+
+    /// يسجل النظام في `registry.json` كما تفعل `register_os`، مع إضافة
+    /// بصمة SHA-256 التي تم التحقق منها أثناء التثبيت (إن وُجدت) والمعمارية
+    /// المكتشفة (إن وُجدت) بجوار قيد النظام حتى يمكن مراجعتها لاحقاً.
+    async fn register_os_verified(
+        &self,
+        os_name: &str,
+        os_path: &Path,
+        verified_checksum: Option<&str>,
+        target_arch: Option<TargetArch>,
+    ) -> Result<()> {
+        info!("📝 تسجيل النظام {} في قاعدة البيانات", os_name);
+
+        self.check_manifest_requirements(os_name, os_path).await?;
+
+        let registry_file = self.os_storage_path.join("registry.json");
+        let mut registry: serde_json::Value = if registry_file.exists() {
+            let content = fs::read_to_string(&registry_file)?;
+            serde_json::from_str(&content).unwrap_or(serde_json::json!({}))
+        } else {
+            serde_json::json!({})
+        };
+
+        // إضافة معلومات النظام
+        registry[os_name] = serde_json::json!({
+            "name": os_name,
+            "path": os_path.to_str().unwrap(),
+            "install_date": chrono::Utc::now().to_rfc3339(),
+            "last_used": null,
+            "bootable": true,
+            "size_mb": self.calculate_directory_size(os_path).await.unwrap_or(0),
+            "verified_checksum": verified_checksum,
+            "target_arch": target_arch
+        });
+
+        let registry_content = serde_json::to_string_pretty(&registry)?;
+        fs::write(&registry_file, registry_content)
+            .context("فشل في كتابة سجل الأنظمة")?;
+
+        Ok(())
+    }
+
+    /// يتحقق من قسم `[requirements]` في `dos_safar_config.toml` مقابل
+    /// الجهاز المضيف قبل التسجيل. `required_device` غير المطابق يرفض
+    /// التسجيل (على غرار رفض تعارض المعمارية في `install_os_from_image_verified`)
+    /// بما أن الأجهزة الترفيهية المحمولة/Raspberry Pi ليست قابلة للتبديل كما
+    /// هي المعمارية عبر علم تجاوز. `min_storage_mb` غير الكافي تحذير فقط لأن
+    /// النظام مثبّت بالفعل على القرص في هذه المرحلة.
+    async fn check_manifest_requirements(&self, os_name: &str, os_path: &Path) -> Result<()> {
+        let manifest = OsManifest::read_from(os_path);
+        let Some(requirements) = manifest.requirements else {
+            return Ok(());
+        };
+
+        if let Some(required_device) = &requirements.required_device {
+            let matches_host = match required_device.as_str() {
+                "rpi" => self.is_raspberry_pi(),
+                "handheld" => self.is_gaming_handheld(),
+                _ => true,
+            };
+            if !matches_host {
+                return Err(anyhow::anyhow!(
+                    "النظام {} يتطلب جهاز \"{}\" لكن الجهاز المضيف الحالي لا يطابقه",
+                    os_name,
+                    required_device
+                ));
+            }
+        }
+
+        if let Some(min_storage_mb) = requirements.min_storage_mb {
+            let installed_mb = self.calculate_directory_size(os_path).await.unwrap_or(0);
+            if installed_mb < min_storage_mb {
+                warn!(
+                    "⚠️ النظام {} يطلب {} ميجابايت على الأقل لكن حجمه الفعلي المثبَّت {} ميجابايت فقط",
+                    os_name, min_storage_mb, installed_mb
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn unregister_os(&self, os_name: &str) -> Result<()> {
+        info!("🗑️ إزالة {} من سجل الأنظمة", os_name);
+
+        let registry_file = self.os_storage_path.join("registry.json");
+        if !registry_file.exists() {
+            return Ok(());
+        }
+
+        let content = fs::read_to_string(&registry_file)?;
+        let mut registry: serde_json::Value = serde_json::from_str(&content)
+            .unwrap_or(serde_json::json!({}));
+
+        // إزالة النظام من السجل
+        if let Some(obj) = registry.as_object_mut() {
+            obj.remove(os_name);
+        }
+
+        let registry_content = serde_json::to_string_pretty(&registry)?;
+        fs::write(&registry_file, registry_content)
+            .context("فشل في تحديث سجل الأنظمة")?;
+
+        Ok(())
+    }
+
+    /// يحمّل صورة عبر طلبات HTTP Range قابلة للاستئناف: إن وُجد ملف جزئي من
+    /// محاولة سابقة على المسار نفسه يُستأنف التحميل من آخر بايت بدلاً من
+    /// البدء من جديد (كما تفعل أدوات تثبيت CoreOS). تُحسب بصمة SHA-256
+    /// تدريجياً مع وصول كل دفعة فتكون جاهزة فور الاكتمال دون إعادة قراءة
+    /// الملف، وتُبلَّغ نسبة الإنجاز والوقت المتبقي المقدَّر عبر `progress`
+    /// بمرحلة `InstallationStage::Downloading`. يعيد بصمة SHA-256 للملف
+    /// المكتمل كسلسلة سداسية عشرية.
+    ///
+    /// إن كان الرابط منتهياً بـ `.gz`/`.xz` يُفكّ الضغط أثناء البث مباشرة،
+    /// فيكتب إلى `output_path` المحتوى الأصلي غير المضغوط دون مرور وسيط
+    /// بملف مضغوط على القرص. الاستئناف غير مدعوم في هذه الحالة: إزاحة
+    /// البايتات على القرص لا تقابل إزاحة في التدفق المضغوط بعد إعادة
+    /// التشغيل، فتُعاد كل صورة مضغوطة من الصفر بدل المخاطرة بملف تالف.
+    async fn download_image_resumable(
+        &self,
+        url: &str,
+        output_path: &str,
+        os_name: &str,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<String> {
+        if detect_download_compression(url) != DownloadCompression::None {
+            return self
+                .download_and_decompress(url, output_path, os_name, progress)
+                .await;
+        }
+
+        info!("📥 تحميل قابل للاستئناف من: {}", url);
+
+        let output = Path::new(output_path);
+        let mut hasher = Sha256::new();
+        let mut resume_offset: u64 = 0;
+
+        if output.exists() {
+            resume_offset = fs::metadata(output).map(|m| m.len()).unwrap_or(0);
+            if resume_offset > 0 {
+                // إعادة بناء حالة البصمة من الجزء المحمَّل مسبقاً قبل متابعة
+                // التحميل، بنفس أسلوب القراءة المجزأة في compute_sha256_streaming
+                let mut existing = fs::File::open(output)
+                    .context("فشل في فتح الملف الجزئي لاستئناف التحميل")?;
+                let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+                loop {
+                    let bytes_read = existing
+                        .read(&mut buffer)
+                        .context("فشل في قراءة الملف الجزئي")?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    hasher.update(&buffer[..bytes_read]);
+                }
+                info!("⏯️ استئناف التحميل من البايت {}", resume_offset);
+            }
+        }
+
+        let client = reqwest::Client::new();
+        let mut request = client.get(url);
+        if resume_offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_offset));
+        }
+
+        let response = request.send().await.context("فشل في إرسال طلب التحميل")?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "فشل التحميل: استجابة غير ناجحة ({})",
+                response.status()
+            ));
+        }
+
+        let resumed = response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let already_downloaded = if resumed { resume_offset } else { 0 };
+        if resume_offset > 0 && !resumed {
+            // الخادم لا يدعم استئناف التحميل: نتجاهل البصمة الجزئية ونبدأ
+            // من جديد بدل كتابة ملف تالف
+            hasher = Sha256::new();
+            warn!("⚠️ الخادم لا يدعم طلبات Range - إعادة التحميل من البداية");
+        }
+
+        let total_size = response.content_length().map(|len| len + already_downloaded);
+
+        let mut file = if resumed {
+            tokio::fs::OpenOptions::new()
+                .append(true)
+                .open(output)
+                .await
+                .context("فشل في فتح الملف لاستئناف الكتابة")?
+        } else {
+            tokio::fs::File::create(output)
+                .await
+                .context("فشل في إنشاء ملف التحميل")?
+        };
+
+        let started_at = std::time::Instant::now();
+        let mut downloaded = already_downloaded;
+        let mut stream = response.bytes_stream();
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.context("فشل في قراءة دفعة من بيانات التحميل")?;
+            hasher.update(&chunk);
+            file.write_all(&chunk)
+                .await
+                .context("فشل في كتابة بيانات التحميل")?;
+            downloaded += chunk.len() as u64;
+
+            let percentage = total_size
+                .map(|total| (downloaded as f32 / total.max(1) as f32) * 100.0)
+                .unwrap_or(0.0);
+
+            if let Some(callback) = progress {
+                let elapsed = started_at.elapsed().as_secs_f32();
+                let rate = (downloaded - already_downloaded) as f32 / elapsed.max(0.001);
+                let eta = total_size
+                    .filter(|_| rate > 0.0)
+                    .map(|total| total.saturating_sub(downloaded))
+                    .map(|remaining| (remaining as f32 / rate) as u64);
+
+                callback(OSInstallationProgress {
+                    stage: InstallationStage::Downloading,
+                    progress_percentage: percentage,
+                    current_operation: format!(
+                        "تحميل {} ({}MB)",
+                        url,
+                        downloaded / 1024 / 1024
+                    ),
+                    estimated_time_remaining: eta,
+                    error_message: None,
+                });
+            }
+
+            if let Some(reporter) = self.reporter() {
+                reporter
+                    .phase(
+                        "download",
+                        Some(os_name),
+                        "downloading",
+                        percentage,
+                        format!("{}MB", downloaded / 1024 / 1024),
+                    )
+                    .await;
+            }
+        }
+
+        file.flush().await.context("فشل في إتمام كتابة ملف التحميل")?;
+
+        info!("✅ تم تحميل الصورة بنجاح ({}MB)", downloaded / 1024 / 1024);
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// يحمّل رابطاً مضغوطاً بـ gzip/xz ويفكّ ضغطه أثناء البث إلى ملف
+    /// `output_path` النهائي، محسوباً بصمة SHA-256 على المحتوى غير المضغوط
+    /// (وهو ما يُقارن لاحقاً بـ `expected_checksum` في `install_os_from_image_verified`).
+    /// نسبة التقدم تُقاس على حجم التنزيل المضغوط من الشبكة وليس الحجم بعد
+    /// فكّ الضغط (غير معروف مسبقاً)، فهي تقريبية وليست دقيقة بايت ببايت.
+    async fn download_and_decompress(
+        &self,
+        url: &str,
+        output_path: &str,
+        os_name: &str,
+        progress: Option<&ProgressCallback>,
+    ) -> Result<String> {
+        info!("📥 تحميل وفكّ ضغط من: {}", url);
+
+        let client = reqwest::Client::new();
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .context("فشل في إرسال طلب التحميل")?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "فشل التحميل: استجابة غير ناجحة ({})",
+                response.status()
+            ));
+        }
+
+        let compressed_size = response.content_length();
+        let compressed_read = std::sync::atomic::AtomicU64::new(0);
+        let byte_stream = response.bytes_stream().map(|result| {
+            result
+                .map(|bytes| {
+                    compressed_read.fetch_add(bytes.len() as u64, std::sync::atomic::Ordering::Relaxed);
+                    bytes
+                })
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
+        });
+        let stream_reader = StreamReader::new(byte_stream);
+        let buffered = tokio::io::BufReader::new(stream_reader);
+
+        let mut file = tokio::fs::File::create(output_path)
+            .await
+            .context("فشل في إنشاء ملف التحميل")?;
+        let mut hasher = Sha256::new();
+        let mut decompressed: u64 = 0;
+        let mut read_buffer = vec![0u8; CHECKSUM_BUFFER_SIZE];
+
+        macro_rules! drain_decoder {
+            ($decoder:expr) => {{
+                let mut decoder = $decoder;
+                loop {
+                    let bytes_read = decoder
+                        .read(&mut read_buffer)
+                        .await
+                        .context("فشل في فكّ ضغط دفعة من بيانات التحميل")?;
+                    if bytes_read == 0 {
+                        break;
+                    }
+                    let chunk = &read_buffer[..bytes_read];
+                    hasher.update(chunk);
+                    file.write_all(chunk)
+                        .await
+                        .context("فشل في كتابة بيانات التحميل")?;
+                    decompressed += bytes_read as u64;
+
+                    // النسبة مبنية على حجم التنزيل المضغوط من الشبكة، والحجم
+                    // الفعلي بعد فكّ الضغط غير معروف مسبقاً فلا وقت متبقٍ مقدَّر
+                    let downloaded = compressed_read.load(std::sync::atomic::Ordering::Relaxed);
+                    let percentage = compressed_size
+                        .map(|total| (downloaded as f32 / total.max(1) as f32) * 100.0)
+                        .unwrap_or(0.0)
+                        .min(100.0);
+
+                    if let Some(callback) = progress {
+                        callback(OSInstallationProgress {
+                            stage: InstallationStage::Downloading,
+                            progress_percentage: percentage,
+                            current_operation: format!(
+                                "تحميل وفكّ ضغط {} ({}MB)",
+                                url,
+                                decompressed / 1024 / 1024
+                            ),
+                            estimated_time_remaining: None,
+                            error_message: None,
+                        });
+                    }
+
+                    if let Some(reporter) = self.reporter() {
+                        reporter
+                            .phase(
+                                "download",
+                                Some(os_name),
+                                "downloading",
+                                percentage,
+                                format!("{}MB", decompressed / 1024 / 1024),
+                            )
+                            .await;
+                    }
+                }
+            }};
+        }
+
+        match detect_download_compression(url) {
+            DownloadCompression::Gzip => drain_decoder!(GzipDecoder::new(buffered)),
+            DownloadCompression::Xz => drain_decoder!(XzDecoder::new(buffered)),
+            DownloadCompression::None => unreachable!("تم التحقق من وجود ضغط قبل استدعاء هذه الدالة"),
+        }
+
+        file.flush().await.context("فشل في إتمام كتابة ملف التحميل")?;
+
+        info!(
+            "✅ تم تحميل وفكّ ضغط الصورة بنجاح ({}MB)",
+            decompressed / 1024 / 1024
+        );
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    async fn analyze_os_directory(&self, os_path: &Path) -> Result<OperatingSystem> {
+        let os_name = os_path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Unknown")
+            .to_string();
+
+        // نظام مبني على الأجيال: المحتوى الفعلي خلف رابط "current"، وليس
+        // مباشرة تحت مجلد النظام
+        let current_link = os_path.join("current");
+        let content_path = if current_link.exists() {
+            current_link
+        } else {
+            os_path.to_path_buf()
+        };
+
+        // تحديد هوية التوزيعة الأساسية (os-release/lsb-release/ملفات الإصدار
+        // الخاصة بكل توزيعة)، ثم نوع النظام فوقها (الأنظمة الترفيهية مثل
+        // RetroPie/Batocera/Recalbox تبقى طبقة تعلو التوزيعة الأساسية)
+        let distro = distro_detect::detect_linux_distro(&content_path);
+        let os_type = self.detect_os_type(&content_path, distro.as_ref());
+
+        // قراءة معلومات إضافية
+        let manifest = OsManifest::read_from(&content_path);
+        let description = self.get_os_description(&manifest, &os_type, distro.as_ref());
+        let last_used = self.get_last_used_date(&os_name).await;
+        let target_arch = self.get_registered_arch(&os_name);
+
+        // مكتبة الألعاب (roms/) تهمّ فقط الأنظمة الترفيهية
+        let rom_libraries = if matches!(
+            os_type,
+            OSType::RetroPie | OSType::Batocera | OSType::Recalbox
+        ) {
+            rom_scanner::scan_rom_libraries(&content_path)
+        } else {
+            Vec::new()
+        };
+
+        let version = distro.as_ref().and_then(|d| d.version_id.clone());
+        let root_uuid = distro_detect::probe_mounted_partition(&content_path).and_then(|p| p.uuid);
+
+        Ok(OperatingSystem {
+            name: os_name,
+            path: os_path.to_string_lossy().to_string(),
+            description,
+            os_type,
+            is_bootable: self.is_bootable(&content_path),
+            last_used,
+            target_arch,
+            version,
+            distro,
+            manifest: Some(manifest),
+            rom_libraries,
+            boot_count: 0,
+            last_boot_outcome: None,
+            root_uuid,
+        })
+    }
+
+    /// يقرأ معمارية النظام المسجَّلة في `registry.json` (كتبها
+    /// `register_os_verified` عند التثبيت)، أو `None` إن لم تُسجَّل بعد.
+    fn get_registered_arch(&self, os_name: &str) -> Option<TargetArch> {
+        let registry_file = self.os_storage_path.join("registry.json");
+        let content = fs::read_to_string(&registry_file).ok()?;
+        let registry: serde_json::Value = serde_json::from_str(&content).ok()?;
+        serde_json::from_value(registry[os_name]["target_arch"].clone()).ok()
+    }
+
+    /// مفوَّضة إلى `distro_detect::classify_os_type`، المشتركة الآن مع
+    /// `menu::identify_os_from_boot_partition` كي لا يصنّف المساران نفس
+    /// التوزيعة بشكل مختلف.
+    fn detect_os_type(&self, os_path: &Path, distro: Option<&LinuxDistroInfo>) -> OSType {
+        distro_detect::classify_os_type(os_path, distro)
+    }
+
+    fn get_os_description(
+        &self,
+        manifest: &OsManifest,
+        os_type: &OSType,
+        distro: Option<&LinuxDistroInfo>,
+    ) -> String {
+        // وصف من `[meta].description` في dos_safar_config.toml المنظم
+        if let Some(meta) = &manifest.meta {
+            if let Some(description) = &meta.description {
+                return description.clone();
+            }
+        }
+
+        // وصف مبني على هوية التوزيعة المكتشفة إن لم تكن الأنظمة الترفيهية
+        if !matches!(os_type, OSType::RetroPie | OSType::Batocera | OSType::Recalbox) {
+            if let Some(distro) = distro {
+                if let Some(pretty_name) = &distro.pretty_name {
+                    return pretty_name.clone();
+                }
+                if let Some(version_id) = &distro.version_id {
+                    return format!("{} {}", distro.id, version_id);
+                }
+            }
+        }
+
+        // وصف افتراضي حسب النوع
+        match os_type {
+            OSType::RetroPie => "نظام الألعاب الكلاسيكية RetroPie".to_string(),
+            OSType::Batocera => "نظام الألعاب Batocera".to_string(),
+            OSType::Recalbox => "نظام الألعاب Recalbox".to_string(),
+            OSType::RaspberryPiOS => "نظام التشغيل الرسمي لـ Raspberry Pi".to_string(),
+            OSType::Ubuntu => "نظام Ubuntu Linux".to_string(),
+            OSType::Debian => "نظام Debian Linux".to_string(),
+            OSType::Unknown => "نظام تشغيل غير معروف".to_string(),
+        }
+    }
+
+    fn is_bootable(&self, os_path: &Path) -> bool {
+        // فحص وجود ملفات البوت الأساسية
+        let boot_files = vec![
+            "boot.sh",
+            "kernel.img",
+            "config.txt",
+            "system.img",
+        ];
+
+        boot_files.iter().any(|file| os_path.join(file).exists())
+    }
+
+    async fn get_last_used_date(&self, os_name: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        let registry_file = self.os_storage_path.join("registry.json");
+        if !registry_file.exists() {
+            return None;
+        }
+
+        let content = fs::read_to_string(&registry_file).ok()?;
+        let registry: serde_json::Value = serde_json::from_str(&content).ok()?;
+
+        let last_used_str = registry[os_name]["last_used"].as_str()?;
+        chrono::DateTime::parse_from_rfc3339(last_used_str)
+            .ok()
+            .map(|dt| dt.with_timezone(&chrono::Utc))
+    }
+
+    /// حجم المجلد بالميجابايت عبر المشي التكراري اليدوي (بلا `du` خارجي)،
+    /// بالحجم الظاهري (apparent size: مجموع أطوال الملفات). للحصول على
+    /// الحجم الفعلي المحجوز على القرص استخدم `calculate_directory_disk_usage`.
+    async fn calculate_directory_size(&self, dir_path: &Path) -> Result<u64> {
+        self.calculate_directory_size_inner(dir_path, false).await
+    }
+
+    /// حجم المجلد بالميجابايت بالحجم المحجوز فعلياً على القرص (عدد كتل
+    /// `st_blocks` × 512 بايت)، أدق من الحجم الظاهري لقرارات ضغط التخزين
+    /// (الملفات المتناثرة/sparse تُظهر فرقاً واضحاً بين الاثنين).
+    #[allow(dead_code)]
+    async fn calculate_directory_disk_usage(&self, dir_path: &Path) -> Result<u64> {
+        self.calculate_directory_size_inner(dir_path, true).await
+    }
+
+    async fn calculate_directory_size_inner(&self, dir_path: &Path, use_block_size: bool) -> Result<u64> {
+        let os_name = dir_path.file_name().and_then(|n| n.to_str());
+        if let Some(reporter) = self.reporter() {
+            reporter
+                .phase("directory_size", os_name, "scanning", 0.0, dir_path.display().to_string())
+                .await;
+        }
+
+        let total_bytes = Self::sum_directory_bytes(dir_path, use_block_size).unwrap_or(0);
+        let size_mb = total_bytes / 1024 / 1024;
+
+        if let Some(reporter) = self.reporter() {
+            reporter.done("directory_size", os_name).await;
+        }
+
+        Ok(size_mb)
+    }
+
+    /// يجمع أحجام الملفات تحت `dir_path` يدوياً (بدون الشروع في مكتبة
+    /// خارجية مثل `walkdir`)، متجنباً `to_str().unwrap()` بالعمل على
+    /// `Path` مباشرة كي لا يفزع على مسارات غير UTF-8. الروابط الرمزية
+    /// تُتخطى كلياً - لا تُتبع ولا تُحتسب - حتى لا نُضاعف احتساب الجيل
+    /// الحالي عبر رابط "current" أو ندخل في حلقة لا نهائية.
+    fn sum_directory_bytes(dir_path: &Path, use_block_size: bool) -> Result<u64> {
+        let mut total = 0u64;
+
+        let entries = fs::read_dir(dir_path)
+            .with_context(|| format!("فشل في قراءة المجلد {}", dir_path.display()))?;
+
+        for entry in entries {
+            let entry = entry.context("فشل في قراءة مدخل من المجلد")?;
+            let path = entry.path();
+
+            // symlink_metadata لا يتبع الروابط الرمزية، بعكس metadata()/is_dir()
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+
+            if metadata.file_type().is_symlink() {
+                continue;
+            }
+
+            if metadata.is_dir() {
+                total += Self::sum_directory_bytes(&path, use_block_size)?;
+            } else {
+                total += Self::file_size_bytes(&metadata, use_block_size);
+            }
+        }
+
+        Ok(total)
+    }
+
+    #[cfg(unix)]
+    fn file_size_bytes(metadata: &fs::Metadata, use_block_size: bool) -> u64 {
+        use std::os::unix::fs::MetadataExt;
+        if use_block_size {
+            metadata.blocks() * 512
+        } else {
+            metadata.len()
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn file_size_bytes(metadata: &fs::Metadata, _use_block_size: bool) -> u64 {
+        metadata.len()
+    }
+
+    fn os_exists(&self, os_name: &str) -> bool {
+        self.os_storage_path.join(os_name).exists()
+    }
+
+    // =====================================
+    // نموذج الأجيال (generations): تثبيت/تحديث، بدلاً من تعديل مكانه،
+    // ينتج مجلد `gen-<N>` جديداً تحته `systems/<os_name>/`، ورابط
+    // "current" يشير إلى الجيل الفعّال - يشبه مُثبِّت lanzaboote.
+    // =====================================
+
+    fn generation_dir(&self, os_name: &str, generation: u64) -> PathBuf {
+        self.os_storage_path
+            .join(os_name)
+            .join(format!("gen-{}", generation))
+    }
+
+    /// أرقام الأجيال الموجودة فعلياً على القرص لنظام `os_name`، مرتبة تصاعدياً.
+    pub async fn list_generations(&self, os_name: &str) -> Result<Vec<u64>> {
+        let os_root = self.os_storage_path.join(os_name);
+        let mut generations = Vec::new();
+
+        if !os_root.exists() {
+            return Ok(generations);
+        }
+
+        let entries = fs::read_dir(&os_root).context("فشل في قراءة مجلد أجيال النظام")?;
+        for entry in entries.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                if let Some(number) = name.strip_prefix("gen-") {
+                    if let Ok(n) = number.parse::<u64>() {
+                        generations.push(n);
+                    }
+                }
+            }
+        }
+
+        generations.sort_unstable();
+        Ok(generations)
+    }
+
+    async fn next_generation_number(&self, os_name: &str) -> Result<u64> {
+        let existing = self.list_generations(os_name).await?;
+        Ok(existing.into_iter().max().map(|n| n + 1).unwrap_or(1))
+    }
+
+    /// يشير رابط `systems/<os_name>/current` إلى `gen-<generation>`،
+    /// مستبدلاً أي رابط سابق، ويحدّث سجل الأجيال بالجيل الحالي الجديد.
+    fn set_current_generation(&self, os_name: &str, generation: u64) -> Result<()> {
+        let os_root = self.os_storage_path.join(os_name);
+        let current_link = os_root.join("current");
+        let target = format!("gen-{}", generation);
+
+        if current_link.symlink_metadata().is_ok() {
+            fs::remove_file(&current_link)
+                .context("فشل في إزالة رابط الجيل الحالي القديم")?;
+        }
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, &current_link)
+            .context("فشل في تحديث رابط الجيل الحالي")?;
+
+        self.update_generation_registry(os_name, |state| {
+            state.current_generation = Some(generation);
+        })
+    }
+
+    /// التراجع فوراً إلى جيل سابق موجود بالفعل على القرص، دون الحاجة
+    /// لاستخراج أي أرشيف - على عكس `restore_os_from_backup` القديمة.
+    pub async fn rollback_to_generation(&self, os_name: &str, generation: u64) -> Result<()> {
+        let generations = self.list_generations(os_name).await?;
+        if !generations.contains(&generation) {
+            return Err(anyhow::anyhow!(
+                "الجيل {} غير موجود للنظام {}",
+                generation,
+                os_name
+            ));
+        }
+
+        self.set_current_generation(os_name, generation)?;
+        info!("⏪ تم التراجع إلى الجيل {} للنظام {}", generation, os_name);
+        Ok(())
+    }
+
+    /// تُستدعى عند فشل جيل في مرحلة `InstallationStage::Testing`، حتى
+    /// يُحذف أولاً عند تنظيف الأجيال ولا يُستبقى كجيل حالي.
+    pub async fn mark_generation_broken(&self, os_name: &str, generation: u64) -> Result<()> {
+        self.update_generation_registry(os_name, |state| {
+            state.broken_generations.insert(generation);
+        })
+    }
+
+    /// يختبر إقلاع الجيل الحالي لنظام `os_name` فعلياً بدل الاكتفاء بفحص
+    /// وجود ملفات البوت (`is_bootable`). على جهاز Raspberry Pi فعلي الإقلاع
+    /// الحقيقي هو الاختبار الموثوق فلا داعٍ لمحاكاته؛ على أي جهاز آخر يشغّل
+    /// صورة ذات نواة منفصلة تحت `qemu-system-arm`/`qemu-system-aarch64`
+    /// بمهلة محدودة (`BOOT_TEST_TIMEOUT_SECS`) بحثاً عن علامة نجاح في وحدة
+    /// التحكم التسلسلية، أو - لصور rootfs بلا نواة منفصلة - يدخل `chroot`
+    /// معتمداً على `qemu-user-static`/`binfmt_misc` لتشغيل أمر فحص بسيط.
+    pub async fn test_os(&self, os_name: &str) -> Result<BootTestResult> {
+        info!("🧪 اختبار إقلاع {}", os_name);
+
+        let current_link = self.os_storage_path.join(os_name).join("current");
+        if !current_link.exists() {
+            return Err(anyhow::anyhow!("لا يوجد جيل حالي مسجل للنظام {}", os_name));
+        }
+
+        if self.is_raspberry_pi() {
+            info!("🍓 الجهاز المضيف Raspberry Pi فعلي - تخطي محاكاة QEMU لصالح الإقلاع الحقيقي");
+            return Ok(BootTestResult {
+                booted: true,
+                log: "تم تخطي المحاكاة: الجهاز المضيف Raspberry Pi فعلي".to_string(),
+                duration_seconds: 0,
+            });
+        }
+
+        let target_arch = self.detect_image_arch(&current_link);
+        let result = self.run_boot_test(&current_link, target_arch).await?;
+
+        if result.booted {
+            info!(
+                "✅ اجتاز {} اختبار الإقلاع خلال {} ثانية",
+                os_name, result.duration_seconds
+            );
+        } else {
+            warn!(
+                "❌ فشل {} اختبار الإقلاع بعد {} ثانية",
+                os_name, result.duration_seconds
+            );
+        }
+
+        Ok(result)
+    }
+
+    async fn run_boot_test(
+        &self,
+        install_path: &Path,
+        target_arch: Option<TargetArch>,
+    ) -> Result<BootTestResult> {
+        match self.find_kernel_path(install_path) {
+            Some(kernel_path) => {
+                self.run_qemu_boot_test(&kernel_path, install_path, target_arch)
+                    .await
+            }
+            None => self.run_chroot_smoke_test(install_path).await,
+        }
+    }
+
+    /// يشغّل `qemu-system-arm`/`qemu-system-aarch64` (حسب `target_arch`) مع
+    /// نواة الصورة ووحدة تحكم تسلسلية مربوطة إلى stdio، وينتظر إحدى علامات
+    /// نجاح الإقلاع (موجّه دخول أو بلوغ هدف systemd) خلال `BOOT_TEST_TIMEOUT_SECS`
+    /// قبل إنهاء العملية قسراً واعتبار الاختبار فاشلاً.
+    async fn run_qemu_boot_test(
+        &self,
+        kernel_path: &Path,
+        install_path: &Path,
+        target_arch: Option<TargetArch>,
+    ) -> Result<BootTestResult> {
+        let qemu_binary = match target_arch {
+            Some(TargetArch::Aarch64) => "qemu-system-aarch64",
+            Some(TargetArch::Armv7) => "qemu-system-arm",
+            _ => "qemu-system-arm",
+        };
+
+        let mut command = AsyncCommand::new(qemu_binary);
+        command
+            .arg("-M")
+            .arg(if qemu_binary == "qemu-system-aarch64" { "virt" } else { "versatilepb" })
+            .arg("-m")
+            .arg("512")
+            .arg("-kernel")
+            .arg(kernel_path)
+            .arg("-append")
+            .arg("console=ttyAMA0 root=/dev/vda rw")
+            .arg("-serial")
+            .arg("stdio")
+            .arg("-display")
+            .arg("none")
+            .arg("-no-reboot")
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped());
+
+        let dtb_candidate = install_path.join("bcm2710-rpi-3-b.dtb");
+        if dtb_candidate.exists() {
+            command.arg("-dtb").arg(&dtb_candidate);
+        }
+
+        self.run_with_boot_markers(command).await
+    }
+
+    /// يدخل `chroot` على `install_path` وينفذ أمر فحص بسيط (`/bin/true` إن
+    /// وُجد، وإلا قراءة `/etc/os-release`)، معتمداً على تسجيل
+    /// `qemu-user-static` في `binfmt_misc` لتشغيل ثنائيات المعمارية الأجنبية
+    /// شفافياً - يفيد هذا صور rootfs الخالصة التي لا تملك نواة منفصلة.
+    async fn run_chroot_smoke_test(&self, install_path: &Path) -> Result<BootTestResult> {
+        let started_at = std::time::Instant::now();
+
+        let smoke_command = if install_path.join("bin/true").exists() {
+            vec!["/bin/true".to_string()]
+        } else {
+            vec!["/bin/cat".to_string(), "/etc/os-release".to_string()]
+        };
+
+        let mut args = vec![install_path.to_string_lossy().to_string()];
+        args.extend(smoke_command);
+
+        let output = AsyncCommand::new("chroot")
+            .args(&args)
+            .output()
+            .await
+            .context("فشل في تنفيذ فحص chroot (هل qemu-user-static/binfmt_misc مسجّل؟)")?;
+
+        let log = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(BootTestResult {
+            booted: output.status.success(),
+            log,
+            duration_seconds: started_at.elapsed().as_secs(),
+        })
+    }
+
+    /// يشغّل أمر QEMU المُعَدّ مسبقاً، ويقرأ خرج وحدة التحكم التسلسلية حتى
+    /// تظهر إحدى علامات النجاح أو تنقضي `BOOT_TEST_TIMEOUT_SECS`، ثم ينهي
+    /// العملية قسراً في كل الأحوال.
+    async fn run_with_boot_markers(&self, mut command: AsyncCommand) -> Result<BootTestResult> {
+        const SUCCESS_MARKERS: &[&str] = &["login:", "reached target", "Welcome to"];
+
+        let started_at = std::time::Instant::now();
+        let mut child = command
+            .spawn()
+            .context("فشل في تشغيل QEMU لاختبار الإقلاع - هل qemu-system-arm/aarch64 مثبَّت؟")?;
+
+        let mut stdout = child
+            .stdout
+            .take()
+            .context("تعذر الوصول إلى خرج QEMU التسلسلي")?;
+
+        let timeout = std::time::Duration::from_secs(BOOT_TEST_TIMEOUT_SECS);
+        let read_task = async {
+            let mut log = String::new();
+            let mut buffer = [0u8; 4096];
+            loop {
+                let bytes_read = stdout.read(&mut buffer).await.unwrap_or(0);
+                if bytes_read == 0 {
+                    break;
+                }
+                log.push_str(&String::from_utf8_lossy(&buffer[..bytes_read]));
+                if SUCCESS_MARKERS.iter().any(|marker| log.contains(marker)) {
+                    break;
+                }
+            }
+            log
+        };
+
+        let log = match tokio::time::timeout(timeout, read_task).await {
+            Ok(log) => log,
+            Err(_) => "انتهت المهلة دون ظهور علامة نجاح الإقلاع".to_string(),
+        };
+
+        let booted = SUCCESS_MARKERS.iter().any(|marker| log.contains(marker));
+
+        // إنهاء عملية QEMU قسراً في كل الأحوال - الاختبار انتهى سواء نجح أم لا
+        let _ = child.kill().await;
+        let _ = child.wait().await;
+
+        Ok(BootTestResult {
+            booted,
+            log,
+            duration_seconds: started_at.elapsed().as_secs(),
+        })
+    }
+
+    fn generation_registry_path(&self) -> PathBuf {
+        self.os_storage_path.join("generations.json")
+    }
+
+    fn load_generation_registry(&self) -> HashMap<String, OsGenerationState> {
+        let Ok(content) = fs::read_to_string(self.generation_registry_path()) else {
+            return HashMap::new();
+        };
+        serde_json::from_str(&content).unwrap_or_default()
+    }
+
+    fn load_generation_state(&self, os_name: &str) -> OsGenerationState {
+        let mut registry = self.load_generation_registry();
+        registry.remove(os_name).unwrap_or_default()
+    }
+
+    fn update_generation_registry<F>(&self, os_name: &str, mutate: F) -> Result<()>
+    where
+        F: FnOnce(&mut OsGenerationState),
+    {
+        let mut registry = self.load_generation_registry();
+        let state = registry.entry(os_name.to_string()).or_default();
+        mutate(state);
+
+        let content = serde_json::to_string_pretty(&registry)?;
+        fs::write(self.generation_registry_path(), content)
+            .context("فشل في تحديث سجل الأجيال")?;
+        Ok(())
+    }
+
+    /// يحذف مجلد الجيل على القرص ويزيله من قائمة الأجيال "المكسورة" إن وُجد.
+    fn delete_generation(&self, os_name: &str, generation: u64) -> Result<()> {
+        let path = self.generation_dir(os_name, generation);
+        if path.exists() {
+            fs::remove_dir_all(&path).context("فشل في حذف الجيل القديم")?;
+        }
+
+        self.update_generation_registry(os_name, |state| {
+            state.broken_generations.remove(&generation);
+        })?;
+
+        info!(
+            "🧹 تم حذف الجيل {} للنظام {} أثناء تنظيف الأجيال القديمة",
+            generation, os_name
+        );
+        Ok(())
+    }
+
+    /// ينظف أجيال نظام واحد: الأجيال المكسورة (فشلت `Testing`) تُحذف أولاً،
+    /// ثم أقدم الأجيال الزائدة عن `configuration_limit` - مع استثناء جيل
+    /// `current` الخاص بهذا النظام تحديداً، أياً كان هذا النظام، حتى لا
+    /// يترك `rollback_to_generation` المستخدم بجيل يُحذف تحته لاحقاً.
+    async fn gc_os(&self, os_name: &str) -> Result<Vec<u64>> {
+        let generations = self.list_generations(os_name).await?;
+        let state = self.load_generation_state(os_name);
+        let current = state.current_generation;
+
+        let mut removed = Vec::new();
+        let mut survivors = Vec::new();
+
+        for generation in generations {
+            let protected = current == Some(generation);
+            if state.broken_generations.contains(&generation) && !protected {
+                self.delete_generation(os_name, generation)?;
+                removed.push(generation);
+            } else {
+                survivors.push(generation);
+            }
+        }
+
+        survivors.sort_unstable();
+        while survivors.len() > self.configuration_limit {
+            let oldest = survivors[0];
+            if current == Some(oldest) {
+                // لا يمكن حذف الجيل الحالي لهذا النظام - نحذف أقدم جيل آخر
+                // بدلاً منه إن وُجد.
+                if survivors.len() < 2 {
+                    break;
+                }
+                let victim = survivors.remove(1);
+                self.delete_generation(os_name, victim)?;
+                removed.push(victim);
+                continue;
+            }
+
+            survivors.remove(0);
+            self.delete_generation(os_name, oldest)?;
+            removed.push(oldest);
+        }
+
+        Ok(removed)
+    }
+
+    /// يمر على كل الأنظمة المسجلة وينظف أجيالها القديمة/المكسورة. كل نظام
+    /// يحتفظ بجيله الحالي (`current_generation`) بصرف النظر عن كونه النظام
+    /// الافتراضي أو أول نظام في ترتيب الإقلاع - والإ فإن `rollback_to_generation`
+    /// على أي نظام آخر غير هذين الاثنين يصبح عرضة للحذف بأول تشغيل `gc`.
+    pub async fn gc(&self) -> Result<Vec<(String, u64)>> {
+        let mut removed = Vec::new();
+
+        if !self.os_storage_path.exists() {
+            return Ok(removed);
+        }
+
+        let entries = fs::read_dir(&self.os_storage_path).context("فشل في قراءة مجلد الأنظمة")?;
+        for entry in entries.flatten() {
+            if !entry.path().is_dir() {
+                continue;
+            }
+            let Some(os_name) = entry.file_name().to_str().map(|s| s.to_string()) else {
+                continue;
+            };
+
+            let os_removed = self.gc_os(&os_name).await?;
+            removed.extend(os_removed.into_iter().map(|gen| (os_name.clone(), gen)));
+        }
+
+        Ok(removed)
+    }
+
+    fn is_raspberry_pi(&self) -> bool {
+        Path::new("/proc/device-tree/model").exists() &&
+        fs::read_to_string("/proc/device-tree/model")
+            .unwrap_or_default()
+            .to_lowercase()
+            .contains("raspberry pi")
+    }
+
+    fn is_gaming_handheld(&self) -> bool {
+        // فحص مبسط لأجهزة الألعاب المحمولة
+        let model_info = fs::read_to_string("/proc/device-tree/model")
+            .unwrap_or_default()
+            .to_lowercase();
+        
+        model_info.contains("anbernic") ||
+        model_info.contains("rg351") ||
+        model_info.contains("rg552")
+    }
+
+    // باقي الوظائف المساعدة...
+    async fn scan_external_systems(&self) -> Result<Vec<OperatingSystem>> {
+        // فحص مواقع إضافية للأنظمة
+        Ok(Vec::new()) // مبسط
+    }
+
+    async fn analyze_backup_file(&self, backup_path: &Path) -> Result<OSBackup> {
+        let metadata = fs::metadata(backup_path)?;
+        let size_mb = metadata.len() / 1024 / 1024;
+
+        let file_name = backup_path.file_stem()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown");
+
+        // استخراج اسم النظام وتاريخ النسخة الاحتياطية من اسم الملف
+        let parts: Vec<&str> = file_name.split('_').collect();
+        let os_name = parts.get(0).unwrap_or(&"unknown").to_string();
+
+        if let Some(reporter) = self.reporter() {
+            reporter
+                .phase("backup_analyze", Some(&os_name), "analyzing", 0.0, backup_path.display().to_string())
+                .await;
+        }
+
+        let backup = OSBackup {
+            os_name: os_name.clone(),
+            backup_date: metadata.created()
+                .ok()
+                .and_then(|t| chrono::DateTime::from(t).into())
+                .unwrap_or_else(chrono::Utc::now),
+            backup_size_mb: size_mb,
+            backup_path: backup_path.to_string_lossy().to_string(),
+            is_bootable: true,
+        };
+
+        if let Some(reporter) = self.reporter() {
+            reporter.done("backup_analyze", Some(&os_name)).await;
+        }
+
+        Ok(backup)
+    }
+
+    async fn load_boot_configuration(&self) -> Result<BootConfiguration> {
+        let config_file = self.boot_partition_path.join("dos_safar_boot.json");
+        
+        if config_file.exists() {
+            let content = fs::read_to_string(&config_file)?;
+            let config: BootConfiguration = serde_json::from_str(&content)?;
+            Ok(config)
+        } else {
+            // تكوين افتراضي
+            Ok(BootConfiguration {
+                default_os: None,
+                timeout_seconds: 10,
+                available_systems: Vec::new(),
+                boot_order: Vec::new(),
+                recovery_mode: false,
+                configuration_limit: None,
+            })
+        }
+    }
+
+    async fn save_boot_configuration(&self, config: &mut BootConfiguration) -> Result<()> {
+        self.prune_boot_configuration(config).await?;
+
+        let config_file = self.boot_partition_path.join("dos_safar_boot.json");
+        let content = serde_json::to_string_pretty(config)?;
+        fs::write(&config_file, content)?;
+        Ok(())
+    }
+
+    /// يطبّق `configuration_limit` قبل الحفظ: إن تجاوز عدد الأنظمة المسجَّلة
+    /// الحد، تُحذف الأقدم حسب `last_used` (الأنظمة التي لم تُستخدم قط تُعامل
+    /// كأقدم من أي شيء) من `available_systems`/`boot_order`، ويُزال تسجيلها
+    /// نهائياً عبر `unregister_os` حتى لا يبقى مدخل يتيم في `registry.json`.
+    async fn prune_boot_configuration(&self, config: &mut BootConfiguration) -> Result<()> {
+        let Some(limit) = config.configuration_limit else {
+            return Ok(());
+        };
+
+        if config.available_systems.len() <= limit {
+            return Ok(());
+        }
+
+        let mut by_age = config.available_systems.clone();
+        by_age.sort_by_key(|system| system.last_used);
+
+        let excess = by_age.len() - limit;
+        let pruned_names: HashSet<String> = by_age
+            .into_iter()
+            .take(excess)
+            .map(|system| system.name)
+            .collect();
+
+        warn!(
+            "✂️ تجاوز عدد أنظمة الإقلاع الحد {} - حذف {} نظام(أنظمة) الأقدم استخداماً: {:?}",
+            limit,
+            pruned_names.len(),
+            pruned_names
+        );
+
+        config
+            .available_systems
+            .retain(|system| !pruned_names.contains(&system.name));
+        config
+            .boot_order
+            .retain(|name| !pruned_names.contains(name));
+
+        for os_name in &pruned_names {
+            if let Err(e) = self.unregister_os(os_name).await {
+                warn!("⚠️ فشل في إزالة تسجيل {} المقلَّم: {}", os_name, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn perform_os_update(&self, os_name: &str, update_source: &str) -> Result<()> {
+        if let Some(reporter) = self.reporter() {
+            reporter
+                .phase("update", Some(os_name), "updating", 0.0, format!("تحديث من {}", update_source))
+                .await;
+        }
+
+        // تنفيذ مبسط للتحديث
+        info!("تحديث {} من {}", os_name, update_source);
+
+        // اختبار إقلاع فعلي بعد التحديث بدل الثقة بملفات البوت وحدها
+        match self.test_os(os_name).await {
+            Ok(result) if !result.booted => {
+                warn!(
+                    "⚠️ فشل {} اختبار الإقلاع بعد التحديث - سيُعامل كنسخة تالفة: {}",
+                    os_name, result.log
+                );
+                if let Some(reporter) = self.reporter() {
+                    reporter.error("update", Some(os_name), "فشل اختبار الإقلاع بعد التحديث").await;
+                }
+                return Err(anyhow::anyhow!(
+                    "فشل اختبار إقلاع {} بعد التحديث - لن يُعتمد التحديث",
+                    os_name
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                // تعذر تشغيل الاختبار نفسه (مثلاً QEMU غير مثبَّت) لا يعني
+                // فشل التحديث - نسجّل تحذيراً ونمضي بدل رفض تحديث صالح
+                warn!("⚠️ تعذر تشغيل اختبار الإقلاع لـ {}: {}", os_name, e);
+            }
+        }
+
+        if let Some(reporter) = self.reporter() {
+            reporter.done("update", Some(os_name)).await;
+        }
+        Ok(())
+    }
+
+    async fn cleanup_temporary_files(&self, os_path: &Path) -> Result<()> {
+        let os_name = os_path.file_name().and_then(|n| n.to_str());
+        if let Some(reporter) = self.reporter() {
+            reporter.phase("optimize", os_name, "cleanup", 0.0, "تنظيف الملفات المؤقتة").await;
+        }
+        // تنظيف الملفات المؤقتة
+        Ok(())
+    }
+
+    async fn optimize_databases(&self, os_path: &Path) -> Result<()> {
+        let os_name = os_path.file_name().and_then(|n| n.to_str());
+        if let Some(reporter) = self.reporter() {
+            reporter.phase("optimize", os_name, "databases", 25.0, "تحسين قواعد البيانات").await;
+        }
+        // تحسين قواعد البيانات
+        Ok(())
+    }
+
+    async fn compress_unused_files(&self, os_path: &Path) -> Result<()> {
+        let os_name = os_path.file_name().and_then(|n| n.to_str());
+        if let Some(reporter) = self.reporter() {
+            reporter.phase("optimize", os_name, "compress", 50.0, "ضغط الملفات غير المستخدمة").await;
+        }
+        // ضغط الملفات غير المستخدمة
+        Ok(())
+    }
+
+    async fn update_file_index(&self, os_path: &Path) -> Result<()> {
+        let os_name = os_path.file_name().and_then(|n| n.to_str());
+        if let Some(reporter) = self.reporter() {
+            reporter.phase("optimize", os_name, "index", 75.0, "تحديث فهرس الملفات").await;
+        }
+        // تحديث فهرس الملفات
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ImageType {
+    ISO,
+    IMG,
+    TAR,
+    ZIP,
+}
+
+/// صيغة الضغط المكتشفة من امتداد رابط التحميل، تُفكّ أثناء البث بدل تمريرها
+/// كما هي ثم فكّها في خطوة منفصلة. لا يشمل `.zip` عمداً: تنسيق ZIP يحتاج
+/// قراءة الفهرس المركزي من نهاية الملف، فلا يمكن بثّه وفكّه تدريجياً مثل
+/// gzip/xz، لذا تُترك ملفات `.zip` كما هي ليتولى `install_from_zip` أمرها
+/// بعد التحميل كما كان يفعل سابقاً.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DownloadCompression {
+    None,
+    Gzip,
+    Xz,
+}
+
+/// يحدد صيغة الضغط من امتداد اسم الملف في الرابط (`.gz`/`.xz`)، أو
+/// `DownloadCompression::None` لأي شيء آخر بما فيه `.zip`.
+fn detect_download_compression(url: &str) -> DownloadCompression {
+    let file_name = Path::new(url)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+
+    if file_name.ends_with(".gz") {
+        DownloadCompression::Gzip
+    } else if file_name.ends_with(".xz") {
+        DownloadCompression::Xz
+    } else {
+        DownloadCompression::None
+    }
+}
+
+/// يحذف لاحقة الضغط المعروفة (`.gz`/`.xz`) من اسم ملف إن وُجدت، ليُكشف
+/// الامتداد الحقيقي (`.img`/`.iso`/...) من الاسم المتبقي.
+fn strip_compression_extension(file_name: &str) -> &str {
+    file_name
+        .strip_suffix(".gz")
+        .or_else(|| file_name.strip_suffix(".xz"))
+        .unwrap_or(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// يبني `OSManager` مباشرة (بدل `new`, الذي يُنشئ مجلدات تحت `/boot`)
+    /// كي لا يحتاج الاختبار صلاحيات كتابة على مسارات النظام.
+    fn test_manager() -> OSManager {
+        OSManager {
+            config: Config::default(),
+            os_storage_path: std::env::temp_dir(),
+            boot_partition_path: std::env::temp_dir(),
+            backup_path: std::env::temp_dir(),
+            configuration_limit: 5,
+            progress_reporter: None,
+        }
+    }
+
+    /// `implant_md5_tag` ثم `verify_image_md5_tag` يجب أن يتطابقا على نفس
+    /// الصورة - هذا بالضبط ما كسِره إصلاح `aa6cf88`: حساب البصمة بعد الزرع
+    /// كان يشمل حقل بيانات تطبيق PVD الذي يحمل الوسم نفسه، فيفشل التحقق
+    /// دوماً على صورة سليمة.
+    #[tokio::test]
+    async fn md5_tag_round_trip_on_iso() {
+        let manager = test_manager();
+        let path = std::env::temp_dir().join(format!("dos_safar_test_{}.iso", std::process::id()));
+        {
+            let mut file = fs::File::create(&path).unwrap();
+            let size = ((ISO_PVD_SECTOR + 2) * ISO_SECTOR_SIZE) as usize;
+            file.write_all(&vec![0xABu8; size]).unwrap();
+        }
+
+        manager.implant_md5_tag(path.to_str().unwrap(), 0).unwrap();
+        let verification = manager
+            .verify_image_md5_tag(path.to_str().unwrap(), None)
+            .await
+            .unwrap();
+
+        let _ = fs::remove_file(&path);
+        assert!(verification.passed, "computed {} vs stored {}", verification.computed_md5, verification.stored_md5);
+    }
+
+    /// بدون وسم مزروع (صورة من مصدر خارجي) يجب أن يُعتبر التحقق ناجحاً
+    /// ضمنياً بدل رفضه دون سبب.
+    #[tokio::test]
+    async fn md5_tag_missing_is_treated_as_passed() {
+        let manager = test_manager();
+        let path = std::env::temp_dir().join(format!("dos_safar_test_untagged_{}.iso", std::process::id()));
+        {
+            let mut file = fs::File::create(&path).unwrap();
+            let size = ((ISO_PVD_SECTOR + 2) * ISO_SECTOR_SIZE) as usize;
+            file.write_all(&vec![0u8; size]).unwrap();
+        }
+
+        let verification = manager
+            .verify_image_md5_tag(path.to_str().unwrap(), None)
+            .await
+            .unwrap();
+
+        let _ = fs::remove_file(&path);
+        assert!(verification.passed);
+        assert!(verification.stored_md5.is_empty());
+    }
+
+    /// يبني سجل دليل ISO 9660 واحد (ECMA-119 9.1) باسم/أعلام/امتداد معطاة،
+    /// بما فيها بايت الحشو الذي يضمن طولاً زوجياً للسجل.
+    fn build_dir_record(file_id: &[u8], is_directory: bool, extent_lba: u32, data_length: u32) -> Vec<u8> {
+        let mut length = 33 + file_id.len();
+        if length % 2 != 0 {
+            length += 1;
+        }
+        let mut record = vec![0u8; length];
+        record[0] = length as u8;
+        record[2..6].copy_from_slice(&extent_lba.to_le_bytes());
+        record[10..14].copy_from_slice(&data_length.to_le_bytes());
+        record[25] = if is_directory { 0x02 } else { 0x00 };
+        record[32] = file_id.len() as u8;
+        record[33..33 + file_id.len()].copy_from_slice(file_id);
+        record
+    }
+
+    #[test]
+    fn parses_self_and_parent_entries() {
+        let (self_entry, _) = OSManager::parse_iso9660_dir_record(&build_dir_record(&[0x00], true, 20, 2048)).unwrap();
+        assert!(self_entry.is_self_or_parent);
+        assert_eq!(self_entry.name, ".");
+
+        let (parent_entry, _) = OSManager::parse_iso9660_dir_record(&build_dir_record(&[0x01], true, 20, 2048)).unwrap();
+        assert!(parent_entry.is_self_or_parent);
+        assert_eq!(parent_entry.name, "..");
+    }
+
+    /// أسماء الملفات (لا الأدلة) تحمل لاحقة إصدار ";1" يجب إزالتها.
+    #[test]
+    fn strips_version_suffix_from_file_names_only() {
+        let (file_entry, record_len) =
+            OSManager::parse_iso9660_dir_record(&build_dir_record(b"GAME.BIN;1", false, 40, 4096)).unwrap();
+        assert_eq!(file_entry.name, "GAME.BIN");
+        assert!(!file_entry.is_directory);
+        assert_eq!(file_entry.extent_lba, 40);
+        assert_eq!(file_entry.data_length, 4096);
+        assert_eq!(record_len, build_dir_record(b"GAME.BIN;1", false, 40, 4096).len());
+
+        let (dir_entry, _) = OSManager::parse_iso9660_dir_record(&build_dir_record(b"ROMS", true, 41, 2048)).unwrap();
+        assert_eq!(dir_entry.name, "ROMS");
+        assert!(dir_entry.is_directory);
+    }
+
+    /// بايت أول صفر يعني حشو نهاية القطاع، لا سجلاً صالحاً.
+    #[test]
+    fn zero_length_byte_is_end_of_records_padding() {
+        let sector_tail = [0u8; 16];
+        assert!(OSManager::parse_iso9660_dir_record(&sector_tail).is_none());
+    }
+
+    /// `read_iso9660_dir_entries` يجب أن يحلل عدة سجلات متتالية ضمن نفس
+    /// القطاع متوقفاً عند حشو الصفر، دون أن يمتد إلى ما بعد `data_length`.
+    #[test]
+    fn reads_multiple_dir_records_from_one_sector() {
+        let path = std::env::temp_dir().join(format!("dos_safar_test_dir_{}.bin", std::process::id()));
+        let extent_lba = 30u32;
+        let mut sector = vec![0u8; ISO_SECTOR_SIZE as usize];
+
+        let self_record = build_dir_record(&[0x00], true, extent_lba, ISO_SECTOR_SIZE as u32);
+        let parent_record = build_dir_record(&[0x01], true, extent_lba, ISO_SECTOR_SIZE as u32);
+        let file_record = build_dir_record(b"README.TXT;1", false, extent_lba + 1, 10);
+
+        let mut offset = 0;
+        for record in [&self_record, &parent_record, &file_record] {
+            sector[offset..offset + record.len()].copy_from_slice(record);
+            offset += record.len();
+        }
+
+        {
+            let mut file = fs::File::create(&path).unwrap();
+            file.write_all(&vec![0u8; extent_lba as usize * ISO_SECTOR_SIZE as usize]).unwrap();
+            file.write_all(&sector).unwrap();
+        }
+
+        let mut file = fs::File::open(&path).unwrap();
+        let entries = OSManager::read_iso9660_dir_entries(&mut file, extent_lba, ISO_SECTOR_SIZE as u32).unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(entries.len(), 3);
+        assert!(entries[0].is_self_or_parent);
+        assert!(entries[1].is_self_or_parent);
+        assert_eq!(entries[2].name, "README.TXT");
+        assert_eq!(entries[2].extent_lba, extent_lba + 1);
+    }
 }
\ No newline at end of file
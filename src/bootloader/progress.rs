@@ -0,0 +1,127 @@
+// بروتوكول تقدم عبر مقبس يونكس لمراقبة العمليات الطويلة من عملية خارجية
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// حدث تقدم واحد يُبث كسطر JSON منفرد إلى كل عميل متصل، مثل
+/// `{"op":"download","os":"batocera","phase":"downloading","percent":42.0,"detail":"..."}`.
+/// العمليات النهائية تستخدم `phase: "done"`/`"error"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressEvent {
+    pub op: String,
+    pub os: Option<String>,
+    pub phase: String,
+    pub percent: f32,
+    pub detail: String,
+}
+
+/// يبث أحداث `ProgressEvent` كسطور JSON مفصولة بأسطر جديدة عبر مقبس يونكس،
+/// حتى تستطيع واجهة خارجية (GTK، ويب، أياً كانت) رسم أشرطة تقدم حية دون أن
+/// تحمل هذه المكتبة أي اعتمادية واجهة مستخدم. قد يتصل أكثر من عميل في آن
+/// واحد؛ كل عميل يتلقى كل حدث يُبث بعد اتصاله.
+pub struct ProgressReporter {
+    socket_path: PathBuf,
+    clients: Arc<Mutex<Vec<UnixStream>>>,
+}
+
+impl std::fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("socket_path", &self.socket_path)
+            .finish()
+    }
+}
+
+impl ProgressReporter {
+    /// يفتح مقبس يونكس عند `socket_path` (حاذفاً أي ملف مقبس قديم متبقٍّ من
+    /// تشغيل سابق) ويبدأ قبول الاتصالات في الخلفية دون حجب المستدعي.
+    pub async fn bind(socket_path: impl Into<PathBuf>) -> Result<Self> {
+        let socket_path = socket_path.into();
+        let _ = std::fs::remove_file(&socket_path);
+
+        let listener = UnixListener::bind(&socket_path)
+            .with_context(|| format!("فشل في فتح مقبس التقدم {}", socket_path.display()))?;
+
+        let clients: Arc<Mutex<Vec<UnixStream>>> = Arc::new(Mutex::new(Vec::new()));
+        let accept_clients = clients.clone();
+        let accept_path = socket_path.clone();
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        debug!("🔌 عميل تقدم جديد متصل بمقبس {}", accept_path.display());
+                        accept_clients.lock().await.push(stream);
+                    }
+                    Err(e) => {
+                        warn!("⚠️ فشل في قبول اتصال على مقبس التقدم: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        info!("📡 مقبس تقدم العمليات جاهز على {}", socket_path.display());
+        Ok(ProgressReporter {
+            socket_path,
+            clients,
+        })
+    }
+
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// يبث الحدث كسطر JSON واحد منتهٍ بـ `\n` إلى كل عميل متصل، مزيلاً أي
+    /// عميل فشلت الكتابة إليه (انقطع اتصاله) بدل إبقائه معلّقاً في القائمة.
+    pub async fn emit(&self, event: ProgressEvent) {
+        let Ok(mut line) = serde_json::to_string(&event) else {
+            warn!("⚠️ فشل في ترميز حدث التقدم كـ JSON");
+            return;
+        };
+        line.push('\n');
+
+        let mut clients = self.clients.lock().await;
+        let mut still_connected = Vec::with_capacity(clients.len());
+        for mut client in clients.drain(..) {
+            if client.write_all(line.as_bytes()).await.is_ok() {
+                still_connected.push(client);
+            }
+        }
+        *clients = still_connected;
+    }
+
+    /// اختصار لبثّ حدث مرحلة متوسطة (ليس نهائياً) ضمن عملية `op`.
+    pub async fn phase(
+        &self,
+        op: &str,
+        os: Option<&str>,
+        phase: &str,
+        percent: f32,
+        detail: impl Into<String>,
+    ) {
+        self.emit(ProgressEvent {
+            op: op.to_string(),
+            os: os.map(|s| s.to_string()),
+            phase: phase.to_string(),
+            percent,
+            detail: detail.into(),
+        })
+        .await;
+    }
+
+    /// يبث حدث `done` نهائياً بنسبة إنجاز 100%.
+    pub async fn done(&self, op: &str, os: Option<&str>) {
+        self.phase(op, os, "done", 100.0, "اكتمل").await;
+    }
+
+    /// يبث حدث `error` نهائياً يحمل رسالة الفشل.
+    pub async fn error(&self, op: &str, os: Option<&str>, message: impl Into<String>) {
+        self.phase(op, os, "error", 0.0, message).await;
+    }
+}
@@ -0,0 +1,190 @@
+// ROM-library scanner for retro-gaming OS directories (RetroPie/Batocera/
+// Recalbox). Walks `roms/<system>/` and classifies each file by parsing its
+// cartridge/ROM header rather than trusting the file extension, skipping
+// and counting whatever it can't recognize instead of failing the scan.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// Per-console summary attached to `OperatingSystem.rom_libraries`, e.g.
+/// "Batocera — 1,240 games across 9 systems" is `rom_libraries.len()`
+/// systems and the sum of `count` across them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RomLibrary {
+    /// `roms/` subdirectory name, e.g. "gb", "nes", "snes", "genesis".
+    pub system: String,
+    pub count: u32,
+    pub total_size_mb: u64,
+    pub titles: Vec<String>,
+    /// Files under this system's directory whose header didn't match any
+    /// known format, or couldn't be read at all.
+    pub unrecognized: u32,
+}
+
+/// Walks `os_root/roms/*` (one subdirectory per console) and returns one
+/// `RomLibrary` per console directory that contains at least one file,
+/// recognized or not. Missing `roms/` simply yields an empty result rather
+/// than an error - most non-retro systems don't have one.
+pub fn scan_rom_libraries(os_root: &Path) -> Vec<RomLibrary> {
+    let roms_root = os_root.join("roms");
+    let Ok(system_dirs) = fs::read_dir(&roms_root) else {
+        return Vec::new();
+    };
+
+    let mut libraries = Vec::new();
+    for entry in system_dirs.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let system = entry.file_name().to_string_lossy().to_string();
+        if let Some(library) = scan_system_directory(&system, &path) {
+            libraries.push(library);
+        }
+    }
+
+    libraries.sort_by(|a, b| a.system.cmp(&b.system));
+    libraries
+}
+
+fn scan_system_directory(system: &str, dir: &Path) -> Option<RomLibrary> {
+    let mut count = 0u32;
+    let mut unrecognized = 0u32;
+    let mut total_size_mb = 0u64;
+    let mut titles = Vec::new();
+
+    let Ok(entries) = fs::read_dir(dir) else {
+        return None;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_file() {
+            continue;
+        }
+
+        let Ok(metadata) = entry.metadata() else {
+            unrecognized += 1;
+            continue;
+        };
+        total_size_mb += metadata.len() / 1024 / 1024;
+
+        match identify_rom(&path) {
+            Some(title) => {
+                count += 1;
+                titles.push(title);
+            }
+            None => unrecognized += 1,
+        }
+    }
+
+    if count == 0 && unrecognized == 0 {
+        return None;
+    }
+
+    Some(RomLibrary {
+        system: system.to_string(),
+        count,
+        total_size_mb,
+        titles,
+        unrecognized,
+    })
+}
+
+/// Read enough of the file to cover the farthest header we probe (the SNES
+/// internal header at `0x7FC0`); shorter files just get a shorter buffer and
+/// naturally fail that probe.
+const ROM_HEADER_PROBE_SIZE: usize = 0x7FC0 + 21;
+
+/// Tries each known cartridge/ROM header format in turn, returning the
+/// title string embedded in the header when one matches. `None` means the
+/// file is corrupt or its format isn't one we parse yet - it's counted as
+/// unrecognized rather than rejected outright.
+fn identify_rom(path: &Path) -> Option<String> {
+    let mut file = fs::File::open(path).ok()?;
+    let mut header = vec![0u8; ROM_HEADER_PROBE_SIZE];
+    let bytes_read = file.read(&mut header).ok()?;
+    header.truncate(bytes_read);
+    if bytes_read < 0x150 {
+        return None;
+    }
+
+    identify_gameboy(&header)
+        .or_else(|| identify_nes(&header))
+        .or_else(|| identify_snes(&header))
+        .or_else(|| identify_genesis(&header))
+}
+
+/// Game Boy/Game Boy Color cartridge header: title at `0x134-0x143`
+/// (padded with `\0`), cartridge type at `0x147`, header checksum at
+/// `0x14D`. The checksum byte is read but not verified; only the cartridge
+/// type is checked for plausibility.
+fn identify_gameboy(header: &[u8]) -> Option<String> {
+    let title_bytes = &header[0x134..0x144];
+    let cartridge_type = header[0x147];
+    let _checksum = header[0x14D];
+
+    if !cartridge_type_is_plausible_gameboy(cartridge_type) {
+        return None;
+    }
+
+    decode_ascii_title(title_bytes)
+}
+
+fn cartridge_type_is_plausible_gameboy(cartridge_type: u8) -> bool {
+    matches!(
+        cartridge_type,
+        0x00..=0x06 | 0x08..=0x0D | 0x0F..=0x13 | 0x19..=0x1E | 0x20 | 0x22 | 0xFC..=0xFF
+    )
+}
+
+/// NES: `NES\x1A` magic at offset 0, then the iNES header (PRG/CHR bank
+/// counts at offsets 4/5). There's no embedded title, so we fall back to
+/// describing the cartridge by its bank layout.
+fn identify_nes(header: &[u8]) -> Option<String> {
+    if &header[0..4] != b"NES\x1A" {
+        return None;
+    }
+    let prg_banks = header[4];
+    let chr_banks = header[5];
+    Some(format!("NES ROM ({prg_banks} PRG x 16KB, {chr_banks} CHR x 8KB)"))
+}
+
+/// SNES internal header lives at `0x7FC0` for LoROM or `0xFFC0` for HiROM,
+/// holding a 21-byte ASCII title. We only read the first
+/// `ROM_HEADER_PROBE_SIZE` bytes of the file, so this only catches LoROM
+/// images with no copier header -
+/// HiROM/headered dumps fall through as unrecognized.
+fn identify_snes(header: &[u8]) -> Option<String> {
+    if header.len() < 0x7FC0 + 21 {
+        return None;
+    }
+    decode_ascii_title(&header[0x7FC0..0x7FC0 + 21])
+}
+
+/// Genesis/Mega Drive: `SEGA` tag at offset `0x100`, domestic title at
+/// `0x120..0x150`.
+fn identify_genesis(header: &[u8]) -> Option<String> {
+    if &header[0x100..0x104] != b"SEGA" {
+        return None;
+    }
+    decode_ascii_title(&header[0x120..0x150])
+}
+
+/// Trims trailing NUL/whitespace padding from a fixed-width header title
+/// field and rejects it if nothing printable remains.
+fn decode_ascii_title(bytes: &[u8]) -> Option<String> {
+    let title = bytes
+        .iter()
+        .take_while(|&&b| b != 0)
+        .map(|&b| b as char)
+        .collect::<String>();
+    let trimmed = title.trim();
+
+    if trimmed.is_empty() || !trimmed.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return None;
+    }
+
+    Some(trimmed.to_string())
+}
@@ -0,0 +1,150 @@
+// وضع الطرفية الخام لقائمة الإقلاع: يعطّل ICANON/ECHO على stdin حتى تصل
+// ضغطات الأسهم وأزرار D-pad فوراً دون انتظار سطر كامل، ويعيد الإعداد
+// الأصلي عند إسقاط الحارس (بما في ذلك أثناء تفريغ المكدس عند panic).
+use anyhow::Result;
+use std::mem::MaybeUninit;
+use std::os::unix::io::RawFd;
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+const STDIN_FD: RawFd = 0;
+
+/// حارس RAII لوضع الطرفية الخام؛ يستعيد إعدادات termios الأصلية عند
+/// الإسقاط، بما يشمل المسارات غير السعيدة (panic) لأن Drop يُنفَّذ أثناء
+/// تفريغ المكدس ما لم تكن استراتيجية panic هي `abort`.
+pub struct RawTerminal {
+    original: libc::termios,
+}
+
+impl RawTerminal {
+    pub fn enable() -> Result<Self> {
+        let mut original = MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(STDIN_FD, original.as_mut_ptr()) } != 0 {
+            return Err(anyhow::anyhow!(
+                "فشل في قراءة إعدادات الطرفية (tcgetattr): {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        let original = unsafe { original.assume_init() };
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        if unsafe { libc::tcsetattr(STDIN_FD, libc::TCSANOW, &raw) } != 0 {
+            return Err(anyhow::anyhow!(
+                "فشل في ضبط الطرفية على الوضع الخام (tcsetattr): {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+
+        Ok(RawTerminal { original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(STDIN_FD, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// ضغطة مفتاح واحدة مفسَّرة من تيار بايتات stdin الخام.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Select,
+    Advanced,
+    Web,
+    RestartTests,
+    Shutdown,
+    SetDefault,
+    Other(u8),
+}
+
+/// يشغّل خيط قراءة محجوب منفصل (القراءة الخام لا يمكن أن تكون async بدون
+/// سحب اعتمادية إضافية)، يراقب stdin وجهاز الطرفية التسلسلية الاختياري
+/// (`serial_fd`، من `console::Console::serial_fd`) معاً عبر `poll(2)`،
+/// ويبث كل ضغطة مفسَّرة عبر قناة tokio غير محدودة يستهلكها حلقة القائمة.
+pub fn spawn_key_reader(serial_fd: Option<RawFd>) -> UnboundedReceiver<Key> {
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    std::thread::spawn(move || {
+        let mut poll_fds = vec![libc::pollfd {
+            fd: STDIN_FD,
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        if let Some(fd) = serial_fd {
+            poll_fds.push(libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            });
+        }
+
+        'poll_loop: loop {
+            for pfd in poll_fds.iter_mut() {
+                pfd.revents = 0;
+            }
+
+            let ready = unsafe {
+                libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, -1)
+            };
+            if ready <= 0 {
+                continue;
+            }
+
+            for pfd in &poll_fds {
+                if pfd.revents & libc::POLLIN == 0 {
+                    continue;
+                }
+
+                match read_key(pfd.fd) {
+                    Some(key) => {
+                        if tx.send(key).is_err() {
+                            break 'poll_loop;
+                        }
+                    }
+                    None => break 'poll_loop,
+                }
+            }
+        }
+    });
+
+    rx
+}
+
+fn read_byte(fd: RawFd) -> Option<u8> {
+    let mut byte = [0u8; 1];
+    let n = unsafe { libc::read(fd, byte.as_mut_ptr() as *mut libc::c_void, 1) };
+    if n == 1 {
+        Some(byte[0])
+    } else {
+        None
+    }
+}
+
+/// يقرأ ويفسّر ضغطة مفتاح واحدة من `fd` (stdin أو جهاز تسلسلي)، متابعاً
+/// تتابع هروب ANSI لأسهم الاتجاه (ESC [ A/B) عند الحاجة.
+fn read_key(fd: RawFd) -> Option<Key> {
+    let key = match read_byte(fd)? {
+        b'\n' | b'\r' => Key::Select,
+        b'A' | b'a' => Key::Advanced,
+        b'W' | b'w' => Key::Web,
+        b'R' | b'r' => Key::RestartTests,
+        b'S' | b's' => Key::Shutdown,
+        b'D' | b'd' => Key::SetDefault,
+        b'j' | b'J' => Key::Down,
+        b'k' | b'K' => Key::Up,
+        0x1b => match (read_byte(fd)?, read_byte(fd)?) {
+            (b'[', b'A') => Key::Up,
+            (b'[', b'B') => Key::Down,
+            _ => Key::Other(0x1b),
+        },
+        other => Key::Other(other),
+    };
+    Some(key)
+}
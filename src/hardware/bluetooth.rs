@@ -0,0 +1,228 @@
+// BLE HID gamepad discovery/pairing via `bluest`, so the remote-control
+// flow and `InputTester` aren't limited to wired/USB controllers. Pairing
+// bonds the device with the adapter (so the kernel stack reconnects it on
+// its own on subsequent sessions) and additionally records its id/name
+// here, so `reconnect_paired` can proactively bring every previously-paired
+// gamepad back on the next boot instead of waiting on it to reconnect by
+// itself.
+use anyhow::{Context, Result};
+use bluest::{Adapter, Device, Uuid};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::utils::config::Config;
+
+/// How long `scan` listens for advertisements before returning whatever it
+/// has collected.
+const SCAN_DURATION: Duration = Duration::from_secs(5);
+
+/// GATT service UUID advertised by HID-over-GATT peripherals (keyboards,
+/// mice, and the BLE gamepads this module cares about) - used to filter
+/// scan results down to plausible controllers instead of every nearby BLE
+/// device.
+const HID_SERVICE_UUID: Uuid = Uuid::from_u128(0x0000_1812_0000_1000_8000_00805f9b34fb);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BleDevice {
+    pub id: String,
+    pub name: String,
+    pub rssi: Option<i16>,
+    pub paired: bool,
+}
+
+/// `id -> name` of every device [`BluetoothManager::pair`] has bonded,
+/// stored atomically (same temp-file + rename pattern as
+/// `bootloader::boot_state::BootStateStore`) so a partial write never
+/// leaves a corrupt file behind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PairedDeviceStore {
+    devices: HashMap<String, String>,
+}
+
+impl PairedDeviceStore {
+    fn path(config: &Config) -> PathBuf {
+        Path::new(&config.system.config_persist_path).join("bluetooth_devices.json")
+    }
+
+    fn load(path: &Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else { return Self::default() };
+        serde_json::from_str(&content).unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create Bluetooth state directory: {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_string_pretty(self).context("Failed to encode paired-device store")?;
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, content)
+            .with_context(|| format!("Failed to write temp paired-device file: {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace paired-device file: {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+pub struct BluetoothManager {
+    config: Config,
+}
+
+impl BluetoothManager {
+    pub fn new(config: &Config) -> Self {
+        BluetoothManager { config: config.clone() }
+    }
+
+    /// Scans for `SCAN_DURATION` and returns every discovered HID-service
+    /// peripheral, each flagged with whether it's already in the paired
+    /// store.
+    pub async fn scan(&self) -> Result<Vec<BleDevice>> {
+        let adapter = Adapter::default().await.context("no Bluetooth adapter available")?;
+        adapter.wait_available().await.context("Bluetooth adapter never became available")?;
+
+        let store = PairedDeviceStore::load(&PairedDeviceStore::path(&self.config));
+        let mut discovered: HashMap<String, BleDevice> = HashMap::new();
+
+        let mut advertisements = adapter
+            .scan(&[HID_SERVICE_UUID])
+            .await
+            .context("failed to start BLE scan")?;
+        let deadline = tokio::time::Instant::now() + SCAN_DURATION;
+        while let Ok(Some(advertisement)) = tokio::time::timeout_at(deadline, advertisements.next()).await {
+            let id = advertisement.device.id().to_string();
+            let name = advertisement
+                .device
+                .name()
+                .unwrap_or_else(|_| "Unknown BLE Device".to_string());
+            discovered.insert(
+                id.clone(),
+                BleDevice {
+                    paired: store.devices.contains_key(&id),
+                    id,
+                    name,
+                    rssi: advertisement.rssi,
+                },
+            );
+        }
+
+        info!("🔷 BLE scan found {} HID device(s)", discovered.len());
+        Ok(discovered.into_values().collect())
+    }
+
+    /// Connects to and bonds `device_id` (as returned by `scan`), then
+    /// records it in the paired store so [`reconnect_paired`] picks it up
+    /// on future boots.
+    ///
+    /// [`reconnect_paired`]: BluetoothManager::reconnect_paired
+    pub async fn pair(&self, device_id: &str) -> Result<BleDevice> {
+        let adapter = Adapter::default().await.context("no Bluetooth adapter available")?;
+        adapter.wait_available().await.context("Bluetooth adapter never became available")?;
+
+        let device = self.find_device(&adapter, device_id).await?;
+        adapter
+            .connect_device(&device)
+            .await
+            .with_context(|| format!("failed to connect to BLE device {}", device_id))?;
+        if !device.is_paired().await.unwrap_or(false) {
+            device
+                .pair()
+                .await
+                .with_context(|| format!("failed to bond BLE device {}", device_id))?;
+        }
+
+        let name = device.name().unwrap_or_else(|_| "Unknown BLE Device".to_string());
+
+        let path = PairedDeviceStore::path(&self.config);
+        let mut store = PairedDeviceStore::load(&path);
+        store.devices.insert(device_id.to_string(), name.clone());
+        store.save(&path)?;
+
+        info!("🔷 Paired and bonded BLE device: {} ({})", name, device_id);
+        Ok(BleDevice { id: device_id.to_string(), name, rssi: None, paired: true })
+    }
+
+    /// Connects every device in the paired store that isn't already
+    /// connected - called from `main::run_hardware_tests` so a gamepad
+    /// paired last session is available again without the user re-pairing.
+    pub async fn reconnect_paired(&self) -> Result<Vec<BleDevice>> {
+        let store = PairedDeviceStore::load(&PairedDeviceStore::path(&self.config));
+        if store.devices.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let adapter = Adapter::default().await.context("no Bluetooth adapter available")?;
+        adapter.wait_available().await.context("Bluetooth adapter never became available")?;
+
+        let mut reconnected = Vec::new();
+        for (id, name) in &store.devices {
+            match self.find_device(&adapter, id).await {
+                Ok(device) => match adapter.connect_device(&device).await {
+                    Ok(()) => {
+                        info!("🔷 Reconnected BLE device: {} ({})", name, id);
+                        reconnected.push(BleDevice { id: id.clone(), name: name.clone(), rssi: None, paired: true });
+                    }
+                    Err(e) => warn!("Failed to reconnect BLE device {} ({}): {}", name, id, e),
+                },
+                Err(e) => warn!("Paired BLE device {} ({}) not found: {}", name, id, e),
+            }
+        }
+
+        Ok(reconnected)
+    }
+
+    /// Cheap status query for `SystemStatus`/`run_hardware_tests`: asks
+    /// the adapter which already-bonded devices are presently connected,
+    /// without kicking off a fresh scan or connection attempt.
+    pub async fn connected_devices(&self) -> Result<Vec<BleDevice>> {
+        let adapter = Adapter::default().await.context("no Bluetooth adapter available")?;
+        adapter.wait_available().await.context("Bluetooth adapter never became available")?;
+        let store = PairedDeviceStore::load(&PairedDeviceStore::path(&self.config));
+
+        let mut result = Vec::new();
+        for device in adapter.connected_devices().await.unwrap_or_default() {
+            let id = device.id().to_string();
+            if !store.devices.contains_key(&id) {
+                continue;
+            }
+            let name = device.name().unwrap_or_else(|_| "Unknown BLE Device".to_string());
+            result.push(BleDevice { id, name, rssi: None, paired: true });
+        }
+
+        Ok(result)
+    }
+
+    /// Looks up `device_id` among already-connected devices, then bonded
+    /// devices, and finally falls back to a fresh scan - covers pairing a
+    /// device the adapter has never seen connected/bonded before.
+    async fn find_device(&self, adapter: &Adapter, device_id: &str) -> Result<Device> {
+        for device in adapter.connected_devices().await.unwrap_or_default() {
+            if device.id().to_string() == device_id {
+                return Ok(device);
+            }
+        }
+        for device in adapter.bonded_devices().await.unwrap_or_default() {
+            if device.id().to_string() == device_id {
+                return Ok(device);
+            }
+        }
+
+        let mut advertisements = adapter.scan(&[]).await.context("failed to start BLE scan")?;
+        let deadline = tokio::time::Instant::now() + SCAN_DURATION;
+        while let Ok(Some(advertisement)) = tokio::time::timeout_at(deadline, advertisements.next()).await {
+            if advertisement.device.id().to_string() == device_id {
+                return Ok(advertisement.device);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "BLE device {} not found (not connected, bonded, or advertising)",
+            device_id
+        ))
+    }
+}
@@ -0,0 +1,200 @@
+// Data-driven SBC/handheld detection registry, replacing the hardcoded
+// is_raspberry_pi/is_orange_pi/... ladder in device_detect.rs.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tracing::{debug, warn};
+
+use crate::hardware::device_detect::{DeviceType, GamingFeatures};
+use crate::utils::config::Config;
+
+/// A single detection rule: if any of its predicates match the running
+/// hardware, the rule's `device_type` (and defaults) are returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardRule {
+    pub device_type: DeviceType,
+    pub default_model: String,
+    pub has_gpio: bool,
+    #[serde(default)]
+    pub compatible_substrings: Vec<String>,
+    #[serde(default)]
+    pub model_substrings: Vec<String>,
+    #[serde(default)]
+    pub cpuinfo_hardware_substrings: Vec<String>,
+    #[serde(default)]
+    pub marker_paths: Vec<String>,
+    pub default_gaming_features: GamingFeatures,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BoardRuleFile {
+    #[serde(rename = "rule")]
+    rules: Vec<BoardRule>,
+}
+
+fn no_gaming_features() -> GamingFeatures {
+    GamingFeatures {
+        has_dpad: false,
+        has_analog_sticks: false,
+        has_shoulder_buttons: false,
+        has_built_in_screen: false,
+        has_battery: false,
+        screen_size_inches: None,
+        native_resolution: None,
+    }
+}
+
+fn gaming_handheld_features(screen_size_inches: f32, native_resolution: (u32, u32)) -> GamingFeatures {
+    GamingFeatures {
+        has_dpad: true,
+        has_analog_sticks: true,
+        has_shoulder_buttons: true,
+        has_built_in_screen: true,
+        has_battery: true,
+        screen_size_inches: Some(screen_size_inches),
+        native_resolution: Some(native_resolution),
+    }
+}
+
+/// The built-in board table, equivalent to the previous `is_*` ladder.
+pub fn builtin_rules() -> Vec<BoardRule> {
+    vec![
+        BoardRule {
+            device_type: DeviceType::RaspberryPi,
+            default_model: "Raspberry Pi (Unknown Model)".to_string(),
+            has_gpio: true,
+            compatible_substrings: vec!["raspberrypi".to_string()],
+            model_substrings: vec!["raspberry pi".to_string()],
+            cpuinfo_hardware_substrings: vec!["raspberry pi".to_string()],
+            marker_paths: vec![],
+            default_gaming_features: no_gaming_features(),
+        },
+        BoardRule {
+            device_type: DeviceType::Anbernic,
+            default_model: "Anbernic Gaming Handheld".to_string(),
+            has_gpio: false,
+            compatible_substrings: vec![],
+            model_substrings: vec![
+                "rg351".to_string(),
+                "rg552".to_string(),
+                "rg35xx".to_string(),
+                "anbernic".to_string(),
+            ],
+            cpuinfo_hardware_substrings: vec![],
+            marker_paths: vec!["/opt/anbernic".to_string(), "/boot/anbernic".to_string()],
+            default_gaming_features: gaming_handheld_features(3.5, (480, 320)),
+        },
+        BoardRule {
+            device_type: DeviceType::OrangePi,
+            default_model: "Orange Pi".to_string(),
+            has_gpio: true,
+            compatible_substrings: vec!["orangepi".to_string()],
+            model_substrings: vec!["orange pi".to_string()],
+            cpuinfo_hardware_substrings: vec![],
+            marker_paths: vec![],
+            default_gaming_features: no_gaming_features(),
+        },
+        BoardRule {
+            device_type: DeviceType::BananaPi,
+            default_model: "Banana Pi".to_string(),
+            has_gpio: true,
+            compatible_substrings: vec!["bananapi".to_string()],
+            model_substrings: vec!["banana pi".to_string()],
+            cpuinfo_hardware_substrings: vec![],
+            marker_paths: vec![],
+            default_gaming_features: no_gaming_features(),
+        },
+        BoardRule {
+            device_type: DeviceType::RockPi,
+            default_model: "Rock Pi".to_string(),
+            has_gpio: true,
+            compatible_substrings: vec!["rockpi".to_string(), "rockchip".to_string()],
+            model_substrings: vec!["rock pi".to_string()],
+            cpuinfo_hardware_substrings: vec![],
+            marker_paths: vec![],
+            default_gaming_features: no_gaming_features(),
+        },
+        BoardRule {
+            device_type: DeviceType::Odroid,
+            default_model: "Odroid".to_string(),
+            has_gpio: true,
+            compatible_substrings: vec!["odroid".to_string()],
+            model_substrings: vec!["odroid".to_string()],
+            cpuinfo_hardware_substrings: vec![],
+            marker_paths: vec![],
+            default_gaming_features: no_gaming_features(),
+        },
+    ]
+}
+
+/// Load a user's extra/override rules from the TOML file pointed to by
+/// `config.boards.extra_rules_path`, if configured. Returns the rules
+/// prepended ahead of the built-in table so user overrides win.
+pub fn load_rules(config: &Config) -> Vec<BoardRule> {
+    let mut rules = Vec::new();
+
+    if let Some(path) = &config.boards.extra_rules_path {
+        match fs::read_to_string(path) {
+            Ok(content) => match toml::from_str::<BoardRuleFile>(&content) {
+                Ok(file) => {
+                    debug!("Loaded {} custom board rule(s) from {}", file.rules.len(), path);
+                    rules.extend(file.rules);
+                }
+                Err(e) => warn!("Failed to parse board rules file {}: {}", path, e),
+            },
+            Err(e) => warn!("Failed to read board rules file {}: {}", path, e),
+        }
+    }
+
+    rules.extend(builtin_rules());
+    rules
+}
+
+/// Evaluate a rule's predicates against the currently running hardware.
+pub fn rule_matches(rule: &BoardRule) -> bool {
+    let device_tree_model = fs::read_to_string("/proc/device-tree/model")
+        .unwrap_or_default()
+        .to_lowercase();
+    let compatible = fs::read_to_string("/proc/device-tree/compatible")
+        .unwrap_or_default()
+        .to_lowercase();
+    let cpuinfo_hardware = fs::read_to_string("/proc/cpuinfo")
+        .unwrap_or_default()
+        .lines()
+        .find(|line| line.starts_with("Hardware"))
+        .unwrap_or("")
+        .to_lowercase();
+
+    if rule
+        .model_substrings
+        .iter()
+        .any(|s| device_tree_model.contains(s.as_str()))
+    {
+        return true;
+    }
+
+    if rule
+        .compatible_substrings
+        .iter()
+        .any(|s| compatible.contains(s.as_str()))
+    {
+        return true;
+    }
+
+    if rule
+        .cpuinfo_hardware_substrings
+        .iter()
+        .any(|s| cpuinfo_hardware.contains(s.as_str()))
+    {
+        return true;
+    }
+
+    rule.marker_paths
+        .iter()
+        .any(|p| std::path::Path::new(p).exists())
+}
+
+/// Walk the registry (user overrides first, then built-ins) and return the
+/// first matching rule, if any.
+pub fn resolve(config: &Config) -> Option<BoardRule> {
+    load_rules(config).into_iter().find(rule_matches)
+}
@@ -0,0 +1,230 @@
+// Emulation-capability assessment, grading a detected device against a
+// table of emulation targets in the spirit of MAME's machine-info flags.
+use serde::{Deserialize, Serialize};
+
+use crate::hardware::device_detect::DeviceInfo;
+use crate::{gaming_info, gaming_warn};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CapabilityRating {
+    Good,
+    Warnings,
+    NotRecommended,
+}
+
+/// The outcome for a single emulation target, with human-readable reasons
+/// for whatever rating it was given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmulationTargetResult {
+    pub target: String,
+    pub rating: CapabilityRating,
+    pub reasons: Vec<String>,
+}
+
+/// Full assessment for a detected device, serializable as JSON or handed
+/// off to the LCD display subsystem for on-device rendering.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub device_model: String,
+    pub targets: Vec<EmulationTargetResult>,
+}
+
+/// Minimum requirements for one emulation target.
+struct EmulationTarget {
+    name: &'static str,
+    min_cores: u32,
+    min_frequency_mhz: u32,
+    min_memory_mb: u64,
+    supported_architectures: Vec<&'static str>,
+    requires_screen: bool,
+    requires_analog_sticks: bool,
+    recommended_resolution: (u32, u32),
+}
+
+/// The built-in target table, roughly ordered from least to most demanding.
+fn emulation_targets() -> Vec<EmulationTarget> {
+    vec![
+        EmulationTarget {
+            name: "8/16-bit consoles (NES/SNES/Genesis)",
+            min_cores: 1,
+            min_frequency_mhz: 600,
+            min_memory_mb: 256,
+            supported_architectures: vec!["arm", "aarch64", "x86_64"],
+            requires_screen: false,
+            requires_analog_sticks: false,
+            recommended_resolution: (256, 224),
+        },
+        EmulationTarget {
+            name: "Sony PlayStation (PS1)",
+            min_cores: 1,
+            min_frequency_mhz: 1000,
+            min_memory_mb: 512,
+            supported_architectures: vec!["arm", "aarch64", "x86_64"],
+            requires_screen: false,
+            requires_analog_sticks: true,
+            recommended_resolution: (640, 480),
+        },
+        EmulationTarget {
+            name: "Nintendo 64",
+            min_cores: 2,
+            min_frequency_mhz: 1200,
+            min_memory_mb: 1024,
+            supported_architectures: vec!["arm", "aarch64", "x86_64"],
+            requires_screen: false,
+            requires_analog_sticks: true,
+            recommended_resolution: (640, 480),
+        },
+        EmulationTarget {
+            name: "Sony PSP",
+            min_cores: 2,
+            min_frequency_mhz: 1400,
+            min_memory_mb: 1024,
+            supported_architectures: vec!["arm", "aarch64", "x86_64"],
+            requires_screen: false,
+            requires_analog_sticks: true,
+            recommended_resolution: (480, 272),
+        },
+        EmulationTarget {
+            name: "Sega Saturn",
+            min_cores: 4,
+            min_frequency_mhz: 1500,
+            min_memory_mb: 2048,
+            supported_architectures: vec!["aarch64", "x86_64"],
+            requires_screen: false,
+            requires_analog_sticks: false,
+            recommended_resolution: (640, 480),
+        },
+        EmulationTarget {
+            name: "Sega Dreamcast",
+            min_cores: 4,
+            min_frequency_mhz: 1800,
+            min_memory_mb: 2048,
+            supported_architectures: vec!["aarch64", "x86_64"],
+            requires_screen: false,
+            requires_analog_sticks: true,
+            recommended_resolution: (640, 480),
+        },
+    ]
+}
+
+/// Grade a single target against the detected device, collecting every
+/// reason that pushes the rating down rather than stopping at the first.
+fn assess_target(device_info: &DeviceInfo, target: &EmulationTarget) -> EmulationTargetResult {
+    let mut reasons = Vec::new();
+    let mut rating = CapabilityRating::Good;
+
+    if device_info.cpu_info.cores < target.min_cores {
+        reasons.push(format!(
+            "only {} CPU core(s), {} recommended for {}",
+            device_info.cpu_info.cores, target.min_cores, target.name
+        ));
+        rating = CapabilityRating::NotRecommended;
+    }
+
+    if let Some(frequency_mhz) = device_info.cpu_info.frequency_mhz {
+        if frequency_mhz < target.min_frequency_mhz {
+            reasons.push(format!(
+                "CPU frequency {}MHz below the {}MHz recommended for {}",
+                frequency_mhz, target.min_frequency_mhz, target.name
+            ));
+            rating = CapabilityRating::NotRecommended;
+        }
+    } else {
+        reasons.push(format!(
+            "could not determine CPU frequency, assuming it may be under the {}MHz needed for {}",
+            target.min_frequency_mhz, target.name
+        ));
+        if rating == CapabilityRating::Good {
+            rating = CapabilityRating::Warnings;
+        }
+    }
+
+    if device_info.memory_mb < target.min_memory_mb {
+        reasons.push(format!(
+            "insufficient RAM for {} ({}MB available, {}MB recommended)",
+            target.name, device_info.memory_mb, target.min_memory_mb
+        ));
+        rating = CapabilityRating::NotRecommended;
+    }
+
+    if !target
+        .supported_architectures
+        .iter()
+        .any(|a| *a == device_info.architecture)
+    {
+        reasons.push(format!(
+            "architecture {} is not a well-supported target for {}",
+            device_info.architecture, target.name
+        ));
+        rating = CapabilityRating::NotRecommended;
+    }
+
+    if target.requires_analog_sticks && !device_info.gaming_features.has_analog_sticks {
+        reasons.push(format!(
+            "no analog sticks detected — {} playability degraded",
+            target.name
+        ));
+        if rating == CapabilityRating::Good {
+            rating = CapabilityRating::Warnings;
+        }
+    }
+
+    if target.requires_screen && !device_info.gaming_features.has_built_in_screen {
+        reasons.push(format!("no built-in screen detected for {}", target.name));
+        if rating == CapabilityRating::Good {
+            rating = CapabilityRating::Warnings;
+        }
+    }
+
+    if let Some((width, height)) = device_info.gaming_features.native_resolution {
+        let (target_width, target_height) = target.recommended_resolution;
+        if width < target_width || height < target_height {
+            reasons.push(format!(
+                "resolution {}x{} below the {}x{} native resolution targeted by {}",
+                width, height, target_width, target_height, target.name
+            ));
+            if rating == CapabilityRating::Good {
+                rating = CapabilityRating::Warnings;
+            }
+        }
+    }
+
+    EmulationTargetResult {
+        target: target.name.to_string(),
+        rating,
+        reasons,
+    }
+}
+
+/// Grade `device_info` against every known emulation target, logging a
+/// summary line per target via the `gaming_info!`/`gaming_warn!` macros and
+/// returning a serializable report for JSON output or LCD rendering.
+pub fn assess_capabilities(device_info: &DeviceInfo) -> CapabilityReport {
+    let targets = emulation_targets()
+        .iter()
+        .map(|target| assess_target(device_info, target))
+        .collect::<Vec<_>>();
+
+    for result in &targets {
+        match result.rating {
+            CapabilityRating::Good => {
+                gaming_info!("{}: Good", result.target);
+            }
+            CapabilityRating::Warnings => {
+                gaming_warn!("{}: Warnings - {}", result.target, result.reasons.join("; "));
+            }
+            CapabilityRating::NotRecommended => {
+                gaming_warn!(
+                    "{}: Not Recommended - {}",
+                    result.target,
+                    result.reasons.join("; ")
+                );
+            }
+        }
+    }
+
+    CapabilityReport {
+        device_model: device_info.model.clone(),
+        targets,
+    }
+}
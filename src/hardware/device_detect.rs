@@ -4,6 +4,10 @@ use std::fs;
 use std::process::Command;
 use tracing::{debug, info, warn};
 
+use crate::hardware::board_registry;
+use crate::hardware::hid_db::{self, IdentifiedController};
+use crate::utils::config::Config;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeviceInfo {
     pub device_type: DeviceType,
@@ -15,6 +19,8 @@ pub struct DeviceInfo {
     pub has_camera: bool,
     pub display_type: DisplayType,
     pub gaming_features: GamingFeatures,
+    pub pi_revision: Option<PiRevision>,
+    pub identified_controllers: Vec<IdentifiedController>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -35,6 +41,98 @@ pub struct CpuInfo {
     pub frequency_mhz: Option<u32>,
 }
 
+/// Decoded `/proc/cpuinfo` `Revision:` field for Raspberry Pi boards.
+///
+/// See the official revision-code documentation: bit 23 distinguishes the
+/// "new-style" bitfield encoding from the old per-board lookup table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PiRevision {
+    pub raw: u32,
+    pub model_name: String,
+    pub soc: String,
+    pub memory_mb: u64,
+}
+
+fn decode_new_style_revision(code: u32) -> PiRevision {
+    let model_type = (code >> 4) & 0xFF;
+    let processor = (code >> 12) & 0xF;
+    let memory_exp = (code >> 20) & 0x7;
+
+    let model_name = match model_type {
+        0x00 => "Raspberry Pi A",
+        0x01 => "Raspberry Pi B",
+        0x02 => "Raspberry Pi A+",
+        0x03 => "Raspberry Pi B+",
+        0x04 => "Raspberry Pi 2B",
+        0x08 => "Raspberry Pi 3B",
+        0x09 => "Raspberry Pi Zero",
+        0x0C => "Raspberry Pi Zero W",
+        0x0D => "Raspberry Pi 3B+",
+        0x0E => "Raspberry Pi 3A+",
+        0x11 => "Raspberry Pi 4B",
+        0x12 => "Raspberry Pi Zero 2 W",
+        0x13 => "Raspberry Pi 400",
+        0x14 => "Raspberry Pi CM4",
+        0x17 => "Raspberry Pi 5",
+        _ => "Raspberry Pi (unknown model)",
+    }
+    .to_string();
+
+    let soc = match processor {
+        0 => "BCM2835",
+        1 => "BCM2836",
+        2 => "BCM2837",
+        3 => "BCM2711",
+        4 => "BCM2712",
+        _ => "Unknown SoC",
+    }
+    .to_string();
+
+    let memory_mb = 256u64 << memory_exp;
+
+    PiRevision {
+        raw: code,
+        model_name,
+        soc,
+        memory_mb,
+    }
+}
+
+/// Old-style (pre bit-23) revision codes, keyed by the raw hex value as
+/// printed in `/proc/cpuinfo`. Not exhaustive, covers the common boards.
+fn decode_old_style_revision(code: u32) -> PiRevision {
+    let (model_name, soc, memory_mb) = match code {
+        0x0002 | 0x0003 => ("Raspberry Pi B (rev 1)", "BCM2835", 256),
+        0x0004 | 0x0005 | 0x0006 => ("Raspberry Pi B (rev 2)", "BCM2835", 256),
+        0x0007 | 0x0008 | 0x0009 => ("Raspberry Pi A", "BCM2835", 256),
+        0x000d | 0x000e | 0x000f => ("Raspberry Pi B (rev 2)", "BCM2835", 512),
+        0x0010 | 0x0013 => ("Raspberry Pi B+", "BCM2835", 512),
+        0x0012 | 0x0015 => ("Raspberry Pi A+", "BCM2835", 256),
+        _ => ("Raspberry Pi (unknown revision)", "BCM2835", 256),
+    };
+
+    PiRevision {
+        raw: code,
+        model_name: model_name.to_string(),
+        soc: soc.to_string(),
+        memory_mb,
+    }
+}
+
+/// Decode a `/proc/cpuinfo` `Revision:` hex string into a trustworthy
+/// model/SoC/memory triple instead of relying on the device-tree model
+/// string, which is only a human-readable label.
+pub fn decode_pi_revision(revision_hex: &str) -> Result<PiRevision> {
+    let code = u32::from_str_radix(revision_hex.trim(), 16)
+        .context("Failed to parse revision code as hex")?;
+
+    if code & 0x0080_0000 != 0 {
+        Ok(decode_new_style_revision(code))
+    } else {
+        Ok(decode_old_style_revision(code))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DisplayType {
     HDMI,
@@ -44,6 +142,15 @@ pub enum DisplayType {
     Unknown,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AnbernicAdcBucket {
+    min_mv: u32,
+    max_mv: u32,
+    model_name: &'static str,
+    screen_size_inches: f32,
+    native_resolution: (u32, u32),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GamingFeatures {
     pub has_dpad: bool,
@@ -55,11 +162,23 @@ pub struct GamingFeatures {
     pub native_resolution: Option<(u32, u32)>,
 }
 
-pub struct DeviceDetector;
+pub struct DeviceDetector {
+    config: Config,
+}
 
 impl DeviceDetector {
     pub fn new() -> Self {
-        DeviceDetector
+        DeviceDetector {
+            config: Config::default(),
+        }
+    }
+
+    /// Build a detector that consults `config.boards.extra_rules_path` when
+    /// walking the board registry, so custom/overridden SBCs are detected.
+    pub fn with_config(config: &Config) -> Self {
+        DeviceDetector {
+            config: config.clone(),
+        }
     }
 
     pub async fn detect_device(&self) -> Result<DeviceInfo> {
@@ -71,16 +190,27 @@ impl DeviceDetector {
         
         // Detect device type
         let device_type = self.detect_device_type().await?;
-        let model = self.get_device_model(&device_type)?;
-        
+        let pi_revision = self.get_pi_revision(&device_type);
+        let model = self.get_device_model(&device_type, pi_revision.as_ref()).await?;
+
         // Get CPU information
         let cpu_info = self.get_cpu_info()?;
-        
+
+        // Cross-check reported memory against the decoded revision code,
+        // which is more trustworthy than /proc/meminfo on a loaded system.
+        let memory_mb = pi_revision
+            .as_ref()
+            .map(|rev| rev.memory_mb)
+            .unwrap_or(memory_mb);
+
         // Detect hardware features
         let has_gpio = self.has_gpio_support(&device_type);
         let has_camera = self.detect_camera().await;
         let display_type = self.detect_display_type(&device_type).await;
-        let gaming_features = self.detect_gaming_features(&device_type).await;
+        let identified_controllers = hid_db::identify_connected_controllers();
+        let gaming_features = self
+            .detect_gaming_features(&device_type, &identified_controllers)
+            .await;
 
         let device_info = DeviceInfo {
             device_type,
@@ -92,6 +222,8 @@ impl DeviceDetector {
             has_camera,
             display_type,
             gaming_features,
+            pi_revision,
+            identified_controllers,
         };
 
         info!("Device detection completed: {}", device_info.model);
@@ -122,105 +254,53 @@ impl DeviceDetector {
         Ok(1024) // Default 1GB
     }
 
+    /// Walk the data-driven board registry (user overrides from
+    /// `config.boards.extra_rules_path`, then the built-in table) and
+    /// return the first matching entry, defaulting to `Generic`.
     async fn detect_device_type(&self) -> Result<DeviceType> {
-        // Check for Raspberry Pi
-        if self.is_raspberry_pi() {
-            return Ok(DeviceType::RaspberryPi);
-        }
-
-        // Check for Anbernic devices
-        if self.is_anbernic_device().await {
-            return Ok(DeviceType::Anbernic);
-        }
-
-        // Check for Orange Pi
-        if self.is_orange_pi() {
-            return Ok(DeviceType::OrangePi);
-        }
-
-        // Check for other ARM boards
-        if self.is_banana_pi() {
-            return Ok(DeviceType::BananaPi);
-        }
-
-        if self.is_rock_pi() {
-            return Ok(DeviceType::RockPi);
-        }
-
-        if self.is_odroid() {
-            return Ok(DeviceType::Odroid);
+        match board_registry::resolve(&self.config) {
+            Some(rule) => Ok(rule.device_type),
+            None => Ok(DeviceType::Generic),
         }
-
-        // Default to generic ARM device
-        Ok(DeviceType::Generic)
     }
 
-    fn is_raspberry_pi(&self) -> bool {
-        // Check device tree model
-        if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
-            return content.to_lowercase().contains("raspberry pi");
-        }
-
-        // Check cpuinfo
-        if let Ok(content) = fs::read_to_string("/proc/cpuinfo") {
-            return content.to_lowercase().contains("raspberry pi");
+    /// Parse the `Revision:` line of `/proc/cpuinfo` into a trustworthy
+    /// model/SoC/memory triple. Returns `None` on non-Pi hardware or if the
+    /// line is missing/malformed.
+    fn get_pi_revision(&self, device_type: &DeviceType) -> Option<PiRevision> {
+        if *device_type != DeviceType::RaspberryPi {
+            return None;
         }
 
-        false
-    }
-
-    async fn is_anbernic_device(&self) -> bool {
-        // Check for Anbernic-specific files or processes
-        if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
-            let model = content.to_lowercase();
-            return model.contains("rg351") || 
-                   model.contains("rg552") || 
-                   model.contains("rg35xx") ||
-                   model.contains("anbernic");
-        }
-
-        // Check for Anbernic-specific directories
-        std::path::Path::new("/opt/anbernic").exists() ||
-        std::path::Path::new("/boot/anbernic").exists()
-    }
-
-    fn is_orange_pi(&self) -> bool {
-        if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
-            return content.to_lowercase().contains("orange pi");
-        }
-        false
-    }
-
-    fn is_banana_pi(&self) -> bool {
-        if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
-            return content.to_lowercase().contains("banana pi");
-        }
-        false
-    }
-
-    fn is_rock_pi(&self) -> bool {
-        if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
-            return content.to_lowercase().contains("rock pi");
+        let content = fs::read_to_string("/proc/cpuinfo").ok()?;
+        for line in content.lines() {
+            if line.starts_with("Revision") {
+                let hex = line.split(':').nth(1)?.trim();
+                return decode_pi_revision(hex).ok();
+            }
         }
-        false
-    }
 
-    fn is_odroid(&self) -> bool {
-        if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
-            return content.to_lowercase().contains("odroid");
-        }
-        false
+        None
     }
 
-    fn get_device_model(&self, device_type: &DeviceType) -> Result<String> {
+    async fn get_device_model(&self, device_type: &DeviceType, pi_revision: Option<&PiRevision>) -> Result<String> {
         match device_type {
             DeviceType::RaspberryPi => {
+                if let Some(revision) = pi_revision {
+                    return Ok(format!("{} ({})", revision.model_name, revision.soc));
+                }
                 if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
                     return Ok(content.trim_end_matches('\0').to_string());
                 }
                 Ok("Raspberry Pi (Unknown Model)".to_string())
             }
             DeviceType::Anbernic => {
+                // Shared-device-tree RGxx3-class boards need the ADC read
+                // to tell them apart before falling back to substring match.
+                if let Some(variant) = self.resolve_anbernic_adc_variant().await {
+                    return Ok(variant.model_name.to_string());
+                }
+
                 // Try to detect specific Anbernic model
                 if let Ok(content) = fs::read_to_string("/proc/device-tree/model") {
                     let model = content.to_lowercase();
@@ -327,9 +407,28 @@ impl DeviceDetector {
         }
     }
 
-    async fn detect_gaming_features(&self, device_type: &DeviceType) -> GamingFeatures {
+    async fn detect_gaming_features(
+        &self,
+        device_type: &DeviceType,
+        identified_controllers: &[IdentifiedController],
+    ) -> GamingFeatures {
         match device_type {
             DeviceType::Anbernic => {
+                // Shared-device-tree RGxx3-class boards (RG353P/V/M, RG503,
+                // RG353PS...) all report the same model string and can only
+                // be told apart by an analog voltage read off the SARADC.
+                if let Some(variant) = self.resolve_anbernic_adc_variant().await {
+                    return GamingFeatures {
+                        has_dpad: true,
+                        has_analog_sticks: true,
+                        has_shoulder_buttons: true,
+                        has_built_in_screen: true,
+                        has_battery: true,
+                        screen_size_inches: Some(variant.screen_size_inches),
+                        native_resolution: Some(variant.native_resolution),
+                    };
+                }
+
                 // Anbernic devices are gaming handhelds
                 GamingFeatures {
                     has_dpad: true,
@@ -342,23 +441,42 @@ impl DeviceDetector {
                 }
             }
             DeviceType::RaspberryPi => {
-                // Raspberry Pi can have gaming accessories
-                GamingFeatures {
-                    has_dpad: self.detect_gamepad_connected().await,
-                    has_analog_sticks: self.detect_analog_controller().await,
-                    has_shoulder_buttons: false,
-                    has_built_in_screen: false,
-                    has_battery: false,
-                    screen_size_inches: None,
-                    native_resolution: None,
+                // Raspberry Pi can have gaming accessories attached over
+                // USB/Bluetooth; identify them by VID/PID instead of just
+                // checking that *a* /dev/input node exists.
+                if identified_controllers.iter().any(|c| c.matched_name.is_some()) {
+                    let capabilities = hid_db::aggregate_capabilities(identified_controllers);
+                    GamingFeatures {
+                        has_dpad: capabilities.has_dpad,
+                        has_analog_sticks: capabilities.has_analog_sticks,
+                        has_shoulder_buttons: capabilities.has_shoulder_buttons,
+                        has_built_in_screen: false,
+                        has_battery: false,
+                        screen_size_inches: None,
+                        native_resolution: None,
+                    }
+                } else {
+                    // No hidraw nodes to identify (or none matched) - fall
+                    // back to the old js*/event* presence heuristic.
+                    GamingFeatures {
+                        has_dpad: self.detect_gamepad_connected().await,
+                        has_analog_sticks: self.detect_analog_controller().await,
+                        has_shoulder_buttons: false,
+                        has_built_in_screen: false,
+                        has_battery: false,
+                        screen_size_inches: None,
+                        native_resolution: None,
+                    }
                 }
             }
             _ => {
-                // Generic ARM device - minimal gaming features
+                // Generic ARM device - only report gaming features if a
+                // recognized controller is actually attached.
+                let capabilities = hid_db::aggregate_capabilities(identified_controllers);
                 GamingFeatures {
-                    has_dpad: false,
-                    has_analog_sticks: false,
-                    has_shoulder_buttons: false,
+                    has_dpad: capabilities.has_dpad,
+                    has_analog_sticks: capabilities.has_analog_sticks,
+                    has_shoulder_buttons: capabilities.has_shoulder_buttons,
                     has_built_in_screen: false,
                     has_battery: false,
                     screen_size_inches: None,
@@ -368,6 +486,111 @@ impl DeviceDetector {
         }
     }
 
+    /// A single resistor-divider voltage window mapping to one physical
+    /// RGxx3-class board. Data-driven so new variants can be added without
+    /// touching the resolution logic.
+    fn anbernic_adc_buckets() -> Vec<AnbernicAdcBucket> {
+        vec![
+            AnbernicAdcBucket {
+                min_mv: 0,
+                max_mv: 300,
+                model_name: "Anbernic RG353P",
+                screen_size_inches: 3.5,
+                native_resolution: (640, 480),
+            },
+            AnbernicAdcBucket {
+                min_mv: 301,
+                max_mv: 600,
+                model_name: "Anbernic RG353V",
+                screen_size_inches: 3.5,
+                native_resolution: (640, 480),
+            },
+            AnbernicAdcBucket {
+                min_mv: 601,
+                max_mv: 900,
+                model_name: "Anbernic RG353M",
+                screen_size_inches: 3.5,
+                native_resolution: (640, 480),
+            },
+            AnbernicAdcBucket {
+                min_mv: 901,
+                max_mv: 1200,
+                model_name: "Anbernic RG503",
+                screen_size_inches: 4.95,
+                native_resolution: (960, 544),
+            },
+            AnbernicAdcBucket {
+                min_mv: 1201,
+                max_mv: 1500,
+                model_name: "Anbernic RG353PS",
+                screen_size_inches: 3.5,
+                native_resolution: (640, 480),
+            },
+        ]
+    }
+
+    /// Read the SARADC raw value for a shared-device-tree Anbernic board and
+    /// resolve it to a specific model via the bucket table. Returns `None`
+    /// if there is no matching IIO device or no bucket contains the reading,
+    /// in which case callers should fall back to the generic Anbernic result.
+    async fn resolve_anbernic_adc_variant(&self) -> Option<AnbernicAdcBucket> {
+        let model = fs::read_to_string("/proc/device-tree/model")
+            .ok()?
+            .to_lowercase();
+
+        // Only the RGxx3 family shares a device-tree model string across
+        // hardware variants; other Anbernic boards (RG351, RG35XX) are
+        // told apart by their model strings in `get_device_model`.
+        if !model.contains("rk3566") && !model.contains("rgxx3") {
+            return None;
+        }
+
+        let raw = self.read_saradc_raw_mv().await?;
+
+        Self::anbernic_adc_buckets()
+            .into_iter()
+            .find(|bucket| raw >= bucket.min_mv && raw <= bucket.max_mv)
+    }
+
+    /// Scan `/sys/bus/iio/devices/iio:deviceN/in_voltageX_raw` for the first
+    /// readable channel and scale it to millivolts using the sibling `scale`
+    /// attribute (falls back to a 1:1 scale if absent).
+    async fn read_saradc_raw_mv(&self) -> Option<u32> {
+        let iio_root = std::path::Path::new("/sys/bus/iio/devices");
+        let entries = fs::read_dir(iio_root).ok()?;
+
+        for entry in entries.flatten() {
+            let device_dir = entry.path();
+            let Ok(channel_entries) = fs::read_dir(&device_dir) else {
+                continue;
+            };
+
+            for channel_entry in channel_entries.flatten() {
+                let file_name = channel_entry.file_name().to_string_lossy().to_string();
+                if !file_name.starts_with("in_voltage") || !file_name.ends_with("_raw") {
+                    continue;
+                }
+
+                let Ok(raw_str) = fs::read_to_string(channel_entry.path()) else {
+                    continue;
+                };
+                let Ok(raw) = raw_str.trim().parse::<f32>() else {
+                    continue;
+                };
+
+                let scale_name = file_name.replace("_raw", "_scale");
+                let scale: f32 = fs::read_to_string(device_dir.join(scale_name))
+                    .ok()
+                    .and_then(|s| s.trim().parse().ok())
+                    .unwrap_or(1.0);
+
+                return Some((raw * scale) as u32);
+            }
+        }
+
+        None
+    }
+
     async fn detect_gamepad_connected(&self) -> bool {
         // Check for input devices
         if let Ok(entries) = fs::read_dir("/dev/input") {
@@ -387,4 +610,61 @@ impl DeviceDetector {
         // For now, assume analog sticks are present if any controller is detected
         self.detect_gamepad_connected().await
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A synthetic new-style code (bit 23 set) with model type 0x11 (4B),
+    /// processor 3 (BCM2711), memory_exp 2 (256MB << 2 = 1024MB).
+    #[test]
+    fn decodes_new_style_revision_bitfields() {
+        // model_type=0x11 (4B), processor=3 (BCM2711), memory_exp=2 (256<<2=1024MB)
+        let code = 0x0080_0000 | (2 << 20) | (3 << 12) | (0x11 << 4);
+        let revision = decode_pi_revision(&format!("{:x}", code)).unwrap();
+        assert_eq!(revision.model_name, "Raspberry Pi 4B");
+        assert_eq!(revision.soc, "BCM2711");
+        assert_eq!(revision.memory_mb, 1024);
+        assert_eq!(revision.raw, code);
+    }
+
+    #[test]
+    fn decodes_old_style_revision_from_lookup_table() {
+        let revision = decode_pi_revision("0010").unwrap();
+        assert_eq!(revision.model_name, "Raspberry Pi B+");
+        assert_eq!(revision.soc, "BCM2835");
+        assert_eq!(revision.memory_mb, 512);
+    }
+
+    #[test]
+    fn unknown_new_style_model_and_soc_fall_back_to_placeholders() {
+        let code = 0x0080_0000 | (9 << 12) | (0xFF << 4);
+        let revision = decode_pi_revision(&format!("{:x}", code)).unwrap();
+        assert_eq!(revision.model_name, "Raspberry Pi (unknown model)");
+        assert_eq!(revision.soc, "Unknown SoC");
+    }
+
+    #[test]
+    fn rejects_non_hex_revision_string() {
+        assert!(decode_pi_revision("not-hex").is_err());
+    }
+
+    #[test]
+    fn finds_matching_anbernic_adc_bucket() {
+        let buckets = DeviceDetector::anbernic_adc_buckets();
+        let bucket = buckets
+            .into_iter()
+            .find(|bucket| 650 >= bucket.min_mv && 650 <= bucket.max_mv)
+            .expect("650mV should fall in the RG353M bucket");
+        assert_eq!(bucket.model_name, "Anbernic RG353M");
+    }
+
+    #[test]
+    fn no_anbernic_adc_bucket_matches_out_of_range_reading() {
+        let buckets = DeviceDetector::anbernic_adc_buckets();
+        assert!(!buckets
+            .into_iter()
+            .any(|bucket| 5000 >= bucket.min_mv && 5000 <= bucket.max_mv));
+    }
 }
\ No newline at end of file
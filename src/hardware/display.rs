@@ -1,345 +1,832 @@
-// Display testing module 
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::process::Command;
-use tracing::{debug, info, warn};
-use crate::hardware::device_detect::{DeviceInfo, DeviceType};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DisplayConfig {
-    pub width: u32,
-    pub height: u32,
-    pub refresh_rate: u32,
-    pub color_depth: u32,
-    pub interface: String,
-    pub is_working: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DisplayTestResult {
-    pub config: DisplayConfig,
-    pub test_passed: bool,
-    pub error_message: Option<String>,
-}
-
-pub struct DisplayTester {
-    device_info: DeviceInfo,
-}
-
-impl DisplayTester {
-    pub fn new(device_info: &DeviceInfo) -> Self {
-        DisplayTester {
-            device_info: device_info.clone(),
-        }
-    }
-
-    pub async fn test_display(&self) -> Result<DisplayConfig> {
-        info!("Testing display configuration for {}", self.device_info.model);
-
-        // Get current display configuration
-        let config = self.detect_current_display_config().await?;
-        
-        // Test display functionality
-        let test_result = self.run_display_test(&config).await?;
-        
-        if test_result.test_passed {
-            info!("Display test passed: {}x{} @ {}Hz", 
-                  config.width, config.height, config.refresh_rate);
-            
-            // Save working configuration
-            self.save_working_config(&config).await?;
-        } else {
-            warn!("Display test failed: {:?}", test_result.error_message);
-        }
-
-        Ok(config)
-    }
-
-    async fn detect_current_display_config(&self) -> Result<DisplayConfig> {
-        match self.device_info.device_type {
-            DeviceType::RaspberryPi => self.detect_raspberry_pi_display().await,
-            DeviceType::Anbernic => self.detect_anbernic_display().await,
-            _ => self.detect_generic_display().await,
-        }
-    }
-
-    async fn detect_raspberry_pi_display(&self) -> Result<DisplayConfig> {
-        // Try to get display info from various sources
-        
-        // Method 1: Check framebuffer
-        if let Ok(config) = self.get_framebuffer_config().await {
-            return Ok(config);
-        }
-
-        // Method 2: Check DRM/KMS
-        if let Ok(config) = self.get_drm_config().await {
-            return Ok(config);
-        }
-
-        // Method 3: Use vcgencmd (Raspberry Pi specific)
-        if let Ok(config) = self.get_vcgencmd_config().await {
-            return Ok(config);
-        }
-
-        // Fallback to default config
-        Ok(DisplayConfig {
-            width: 1920,
-            height: 1080,
-            refresh_rate: 60,
-            color_depth: 24,
-            interface: "HDMI".to_string(),
-            is_working: false,
-        })
-    }
-
-    async fn detect_anbernic_display(&self) -> Result<DisplayConfig> {
-        // Anbernic devices typically have fixed resolution displays
-        let (width, height) = match self.device_info.gaming_features.native_resolution {
-            Some((w, h)) => (w, h),
-            None => (480, 320), // Common Anbernic resolution
-        };
-
-        Ok(DisplayConfig {
-            width,
-            height,
-            refresh_rate: 60,
-            color_depth: 16, // Gaming handhelds often use 16-bit color
-            interface: "LCD".to_string(),
-            is_working: true, // Assume built-in display works
-        })
-    }
-
-    async fn detect_generic_display(&self) -> Result<DisplayConfig> {
-        // Try framebuffer first
-        if let Ok(config) = self.get_framebuffer_config().await {
-            return Ok(config);
-        }
-
-        // Fallback to safe defaults
-        Ok(DisplayConfig {
-            width: 1024,
-            height: 768,
-            refresh_rate: 60,
-            color_depth: 24,
-            interface: "Unknown".to_string(),
-            is_working: false,
-        })
-    }
-
-    async fn get_framebuffer_config(&self) -> Result<DisplayConfig> {
-        // Check /sys/class/graphics/fb0/ for framebuffer info
-        let fb_path = "/sys/class/graphics/fb0";
-        
-        if !std::path::Path::new(fb_path).exists() {
-            return Err(anyhow::anyhow!("Framebuffer not found"));
-        }
-
-        // Read virtual resolution
-        let virtual_size = fs::read_to_string(format!("{}/virtual_size", fb_path))
-            .context("Failed to read virtual_size")?;
-        
-        let (width, height) = parse_resolution(&virtual_size)?;
-
-        // Read bits per pixel
-        let bits_per_pixel = fs::read_to_string(format!("{}/bits_per_pixel", fb_path))
-            .unwrap_or_else(|_| "24".to_string());
-        
-        let color_depth = bits_per_pixel.trim().parse::<u32>().unwrap_or(24);
-
-        Ok(DisplayConfig {
-            width,
-            height,
-            refresh_rate: 60, // Default refresh rate
-            color_depth,
-            interface: "Framebuffer".to_string(),
-            is_working: true,
-        })
-    }
-
-    async fn get_drm_config(&self) -> Result<DisplayConfig> {
-        // Try to get info from DRM subsystem
-        let drm_path = "/sys/class/drm";
-        
-        if !std::path::Path::new(drm_path).exists() {
-            return Err(anyhow::anyhow!("DRM not available"));
-        }
-
-        // Look for connected displays
-        if let Ok(entries) = fs::read_dir(drm_path) {
-            for entry in entries.flatten() {
-                let name = entry.file_name();
-                let name_str = name.to_string_lossy();
-                
-                if name_str.contains("HDMI") || name_str.contains("DSI") {
-                    let status_path = entry.path().join("status");
-                    if let Ok(status) = fs::read_to_string(&status_path) {
-                        if status.trim() == "connected" {
-                            // Try to get mode information
-                            if let Ok(config) = self.parse_drm_mode(&entry.path()).await {
-                                return Ok(config);
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        Err(anyhow::anyhow!("No connected displays found via DRM"))
-    }
-
-    async fn get_vcgencmd_config(&self) -> Result<DisplayConfig> {
-        // Raspberry Pi specific: use vcgencmd to get display info
-        let output = Command::new("vcgencmd")
-            .arg("get_config")
-            .arg("hdmi_mode")
-            .output();
-
-        if let Ok(output) = output {
-            if output.status.success() {
-                let mode_str = String::from_utf8_lossy(&output.stdout);
-                debug!("vcgencmd hdmi_mode: {}", mode_str);
-                
-                // Parse HDMI mode and return appropriate config
-                // This is a simplified implementation
-                return Ok(DisplayConfig {
-                    width: 1920,
-                    height: 1080,
-                    refresh_rate: 60,
-                    color_depth: 24,
-                    interface: "HDMI".to_string(),
-                    is_working: true,
-                });
-            }
-        }
-
-        Err(anyhow::anyhow!("vcgencmd not available or failed"))
-    }
-
-    async fn parse_drm_mode(&self, drm_path: &std::path::Path) -> Result<DisplayConfig> {
-        let modes_path = drm_path.join("modes");
-        
-        if let Ok(modes_content) = fs::read_to_string(&modes_path) {
-            // Parse the first mode (usually the preferred one)
-            if let Some(first_line) = modes_content.lines().next() {
-                if let Ok((width, height, refresh)) = parse_drm_mode_line(first_line) {
-                    return Ok(DisplayConfig {
-                        width,
-                        height,
-                        refresh_rate: refresh,
-                        color_depth: 24,
-                        interface: "DRM".to_string(),
-                        is_working: true,
-                    });
-                }
-            }
-        }
-
-        Err(anyhow::anyhow!("Failed to parse DRM modes"))
-    }
-
-    async fn run_display_test(&self, config: &DisplayConfig) -> Result<DisplayTestResult> {
-        info!("Running display test for {}x{}", config.width, config.height);
-
-        // For gaming handhelds with built-in screens, assume test passes
-        if self.device_info.gaming_features.has_built_in_screen {
-            return Ok(DisplayTestResult {
-                config: config.clone(),
-                test_passed: true,
-                error_message: None,
-            });
-        }
-
-        // Test 1: Try to write to framebuffer
-        let fb_test = self.test_framebuffer_write().await;
-        
-        // Test 2: Check if display is responsive
-        let responsive_test = self.test_display_responsive().await;
-
-        let test_passed = fb_test && responsive_test;
-        let error_message = if !test_passed {
-            Some("Display test failed: framebuffer or responsiveness issue".to_string())
-        } else {
-            None
-        };
-
-        Ok(DisplayTestResult {
-            config: config.clone(),
-            test_passed,
-            error_message,
-        })
-    }
-
-    async fn test_framebuffer_write(&self) -> bool {
-        // Try to write a simple pattern to framebuffer
-        match fs::OpenOptions::new().write(true).open("/dev/fb0") {
-            Ok(_) => {
-                debug!("Framebuffer write test passed");
-                true
-            }
-            Err(e) => {
-                debug!("Framebuffer write test failed: {}", e);
-                false
-            }
-        }
-    }
-
-    async fn test_display_responsive(&self) -> bool {
-        // For now, just check if display files are accessible
-        std::path::Path::new("/sys/class/graphics/fb0").exists() ||
-        std::path::Path::new("/dev/fb0").exists()
-    }
-
-    async fn save_working_config(&self, config: &DisplayConfig) -> Result<()> {
-        let config_dir = "/boot/dos_safar/display";
-        std::fs::create_dir_all(config_dir)
-            .context("Failed to create display config directory")?;
-
-        let config_file = format!("{}/working_config.toml", config_dir);
-        let config_content = toml::to_string_pretty(config)
-            .context("Failed to serialize display config")?;
-
-        fs::write(&config_file, config_content)
-            .context("Failed to save display config")?;
-
-        info!("Saved working display configuration to {}", config_file);
-        Ok(())
-    }
-}
-
-fn parse_resolution(resolution_str: &str) -> Result<(u32, u32)> {
-    let parts: Vec<&str> = resolution_str.trim().split(',').collect();
-    if parts.len() != 2 {
-        return Err(anyhow::anyhow!("Invalid resolution format"));
-    }
-
-    let width = parts[0].parse::<u32>()
-        .context("Failed to parse width")?;
-    let height = parts[1].parse::<u32>()
-        .context("Failed to parse height")?;
-
-    Ok((width, height))
-}
-
-fn parse_drm_mode_line(mode_line: &str) -> Result<(u32, u32, u32)> {
-    // Parse DRM mode line format: "1920x1080@60"
-    let mode_line = mode_line.trim();
-    
-    // Split by '@' to separate resolution and refresh rate
-    let parts: Vec<&str> = mode_line.split('@').collect();
-    if parts.len() != 2 {
-        return Err(anyhow::anyhow!("Invalid DRM mode format"));
-    }
-
-    // Parse resolution
-    let resolution_parts: Vec<&str> = parts[0].split('x').collect();
-    if resolution_parts.len() != 2 {
-        return Err(anyhow::anyhow!("Invalid resolution format in DRM mode"));
-    }
-
-    let width = resolution_parts[0].parse::<u32>()?;
-    let height = resolution_parts[1].parse::<u32>()?;
-    let refresh = parts[1].parse::<u32>()?;
-
-    Ok((width, height, refresh))
+// Display testing module
+use anyhow::{Context, Result};
+use memmap2::MmapMut;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::fs::OpenOptions;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+use crate::hardware::device_detect::{DeviceInfo, DeviceType};
+use crate::hardware::drm::{self, ConnectorInfo, DrmModeInfo};
+use crate::hardware::edid;
+use crate::hardware::lcd_display::LcdDisplayDetector;
+use crate::utils::config::{HardwareConfig, LcdConfig};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayConfig {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+    pub color_depth: u32,
+    /// Bytes per scanline. Usually `width * ceil(color_depth / 8)`, but a
+    /// real `/dev/fb0` often pads this (the framebuffer's `line_length`,
+    /// read via `FBIOGET_FSCREENINFO`) for alignment, so callers writing
+    /// raw framebuffer bytes must use this instead of recomputing it.
+    pub bytes_per_line: u32,
+    pub interface: String,
+    pub is_working: bool,
+}
+
+/// `width * ceil(color_depth / 8)` - the packed stride assumed wherever a
+/// real stride hasn't been read back from the hardware (e.g. `/dev/fb0`'s
+/// `line_length`).
+fn packed_bytes_per_line(width: u32, color_depth: u32) -> u32 {
+    width * color_depth.div_ceil(8)
+}
+
+/// One supported video mode. Refresh is stored in millihertz (like winit's
+/// `VideoMode`) so non-integer rates such as 59.94Hz round-trip exactly
+/// instead of being truncated to 60.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VideoMode {
+    pub size: (u32, u32),
+    pub bit_depth: u16,
+    pub refresh_rate_millihertz: u32,
+}
+
+impl VideoMode {
+    fn area(&self) -> u64 {
+        self.size.0 as u64 * self.size.1 as u64
+    }
+}
+
+/// Every mode a connector reports, plus the current and policy-selected
+/// preferred mode, for a real mode picker in the web UI instead of the
+/// single first-line guess `DisplayConfig` used to carry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisplayInfo {
+    pub modes: Vec<VideoMode>,
+    pub current: Option<VideoMode>,
+    pub preferred: Option<VideoMode>,
+}
+
+/// Mode-selection policy, applied whenever the source (DRM/EDID) doesn't
+/// mark one mode as preferred: prefer `drm_preferred` if the caller found
+/// one; otherwise the highest resolution with refresh >= 50Hz; otherwise
+/// just the largest area available.
+fn select_preferred_mode(modes: &[VideoMode], drm_preferred: Option<VideoMode>) -> Option<VideoMode> {
+    if drm_preferred.is_some() {
+        return drm_preferred;
+    }
+
+    const MIN_ACCEPTABLE_REFRESH_MILLIHERTZ: u32 = 50_000;
+
+    modes
+        .iter()
+        .filter(|m| m.refresh_rate_millihertz >= MIN_ACCEPTABLE_REFRESH_MILLIHERTZ)
+        .max_by_key(|m| m.area())
+        .or_else(|| modes.iter().max_by_key(|m| m.area()))
+        .copied()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisplayTestResult {
+    pub config: DisplayConfig,
+    pub test_passed: bool,
+    pub error_message: Option<String>,
+}
+
+pub struct DisplayTester {
+    device_info: DeviceInfo,
+    lcd_config: LcdConfig,
+    hardware_config: HardwareConfig,
+}
+
+impl DisplayTester {
+    pub fn new(device_info: &DeviceInfo, lcd_config: &LcdConfig, hardware_config: &HardwareConfig) -> Self {
+        DisplayTester {
+            device_info: device_info.clone(),
+            lcd_config: lcd_config.clone(),
+            hardware_config: hardware_config.clone(),
+        }
+    }
+
+    pub async fn test_display(&self) -> Result<DisplayConfig> {
+        info!("Testing display configuration for {}", self.device_info.model);
+
+        // Get current display configuration
+        let config = self.detect_current_display_config().await?;
+        
+        // Test display functionality
+        let test_result = self.run_display_test(&config).await?;
+        
+        if test_result.test_passed {
+            info!("Display test passed: {}x{} @ {}Hz", 
+                  config.width, config.height, config.refresh_rate);
+            
+            // Save working configuration
+            self.save_working_config(&config).await?;
+        } else {
+            warn!("Display test failed: {:?}", test_result.error_message);
+        }
+
+        Ok(config)
+    }
+
+    async fn detect_current_display_config(&self) -> Result<DisplayConfig> {
+        match self.device_info.device_type {
+            DeviceType::RaspberryPi => self.detect_raspberry_pi_display().await,
+            DeviceType::Anbernic => self.detect_anbernic_display().await,
+            _ => self.detect_generic_display().await,
+        }
+    }
+
+    async fn detect_raspberry_pi_display(&self) -> Result<DisplayConfig> {
+        // Try to get display info from various sources
+        
+        // Method 1: Check framebuffer
+        if let Ok(config) = self.get_framebuffer_config().await {
+            return Ok(config);
+        }
+
+        // Method 2: Check DRM/KMS
+        if let Ok(config) = self.get_drm_config().await {
+            return Ok(config);
+        }
+
+        // Method 3: Use vcgencmd (Raspberry Pi specific)
+        if let Ok(config) = self.get_vcgencmd_config().await {
+            return Ok(config);
+        }
+
+        // Fallback to default config
+        Ok(DisplayConfig {
+            width: 1920,
+            height: 1080,
+            refresh_rate: 60,
+            color_depth: 24,
+            bytes_per_line: packed_bytes_per_line(1920, 24),
+            interface: "HDMI".to_string(),
+            is_working: false,
+        })
+    }
+
+    async fn detect_anbernic_display(&self) -> Result<DisplayConfig> {
+        // Anbernic devices typically have fixed resolution displays
+        let (width, height) = match self.device_info.gaming_features.native_resolution {
+            Some((w, h)) => (w, h),
+            None => (480, 320), // Common Anbernic resolution
+        };
+
+        Ok(DisplayConfig {
+            width,
+            height,
+            refresh_rate: 60,
+            color_depth: 16, // Gaming handhelds often use 16-bit color
+            bytes_per_line: packed_bytes_per_line(width, 16),
+            interface: "LCD".to_string(),
+            is_working: true, // Assume built-in display works
+        })
+    }
+
+    async fn detect_generic_display(&self) -> Result<DisplayConfig> {
+        // Try framebuffer first
+        if let Ok(config) = self.get_framebuffer_config().await {
+            return Ok(config);
+        }
+
+        // Fallback to safe defaults
+        Ok(DisplayConfig {
+            width: 1024,
+            height: 768,
+            refresh_rate: 60,
+            color_depth: 24,
+            bytes_per_line: packed_bytes_per_line(1024, 24),
+            interface: "Unknown".to_string(),
+            is_working: false,
+        })
+    }
+
+    async fn get_framebuffer_config(&self) -> Result<DisplayConfig> {
+        // Check /sys/class/graphics/fb0/ for framebuffer info
+        let fb_path = "/sys/class/graphics/fb0";
+        
+        if !std::path::Path::new(fb_path).exists() {
+            return Err(anyhow::anyhow!("Framebuffer not found"));
+        }
+
+        // Read virtual resolution
+        let virtual_size = fs::read_to_string(format!("{}/virtual_size", fb_path))
+            .context("Failed to read virtual_size")?;
+        
+        let (width, height) = parse_resolution(&virtual_size)?;
+
+        // Read bits per pixel
+        let bits_per_pixel = fs::read_to_string(format!("{}/bits_per_pixel", fb_path))
+            .unwrap_or_else(|_| "24".to_string());
+        
+        let color_depth = bits_per_pixel.trim().parse::<u32>().unwrap_or(24);
+
+        let bytes_per_line = fs::File::open("/dev/fb0")
+            .ok()
+            .and_then(|file| read_fb_fix_screeninfo(&file).ok())
+            .map(|fix_info| fix_info.line_length)
+            .filter(|&line_length| line_length > 0)
+            .unwrap_or_else(|| packed_bytes_per_line(width, color_depth));
+
+        Ok(DisplayConfig {
+            width,
+            height,
+            refresh_rate: 60, // Default refresh rate
+            color_depth,
+            bytes_per_line,
+            interface: "Framebuffer".to_string(),
+            is_working: true,
+        })
+    }
+
+    async fn get_drm_config(&self) -> Result<DisplayConfig> {
+        // Primary path: the real DRM/KMS backend (enumerate_connectors),
+        // which reads connectors and modes via actual ioctls instead of a
+        // single line of sysfs.
+        match self.get_drm_config_via_kms().await {
+            Ok(config) => return Ok(config),
+            Err(e) => {
+                debug!("Couldn't use the atomic DRM/KMS backend: {} - falling back to sysfs", e);
+            }
+        }
+
+        // Fallback: text scan of /sys/class/drm for devices that lack
+        // permission to open the device node (no DRM master) or don't
+        // support the resources/properties interface.
+        let drm_path = "/sys/class/drm";
+        if !std::path::Path::new(drm_path).exists() {
+            return Err(anyhow::anyhow!("DRM not available"));
+        }
+
+        if let Ok(entries) = fs::read_dir(drm_path) {
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let name_str = name.to_string_lossy();
+
+                if name_str.contains("HDMI") || name_str.contains("DSI") {
+                    let status_path = entry.path().join("status");
+                    if let Ok(status) = fs::read_to_string(&status_path) {
+                        if status.trim() == "connected" {
+                            // Try to get mode information
+                            if let Ok(config) = self.parse_drm_mode(&entry.path()).await {
+                                return Ok(config);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("No connected displays found via DRM"))
+    }
+
+    /// Opens the first available DRM device node, enumerates its
+    /// connectors, and builds a `DisplayConfig` from the first connected
+    /// connector that has a preferred mode (or its first mode, if none is
+    /// marked preferred).
+    async fn get_drm_config_via_kms(&self) -> Result<DisplayConfig> {
+        let devices = self.list_drm_devices().await?;
+        let device_path = devices
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No DRM device nodes found under /dev/dri"))?;
+
+        let connectors = self.enumerate_connectors(device_path).await?;
+        let connector = connectors
+            .iter()
+            .find(|c| c.connected && !c.modes.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("No connected DRM connector with any modes"))?;
+
+        let mode = connector
+            .modes
+            .iter()
+            .find(|m| m.preferred)
+            .or_else(|| connector.modes.first())
+            .ok_or_else(|| anyhow::anyhow!("Connector {} has no modes", connector.name))?;
+
+        Ok(DisplayConfig {
+            width: mode.width,
+            height: mode.height,
+            refresh_rate: mode.refresh_hz,
+            color_depth: 24,
+            bytes_per_line: packed_bytes_per_line(mode.width, 24),
+            interface: format!("DRM ({})", connector.name),
+            is_working: true,
+        })
+    }
+
+    /// Enumerates the DRM device nodes (`/dev/dri/cardN`) available on this
+    /// device.
+    pub async fn list_drm_devices(&self) -> Result<Vec<PathBuf>> {
+        drm::list_drm_devices().await
+    }
+
+    /// Enumerates DRM connectors (with their status and full mode list) on
+    /// the given device node.
+    pub async fn enumerate_connectors(&self, device_path: &Path) -> Result<Vec<ConnectorInfo>> {
+        drm::enumerate_connectors(device_path).await
+    }
+
+    /// Forces a specific mode onto a connector via an atomic modeset,
+    /// useful for forcing a known-working mode on a Pi when the
+    /// auto-detected mode shows nothing. Returns a structured error if the
+    /// operation lacks DRM master or the kernel rejects the commit.
+    pub async fn set_mode(&self, device_path: &Path, connector_name: &str, mode: &DrmModeInfo) -> Result<()> {
+        drm::set_mode(device_path, connector_name, mode).await
+    }
+
+    async fn get_vcgencmd_config(&self) -> Result<DisplayConfig> {
+        // Raspberry Pi specific: use vcgencmd to get display info
+        let output = Command::new("vcgencmd")
+            .arg("get_config")
+            .arg("hdmi_mode")
+            .output();
+
+        if let Ok(output) = output {
+            if output.status.success() {
+                let mode_str = String::from_utf8_lossy(&output.stdout);
+                debug!("vcgencmd hdmi_mode: {}", mode_str);
+                
+                // Parse HDMI mode and return appropriate config
+                // This is a simplified implementation
+                return Ok(DisplayConfig {
+                    width: 1920,
+                    height: 1080,
+                    refresh_rate: 60,
+                    color_depth: 24,
+                    bytes_per_line: packed_bytes_per_line(1920, 24),
+                    interface: "HDMI".to_string(),
+                    is_working: true,
+                });
+            }
+        }
+
+        Err(anyhow::anyhow!("vcgencmd not available or failed"))
+    }
+
+    async fn parse_drm_mode(&self, drm_path: &std::path::Path) -> Result<DisplayConfig> {
+        let info = self.parse_drm_connector_info(drm_path)?;
+        let mode = info
+            .preferred
+            .ok_or_else(|| anyhow::anyhow!("Failed to parse DRM modes"))?;
+
+        Ok(DisplayConfig {
+            width: mode.size.0,
+            height: mode.size.1,
+            refresh_rate: mode.refresh_rate_millihertz / 1000,
+            color_depth: mode.bit_depth as u32,
+            bytes_per_line: packed_bytes_per_line(mode.size.0, mode.bit_depth as u32),
+            interface: "DRM".to_string(),
+            is_working: true,
+        })
+    }
+
+    /// Builds a full `DisplayInfo` for one sysfs connector: parses every
+    /// line in its `modes` file (not just the first), and tries reading
+    /// EDID to determine the original preferred mode before falling back
+    /// to the selection policy.
+    fn parse_drm_connector_info(&self, drm_path: &std::path::Path) -> Result<DisplayInfo> {
+        let modes_content = fs::read_to_string(drm_path.join("modes"))
+            .context("Failed to read DRM modes file")?;
+
+        let modes: Vec<VideoMode> = modes_content
+            .lines()
+            .filter_map(|line| parse_drm_mode_line(line).ok())
+            .collect();
+
+        if modes.is_empty() {
+            return Err(anyhow::anyhow!("Failed to parse DRM modes"));
+        }
+
+        let edid_preferred = edid::read_preferred_mode(&drm_path.join("edid"));
+        let preferred = select_preferred_mode(&modes, edid_preferred);
+
+        Ok(DisplayInfo {
+            modes,
+            current: preferred,
+            preferred,
+        })
+    }
+
+    /// Gathers `DisplayInfo` from every available source (atomic DRM/KMS
+    /// first, then the sysfs fallback), for a real mode picker in the web
+    /// UI.
+    pub async fn get_display_info(&self) -> Result<DisplayInfo> {
+        if let Ok(devices) = self.list_drm_devices().await {
+            if let Some(device_path) = devices.first() {
+                if let Ok(connectors) = self.enumerate_connectors(device_path).await {
+                    if let Some(connector) = connectors.iter().find(|c| c.connected && !c.modes.is_empty()) {
+                        let modes: Vec<VideoMode> = connector
+                            .modes
+                            .iter()
+                            .map(|m| VideoMode {
+                                size: (m.width, m.height),
+                                bit_depth: 24,
+                                refresh_rate_millihertz: m.refresh_hz * 1000,
+                            })
+                            .collect();
+                        let drm_preferred = connector
+                            .modes
+                            .iter()
+                            .find(|m| m.preferred)
+                            .map(|m| VideoMode {
+                                size: (m.width, m.height),
+                                bit_depth: 24,
+                                refresh_rate_millihertz: m.refresh_hz * 1000,
+                            });
+                        let preferred = select_preferred_mode(&modes, drm_preferred);
+                        return Ok(DisplayInfo {
+                            modes,
+                            current: preferred,
+                            preferred,
+                        });
+                    }
+                }
+            }
+        }
+
+        // Fallback: the same sysfs scan get_drm_config uses.
+        let drm_path = "/sys/class/drm";
+        if let Ok(entries) = fs::read_dir(drm_path) {
+            for entry in entries.flatten() {
+                let name_str = entry.file_name().to_string_lossy().to_string();
+                if !name_str.contains("HDMI") && !name_str.contains("DSI") {
+                    continue;
+                }
+                if let Ok(status) = fs::read_to_string(entry.path().join("status")) {
+                    if status.trim() == "connected" {
+                        if let Ok(info) = self.parse_drm_connector_info(&entry.path()) {
+                            return Ok(info);
+                        }
+                    }
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("No connected displays found to build DisplayInfo"))
+    }
+
+    async fn run_display_test(&self, config: &DisplayConfig) -> Result<DisplayTestResult> {
+        info!("Running display test for {}x{}", config.width, config.height);
+
+        // An SPI panel explicitly defined and enabled in the config is
+        // tested by drawing a real test pattern on it instead of the
+        // generic framebuffer checks below.
+        if self.lcd_config.enabled {
+            return self.run_lcd_test().await;
+        }
+
+        // For gaming handhelds with built-in screens, assume test passes
+        if self.device_info.gaming_features.has_built_in_screen {
+            return Ok(DisplayTestResult {
+                config: config.clone(),
+                test_passed: true,
+                error_message: None,
+            });
+        }
+
+        // Test 1: Draw the color-bars/sweep diagnostic to the framebuffer
+        let fb_test = self.test_framebuffer_write(config).await;
+
+        // Test 2: Check if display is responsive
+        let responsive_test = self.test_display_responsive().await;
+
+        // Only ask for visual confirmation if the diagnostic actually drew
+        // something - no point prompting over a framebuffer we couldn't
+        // even open.
+        let test_passed = fb_test && responsive_test && self.confirm_pattern_visible().await;
+        let error_message = if !test_passed {
+            Some("Display test failed: framebuffer write, responsiveness, or visual confirmation failed".to_string())
+        } else {
+            None
+        };
+
+        Ok(DisplayTestResult {
+            config: config.clone(),
+            test_passed,
+            error_message,
+        })
+    }
+
+    /// Initializes the LCD panel via `lcd_display::LcdDisplayDetector` and
+    /// draws a full test pattern (alternating primary colors) on it; any
+    /// init or blit failure is recorded as a test failure instead of
+    /// panicking.
+    async fn run_lcd_test(&self) -> Result<DisplayTestResult> {
+        let mut detector = LcdDisplayDetector::new(&self.lcd_config);
+
+        let resolved = match detector.init().await {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                return Ok(DisplayTestResult {
+                    config: DisplayConfig {
+                        width: 0,
+                        height: 0,
+                        refresh_rate: 0,
+                        color_depth: 16,
+                        bytes_per_line: 0,
+                        interface: "LCD-SPI".to_string(),
+                        is_working: false,
+                    },
+                    test_passed: false,
+                    error_message: Some(format!("Failed to initialize LCD display: {}", e)),
+                });
+            }
+        };
+
+        let test_pattern = build_test_pattern(resolved.width, resolved.height);
+        let blit_result = detector
+            .blit(&test_pattern, 0, 0, resolved.width, resolved.height)
+            .await;
+
+        let config = DisplayConfig {
+            width: resolved.width,
+            height: resolved.height,
+            refresh_rate: 60,
+            color_depth: 16, // RGB565
+            bytes_per_line: packed_bytes_per_line(resolved.width, 16),
+            interface: format!("LCD-SPI ({:?})", resolved.driver),
+            is_working: blit_result.is_ok(),
+        };
+
+        Ok(DisplayTestResult {
+            config,
+            test_passed: blit_result.is_ok(),
+            error_message: blit_result.err().map(|e| format!("Failed to draw test pattern: {}", e)),
+        })
+    }
+
+    /// Mmaps `/dev/fb0` and actually draws the diagnostic pattern (color
+    /// bars plus a moving sweep, see `draw_framebuffer_diagnostic`),
+    /// restoring the original contents afterward either way, instead of
+    /// merely opening the device and declaring victory.
+    async fn test_framebuffer_write(&self, config: &DisplayConfig) -> bool {
+        match draw_framebuffer_diagnostic(config).await {
+            Ok(()) => {
+                debug!("Framebuffer write test passed");
+                true
+            }
+            Err(e) => {
+                debug!("Framebuffer write test failed: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn test_display_responsive(&self) -> bool {
+        // For now, just check if display files are accessible
+        std::path::Path::new("/sys/class/graphics/fb0").exists() ||
+        std::path::Path::new("/dev/fb0").exists()
+    }
+
+    /// Asks the operator whether the color-bars/sweep pattern drawn by
+    /// `test_framebuffer_write` actually showed up correctly, auto-passing
+    /// after `hardware.test_timeout_seconds` if nobody answers (e.g.
+    /// running headless with no one watching the panel).
+    async fn confirm_pattern_visible(&self) -> bool {
+        use std::io::{self, BufRead};
+        use std::sync::mpsc;
+        use std::thread;
+
+        println!(
+            "📺 Did the color bars and sweep line appear correctly on the screen? [Y/n] (auto-pass in {}s)",
+            self.hardware_config.test_timeout_seconds
+        );
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut line = String::new();
+            if io::stdin().lock().read_line(&mut line).is_ok() {
+                let _ = tx.send(line);
+            }
+        });
+
+        match tokio::time::timeout(
+            Duration::from_secs(self.hardware_config.test_timeout_seconds),
+            tokio::task::spawn_blocking(move || rx.recv()),
+        )
+        .await
+        {
+            Ok(Ok(Ok(line))) => !line.trim().eq_ignore_ascii_case("n"),
+            _ => {
+                debug!("No response from the user within the timeout - auto-passing the display test");
+                true
+            }
+        }
+    }
+
+    async fn save_working_config(&self, config: &DisplayConfig) -> Result<()> {
+        let config_dir = "/boot/dos_safar/display";
+        std::fs::create_dir_all(config_dir)
+            .context("Failed to create display config directory")?;
+
+        let config_file = format!("{}/working_config.toml", config_dir);
+        let config_content = toml::to_string_pretty(config)
+            .context("Failed to serialize display config")?;
+
+        fs::write(&config_file, config_content)
+            .context("Failed to save display config")?;
+
+        info!("Saved working display configuration to {}", config_file);
+        Ok(())
+    }
+}
+
+/// A simple test pattern of vertical bars in the primary colors (red/
+/// green/blue/white) in RGB565, to visually catch any data-line or color
+/// fault.
+fn build_test_pattern(width: u32, height: u32) -> Vec<u16> {
+    const RED: u16 = 0xF800;
+    const GREEN: u16 = 0x07E0;
+    const BLUE: u16 = 0x001F;
+    const WHITE: u16 = 0xFFFF;
+    let bars = [RED, GREEN, BLUE, WHITE];
+
+    let mut pattern = Vec::with_capacity((width * height) as usize);
+    for _ in 0..height {
+        for x in 0..width {
+            let bar_index = (x * bars.len() as u32 / width.max(1)) as usize;
+            pattern.push(bars[bar_index.min(bars.len() - 1)]);
+        }
+    }
+    pattern
+}
+
+/// Colors used for the full-screen color-bars diagnostic, in RGB888.
+const COLOR_BARS: [(u8, u8, u8); 4] = [(255, 0, 0), (0, 255, 0), (0, 0, 255), (255, 255, 255)];
+/// Number of frames the moving sweep line advances across before
+/// restoring the original framebuffer contents.
+const SWEEP_FRAMES: u32 = 30;
+
+/// Packs one RGB888 color into `out` using the framebuffer's native pixel
+/// layout: 16-bit RGB565 for the gaming-handheld panels most devices this
+/// targets actually have, 24-bit packed RGB, or 32-bit BGRX (the common
+/// Linux fbdev default) for anything else.
+fn pack_pixel(r: u8, g: u8, b: u8, color_depth: u32, out: &mut Vec<u8>) {
+    match color_depth {
+        16 => {
+            let packed: u16 = ((r as u16 & 0xF8) << 8) | ((g as u16 & 0xFC) << 3) | (b as u16 >> 3);
+            out.extend_from_slice(&packed.to_le_bytes());
+        }
+        24 => out.extend_from_slice(&[b, g, r]),
+        _ => out.extend_from_slice(&[b, g, r, 0]),
+    }
+}
+
+/// Builds one scanline of the color-bars pattern in the framebuffer's
+/// native pixel format.
+fn build_color_bars_row(width: u32, color_depth: u32) -> Vec<u8> {
+    let mut row = Vec::with_capacity((width * (color_depth.div_ceil(8))) as usize);
+    for x in 0..width {
+        let bar_index = (x * COLOR_BARS.len() as u32 / width.max(1)) as usize;
+        let (r, g, b) = COLOR_BARS[bar_index.min(COLOR_BARS.len() - 1)];
+        pack_pixel(r, g, b, color_depth, &mut row);
+    }
+    row
+}
+
+/// The fields of the kernel's `struct fb_fix_screeninfo` (see
+/// `linux/fb.h`) this module actually needs: `line_length`, the real
+/// (possibly padded) stride `FBIOGET_FSCREENINFO` reports, which can
+/// differ from `width * bytes_per_pixel`.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct FbFixScreenInfo {
+    id: [u8; 16],
+    smem_start: usize,
+    smem_len: u32,
+    fb_type: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    pub(crate) line_length: u32,
+    mmio_start: usize,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+pub(crate) const FBIOGET_FSCREENINFO: libc::c_ulong = 0x4602;
+
+pub(crate) fn read_fb_fix_screeninfo(file: &fs::File) -> Result<FbFixScreenInfo> {
+    let mut info = FbFixScreenInfo::default();
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), FBIOGET_FSCREENINFO, &mut info as *mut _) };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error()).context("FBIOGET_FSCREENINFO call failed on /dev/fb0");
+    }
+    Ok(info)
+}
+
+/// Mmaps `/dev/fb0` and draws a real diagnostic - full-screen color bars
+/// plus a vertical sweep line advancing over `SWEEP_FRAMES` frames, so a
+/// human watching the panel can catch tearing or dead color channels -
+/// then restores the framebuffer's original contents whether the draw
+/// succeeded or not.
+async fn draw_framebuffer_diagnostic(config: &DisplayConfig) -> Result<()> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/fb0")
+        .context("Failed to open /dev/fb0")?;
+
+    let bytes_per_line = match read_fb_fix_screeninfo(&file) {
+        Ok(fix_info) if fix_info.line_length > 0 => fix_info.line_length,
+        _ => config.bytes_per_line,
+    };
+    let bytes_per_pixel = config.color_depth.div_ceil(8).max(1);
+    let frame_size = (bytes_per_line as u64) * (config.height as u64);
+
+    let mut mmap = unsafe { MmapMut::map_mut(&file).context("Failed to mmap /dev/fb0 framebuffer memory")? };
+    if frame_size as usize > mmap.len() {
+        return Err(anyhow::anyhow!(
+            "Computed frame size ({} bytes) is larger than the available /dev/fb0 memory ({} bytes)",
+            frame_size,
+            mmap.len()
+        ));
+    }
+
+    let region = &mut mmap[..frame_size as usize];
+    let original = region.to_vec();
+
+    let bars_row = build_color_bars_row(config.width, config.color_depth);
+    let mut sweep_pixel = Vec::with_capacity(bytes_per_pixel as usize);
+    pack_pixel(255, 255, 255, config.color_depth, &mut sweep_pixel);
+
+    let result = async {
+        for frame in 0..SWEEP_FRAMES {
+            let sweep_x = (frame * config.width / SWEEP_FRAMES) as usize;
+            let sweep_offset = sweep_x * bytes_per_pixel as usize;
+
+            for y in 0..config.height as usize {
+                let row_start = y * bytes_per_line as usize;
+                region[row_start..row_start + bars_row.len()].copy_from_slice(&bars_row);
+
+                let px_start = row_start + sweep_offset;
+                let px_end = px_start + sweep_pixel.len();
+                if px_end <= region.len() {
+                    region[px_start..px_end].copy_from_slice(&sweep_pixel);
+                }
+            }
+            sleep(Duration::from_millis(16)).await;
+        }
+        Ok::<(), anyhow::Error>(())
+    }
+    .await;
+
+    region.copy_from_slice(&original);
+    mmap.flush().context("Failed to sync framebuffer memory after restoring its original contents")?;
+
+    result
+}
+
+fn parse_resolution(resolution_str: &str) -> Result<(u32, u32)> {
+    let parts: Vec<&str> = resolution_str.trim().split(',').collect();
+    if parts.len() != 2 {
+        return Err(anyhow::anyhow!("Invalid resolution format"));
+    }
+
+    let width = parts[0].parse::<u32>()
+        .context("Failed to parse width")?;
+    let height = parts[1].parse::<u32>()
+        .context("Failed to parse height")?;
+
+    Ok((width, height))
+}
+
+/// Default refresh (60Hz, as millihertz) for mode lines that omit `@rate`
+/// entirely - some drivers list a bare `1920x1080` with no rate suffix.
+const DEFAULT_REFRESH_RATE_MILLIHERTZ: u32 = 60_000;
+
+/// Parses one line of `/sys/class/drm/*/modes`, e.g. `"1920x1080@60"`,
+/// `"1920x1080i@60"` (interlaced - the `i` is stripped, not rejected), or a
+/// bare `"1920x1080"` with no rate at all (defaults to 60Hz).
+fn parse_drm_mode_line(mode_line: &str) -> Result<VideoMode> {
+    let mode_line = mode_line.trim();
+
+    let (resolution_part, refresh_rate_millihertz) = match mode_line.split_once('@') {
+        Some((resolution, rate)) => {
+            let rate_hz = rate.trim().parse::<u32>()?;
+            (resolution, rate_hz * 1000)
+        }
+        None => (mode_line, DEFAULT_REFRESH_RATE_MILLIHERTZ),
+    };
+
+    // The "i" suffix means interlaced; we strip it from the resolution (not
+    // the refresh rate) rather than rejecting the line over it.
+    let resolution_part = resolution_part.strip_suffix('i').unwrap_or(resolution_part);
+
+    let (width_str, height_str) = resolution_part
+        .split_once('x')
+        .ok_or_else(|| anyhow::anyhow!("Invalid resolution format in DRM mode"))?;
+
+    let width = width_str.parse::<u32>()?;
+    let height = height_str.parse::<u32>()?;
+
+    Ok(VideoMode {
+        size: (width, height),
+        bit_depth: 24,
+        refresh_rate_millihertz,
+    })
 }
\ No newline at end of file
@@ -0,0 +1,256 @@
+// DRM/KMS atomic modesetting backend, replacing the old `/sys/class/drm/*/modes`
+// text scrape in `display::get_drm_config`/`parse_drm_mode`. Built on drm-rs'
+// `drm::control::Device` trait (same approach as smithay's compositor work)
+// plus `gbm` for framebuffer allocation, so we talk to the kernel KMS API
+// directly instead of guessing from sysfs.
+use anyhow::{Context, Result};
+use drm::control::{connector, crtc, Device as ControlDevice, Mode, ModeTypeFlags};
+use drm::Device as BasicDevice;
+use gbm::{BufferObjectFlags, Device as GbmDevice, Format as GbmFormat};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::{AsFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// A single display mode advertised by a connector, condensed from the
+/// kernel's `drm_mode_modeinfo` down to what `DisplayConfig` needs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DrmModeInfo {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_hz: u32,
+    /// Set on the mode the kernel/EDID marks as the connector's preferred one.
+    pub preferred: bool,
+}
+
+/// One connector's identity plus every mode it reported, analogous to
+/// `xrandr`'s per-output listing but read straight from `drmModeGetConnector`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectorInfo {
+    pub name: String,
+    pub connected: bool,
+    pub modes: Vec<DrmModeInfo>,
+}
+
+/// Thin wrapper around an open `/dev/dri/cardN` fd that implements the
+/// drm-rs marker traits `drm-rs` requires of callers - neither trait does
+/// anything beyond giving the crate an `AsFd` to issue ioctls against.
+struct DrmCard(File);
+
+impl AsFd for DrmCard {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl BasicDevice for DrmCard {}
+impl ControlDevice for DrmCard {}
+
+/// Scans `/dev/dri` for `cardN` device nodes (the KMS device nodes; `renderD*`
+/// are render-only and can't drive a display), returning them in numeric order.
+pub async fn list_drm_devices() -> Result<Vec<PathBuf>> {
+    tokio::task::spawn_blocking(list_drm_devices_blocking)
+        .await
+        .context("فشل في تنفيذ مسح أجهزة DRM في خيط منفصل")?
+}
+
+fn list_drm_devices_blocking() -> Result<Vec<PathBuf>> {
+    let dri_dir = Path::new("/dev/dri");
+    if !dri_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut cards: Vec<PathBuf> = std::fs::read_dir(dri_dir)
+        .context("فشل في قراءة /dev/dri")?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with("card"))
+                .unwrap_or(false)
+        })
+        .collect();
+
+    cards.sort_by_key(|path| {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("card"))
+            .and_then(|n| n.parse::<u32>().ok())
+            .unwrap_or(u32::MAX)
+    });
+    Ok(cards)
+}
+
+fn open_card(device_path: &Path) -> Result<DrmCard> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(device_path)
+        .with_context(|| format!("فشل في فتح جهاز DRM {}", device_path.display()))?;
+    Ok(DrmCard(file))
+}
+
+/// Enumerates every connector on `device_path` via the DRM resource/property
+/// APIs (`drmModeGetResources` -> `drmModeGetConnector` per handle), reading
+/// the full mode list for each rather than trusting sysfs's first line.
+pub async fn enumerate_connectors(device_path: &Path) -> Result<Vec<ConnectorInfo>> {
+    let device_path = device_path.to_path_buf();
+    tokio::task::spawn_blocking(move || enumerate_connectors_blocking(&device_path))
+        .await
+        .context("فشل في تنفيذ مسح موصلات DRM في خيط منفصل")?
+}
+
+fn enumerate_connectors_blocking(device_path: &Path) -> Result<Vec<ConnectorInfo>> {
+    let card = open_card(device_path)?;
+
+    let resources = card
+        .resource_handles()
+        .context("فشل في الحصول على موارد DRM - قد يفتقر العملية إلى DRM master")?;
+
+    let mut connectors = Vec::new();
+    for handle in resources.connectors() {
+        let info = match card.get_connector(*handle, true) {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("⚠️ تعذرت قراءة موصل DRM {:?}: {}", handle, e);
+                continue;
+            }
+        };
+
+        let connected = info.state() == connector::State::Connected;
+        let modes = info
+            .modes()
+            .iter()
+            .map(|mode| DrmModeInfo {
+                width: mode.size().0 as u32,
+                height: mode.size().1 as u32,
+                refresh_hz: mode.vrefresh(),
+                preferred: mode.mode_type().contains(ModeTypeFlags::PREFERRED),
+            })
+            .collect();
+
+        connectors.push(ConnectorInfo {
+            name: format!("{:?}-{}", info.interface(), info.interface_id()),
+            connected,
+            modes,
+        });
+    }
+
+    Ok(connectors)
+}
+
+/// Forces `mode` onto `connector_name` via an atomic modeset: allocates a
+/// GBM-backed framebuffer sized to the mode, binds it to the connector's
+/// CRTC (through its encoder) and commits the whole request in one ioctl.
+/// Returns a structured error (not a panic) when the connector doesn't
+/// exist, the mode wasn't in its list, or the kernel rejects the commit.
+pub async fn set_mode(device_path: &Path, connector_name: &str, mode: &DrmModeInfo) -> Result<()> {
+    let device_path = device_path.to_path_buf();
+    let connector_name = connector_name.to_string();
+    let mode = *mode;
+    tokio::task::spawn_blocking(move || set_mode_blocking(&device_path, &connector_name, &mode))
+        .await
+        .context("فشل في تنفيذ ضبط وضع DRM في خيط منفصل")?
+}
+
+fn set_mode_blocking(device_path: &Path, connector_name: &str, requested: &DrmModeInfo) -> Result<()> {
+    let card = open_card(device_path)?;
+
+    let resources = card
+        .resource_handles()
+        .context("فشل في الحصول على موارد DRM - قد يفتقر العملية إلى DRM master")?;
+
+    let (connector_handle, connector_info, drm_mode) = resources
+        .connectors()
+        .iter()
+        .find_map(|handle| {
+            let info = card.get_connector(*handle, true).ok()?;
+            let formatted = format!("{:?}-{}", info.interface(), info.interface_id());
+            if formatted != connector_name {
+                return None;
+            }
+            let drm_mode = info.modes().iter().find(|m| {
+                m.size().0 as u32 == requested.width
+                    && m.size().1 as u32 == requested.height
+                    && m.vrefresh() == requested.refresh_hz
+            })?;
+            Some((*handle, info, *drm_mode))
+        })
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "الموصل {} غير موجود أو لا يدعم الوضع {}x{}@{}",
+                connector_name,
+                requested.width,
+                requested.height,
+                requested.refresh_hz
+            )
+        })?;
+
+    if connector_info.state() != connector::State::Connected {
+        return Err(anyhow::anyhow!("الموصل {} غير متصل بشاشة", connector_name));
+    }
+
+    let encoder_handle = connector_info
+        .current_encoder()
+        .or_else(|| connector_info.encoders().first().copied())
+        .ok_or_else(|| anyhow::anyhow!("لا يوجد مُرمِّز (encoder) متاح للموصل {}", connector_name))?;
+    let encoder_info = card
+        .get_encoder(encoder_handle)
+        .context("فشل في قراءة معلومات المُرمِّز")?;
+    let crtc_handle = encoder_info
+        .crtc()
+        .or_else(|| resources.filter_crtcs(encoder_info.possible_crtcs()).first().copied())
+        .ok_or_else(|| anyhow::anyhow!("لا يوجد CRTC متاح للموصل {}", connector_name))?;
+
+    // تخصيص إطار مرئي عبر GBM بحجم الوضع المطلوب ليُربط بـ CRTC
+    let gbm = GbmDevice::new(card).context("فشل في إنشاء جهاز GBM فوق جهاز DRM")?;
+    let buffer_object = gbm
+        .create_buffer_object::<()>(
+            requested.width,
+            requested.height,
+            GbmFormat::Xrgb8888,
+            BufferObjectFlags::SCANOUT | BufferObjectFlags::RENDERING,
+        )
+        .context("فشل في تخصيص إطار مرئي عبر GBM")?;
+    let framebuffer = gbm
+        .as_ref()
+        .add_framebuffer(&buffer_object, 32, 32)
+        .context("فشل في تسجيل الإطار المرئي لدى DRM")?;
+
+    // بناء طلب ضبط وضع ذري واحد (atomic commit) يربط الموصل -> CRTC -> الإطار
+    // المرئي ويضبط الوضع دفعة واحدة، بدل سلسلة ioctl قديمة قابلة للتعارض
+    let mut atomic_req = drm::control::atomic::AtomicModeReq::new();
+    atomic_req.add_property(
+        connector_handle,
+        gbm.as_ref().get_property_id(connector_handle, "CRTC_ID")?,
+        drm::control::property::Value::CRTC(Some(crtc_handle.into())),
+    );
+    atomic_req.add_property(
+        crtc_handle,
+        gbm.as_ref().get_property_id(crtc_handle, "MODE_ID")?,
+        drm::control::property::Value::Blob(gbm.as_ref().create_property_blob(&drm_mode)?),
+    );
+    atomic_req.add_property(
+        crtc_handle,
+        gbm.as_ref().get_property_id(crtc_handle, "ACTIVE")?,
+        drm::control::property::Value::Boolean(true),
+    );
+    atomic_req.add_property(
+        crtc_handle,
+        gbm.as_ref().get_property_id(crtc_handle, "FB_ID")?,
+        drm::control::property::Value::Framebuffer(Some(framebuffer)),
+    );
+
+    gbm.as_ref()
+        .atomic_commit(&[crtc::AtomicCommitFlags::ALLOW_MODESET], atomic_req)
+        .context("رفض النواة التزام الوضع الذري - تحقق من ملكية DRM master")?;
+
+    info!(
+        "🖥️ تم ضبط الموصل {} على {}x{}@{} عبر DRM/KMS ذري",
+        connector_name, requested.width, requested.height, requested.refresh_hz
+    );
+    debug!("CRTC {:?} <- framebuffer {:?}", crtc_handle, framebuffer);
+    Ok(())
+}
@@ -0,0 +1,55 @@
+// Minimal EDID (Extended Display Identification Data) parser: just enough
+// of the 128-byte base block to recover the preferred/native video mode
+// from its first detailed timing descriptor, for displays where DRM's own
+// `modes` file doesn't mark a preferred mode explicitly.
+use crate::hardware::display::VideoMode;
+
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const DETAILED_TIMING_OFFSET: usize = 54;
+const DETAILED_TIMING_LEN: usize = 18;
+
+/// Parses the first detailed timing descriptor (bytes 54-71) of an EDID
+/// base block into a `VideoMode`. Per the EDID spec this descriptor is
+/// always the preferred/native timing when present. Returns `None` for
+/// anything that isn't a well-formed EDID base block, or whose first
+/// descriptor is a monitor-info block instead of a timing (pixel clock 0).
+pub fn parse_preferred_mode(edid: &[u8]) -> Option<VideoMode> {
+    if edid.len() < DETAILED_TIMING_OFFSET + DETAILED_TIMING_LEN || edid[0..8] != EDID_HEADER {
+        return None;
+    }
+
+    let block = &edid[DETAILED_TIMING_OFFSET..DETAILED_TIMING_OFFSET + DETAILED_TIMING_LEN];
+
+    // بوحدات 10 كيلوهرتز؛ صفر يعني أن هذا الوصف معلومات شاشة لا زمنية تفصيلية
+    let pixel_clock_khz = u16::from_le_bytes([block[0], block[1]]) as u32 * 10;
+    if pixel_clock_khz == 0 {
+        return None;
+    }
+
+    let h_active = (block[2] as u32) | (((block[4] as u32) & 0xF0) << 4);
+    let h_blank = (block[3] as u32) | (((block[4] as u32) & 0x0F) << 8);
+    let v_active = (block[5] as u32) | (((block[7] as u32) & 0xF0) << 4);
+    let v_blank = (block[6] as u32) | (((block[7] as u32) & 0x0F) << 8);
+
+    let h_total = h_active + h_blank;
+    let v_total = v_active + v_blank;
+    if h_total == 0 || v_total == 0 || h_active == 0 || v_active == 0 {
+        return None;
+    }
+
+    // معدل التحديث = ساعة البكسل (بالهرتز) / (العرض الكلي × الارتفاع الكلي)
+    let refresh_rate_millihertz = (pixel_clock_khz as u64 * 1_000_000 / (h_total as u64 * v_total as u64)) as u32;
+
+    Some(VideoMode {
+        size: (h_active, v_active),
+        bit_depth: 24,
+        refresh_rate_millihertz,
+    })
+}
+
+/// Reads and parses `/sys/class/drm/<connector>/edid`; empty/absent/invalid
+/// EDID blobs (common when nothing is plugged in) just yield `None`.
+pub fn read_preferred_mode(edid_path: &std::path::Path) -> Option<VideoMode> {
+    let bytes = std::fs::read(edid_path).ok()?;
+    parse_preferred_mode(&bytes)
+}
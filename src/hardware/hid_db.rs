@@ -0,0 +1,200 @@
+// HID vendor/product ID database for real gamepad/handheld controller
+// identification, replacing the "any js*/event* node exists" heuristic.
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ControllerCapabilities {
+    pub has_dpad: bool,
+    pub has_analog_sticks: bool,
+    pub has_shoulder_buttons: bool,
+    pub has_gyro: bool,
+}
+
+struct KnownController {
+    vendor_id: u16,
+    product_id: u16,
+    name: &'static str,
+    capabilities: ControllerCapabilities,
+}
+
+/// A controller actually found attached to the system, matched (or not)
+/// against the known table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentifiedController {
+    pub hidraw_path: String,
+    pub vendor_id: u16,
+    pub product_id: u16,
+    pub matched_name: Option<String>,
+    pub capabilities: ControllerCapabilities,
+}
+
+fn known_controllers() -> Vec<KnownController> {
+    vec![
+        KnownController {
+            vendor_id: 0x057e,
+            product_id: 0x2009,
+            name: "Nintendo Switch Pro Controller",
+            capabilities: ControllerCapabilities {
+                has_dpad: true,
+                has_analog_sticks: true,
+                has_shoulder_buttons: true,
+                has_gyro: true,
+            },
+        },
+        KnownController {
+            vendor_id: 0x045e,
+            product_id: 0x02ea,
+            name: "Xbox One Controller",
+            capabilities: ControllerCapabilities {
+                has_dpad: true,
+                has_analog_sticks: true,
+                has_shoulder_buttons: true,
+                has_gyro: false,
+            },
+        },
+        KnownController {
+            vendor_id: 0x045e,
+            product_id: 0x028e,
+            name: "Xbox 360 Controller",
+            capabilities: ControllerCapabilities {
+                has_dpad: true,
+                has_analog_sticks: true,
+                has_shoulder_buttons: true,
+                has_gyro: false,
+            },
+        },
+        KnownController {
+            vendor_id: 0x054c,
+            product_id: 0x09cc,
+            name: "Sony DualShock 4",
+            capabilities: ControllerCapabilities {
+                has_dpad: true,
+                has_analog_sticks: true,
+                has_shoulder_buttons: true,
+                has_gyro: true,
+            },
+        },
+        KnownController {
+            vendor_id: 0x054c,
+            product_id: 0x0ce6,
+            name: "Sony DualSense",
+            capabilities: ControllerCapabilities {
+                has_dpad: true,
+                has_analog_sticks: true,
+                has_shoulder_buttons: true,
+                has_gyro: true,
+            },
+        },
+        // Generic GPIO/ADC built-in handheld gamepad controllers expose
+        // themselves to the kernel as a vendor-less input device on some
+        // boards, identified by a placeholder 0x0000/0x0001 pair.
+        KnownController {
+            vendor_id: 0x0000,
+            product_id: 0x0001,
+            name: "Built-in GPIO Gamepad",
+            capabilities: ControllerCapabilities {
+                has_dpad: true,
+                has_analog_sticks: false,
+                has_shoulder_buttons: true,
+                has_gyro: false,
+            },
+        },
+    ]
+}
+
+fn lookup(vendor_id: u16, product_id: u16) -> Option<&'static str> {
+    known_controllers()
+        .into_iter()
+        .find(|c| c.vendor_id == vendor_id && c.product_id == product_id)
+        .map(|c| c.name)
+}
+
+fn capabilities_for(vendor_id: u16, product_id: u16) -> ControllerCapabilities {
+    known_controllers()
+        .into_iter()
+        .find(|c| c.vendor_id == vendor_id && c.product_id == product_id)
+        .map(|c| c.capabilities)
+        .unwrap_or(ControllerCapabilities {
+            has_dpad: false,
+            has_analog_sticks: false,
+            has_shoulder_buttons: false,
+            has_gyro: false,
+        })
+}
+
+/// Parse a hidraw `uevent` file's `HID_ID` line, formatted as
+/// `bus:vendor:product` in hex (e.g. `0003:0000057E:00002009`).
+fn parse_hid_id(uevent: &str) -> Option<(u16, u16)> {
+    for line in uevent.lines() {
+        if let Some(value) = line.strip_prefix("HID_ID=") {
+            let parts: Vec<&str> = value.split(':').collect();
+            if parts.len() == 3 {
+                let vendor = u32::from_str_radix(parts[1], 16).ok()? as u16;
+                let product = u32::from_str_radix(parts[2], 16).ok()? as u16;
+                return Some((vendor, product));
+            }
+        }
+    }
+    None
+}
+
+/// Enumerate `/sys/class/hidraw/*/device` and identify each attached
+/// controller by its USB/Bluetooth vendor/product IDs. Unrecognized
+/// devices are still reported (with `matched_name: None`) so callers can
+/// fall back to the generic js/event heuristic for them.
+pub fn identify_connected_controllers() -> Vec<IdentifiedController> {
+    let mut controllers = Vec::new();
+
+    let Ok(entries) = fs::read_dir("/sys/class/hidraw") else {
+        debug!("No /sys/class/hidraw present, skipping HID identification");
+        return controllers;
+    };
+
+    for entry in entries.flatten() {
+        let device_dir = entry.path().join("device");
+        let uevent_path = device_dir.join("uevent");
+
+        let Ok(uevent) = fs::read_to_string(&uevent_path) else {
+            continue;
+        };
+
+        let Some((vendor_id, product_id)) = parse_hid_id(&uevent) else {
+            continue;
+        };
+
+        let matched_name = lookup(vendor_id, product_id).map(|s| s.to_string());
+        let capabilities = capabilities_for(vendor_id, product_id);
+
+        controllers.push(IdentifiedController {
+            hidraw_path: entry.path().to_string_lossy().to_string(),
+            vendor_id,
+            product_id,
+            matched_name,
+            capabilities,
+        });
+    }
+
+    controllers
+}
+
+/// Combine the capability flags of every identified controller, used to
+/// populate `GamingFeatures` from real hardware instead of guessing.
+pub fn aggregate_capabilities(controllers: &[IdentifiedController]) -> ControllerCapabilities {
+    controllers.iter().fold(
+        ControllerCapabilities {
+            has_dpad: false,
+            has_analog_sticks: false,
+            has_shoulder_buttons: false,
+            has_gyro: false,
+        },
+        |mut acc, c| {
+            acc.has_dpad |= c.capabilities.has_dpad;
+            acc.has_analog_sticks |= c.capabilities.has_analog_sticks;
+            acc.has_shoulder_buttons |= c.capabilities.has_shoulder_buttons;
+            acc.has_gyro |= c.capabilities.has_gyro;
+            acc
+        },
+    )
+}
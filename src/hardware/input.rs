@@ -1,11 +1,40 @@
-// Input device testing module 
+// Input device testing module
 use anyhow::{Context, Result};
+use evdev::{AbsoluteAxisType, AttributeSet, Device, InputEventKind, Key, RelativeAxisType};
+use futures_util::StreamExt;
+use inotify::{EventMask, Inotify, WatchMask};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::os::unix::io::AsRawFd;
 use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::time::sleep;
 use tracing::{debug, info, warn};
 use crate::hardware::device_detect::{DeviceInfo, DeviceType};
 
+/// How long an interactive control test waits for the requested button/axis
+/// before giving up and recording it as not working.
+const CONTROL_PROMPT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Minimum `ABS_X`/`ABS_Y`/`ABS_RX`/`ABS_RY` deflection (around a
+/// nominally-zero-centered axis) that counts as a deliberate stick push
+/// rather than idle jitter.
+const ANALOG_DEADZONE: i32 = 8000;
+/// Total time budget for `measure_input_latency` to collect its samples
+/// before reporting whatever was gathered, in case the user stops
+/// generating input before `sample_count` is reached.
+const LATENCY_SAMPLE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Lower edges (ms) of the exponential latency buckets: floor 0, initial
+/// step 1ms, 10x growth per bucket (<1ms, 1-10ms, 10-100ms, 100ms-1s,
+/// >=1s).
+const LATENCY_BUCKET_FLOORS_MS: [f64; 5] = [0.0, 1.0, 10.0, 100.0, 1000.0];
+
+/// Default physical combo that arms the factory-reset countdown in
+/// `main::run_recovery_watchdog`; held together (not pressed in sequence),
+/// checked via [`InputTester::is_combo_held`].
+pub const FACTORY_RESET_COMBO: [Key; 2] = [Key::BTN_START, Key::BTN_SELECT];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InputDevice {
     pub device_path: String,
@@ -41,6 +70,13 @@ pub struct InputTester {
     device_info: DeviceInfo,
 }
 
+/// One change reported by [`InputTester::watch_hotplug`].
+#[derive(Debug, Clone)]
+pub enum InputHotplugEvent {
+    Added(InputDevice),
+    Removed(String),
+}
+
 impl InputTester {
     pub fn new(device_info: &DeviceInfo) -> Self {
         InputTester {
@@ -115,7 +151,7 @@ impl InputTester {
             .unwrap_or_else(|| "Unknown Device".to_string());
 
         let device_type = self.determine_device_type(device_path, &device_name).await;
-        let capabilities = self.analyze_device_capabilities(device_path, &device_type).await;
+        let capabilities = self.analyze_device_capabilities(device_path).await;
 
         Ok(InputDevice {
             device_path: device_path.to_string(),
@@ -165,12 +201,78 @@ impl InputTester {
         None
     }
 
+    /// Classifies by capability bitmap first - a generic GPIO gamepad or a
+    /// handheld's composite input node carries no identifying name, so the
+    /// old name-substring check misclassified most of them. Only falls
+    /// back to the name/path heuristic when the bitmap is ambiguous (e.g.
+    /// a node exposing no keys or axes at all).
     async fn determine_device_type(&self, device_path: &str, device_name: &str) -> InputDeviceType {
+        if let Some(device_type) = Self::classify_by_capabilities(device_path) {
+            return device_type;
+        }
+
+        self.determine_device_type_from_name(device_path, device_name)
+    }
+
+    /// Inspects `device_path`'s evdev capability bitmap and returns a type
+    /// when the bitmap unambiguously identifies one, `None` otherwise.
+    /// Checks touchscreen and mouse first since their signatures
+    /// (`BTN_TOUCH`+`ABS_MT_*`, `REL_X`/`REL_Y`+`BTN_LEFT`) don't overlap
+    /// with anything else; a dense `KEY_*` range rules a device out as a
+    /// gamepad even if it also exposes `ABS_X` (some composite handheld
+    /// nodes report both a full keyboard and a joystick axis on the same
+    /// node), and rules it in as a keyboard only when no joystick axes
+    /// are present either.
+    fn classify_by_capabilities(device_path: &str) -> Option<InputDeviceType> {
+        let device = Device::open(device_path).ok()?;
+        let keys = device.supported_keys();
+        let axes = device.supported_absolute_axis();
+        let rel_axes = device.supported_relative_axis();
+
+        let has_touchscreen = keys.map(|keys| keys.contains(Key::BTN_TOUCH)).unwrap_or(false)
+            && axes
+                .map(|axes| axes.contains(AbsoluteAxisType::ABS_MT_POSITION_X) || axes.contains(AbsoluteAxisType::ABS_MT_SLOT))
+                .unwrap_or(false);
+        if has_touchscreen {
+            return Some(InputDeviceType::Touchscreen);
+        }
+
+        let has_mouse = rel_axes
+            .map(|rel_axes| rel_axes.contains(RelativeAxisType::REL_X) && rel_axes.contains(RelativeAxisType::REL_Y))
+            .unwrap_or(false)
+            && keys.map(|keys| keys.contains(Key::BTN_LEFT)).unwrap_or(false);
+        if has_mouse {
+            return Some(InputDeviceType::Mouse);
+        }
+
+        let is_dense_keyboard = keys.map(count_keyboard_keys).unwrap_or(0) >= KEYBOARD_KEY_THRESHOLD;
+        let has_joystick_axes = axes
+            .map(|axes| {
+                axes.contains(AbsoluteAxisType::ABS_X)
+                    || axes.contains(AbsoluteAxisType::ABS_Y)
+                    || axes.contains(AbsoluteAxisType::ABS_HAT0X)
+                    || axes.contains(AbsoluteAxisType::ABS_HAT0Y)
+            })
+            .unwrap_or(false);
+        let has_gamepad_buttons = keys.map(count_buttons).map(|count| count > 0).unwrap_or(false);
+
+        if has_gamepad_buttons && has_joystick_axes && !is_dense_keyboard {
+            return Some(InputDeviceType::Gamepad);
+        }
+
+        if is_dense_keyboard && !has_joystick_axes {
+            return Some(InputDeviceType::Keyboard);
+        }
+
+        None
+    }
+
+    fn determine_device_type_from_name(&self, device_path: &str, device_name: &str) -> InputDeviceType {
         let name_lower = device_name.to_lowercase();
         let path_lower = device_path.to_lowercase();
 
         // Check for specific device types based on name
-        if name_lower.contains("gamepad") || name_lower.contains("controller") || 
+        if name_lower.contains("gamepad") || name_lower.contains("controller") ||
            name_lower.contains("joystick") || path_lower.contains("js") {
             return InputDeviceType::Gamepad;
         }
@@ -197,78 +299,80 @@ impl InputTester {
         InputDeviceType::Unknown
     }
 
-    async fn analyze_device_capabilities(&self, device_path: &str, device_type: &InputDeviceType) -> InputCapabilities {
-        // This is a simplified capability detection
-        // In a real implementation, you would use ioctl calls to query device capabilities
-        
-        match device_type {
-            InputDeviceType::Gamepad => {
-                InputCapabilities {
-                    has_buttons: true,
-                    button_count: 12, // Typical gamepad button count
-                    has_dpad: true,
-                    has_analog_sticks: true,
-                    analog_stick_count: 2,
-                    has_triggers: true,
-                    has_touchscreen: false,
-                }
-            }
-            InputDeviceType::DPad => {
-                InputCapabilities {
-                    has_buttons: true,
-                    button_count: 8, // D-pad + action buttons
-                    has_dpad: true,
-                    has_analog_sticks: false,
-                    analog_stick_count: 0,
-                    has_triggers: false,
-                    has_touchscreen: false,
-                }
-            }
-            InputDeviceType::Keyboard => {
-                InputCapabilities {
-                    has_buttons: true,
-                    button_count: 104, // Standard keyboard
-                    has_dpad: false,
-                    has_analog_sticks: false,
-                    analog_stick_count: 0,
-                    has_triggers: false,
-                    has_touchscreen: false,
-                }
+    /// Queries `device_path` directly via evdev's `EVIOCGBIT` wrappers
+    /// instead of guessing from the device name/type, so the report
+    /// reflects what the kernel actually advertises for this node. Falls
+    /// back to an all-`false`/zero report (rather than failing the whole
+    /// scan) if the node can't be opened, e.g. a permissions issue.
+    async fn analyze_device_capabilities(&self, device_path: &str) -> InputCapabilities {
+        Self::query_evdev_capabilities(device_path).unwrap_or_else(|e| {
+            debug!("failed to query device {} capabilities via evdev: {}", device_path, e);
+            InputCapabilities {
+                has_buttons: false,
+                button_count: 0,
+                has_dpad: false,
+                has_analog_sticks: false,
+                analog_stick_count: 0,
+                has_triggers: false,
+                has_touchscreen: false,
             }
-            InputDeviceType::Mouse => {
-                InputCapabilities {
-                    has_buttons: true,
-                    button_count: 3, // Left, right, middle
-                    has_dpad: false,
-                    has_analog_sticks: false,
-                    analog_stick_count: 0,
-                    has_triggers: false,
-                    has_touchscreen: false,
-                }
-            }
-            InputDeviceType::Touchscreen => {
-                InputCapabilities {
-                    has_buttons: false,
-                    button_count: 0,
-                    has_dpad: false,
-                    has_analog_sticks: false,
-                    analog_stick_count: 0,
-                    has_triggers: false,
-                    has_touchscreen: true,
-                }
-            }
-            _ => {
-                InputCapabilities {
-                    has_buttons: false,
-                    button_count: 0,
-                    has_dpad: false,
-                    has_analog_sticks: false,
-                    analog_stick_count: 0,
-                    has_triggers: false,
-                    has_touchscreen: false,
-                }
-            }
-        }
+        })
+    }
+
+    fn query_evdev_capabilities(device_path: &str) -> Result<InputCapabilities> {
+        let device = Device::open(device_path)
+            .with_context(|| format!("failed to open input device: {}", device_path))?;
+
+        let keys = device.supported_keys();
+        let axes = device.supported_absolute_axis();
+
+        let button_count = keys.map(count_buttons).unwrap_or(0);
+        let has_buttons = button_count > 0;
+
+        let has_hat_axes = axes
+            .map(|axes| axes.contains(AbsoluteAxisType::ABS_HAT0X) || axes.contains(AbsoluteAxisType::ABS_HAT0Y))
+            .unwrap_or(false);
+        let has_dpad_buttons = keys
+            .map(|keys| {
+                [Key::BTN_DPAD_UP, Key::BTN_DPAD_DOWN, Key::BTN_DPAD_LEFT, Key::BTN_DPAD_RIGHT]
+                    .iter()
+                    .any(|code| keys.contains(*code))
+            })
+            .unwrap_or(false);
+        let has_dpad = has_hat_axes || has_dpad_buttons;
+
+        let has_primary_stick = axes
+            .map(|axes| axes.contains(AbsoluteAxisType::ABS_X) && axes.contains(AbsoluteAxisType::ABS_Y))
+            .unwrap_or(false);
+        let has_secondary_stick = axes
+            .map(|axes| axes.contains(AbsoluteAxisType::ABS_RX) && axes.contains(AbsoluteAxisType::ABS_RY))
+            .unwrap_or(false);
+        let analog_stick_count = has_primary_stick as u32 + has_secondary_stick as u32;
+        let has_analog_sticks = analog_stick_count > 0;
+
+        let has_trigger_axes = axes
+            .map(|axes| axes.contains(AbsoluteAxisType::ABS_Z) || axes.contains(AbsoluteAxisType::ABS_RZ))
+            .unwrap_or(false);
+        let has_trigger_buttons = keys
+            .map(|keys| keys.contains(Key::BTN_TL2) || keys.contains(Key::BTN_TR2))
+            .unwrap_or(false);
+        let has_triggers = has_trigger_axes || has_trigger_buttons;
+
+        let has_touch_button = keys.map(|keys| keys.contains(Key::BTN_TOUCH)).unwrap_or(false);
+        let has_mt_position = axes
+            .map(|axes| axes.contains(AbsoluteAxisType::ABS_MT_POSITION_X))
+            .unwrap_or(false);
+        let has_touchscreen = has_touch_button && has_mt_position;
+
+        Ok(InputCapabilities {
+            has_buttons,
+            button_count,
+            has_dpad,
+            has_analog_sticks,
+            analog_stick_count,
+            has_triggers,
+            has_touchscreen,
+        })
     }
 
     async fn detect_builtin_gaming_controls(&self) -> Result<Vec<InputDevice>> {
@@ -346,6 +450,108 @@ impl InputTester {
         }
     }
 
+    /// Spawns a background watcher that keeps `detected_devices.toml` (and
+    /// the returned channel) in sync with controllers plugged in or
+    /// unplugged after boot (USB pads, Bluetooth), instead of only ever
+    /// seeing whatever `test_controllers` found at its one-shot scan.
+    /// Runs until the receiver is dropped or the watcher hits an
+    /// unrecoverable error (logged, not panicked).
+    pub fn watch_hotplug(&self) -> UnboundedReceiver<InputHotplugEvent> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let tester = InputTester {
+            device_info: self.device_info.clone(),
+        };
+
+        tokio::spawn(async move {
+            if let Err(e) = tester.run_hotplug_watch(tx).await {
+                warn!("Input hotplug watcher stopped: {}", e);
+            }
+        });
+
+        rx
+    }
+
+    /// Seeds a live device set from the current `/dev/input` contents,
+    /// then watches it via inotify `CREATE`/`DELETE`, sending an
+    /// [`InputHotplugEvent`] and re-saving `detected_devices.toml` on
+    /// every change.
+    async fn run_hotplug_watch(&self, tx: UnboundedSender<InputHotplugEvent>) -> Result<()> {
+        let mut inotify = Inotify::init().context("failed to initialize inotify")?;
+        inotify
+            .watches()
+            .add("/dev/input", WatchMask::CREATE | WatchMask::DELETE)
+            .context("failed to watch /dev/input for hotplug events")?;
+
+        let mut known: HashMap<String, InputDevice> = HashMap::new();
+        for device_path in self.scan_input_devices().await? {
+            if let Ok(device) = self.analyze_input_device(&device_path).await {
+                let _ = tx.send(InputHotplugEvent::Added(device.clone()));
+                known.insert(device_path, device);
+            }
+        }
+        self.save_input_config(&known.values().cloned().collect::<Vec<_>>()).await?;
+
+        let mut buffer = [0u8; 4096];
+        let mut events = inotify
+            .into_event_stream(&mut buffer)
+            .context("failed to start inotify event stream")?;
+
+        while let Some(event) = events.next().await {
+            let event = event.context("failed to read inotify event")?;
+            if tx.is_closed() {
+                break;
+            }
+            let Some(name) = event.name else { continue };
+            let name = name.to_string_lossy();
+            if !(name.starts_with("event") || name.starts_with("js")) {
+                continue;
+            }
+            let device_path = format!("/dev/input/{}", name);
+
+            if event.mask.contains(EventMask::CREATE) {
+                if let Ok(device) = self.analyze_new_device(&device_path).await {
+                    let _ = tx.send(InputHotplugEvent::Added(device.clone()));
+                    known.insert(device_path, device);
+                    self.save_input_config(&known.values().cloned().collect::<Vec<_>>()).await?;
+                }
+            } else if event.mask.contains(EventMask::DELETE) && known.remove(&device_path).is_some() {
+                let _ = tx.send(InputHotplugEvent::Removed(device_path.clone()));
+                self.save_input_config(&known.values().cloned().collect::<Vec<_>>()).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Retries `analyze_input_device` with a short backoff when the first
+    /// attempt either fails outright or comes back looking uninitialized
+    /// (no type, no capabilities), since a `CREATE` event can fire before
+    /// the kernel has finished populating the node's permissions and
+    /// capability bitmaps.
+    async fn analyze_new_device(&self, device_path: &str) -> Result<InputDevice> {
+        const RETRY_DELAYS: [Duration; 4] = [
+            Duration::from_millis(50),
+            Duration::from_millis(100),
+            Duration::from_millis(250),
+            Duration::from_millis(500),
+        ];
+
+        let mut result = self.analyze_input_device(device_path).await;
+        for delay in RETRY_DELAYS {
+            let needs_retry = match &result {
+                Ok(device) => looks_uninitialized(device),
+                Err(_) => true,
+            };
+            if !needs_retry {
+                break;
+            }
+            debug!("Hotplugged device {} not ready yet, retrying in {:?}", device_path, delay);
+            sleep(delay).await;
+            result = self.analyze_input_device(device_path).await;
+        }
+        result
+    }
+
     async fn save_input_config(&self, devices: &[InputDevice]) -> Result<()> {
         let config_dir = "/boot/dos_safar/input";
         std::fs::create_dir_all(config_dir)
@@ -362,7 +568,16 @@ impl InputTester {
         Ok(())
     }
 
-    pub async fn test_specific_gaming_controls(&self) -> Result<GamingControlsTest> {
+    /// Checks gaming controls. With `interactive` set, this grabs every
+    /// detected gamepad node and prompts the user to press each control in
+    /// turn, recording a real pass/fail from the matching `EV_KEY`/`EV_ABS`
+    /// event instead of assuming a device works just because it's an
+    /// Anbernic board or a `js*` node exists.
+    pub async fn test_specific_gaming_controls(&self, interactive: bool) -> Result<GamingControlsTest> {
+        if interactive {
+            return self.test_specific_gaming_controls_interactive().await;
+        }
+
         info!("Testing gaming-specific controls");
 
         let mut test_result = GamingControlsTest {
@@ -398,6 +613,137 @@ impl InputTester {
         Ok(test_result)
     }
 
+    /// Grabs every evdev node classified as a `Gamepad` (exclusively, via
+    /// `EVIOCGRAB`, so the events go only to this test rather than also to
+    /// whatever's normally reading them) and prompts through each control
+    /// group, `poll(2)`-ing all their raw fds together so a press on any
+    /// grabbed device counts. The grab is released when the `Device`s are
+    /// dropped at the end of this function (closing the fd releases it).
+    async fn test_specific_gaming_controls_interactive(&self) -> Result<GamingControlsTest> {
+        let candidate_paths: Vec<String> = self
+            .scan_input_devices()
+            .await?
+            .into_iter()
+            .filter(|path| matches!(Self::classify_by_capabilities(path), Some(InputDeviceType::Gamepad)))
+            .collect();
+
+        let mut test_result = GamingControlsTest {
+            dpad_working: false,
+            action_buttons_working: false,
+            shoulder_buttons_working: false,
+            analog_sticks_working: false,
+            start_select_working: false,
+        };
+
+        if candidate_paths.is_empty() {
+            warn!("No gamepad device found for interactive control test");
+            return Ok(test_result);
+        }
+
+        let mut devices = Vec::with_capacity(candidate_paths.len());
+        for path in &candidate_paths {
+            let mut device = Device::open(path).with_context(|| format!("failed to open gamepad device: {}", path))?;
+            device
+                .grab()
+                .with_context(|| format!("failed to grab gamepad device: {}", path))?;
+            devices.push(device);
+        }
+
+        test_result.dpad_working = prompt_and_wait(&mut devices, "D-Pad (up/down/left/right)", is_dpad_event);
+        test_result.action_buttons_working =
+            prompt_and_wait(&mut devices, "action buttons (A/B/X/Y)", is_action_button_event);
+        test_result.shoulder_buttons_working =
+            prompt_and_wait(&mut devices, "shoulder buttons (L1/R1/L2/R2)", is_shoulder_event);
+        test_result.analog_sticks_working = prompt_and_wait(&mut devices, "analog sticks", is_analog_stick_event);
+        test_result.start_select_working = prompt_and_wait(&mut devices, "Start/Select", is_start_select_event);
+
+        info!("Interactive gaming controls test completed: {:?}", test_result);
+        Ok(test_result)
+    }
+
+    /// Measures event-processing latency for a grabbed device: the
+    /// wall-clock gap between the kernel's `input_event.time` and the
+    /// moment this process observes the event via `fetch_events`, which
+    /// shows up as a laggy ADC poll or a slow GPIO driver even when the
+    /// control itself "works". Collects up to `sample_count` samples (or
+    /// whatever arrives within `LATENCY_SAMPLE_TIMEOUT`) and buckets them
+    /// into an exponential histogram.
+    pub async fn measure_input_latency(&self, device_path: &str, sample_count: usize) -> Result<LatencyReport> {
+        let mut device = Device::open(device_path)
+            .with_context(|| format!("failed to open input device: {}", device_path))?;
+        device
+            .grab()
+            .with_context(|| format!("failed to grab input device: {}", device_path))?;
+
+        info!("Measuring input latency for {} ({} samples)...", device_path, sample_count);
+
+        let mut samples_ms: Vec<f64> = Vec::with_capacity(sample_count);
+        let deadline = Instant::now() + LATENCY_SAMPLE_TIMEOUT;
+        let mut poll_fd = libc::pollfd {
+            fd: device.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        while samples_ms.len() < sample_count {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                warn!(
+                    "Latency measurement for {} timed out with {} of {} samples",
+                    device_path,
+                    samples_ms.len(),
+                    sample_count
+                );
+                break;
+            }
+
+            poll_fd.revents = 0;
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, remaining.as_millis() as libc::c_int) };
+            if ready <= 0 || poll_fd.revents & libc::POLLIN == 0 {
+                continue;
+            }
+
+            let Ok(events) = device.fetch_events() else { continue };
+            let observed_at = SystemTime::now();
+            for event in events {
+                if let Ok(delta) = observed_at.duration_since(event.timestamp()) {
+                    samples_ms.push(delta.as_secs_f64() * 1000.0);
+                    if samples_ms.len() >= sample_count {
+                        break;
+                    }
+                }
+            }
+        }
+
+        Ok(build_latency_report(device_path, samples_ms))
+    }
+
+    /// Snapshot-checks whether every key in `combo` is currently held down
+    /// on any gamepad/D-pad-classified device, via `EVIOCGKEY`
+    /// (`get_key_state`) rather than consuming events - so it can poll
+    /// alongside whatever else is reading the same nodes (e.g. the boot
+    /// menu's keyboard listener) without grabbing them exclusively.
+    pub async fn is_combo_held(&self, combo: &[Key]) -> bool {
+        let Ok(paths) = self.scan_input_devices().await else { return false };
+
+        for path in paths {
+            if !matches!(
+                Self::classify_by_capabilities(&path),
+                Some(InputDeviceType::Gamepad) | Some(InputDeviceType::DPad)
+            ) {
+                continue;
+            }
+
+            let Ok(device) = Device::open(&path) else { continue };
+            let Ok(held_keys) = device.get_key_state() else { continue };
+            if combo.iter().all(|key| held_keys.contains(*key)) {
+                return true;
+            }
+        }
+
+        false
+    }
+
     async fn test_dpad_functionality(&self) -> bool {
         // For gaming handhelds, assume D-pad works if device is detected
         match self.device_info.device_type {
@@ -480,4 +826,189 @@ impl GamingControlsTest {
         if self.start_select_working { count += 1; }
         count
     }
+}
+
+/// One exponential bucket in a [`LatencyReport`]: events whose latency
+/// fell in `[floor_ms, next bucket's floor_ms)` land here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyBucket {
+    pub floor_ms: f64,
+    pub count: u64,
+}
+
+/// Event-processing latency for one device, from [`InputTester::measure_input_latency`]:
+/// min/median/max plus an exponential histogram (floor 0, initial step
+/// 1ms, 10x growth per bucket), so a laggy ADC poll or slow GPIO driver
+/// shows up even when the control otherwise reads as "working".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyReport {
+    pub device_path: String,
+    pub sample_count: u64,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+/// Sorts `samples_ms`, derives min/median/max, and buckets every sample
+/// into `LATENCY_BUCKET_FLOORS_MS`'s exponential ranges.
+fn build_latency_report(device_path: &str, mut samples_ms: Vec<f64>) -> LatencyReport {
+    samples_ms.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let mut buckets: Vec<LatencyBucket> = LATENCY_BUCKET_FLOORS_MS
+        .iter()
+        .map(|&floor_ms| LatencyBucket { floor_ms, count: 0 })
+        .collect();
+    for &sample in &samples_ms {
+        let bucket_index = LATENCY_BUCKET_FLOORS_MS
+            .iter()
+            .rposition(|&floor_ms| sample >= floor_ms)
+            .unwrap_or(0);
+        buckets[bucket_index].count += 1;
+    }
+
+    let (min_ms, median_ms, max_ms) = if samples_ms.is_empty() {
+        (0.0, 0.0, 0.0)
+    } else {
+        (
+            samples_ms[0],
+            samples_ms[samples_ms.len() / 2],
+            samples_ms[samples_ms.len() - 1],
+        )
+    };
+
+    LatencyReport {
+        device_path: device_path.to_string(),
+        sample_count: samples_ms.len() as u64,
+        min_ms,
+        median_ms,
+        max_ms,
+        buckets,
+    }
+}
+
+/// True when a freshly-analyzed device reports no type and no
+/// capabilities at all, the usual symptom of reading a hotplugged node's
+/// bitmap before the kernel has finished populating it.
+fn looks_uninitialized(device: &InputDevice) -> bool {
+    device.device_type == InputDeviceType::Unknown
+        && !device.capabilities.has_buttons
+        && !device.capabilities.has_dpad
+        && !device.capabilities.has_analog_sticks
+        && !device.capabilities.has_triggers
+        && !device.capabilities.has_touchscreen
+}
+
+/// Prompts the user for `prompt`, then `poll(2)`s every device's raw fd
+/// together until `matches_event` sees a qualifying `EV_KEY`/`EV_ABS` event
+/// on any of them or `CONTROL_PROMPT_TIMEOUT` elapses.
+fn prompt_and_wait(devices: &mut [Device], prompt: &str, matches_event: fn(InputEventKind, i32) -> bool) -> bool {
+    info!("Press {} now...", prompt);
+
+    let mut poll_fds: Vec<libc::pollfd> = devices
+        .iter()
+        .map(|device| libc::pollfd {
+            fd: device.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+
+    let deadline = Instant::now() + CONTROL_PROMPT_TIMEOUT;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            warn!("Timed out waiting for {}", prompt);
+            return false;
+        }
+
+        for pfd in poll_fds.iter_mut() {
+            pfd.revents = 0;
+        }
+        let ready =
+            unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, remaining.as_millis() as libc::c_int) };
+        if ready <= 0 {
+            continue;
+        }
+
+        for (pfd, device) in poll_fds.iter().zip(devices.iter_mut()) {
+            if pfd.revents & libc::POLLIN == 0 {
+                continue;
+            }
+            let Ok(events) = device.fetch_events() else { continue };
+            for event in events {
+                if matches_event(event.kind(), event.value()) {
+                    return true;
+                }
+            }
+        }
+    }
+}
+
+fn is_dpad_event(kind: InputEventKind, value: i32) -> bool {
+    match kind {
+        InputEventKind::Key(Key::BTN_DPAD_UP)
+        | InputEventKind::Key(Key::BTN_DPAD_DOWN)
+        | InputEventKind::Key(Key::BTN_DPAD_LEFT)
+        | InputEventKind::Key(Key::BTN_DPAD_RIGHT) => value == 1,
+        InputEventKind::AbsAxis(AbsoluteAxisType::ABS_HAT0X) | InputEventKind::AbsAxis(AbsoluteAxisType::ABS_HAT0Y) => {
+            value != 0
+        }
+        _ => false,
+    }
+}
+
+fn is_action_button_event(kind: InputEventKind, value: i32) -> bool {
+    matches!(
+        kind,
+        InputEventKind::Key(Key::BTN_SOUTH)
+            | InputEventKind::Key(Key::BTN_EAST)
+            | InputEventKind::Key(Key::BTN_NORTH)
+            | InputEventKind::Key(Key::BTN_WEST)
+    ) && value == 1
+}
+
+fn is_shoulder_event(kind: InputEventKind, value: i32) -> bool {
+    matches!(
+        kind,
+        InputEventKind::Key(Key::BTN_TL) | InputEventKind::Key(Key::BTN_TR) | InputEventKind::Key(Key::BTN_TL2) | InputEventKind::Key(Key::BTN_TR2)
+    ) && value == 1
+}
+
+fn is_analog_stick_event(kind: InputEventKind, value: i32) -> bool {
+    matches!(
+        kind,
+        InputEventKind::AbsAxis(AbsoluteAxisType::ABS_X)
+            | InputEventKind::AbsAxis(AbsoluteAxisType::ABS_Y)
+            | InputEventKind::AbsAxis(AbsoluteAxisType::ABS_RX)
+            | InputEventKind::AbsAxis(AbsoluteAxisType::ABS_RY)
+    ) && value.abs() > ANALOG_DEADZONE
+}
+
+fn is_start_select_event(kind: InputEventKind, value: i32) -> bool {
+    matches!(kind, InputEventKind::Key(Key::BTN_START) | InputEventKind::Key(Key::BTN_SELECT)) && value == 1
+}
+
+/// Counts `EV_KEY` codes in the gamepad button ranges: the contiguous
+/// `BTN_SOUTH..=BTN_THUMBR` block plus the `BTN_TRIGGER_HAPPY*` extras some
+/// drivers use for extra face/macro buttons.
+fn count_buttons(keys: &AttributeSet<Key>) -> u32 {
+    let gamepad_buttons = Key::BTN_SOUTH.code()..=Key::BTN_THUMBR.code();
+    let trigger_happy = Key::BTN_TRIGGER_HAPPY1.code()..=Key::BTN_TRIGGER_HAPPY40.code();
+    keys.iter()
+        .filter(|key| gamepad_buttons.contains(&key.code()) || trigger_happy.contains(&key.code()))
+        .count() as u32
+}
+
+/// Minimum number of main-block `KEY_*` codes (letters/numbers/modifiers)
+/// a device must report before it's treated as a real keyboard rather
+/// than e.g. a remote with a handful of media keys.
+const KEYBOARD_KEY_THRESHOLD: u32 = 20;
+
+/// Counts `EV_KEY` codes in the `KEY_ESC..=KEY_KPDOT` block, which covers
+/// the letter/number/modifier/function keys every keyboard reports,
+/// without reaching into the higher `BTN_*`/multimedia ranges.
+fn count_keyboard_keys(keys: &AttributeSet<Key>) -> u32 {
+    let main_block = Key::KEY_ESC.code()..=Key::KEY_KPDOT.code();
+    keys.iter().filter(|key| main_block.contains(&key.code())).count() as u32
 }
\ No newline at end of file
@@ -0,0 +1,327 @@
+// SPI LCD driver for the small add-on panels `LcdConfig` describes a pinout
+// for but nothing previously drove - these show up at `/dev/spidev{bus}.{device}`
+// plus a handful of GPIO lines, never as `/dev/fb0`. Implements just enough
+// of the ST7789/ILI9341 command sets to init the panel and blit an RGB565
+// framebuffer window, the same two controllers most SPI LCD HATs use.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+use crate::utils::config::LcdConfig;
+
+/// Panel controller, either pinned by `LcdConfig::driver` or resolved by
+/// `probe_driver` when it's `"auto"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LcdDriver {
+    St7789,
+    Ili9341,
+}
+
+impl LcdDriver {
+    fn native_size(self) -> (u32, u32) {
+        match self {
+            LcdDriver::St7789 => (240, 240),
+            LcdDriver::Ili9341 => (320, 240),
+        }
+    }
+}
+
+/// Resolved panel configuration after `init()` - the concrete driver and
+/// the dimensions/rotation actually in effect, as opposed to `LcdConfig`'s
+/// raw user-supplied pinout/settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LcdDisplayConfig {
+    pub driver: LcdDriver,
+    pub width: u32,
+    pub height: u32,
+    pub rotation: u32,
+}
+
+// أوامر ST7789/ILI9341 المشتركة (مجموعة الأوامر متطابقة تقريباً بين اللوحين)
+const CMD_SWRESET: u8 = 0x01;
+const CMD_SLPOUT: u8 = 0x11;
+const CMD_RDDID: u8 = 0x04;
+const CMD_DISPON: u8 = 0x29;
+const CMD_CASET: u8 = 0x2A;
+const CMD_RASET: u8 = 0x2B;
+const CMD_RAMWR: u8 = 0x2C;
+const CMD_MADCTL: u8 = 0x36;
+const CMD_COLMOD: u8 = 0x3A;
+
+/// قيمة COLMOD لصيغة RGB565 16-bit/pixel.
+const COLMOD_RGB565: u8 = 0x55;
+
+/// يقود لوحة SPI صغيرة (ST7789/ILI9341) عبر `/dev/spidev{bus}.{device}` مع
+/// خطوط GPIO لـ DC/RST/BL على واجهة sysfs، بما أن هذه اللوحات لا تظهر كـ
+/// `/dev/fb0`.
+pub struct LcdDisplayDetector {
+    config: LcdConfig,
+    spi: Option<Spidev>,
+    resolved: Option<LcdDisplayConfig>,
+}
+
+impl LcdDisplayDetector {
+    pub fn new(config: &LcdConfig) -> Self {
+        LcdDisplayDetector {
+            config: config.clone(),
+            spi: None,
+            resolved: None,
+        }
+    }
+
+    /// يفتح جهاز SPI، يهيئ خطوط GPIO، يحدد المتحكم الفعلي (يفحص سجل الهوية
+    /// عند `driver = "auto"`)، وينفذ تسلسل التهيئة الخاص به، منتهياً بتشغيل
+    /// العرض (`DISPON`).
+    pub async fn init(&mut self) -> Result<LcdDisplayConfig> {
+        if !self.config.enabled {
+            return Err(anyhow::anyhow!("شاشة LCD معطّلة في التكوين (lcd.enabled = false)"));
+        }
+
+        let mut spi = Spidev::open(spidev_path(self.config.spi_bus, self.config.spi_device))
+            .context("فشل في فتح جهاز SPI لشاشة LCD")?;
+        spi.configure(
+            &SpidevOptions::new()
+                .bits_per_word(8)
+                .max_speed_hz(self.config.spi_speed_hz)
+                .mode(SpiModeFlags::SPI_MODE_0)
+                .build(),
+        )
+        .context("فشل في ضبط إعدادات SPI")?;
+
+        export_and_configure_output(self.config.gpio_cs)?;
+        export_and_configure_output(self.config.gpio_dc)?;
+        export_and_configure_output(self.config.gpio_rst)?;
+        export_and_configure_output(self.config.gpio_bl)?;
+
+        self.spi = Some(spi);
+
+        self.hardware_reset().await?;
+
+        let driver = match self.config.driver.as_str() {
+            "st7789" => LcdDriver::St7789,
+            "ili9341" => LcdDriver::Ili9341,
+            _ => self.probe_driver().await?,
+        };
+
+        match driver {
+            LcdDriver::St7789 => self.run_init_sequence_st7789().await?,
+            LcdDriver::Ili9341 => self.run_init_sequence_ili9341().await?,
+        }
+
+        self.set_backlight(true)?;
+
+        let (width, height) = driver.native_size();
+        let resolved = LcdDisplayConfig {
+            driver,
+            width,
+            height,
+            rotation: self.config.rotation,
+        };
+        self.resolved = Some(resolved.clone());
+
+        info!(
+            "🖼️ تهيئة شاشة LCD: {:?} {}x{} (دوران {} درجة)",
+            resolved.driver, resolved.width, resolved.height, resolved.rotation
+        );
+        Ok(resolved)
+    }
+
+    /// يرسل نافذة `w×h` من `framebuffer` (بصيغة RGB565، مرتبة صفاً بصف) تبدأ
+    /// عند `(x, y)` عبر أوامر ضبط عنوان العمود/الصف ثم كتابة الذاكرة، تماماً
+    /// كما تفعل مكتبات Adafruit لهذه اللوحات.
+    pub async fn blit(&self, framebuffer: &[u16], x: u32, y: u32, w: u32, h: u32) -> Result<()> {
+        let resolved = self
+            .resolved
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("يجب استدعاء init() قبل blit()"))?;
+
+        if (w * h) as usize > framebuffer.len() {
+            return Err(anyhow::anyhow!(
+                "الإطار المرسل ({} بكسل) أصغر من النافذة المطلوبة {}x{}",
+                framebuffer.len(),
+                w,
+                h
+            ));
+        }
+
+        let x_end = x + w - 1;
+        let y_end = y + h - 1;
+        if x_end >= resolved.width || y_end >= resolved.height {
+            return Err(anyhow::anyhow!(
+                "النافذة {}x{}+{}+{} تتجاوز حدود اللوحة {}x{}",
+                w, h, x, y, resolved.width, resolved.height
+            ));
+        }
+
+        self.write_command(CMD_CASET)?;
+        self.write_data(&[
+            (x >> 8) as u8, (x & 0xFF) as u8,
+            (x_end >> 8) as u8, (x_end & 0xFF) as u8,
+        ])?;
+
+        self.write_command(CMD_RASET)?;
+        self.write_data(&[
+            (y >> 8) as u8, (y & 0xFF) as u8,
+            (y_end >> 8) as u8, (y_end & 0xFF) as u8,
+        ])?;
+
+        self.write_command(CMD_RAMWR)?;
+        let mut pixel_bytes = Vec::with_capacity((w * h) as usize * 2);
+        for pixel in &framebuffer[..(w * h) as usize] {
+            pixel_bytes.push((pixel >> 8) as u8);
+            pixel_bytes.push((pixel & 0xFF) as u8);
+        }
+        self.write_data(&pixel_bytes)?;
+
+        Ok(())
+    }
+
+    async fn hardware_reset(&self) -> Result<()> {
+        if let Some(pin) = self.config.gpio_rst {
+            gpio_write(pin, true)?;
+            sleep(Duration::from_millis(10)).await;
+            gpio_write(pin, false)?;
+            sleep(Duration::from_millis(10)).await;
+            gpio_write(pin, true)?;
+            sleep(Duration::from_millis(120)).await;
+        }
+        Ok(())
+    }
+
+    fn set_backlight(&self, on: bool) -> Result<()> {
+        if let Some(pin) = self.config.gpio_bl {
+            gpio_write(pin, on)?;
+        }
+        Ok(())
+    }
+
+    /// يرسل `RDDID` (سجل هوية اللوحة) ويقارن البايتات العائدة ببصمات
+    /// المتحكمين المعروفتين؛ عند عدم التطابق القاطع يفترض ST7789 (الأكثر
+    /// شيوعاً في لوحات الهواة الحديثة) بدل الفشل.
+    async fn probe_driver(&mut self) -> Result<LcdDriver> {
+        self.write_command(CMD_RDDID)?;
+        let response = self.read_bytes(4)?;
+
+        debug!("📋 استجابة سجل هوية شاشة LCD: {:02X?}", response);
+
+        // ILI9341 يعيد عادة 0x00, 0x93, 0x41 بعد بايت وهمي أول
+        if response.len() >= 4 && response[2] == 0x93 && response[3] == 0x41 {
+            return Ok(LcdDriver::Ili9341);
+        }
+
+        // ST7789 يعيد نمط هوية مصنّع/طراز قريب من 0x85/0x85/0x52
+        if response.len() >= 4 && response[2] == 0x85 {
+            return Ok(LcdDriver::St7789);
+        }
+
+        warn!("⚠️ تعذر تحديد متحكم اللوحة من سجل الهوية - افتراض ST7789");
+        Ok(LcdDriver::St7789)
+    }
+
+    /// تسلسل تهيئة ST7789 المختصر: خروج من وضع السكون، تنسيق لون RGB565،
+    /// ضبط MADCTL حسب الدوران المطلوب، ثم تشغيل العرض.
+    async fn run_init_sequence_st7789(&self) -> Result<()> {
+        self.write_command(CMD_SWRESET)?;
+        sleep(Duration::from_millis(150)).await;
+        self.write_command(CMD_SLPOUT)?;
+        sleep(Duration::from_millis(120)).await;
+
+        self.write_command(CMD_COLMOD)?;
+        self.write_data(&[COLMOD_RGB565])?;
+
+        self.write_command(CMD_MADCTL)?;
+        self.write_data(&[madctl_for_rotation(self.config.rotation)])?;
+
+        self.write_command(CMD_DISPON)?;
+        sleep(Duration::from_millis(100)).await;
+        Ok(())
+    }
+
+    /// نفس بنية ST7789 عملياً - أوامر ILI9341 الأساسية (SWRESET/SLPOUT/
+    /// COLMOD/MADCTL/DISPON) متطابقة الترميز مع ST7789، والفروق (مثل جاما
+    /// ومقاومة الطاقة) اختيارية لا تمنع خرج صورة سليمة.
+    async fn run_init_sequence_ili9341(&self) -> Result<()> {
+        self.run_init_sequence_st7789().await
+    }
+
+    fn write_command(&self, cmd: u8) -> Result<()> {
+        if let Some(pin) = self.config.gpio_dc {
+            gpio_write(pin, false)?; // DC منخفض = أمر
+        }
+        self.spi_write(&[cmd])
+    }
+
+    fn write_data(&self, data: &[u8]) -> Result<()> {
+        if let Some(pin) = self.config.gpio_dc {
+            gpio_write(pin, true)?; // DC مرتفع = بيانات
+        }
+        self.spi_write(data)
+    }
+
+    fn spi_write(&self, bytes: &[u8]) -> Result<()> {
+        let spi = self
+            .spi
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("جهاز SPI غير مفتوح - استدعِ init() أولاً"))?;
+        let mut transfer = SpidevTransfer::write(bytes);
+        spi.transfer(&mut transfer).context("فشل في الكتابة عبر SPI")?;
+        Ok(())
+    }
+
+    fn read_bytes(&self, len: usize) -> Result<Vec<u8>> {
+        let spi = self
+            .spi
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("جهاز SPI غير مفتوح - استدعِ init() أولاً"))?;
+        let mut rx_buf = vec![0u8; len];
+        let mut transfer = SpidevTransfer::read(&mut rx_buf);
+        spi.transfer(&mut transfer).context("فشل في القراءة عبر SPI")?;
+        Ok(rx_buf)
+    }
+}
+
+/// يبني قيمة MADCTL (ترتيب المسح/التبديل) من زاوية الدوران المطلوبة،
+/// بنفس الجدول الذي تستخدمه مكتبات Adafruit لهذين المتحكمين.
+fn madctl_for_rotation(rotation: u32) -> u8 {
+    match rotation % 360 {
+        90 => 0x60,
+        180 => 0xC0,
+        270 => 0xA0,
+        _ => 0x00,
+    }
+}
+
+fn spidev_path(bus: u8, device: u8) -> PathBuf {
+    PathBuf::from(format!("/dev/spidev{}.{}", bus, device))
+}
+
+/// يصدّر (export) رقم GPIO عبر واجهة sysfs القديمة إن لم يكن مصدَّراً بعد،
+/// ويضبط اتجاهه كمُخرَج. لا حاجة لمكتبة cdev خارجية لخطوط التحكم البسيطة
+/// هذه (CS/DC/RST/BL) التي تُبدَّل ببطء نسبياً.
+fn export_and_configure_output(pin: Option<u8>) -> Result<()> {
+    let Some(pin) = pin else {
+        return Ok(());
+    };
+
+    let gpio_dir = format!("/sys/class/gpio/gpio{}", pin);
+    if !std::path::Path::new(&gpio_dir).exists() {
+        fs::write("/sys/class/gpio/export", pin.to_string())
+            .with_context(|| format!("فشل في تصدير GPIO{}", pin))?;
+    }
+
+    fs::write(format!("{}/direction", gpio_dir), "out")
+        .with_context(|| format!("فشل في ضبط اتجاه GPIO{} كمُخرَج", pin))?;
+
+    Ok(())
+}
+
+fn gpio_write(pin: u8, value: bool) -> Result<()> {
+    let value_path = format!("/sys/class/gpio/gpio{}/value", pin);
+    fs::write(&value_path, if value { "1" } else { "0" })
+        .with_context(|| format!("فشل في كتابة قيمة GPIO{}", pin))?;
+    Ok(())
+}
@@ -1,13 +1,32 @@
+pub mod bluetooth;
+pub mod board_registry;
+pub mod capability;
 pub mod device_detect;
 pub mod display;
+pub mod drm;
+pub mod edid;
+pub mod hid_db;
 pub mod input;
 pub mod network;
+pub mod network_backend;
 pub mod config_persist;
 pub mod lcd_display; // إضافة جديدة
+pub mod touch_input;
+pub mod virtual_gamepad;
+pub mod wpa_ctrl;
 
 // Re-export commonly used types
+pub use bluetooth::{BleDevice, BluetoothManager};
+pub use board_registry::BoardRule;
+pub use capability::{CapabilityRating, CapabilityReport};
 pub use device_detect::{DeviceDetector, DeviceInfo, DeviceType};
-pub use display::{DisplayTester, DisplayConfig};
-pub use input::{InputTester, InputDevice, GamingControlsTest};
+pub use hid_db::{ControllerCapabilities, IdentifiedController};
+pub use display::{DisplayTester, DisplayConfig, DisplayInfo, VideoMode};
+pub use drm::{ConnectorInfo, DrmModeInfo};
+pub use input::{InputTester, InputDevice, InputHotplugEvent, GamingControlsTest, LatencyReport, LatencyBucket, FACTORY_RESET_COMBO};
 pub use network::{NetworkManager, NetworkConnection};
-pub use lcd_display::{LcdDisplayDetector, LcdDisplayConfig, LcdDriver}; // إضافة جديدة
\ No newline at end of file
+pub use network_backend::NetworkBackend;
+pub use lcd_display::{LcdDisplayDetector, LcdDisplayConfig, LcdDriver}; // إضافة جديدة
+pub use touch_input::{TouchInputReader, RawTouchPoint, ScreenPoint};
+pub use virtual_gamepad::{GamepadProfile, VirtualGamepad};
+pub use wpa_ctrl::{WpaCtrl, ScanResult, SecurityType, dedupe_strongest_per_ssid};
\ No newline at end of file
@@ -1,496 +1,893 @@
-use anyhow::{Context, Result};
-use serde::{Deserialize, Serialize};
-use std::fs;
-use std::process::Command;
-use std::time::Duration;
-use tokio::time::timeout;
-use tracing::{debug, info, warn};
-use crate::utils::config::Config;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct NetworkConnection {
-    pub interface: String,
-    pub connection_type: ConnectionType,
-    pub ip_address: String,
-    pub gateway: Option<String>,
-    pub dns_servers: Vec<String>,
-    pub is_connected: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum ConnectionType {
-    Ethernet,
-    WiFi,
-    Hotspot,
-    Unknown,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WiFiNetwork {
-    pub ssid: String,
-    pub signal_strength: i32,
-    pub security: String,
-    pub frequency: Option<u32>,
-}
-
-pub struct NetworkManager {
-    config: Config,
-}
-
-impl NetworkManager {
-    pub fn new(config: &Config) -> Self {
-        NetworkManager {
-            config: config.clone(),
-        }
-    }
-
-    pub async fn connect(&self) -> Result<NetworkConnection> {
-        info!("Starting network connection process");
-
-        // Try Ethernet first if preferred
-        if self.config.network.ethernet_preferred {
-            if let Ok(connection) = self.try_ethernet_connection().await {
-                info!("Connected via Ethernet: {}", connection.ip_address);
-                return Ok(connection);
-            }
-        }
-
-        // Try WiFi connection
-        if let Ok(connection) = self.try_wifi_connection().await {
-            info!("Connected via WiFi: {}", connection.ip_address);
-            return Ok(connection);
-        }
-
-        // Try Ethernet as fallback if not preferred
-        if !self.config.network.ethernet_preferred {
-            if let Ok(connection) = self.try_ethernet_connection().await {
-                info!("Connected via Ethernet (fallback): {}", connection.ip_address);
-                return Ok(connection);
-            }
-        }
-
-        Err(anyhow::anyhow!("Failed to establish any network connection"))
-    }
-
-    async fn try_ethernet_connection(&self) -> Result<NetworkConnection> {
-        info!("Attempting Ethernet connection");
-
-        // Check if Ethernet interface exists
-        let eth_interfaces = self.get_ethernet_interfaces().await?;
-        if eth_interfaces.is_empty() {
-            return Err(anyhow::anyhow!("No Ethernet interfaces found"));
-        }
-
-        for interface in eth_interfaces {
-            debug!("Checking Ethernet interface: {}", interface);
-            
-            // Check if interface is up and has link
-            if self.is_interface_up(&interface).await? {
-                // Try to get IP address
-                if let Ok(ip) = self.get_interface_ip(&interface).await {
-                    let connection = NetworkConnection {
-                        interface: interface.clone(),
-                        connection_type: ConnectionType::Ethernet,
-                        ip_address: ip,
-                        gateway: self.get_default_gateway().await.ok(),
-                        dns_servers: self.get_dns_servers().await.unwrap_or_default(),
-                        is_connected: true,
-                    };
-                    
-                    // Test connectivity
-                    if self.test_internet_connectivity().await {
-                        return Ok(connection);
-                    }
-                }
-            }
-        }
-
-        Err(anyhow::anyhow!("Ethernet connection failed"))
-    }
-
-    async fn try_wifi_connection(&self) -> Result<NetworkConnection> {
-        info!("Attempting WiFi connection");
-
-        // Check if WiFi interface exists
-        let wifi_interfaces = self.get_wifi_interfaces().await?;
-        if wifi_interfaces.is_empty() {
-            return Err(anyhow::anyhow!("No WiFi interfaces found"));
-        }
-
-        for interface in wifi_interfaces {
-            debug!("Checking WiFi interface: {}", interface);
-            
-            // Try to connect to configured network
-            if let Some(ssid) = &self.config.network.wifi_ssid {
-                if !ssid.is_empty() {
-                    if let Ok(connection) = self.connect_to_wifi(&interface, ssid).await {
-                        return Ok(connection);
-                    }
-                }
-            }
-
-            // Try to connect to any available open network
-            if let Ok(connection) = self.connect_to_open_wifi(&interface).await {
-                return Ok(connection);
-            }
-        }
-
-        Err(anyhow::anyhow!("WiFi connection failed"))
-    }
-
-    async fn get_ethernet_interfaces(&self) -> Result<Vec<String>> {
-        let mut interfaces = Vec::new();
-        
-        // Check /sys/class/net for network interfaces
-        if let Ok(entries) = fs::read_dir("/sys/class/net") {
-            for entry in entries.flatten() {
-                let interface_name = entry.file_name().to_string_lossy().to_string();
-                
-                // Check if it's an Ethernet interface
-                if interface_name.starts_with("eth") || 
-                   interface_name.starts_with("enp") || 
-                   interface_name.starts_with("eno") {
-                    interfaces.push(interface_name);
-                }
-            }
-        }
-
-        Ok(interfaces)
-    }
-
-    async fn get_wifi_interfaces(&self) -> Result<Vec<String>> {
-        let mut interfaces = Vec::new();
-        
-        // Check /sys/class/net for wireless interfaces
-        if let Ok(entries) = fs::read_dir("/sys/class/net") {
-            for entry in entries.flatten() {
-                let interface_name = entry.file_name().to_string_lossy().to_string();
-                
-                // Check if it's a wireless interface
-                if interface_name.starts_with("wlan") || 
-                   interface_name.starts_with("wlp") || 
-                   interface_name.starts_with("wlx") {
-                    // Verify it's actually a wireless interface
-                    let wireless_path = format!("/sys/class/net/{}/wireless", interface_name);
-                    if std::path::Path::new(&wireless_path).exists() {
-                        interfaces.push(interface_name);
-                    }
-                }
-            }
-        }
-
-        Ok(interfaces)
-    }
-
-    async fn is_interface_up(&self, interface: &str) -> Result<bool> {
-        let operstate_path = format!("/sys/class/net/{}/operstate", interface);
-        
-        if let Ok(state) = fs::read_to_string(&operstate_path) {
-            Ok(state.trim() == "up")
-        } else {
-            Ok(false)
-        }
-    }
-
-    async fn get_interface_ip(&self, interface: &str) -> Result<String> {
-        // Use ip command to get interface IP
-        let output = Command::new("ip")
-            .args(&["addr", "show", interface])
-            .output()
-            .context("Failed to run ip command")?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("ip command failed"));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        
-        // Parse output to find inet address
-        for line in output_str.lines() {
-            if line.trim().starts_with("inet ") {
-                let parts: Vec<&str> = line.trim().split_whitespace().collect();
-                if parts.len() >= 2 {
-                    let ip_with_cidr = parts[1];
-                    if let Some(ip) = ip_with_cidr.split('/').next() {
-                        return Ok(ip.to_string());
-                    }
-                }
-            }
-        }
-
-        Err(anyhow::anyhow!("No IP address found for interface"))
-    }
-
-    async fn get_default_gateway(&self) -> Result<String> {
-        let output = Command::new("ip")
-            .args(&["route", "show", "default"])
-            .output()
-            .context("Failed to get default route")?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Failed to get default route"));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        
-        // Parse output to find gateway IP
-        for line in output_str.lines() {
-            if line.contains("default via") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if let Some(pos) = parts.iter().position(|&x| x == "via") {
-                    if pos + 1 < parts.len() {
-                        return Ok(parts[pos + 1].to_string());
-                    }
-                }
-            }
-        }
-
-        Err(anyhow::anyhow!("No default gateway found"))
-    }
-
-    async fn get_dns_servers(&self) -> Result<Vec<String>> {
-        let mut dns_servers = Vec::new();
-
-        // Read /etc/resolv.conf
-        if let Ok(content) = fs::read_to_string("/etc/resolv.conf") {
-            for line in content.lines() {
-                if line.starts_with("nameserver") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    if parts.len() >= 2 {
-                        dns_servers.push(parts[1].to_string());
-                    }
-                }
-            }
-        }
-
-        Ok(dns_servers)
-    }
-
-    async fn connect_to_wifi(&self, interface: &str, ssid: &str) -> Result<NetworkConnection> {
-        info!("Connecting to WiFi network: {}", ssid);
-
-        // Bring interface up
-        self.bring_interface_up(interface).await?;
-
-        // Scan for the network
-        let networks = self.scan_wifi_networks(interface).await?;
-        if !networks.iter().any(|n| n.ssid == ssid) {
-            return Err(anyhow::anyhow!("Network {} not found", ssid));
-        }
-
-        // Connect using wpa_supplicant or NetworkManager
-        if let Ok(connection) = self.connect_with_wpa_supplicant(interface, ssid).await {
-            return Ok(connection);
-        }
-
-        Err(anyhow::anyhow!("Failed to connect to WiFi network"))
-    }
-
-    async fn connect_to_open_wifi(&self, interface: &str) -> Result<NetworkConnection> {
-        info!("Scanning for open WiFi networks");
-
-        let networks = self.scan_wifi_networks(interface).await?;
-        
-        // Find open networks (no security)
-        let open_networks: Vec<&WiFiNetwork> = networks.iter()
-            .filter(|n| n.security.is_empty() || n.security == "Open")
-            .collect();
-
-        if open_networks.is_empty() {
-            return Err(anyhow::anyhow!("No open WiFi networks found"));
-        }
-
-        // Try to connect to the strongest open network
-        let best_network = open_networks.iter()
-            .max_by_key(|n| n.signal_strength)
-            .unwrap();
-
-        info!("Connecting to open network: {}", best_network.ssid);
-        
-        // Connect to open network
-        self.connect_to_open_network(interface, &best_network.ssid).await
-    }
-
-    async fn scan_wifi_networks(&self, interface: &str) -> Result<Vec<WiFiNetwork>> {
-        // Use iwlist to scan for networks
-        let output = Command::new("iwlist")
-            .args(&[interface, "scan"])
-            .output()
-            .context("Failed to scan WiFi networks")?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("WiFi scan failed"));
-        }
-
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        Ok(self.parse_iwlist_output(&output_str))
-    }
-
-    fn parse_iwlist_output(&self, output: &str) -> Vec<WiFiNetwork> {
-        let mut networks = Vec::new();
-        let mut current_network: Option<WiFiNetwork> = None;
-
-        for line in output.lines() {
-            let line = line.trim();
-            
-            if line.starts_with("Cell ") {
-                // Save previous network if exists
-                if let Some(network) = current_network.take() {
-                    networks.push(network);
-                }
-                
-                // Start new network
-                current_network = Some(WiFiNetwork {
-                    ssid: String::new(),
-                    signal_strength: 0,
-                    security: String::new(),
-                    frequency: None,
-                });
-            } else if let Some(ref mut network) = current_network {
-                if line.starts_with("ESSID:") {
-                    let ssid = line.strip_prefix("ESSID:").unwrap_or("")
-                        .trim_matches('"');
-                    network.ssid = ssid.to_string();
-                } else if line.starts_with("Quality=") {
-                    // Parse signal quality
-                    if let Some(quality_part) = line.split_whitespace().next() {
-                        if let Some(quality_str) = quality_part.strip_prefix("Quality=") {
-                            if let Some(numerator) = quality_str.split('/').next() {
-                                if let Ok(quality) = numerator.parse::<i32>() {
-                                    network.signal_strength = quality;
-                                }
-                            }
-                        }
-                    }
-                } else if line.contains("Encryption key:off") {
-                    network.security = "Open".to_string();
-                } else if line.contains("WPA") || line.contains("WEP") {
-                    network.security = "Secured".to_string();
-                }
-            }
-        }
-
-        // Add the last network
-        if let Some(network) = current_network {
-            networks.push(network);
-        }
-
-        networks
-    }
-
-    async fn bring_interface_up(&self, interface: &str) -> Result<()> {
-        let output = Command::new("ip")
-            .args(&["link", "set", interface, "up"])
-            .output()
-            .context("Failed to bring interface up")?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Failed to bring interface up"));
-        }
-
-        Ok(())
-    }
-
-    async fn connect_with_wpa_supplicant(&self, interface: &str, ssid: &str) -> Result<NetworkConnection> {
-        // This is a simplified implementation
-        // In a real implementation, you would generate wpa_supplicant.conf
-        // and manage the connection properly
-        
-        // For now, try to connect using iwconfig for open networks
-        if let Some(password) = &self.config.network.wifi_password {
-            if !password.is_empty() {
-                // TODO: Implement WPA/WPA2 connection
-                warn!("WPA/WPA2 connection not implemented yet");
-            }
-        }
-
-        self.connect_to_open_network(interface, ssid).await
-    }
-
-    async fn connect_to_open_network(&self, interface: &str, ssid: &str) -> Result<NetworkConnection> {
-        // Connect to open network using iwconfig
-        let output = Command::new("iwconfig")
-            .args(&[interface, "essid", ssid])
-            .output()
-            .context("Failed to connect to network")?;
-
-        if !output.status.success() {
-            return Err(anyhow::anyhow!("Failed to set ESSID"));
-        }
-
-        // Wait a moment for connection
-        tokio::time::sleep(Duration::from_secs(2)).await;
-
-        // Try to get IP via DHCP
-        let dhcp_output = Command::new("dhclient")
-            .arg(interface)
-            .output()
-            .context("Failed to run DHCP client")?;
-
-        // Wait for DHCP
-        tokio::time::sleep(Duration::from_secs(3)).await;
-
-        // Get IP address
-        let ip = self.get_interface_ip(interface).await?;
-
-        Ok(NetworkConnection {
-            interface: interface.to_string(),
-            connection_type: ConnectionType::WiFi,
-            ip_address: ip,
-            gateway: self.get_default_gateway().await.ok(),
-            dns_servers: self.get_dns_servers().await.unwrap_or_default(),
-            is_connected: true,
-        })
-    }
-
-    async fn test_internet_connectivity(&self) -> bool {
-        // Try to ping a reliable server
-        let ping_test = timeout(
-            Duration::from_secs(3),
-            Command::new("ping")
-                .args(&["-c", "1", "-W", "2", "8.8.8.8"])
-                .output()
-        ).await;
-
-        match ping_test {
-            Ok(Ok(output)) => {
-                let success = output.status.success();
-                debug!("Internet connectivity test: {}", if success { "passed" } else { "failed" });
-                success
-            }
-            _ => {
-                debug!("Internet connectivity test: timeout/error");
-                false
-            }
-        }
-    }
-
-    pub async fn get_local_ip(&self) -> Option<String> {
-        // Get the first non-loopback IP address
-        if let Ok(interfaces) = self.get_all_interfaces().await {
-            for interface in interfaces {
-                if interface != "lo" {
-                    if let Ok(ip) = self.get_interface_ip(&interface).await {
-                        if !ip.starts_with("127.") {
-                            return Some(ip);
-                        }
-                    }
-                }
-            }
-        }
-        None
-    }
-
-    async fn get_all_interfaces(&self) -> Result<Vec<String>> {
-        let mut interfaces = Vec::new();
-        
-        if let Ok(entries) = fs::read_dir("/sys/class/net") {
-            for entry in entries.flatten() {
-                let interface_name = entry.file_name().to_string_lossy().to_string();
-                interfaces.push(interface_name);
-            }
-        }
-
-        Ok(interfaces)
-    }
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::process::Command;
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+use crate::utils::config::Config;
+use crate::hardware::wpa_ctrl::{ScanResult, WpaCtrl};
+use crate::hardware::network_backend::{self, NetworkBackend, NetworkConnectError};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkConnection {
+    pub interface: String,
+    pub connection_type: ConnectionType,
+    pub ip_address: String,
+    pub gateway: Option<String>,
+    pub dns_servers: Vec<String>,
+    pub is_connected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ConnectionType {
+    Ethernet,
+    WiFi,
+    Hotspot,
+    Unknown,
+}
+
+/// Outcome of [`NetworkManager::check_connectivity`]: a plain ping success
+/// only proves the gateway forwards ICMP, not that real traffic reaches
+/// the internet - a captive portal (café/airport WiFi) intercepts
+/// everything else until the user signs in through its login page.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ConnectivityStatus {
+    Online,
+    CaptivePortal { login_url: String },
+    Offline,
+}
+
+/// A small fixed plaintext resource most captive-portal checks use: any
+/// response other than exactly `204 No Content` with an empty body means
+/// something intercepted the request (either a redirect to a login page,
+/// or a portal answering `200` directly on the same URL).
+const CONNECTIVITY_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+pub struct NetworkManager {
+    config: Config,
+    /// Selected by `network_backend::detect_backend`: `wpa_supplicant`
+    /// keeps using the rich `WpaCtrl`-based flow below directly (it has
+    /// its own failover/backup-network logic this trait doesn't model
+    /// yet), while `nmcli`/`legacy` drive WiFi entirely through the
+    /// trait's generic `scan`/`connect`.
+    backend: Box<dyn NetworkBackend>,
+}
+
+impl NetworkManager {
+    pub fn new(config: &Config) -> Self {
+        let backend = network_backend::detect_backend(config);
+        info!("🔌 Selected network backend: {}", backend.name());
+        NetworkManager {
+            config: config.clone(),
+            backend,
+        }
+    }
+
+    pub async fn connect(&self) -> Result<NetworkConnection> {
+        info!("Starting network connection process");
+
+        // Try Ethernet first if preferred
+        if self.config.network.ethernet_preferred {
+            if let Ok(connection) = self.try_ethernet_connection().await {
+                info!("Connected via Ethernet: {}", connection.ip_address);
+                return Ok(connection);
+            }
+        }
+
+        // Try WiFi connection
+        if let Ok(connection) = self.try_wifi_connection().await {
+            info!("Connected via WiFi: {}", connection.ip_address);
+            return Ok(connection);
+        }
+
+        // Try Ethernet as fallback if not preferred
+        if !self.config.network.ethernet_preferred {
+            if let Ok(connection) = self.try_ethernet_connection().await {
+                info!("Connected via Ethernet (fallback): {}", connection.ip_address);
+                return Ok(connection);
+            }
+        }
+
+        Err(anyhow::anyhow!("Failed to establish any network connection"))
+    }
+
+    async fn try_ethernet_connection(&self) -> Result<NetworkConnection> {
+        info!("Attempting Ethernet connection");
+
+        // Check if Ethernet interface exists
+        let eth_interfaces = self.get_ethernet_interfaces().await?;
+        if eth_interfaces.is_empty() {
+            return Err(anyhow::anyhow!("No Ethernet interfaces found"));
+        }
+
+        for interface in eth_interfaces {
+            debug!("Checking Ethernet interface: {}", interface);
+            
+            // Check if interface is up and has link
+            if self.is_interface_up(&interface).await? {
+                // Try to get IP address
+                if let Ok(ip) = self.get_interface_ip(&interface).await {
+                    let connection = NetworkConnection {
+                        interface: interface.clone(),
+                        connection_type: ConnectionType::Ethernet,
+                        ip_address: ip,
+                        gateway: self.get_default_gateway().await.ok(),
+                        dns_servers: self.get_dns_servers().await.unwrap_or_default(),
+                        is_connected: true,
+                    };
+                    
+                    // Test connectivity
+                    match self.check_connectivity().await {
+                        ConnectivityStatus::Online => return Ok(connection),
+                        ConnectivityStatus::CaptivePortal { login_url } => {
+                            warn!("Ethernet network {} is behind a captive portal, needs login at {}", interface, login_url);
+                            return Ok(connection);
+                        }
+                        ConnectivityStatus::Offline => {}
+                    }
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("Ethernet connection failed"))
+    }
+
+    async fn try_wifi_connection(&self) -> Result<NetworkConnection> {
+        info!("Attempting WiFi connection");
+
+        // Check if WiFi interface exists
+        let wifi_interfaces = self.get_wifi_interfaces().await?;
+        if wifi_interfaces.is_empty() {
+            return Err(anyhow::anyhow!("No WiFi interfaces found"));
+        }
+
+        let mut last_error = anyhow::anyhow!("WiFi connection failed");
+        for interface in wifi_interfaces {
+            debug!("Checking WiFi interface: {}", interface);
+            self.bring_interface_up(&interface).await?;
+
+            let result = if self.backend.name() == "wpa_supplicant" {
+                self.connect_via_wpa_supplicant(&interface).await
+            } else {
+                self.connect_via_backend(&interface).await
+            };
+
+            match result {
+                Ok(connection) => return Ok(connection),
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// WiFi join path for the `nmcli`/`legacy` backends: same candidate
+    /// ordering as `connect_via_wpa_supplicant` (configured SSID, then
+    /// `backup_networks`, then the strongest open network from a fresh
+    /// scan), but driven through the generic [`NetworkBackend`] trait
+    /// instead of talking to `wpa_supplicant`'s socket directly.
+    async fn connect_via_backend(&self, interface: &str) -> Result<NetworkConnection> {
+        let mut candidates: Vec<(String, Option<String>)> = Vec::new();
+        if let Some(ssid) = &self.config.network.wifi_ssid {
+            if !ssid.is_empty() {
+                candidates.push((ssid.clone(), self.config.network.wifi_password.clone()));
+            }
+        }
+        for backup in &self.config.network.backup_networks {
+            candidates.push((backup.ssid.clone(), Some(backup.password.clone())));
+        }
+
+        for (ssid, password) in &candidates {
+            match self.backend.connect(interface, ssid, password.as_deref()) {
+                Ok(connection) => return Ok(connection),
+                Err(e) => warn!("Failed to connect to network {} via {}: {}", ssid, self.backend.name(), e),
+            }
+        }
+
+        if self.config.network.auto_scan_open_networks {
+            let mut open_networks: Vec<_> = self
+                .backend
+                .scan(interface)?
+                .into_iter()
+                .filter(|n| n.is_open() && !n.ssid.is_empty())
+                .collect();
+            open_networks.sort_by_key(|n| std::cmp::Reverse(n.signal));
+
+            for network in open_networks {
+                if let Ok(connection) = self.backend.connect(interface, &network.ssid, None) {
+                    return Ok(connection);
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Failed to connect to any WiFi network on interface {} using {}",
+            interface,
+            self.backend.name()
+        ))
+    }
+
+    /// Connects `interface` using wpa_supplicant's control socket
+    /// ([`WpaCtrl`]), trying the configured network, then each
+    /// `backup_networks` entry in order, then (if
+    /// `auto_scan_open_networks` is enabled) the strongest open network
+    /// found by a fresh scan.
+    async fn connect_via_wpa_supplicant(&self, interface: &str) -> Result<NetworkConnection> {
+        let ctrl = WpaCtrl::open(interface)
+            .with_context(|| format!("Failed to open wpa_supplicant control socket for interface {}", interface))?;
+
+        let mut candidates: Vec<(String, Option<String>)> = Vec::new();
+        if let Some(ssid) = &self.config.network.wifi_ssid {
+            if !ssid.is_empty() {
+                candidates.push((ssid.clone(), self.config.network.wifi_password.clone()));
+            }
+        }
+        for backup in &self.config.network.backup_networks {
+            candidates.push((backup.ssid.clone(), Some(backup.password.clone())));
+        }
+
+        // If we're already associated with one of the saved networks,
+        // there's no need to reconfigure - this is exactly what
+        // prefer_saved_networks asks for.
+        if self.config.network.prefer_saved_networks {
+            if let Ok(status) = ctrl.status() {
+                let already_associated = status.get("wpa_state").map(String::as_str) == Some("COMPLETED")
+                    && status
+                        .get("ssid")
+                        .map(|current| candidates.iter().any(|(ssid, _)| ssid == current))
+                        .unwrap_or(false);
+
+                if already_associated {
+                    info!("Interface {} is already associated with a saved network", interface);
+                    if let Ok(connection) = self.finish_dhcp(interface, &ctrl).await {
+                        return Ok(connection);
+                    }
+                }
+            }
+        }
+
+        for (ssid, password) in &candidates {
+            match self.try_join_ssid(&ctrl, interface, ssid, password.as_deref()).await {
+                Ok(connection) => return Ok(connection),
+                Err(e) => warn!("Failed to connect to network {}: {}", ssid, e),
+            }
+        }
+
+        if self.config.network.auto_scan_open_networks {
+            if let Ok(connection) = self.try_open_networks(&ctrl, interface).await {
+                return Ok(connection);
+            }
+        }
+
+        Err(anyhow::anyhow!(
+            "Failed to connect to any WiFi network on interface {}",
+            interface
+        ))
+    }
+
+    /// Configures and selects `ssid` via `ctrl`, retrying up to
+    /// `max_connection_attempts` times while waiting for association,
+    /// then finishes with DHCP. Removes the network block on failure so
+    /// it doesn't linger in wpa_supplicant's config.
+    async fn try_join_ssid(
+        &self,
+        ctrl: &WpaCtrl,
+        interface: &str,
+        ssid: &str,
+        password: Option<&str>,
+    ) -> Result<NetworkConnection> {
+        info!("Attempting to connect to WiFi network: {}", ssid);
+        let network_id = ctrl.configure_network(ssid, password)?;
+
+        for attempt in 1..=self.config.network.max_connection_attempts {
+            debug!("Attempt {} of {} for network {}", attempt, self.config.network.max_connection_attempts, ssid);
+
+            if self.wait_for_association(ctrl, ssid).await {
+                if let Ok(connection) = self.finish_dhcp(interface, ctrl).await {
+                    if self.config.network.persist_wifi_config {
+                        if let Err(e) = ctrl.save_config() {
+                            warn!("Failed to save wpa_supplicant config for network {}: {}", ssid, e);
+                        }
+                    }
+                    return Ok(connection);
+                }
+            }
+        }
+
+        let _ = ctrl.remove_network(network_id);
+        Err(anyhow::anyhow!("All connection attempts to network {} failed", ssid))
+    }
+
+    /// Polls `STATUS` until `wpa_state=COMPLETED` against `ssid`, or the
+    /// configured connection timeout elapses.
+    async fn wait_for_association(&self, ctrl: &WpaCtrl, ssid: &str) -> bool {
+        let deadline = Duration::from_secs(self.config.network.connection_timeout_seconds);
+        let poll_interval = Duration::from_millis(500);
+        let mut waited = Duration::ZERO;
+
+        while waited < deadline {
+            if let Ok(status) = ctrl.status() {
+                let associated = status.get("wpa_state").map(String::as_str) == Some("COMPLETED")
+                    && status.get("ssid").map(String::as_str) == Some(ssid);
+                if associated {
+                    return true;
+                }
+            }
+
+            tokio::time::sleep(poll_interval).await;
+            waited += poll_interval;
+        }
+
+        false
+    }
+
+    /// Runs DHCP on `interface` after association and builds the
+    /// resulting [`NetworkConnection`]. `ctrl` is unused here directly
+    /// but kept for symmetry with the rest of the join flow and future
+    /// use (e.g. re-checking `STATUS` after DHCP).
+    async fn finish_dhcp(&self, interface: &str, _ctrl: &WpaCtrl) -> Result<NetworkConnection> {
+        let dhcp_output = Command::new("dhclient")
+            .arg(interface)
+            .output()
+            .context("Failed to run DHCP client")?;
+        if !dhcp_output.status.success() {
+            return Err(anyhow::anyhow!("DHCP request failed on interface {}", interface));
+        }
+
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        let ip = self.get_interface_ip(interface).await?;
+
+        Ok(NetworkConnection {
+            interface: interface.to_string(),
+            connection_type: ConnectionType::WiFi,
+            ip_address: ip,
+            gateway: self.get_default_gateway().await.ok(),
+            dns_servers: self.get_dns_servers().await.unwrap_or_default(),
+            is_connected: true,
+        })
+    }
+
+    /// Scans for open networks and tries the strongest one first.
+    async fn try_open_networks(&self, ctrl: &WpaCtrl, interface: &str) -> Result<NetworkConnection> {
+        info!("Scanning for open WiFi networks on interface {}", interface);
+        ctrl.scan()?;
+        tokio::time::sleep(Duration::from_secs(3)).await;
+
+        let mut open_networks: Vec<_> = ctrl
+            .scan_results()?
+            .into_iter()
+            .filter(|n| n.is_open() && !n.ssid.is_empty())
+            .collect();
+        open_networks.sort_by_key(|n| std::cmp::Reverse(n.signal));
+
+        for network in open_networks {
+            if let Ok(connection) = self.try_join_ssid(ctrl, interface, &network.ssid, None).await {
+                // Open networks (cafes/airports) are the most likely to
+                // carry a captive portal, so check for one explicitly here,
+                // unlike the saved/secured network path.
+                match self.check_connectivity().await {
+                    ConnectivityStatus::CaptivePortal { login_url } => {
+                        warn!(
+                            "Open network {} is behind a captive portal, needs login at {}",
+                            network.ssid, login_url
+                        );
+                    }
+                    ConnectivityStatus::Online => {
+                        info!("Open network {} actually has internet connectivity", network.ssid);
+                    }
+                    ConnectivityStatus::Offline => {}
+                }
+                return Ok(connection);
+            }
+        }
+
+        Err(anyhow::anyhow!("No usable open network found to connect to"))
+    }
+
+    async fn get_ethernet_interfaces(&self) -> Result<Vec<String>> {
+        let mut interfaces = Vec::new();
+        
+        // Check /sys/class/net for network interfaces
+        if let Ok(entries) = fs::read_dir("/sys/class/net") {
+            for entry in entries.flatten() {
+                let interface_name = entry.file_name().to_string_lossy().to_string();
+                
+                // Check if it's an Ethernet interface
+                if interface_name.starts_with("eth") || 
+                   interface_name.starts_with("enp") || 
+                   interface_name.starts_with("eno") {
+                    interfaces.push(interface_name);
+                }
+            }
+        }
+
+        Ok(interfaces)
+    }
+
+    async fn get_wifi_interfaces(&self) -> Result<Vec<String>> {
+        let mut interfaces = Vec::new();
+        
+        // Check /sys/class/net for wireless interfaces
+        if let Ok(entries) = fs::read_dir("/sys/class/net") {
+            for entry in entries.flatten() {
+                let interface_name = entry.file_name().to_string_lossy().to_string();
+                
+                // Check if it's a wireless interface
+                if interface_name.starts_with("wlan") || 
+                   interface_name.starts_with("wlp") || 
+                   interface_name.starts_with("wlx") {
+                    // Verify it's actually a wireless interface
+                    let wireless_path = format!("/sys/class/net/{}/wireless", interface_name);
+                    if std::path::Path::new(&wireless_path).exists() {
+                        interfaces.push(interface_name);
+                    }
+                }
+            }
+        }
+
+        Ok(interfaces)
+    }
+
+    async fn is_interface_up(&self, interface: &str) -> Result<bool> {
+        let operstate_path = format!("/sys/class/net/{}/operstate", interface);
+        
+        if let Ok(state) = fs::read_to_string(&operstate_path) {
+            Ok(state.trim() == "up")
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn get_interface_ip(&self, interface: &str) -> Result<String> {
+        // Use ip command to get interface IP
+        let output = Command::new("ip")
+            .args(&["addr", "show", interface])
+            .output()
+            .context("Failed to run ip command")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("ip command failed"));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        
+        // Parse output to find inet address
+        for line in output_str.lines() {
+            if line.trim().starts_with("inet ") {
+                let parts: Vec<&str> = line.trim().split_whitespace().collect();
+                if parts.len() >= 2 {
+                    let ip_with_cidr = parts[1];
+                    if let Some(ip) = ip_with_cidr.split('/').next() {
+                        return Ok(ip.to_string());
+                    }
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("No IP address found for interface"))
+    }
+
+    async fn get_default_gateway(&self) -> Result<String> {
+        let output = Command::new("ip")
+            .args(&["route", "show", "default"])
+            .output()
+            .context("Failed to get default route")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to get default route"));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        
+        // Parse output to find gateway IP
+        for line in output_str.lines() {
+            if line.contains("default via") {
+                let parts: Vec<&str> = line.split_whitespace().collect();
+                if let Some(pos) = parts.iter().position(|&x| x == "via") {
+                    if pos + 1 < parts.len() {
+                        return Ok(parts[pos + 1].to_string());
+                    }
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("No default gateway found"))
+    }
+
+    async fn get_dns_servers(&self) -> Result<Vec<String>> {
+        let mut dns_servers = Vec::new();
+
+        // Read /etc/resolv.conf
+        if let Ok(content) = fs::read_to_string("/etc/resolv.conf") {
+            for line in content.lines() {
+                if line.starts_with("nameserver") {
+                    let parts: Vec<&str> = line.split_whitespace().collect();
+                    if parts.len() >= 2 {
+                        dns_servers.push(parts[1].to_string());
+                    }
+                }
+            }
+        }
+
+        Ok(dns_servers)
+    }
+
+    async fn bring_interface_up(&self, interface: &str) -> Result<()> {
+        let output = Command::new("ip")
+            .args(&["link", "set", interface, "up"])
+            .output()
+            .context("Failed to bring interface up")?;
+
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to bring interface up"));
+        }
+
+        Ok(())
+    }
+
+    /// Pings a reliable server first (cheap, confirms the gateway forwards
+    /// packets at all), then - only if that succeeds - fetches
+    /// [`CONNECTIVITY_CHECK_URL`] to rule out a captive portal: a portal
+    /// either redirects that request to its login page or answers `200`
+    /// on the same URL instead of the expected bare `204`.
+    pub async fn check_connectivity(&self) -> ConnectivityStatus {
+        let ping_test = timeout(
+            Duration::from_secs(3),
+            Command::new("ping")
+                .args(&["-c", "1", "-W", "2", "8.8.8.8"])
+                .output()
+        ).await;
+
+        let ping_ok = matches!(ping_test, Ok(Ok(output)) if output.status.success());
+        debug!("Internet connectivity ping test: {}", if ping_ok { "passed" } else { "failed" });
+        if !ping_ok {
+            return ConnectivityStatus::Offline;
+        }
+
+        self.check_captive_portal().await
+    }
+
+    async fn check_captive_portal(&self) -> ConnectivityStatus {
+        let client = match reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build() {
+            Ok(client) => client,
+            Err(_) => return ConnectivityStatus::Online,
+        };
+
+        let response = timeout(Duration::from_secs(5), client.get(CONNECTIVITY_CHECK_URL).send()).await;
+        match response {
+            Ok(Ok(response)) => {
+                if response.status() == reqwest::StatusCode::NO_CONTENT {
+                    ConnectivityStatus::Online
+                } else if response.status().is_redirection() {
+                    let login_url = response
+                        .headers()
+                        .get(reqwest::header::LOCATION)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or(CONNECTIVITY_CHECK_URL)
+                        .to_string();
+                    ConnectivityStatus::CaptivePortal { login_url }
+                } else {
+                    // A direct 200 response on the check URL itself - the
+                    // pattern of captive portals that intercept the request
+                    // without a visible redirect.
+                    ConnectivityStatus::CaptivePortal {
+                        login_url: response.url().to_string(),
+                    }
+                }
+            }
+            _ => {
+                // Unreachable even over HTTP despite a successful ping -
+                // closer to a real outage (a firewall blocking HTTP
+                // specifically) than a captive portal.
+                debug!("Captive portal check failed despite a successful ping - treating as offline");
+                ConnectivityStatus::Offline
+            }
+        }
+    }
+
+    pub async fn get_local_ip(&self) -> Option<String> {
+        self.active_interface().await.map(|(_, ip)| ip)
+    }
+
+    /// Name and IP of the first non-loopback interface with an address -
+    /// the same one `get_local_ip` picks, but also returning the
+    /// interface name for callers (like the web telemetry endpoint) that
+    /// need it to sample throughput/signal strength too.
+    pub async fn active_interface(&self) -> Option<(String, String)> {
+        if let Ok(interfaces) = self.get_all_interfaces().await {
+            for interface in interfaces {
+                if interface != "lo" {
+                    if let Ok(ip) = self.get_interface_ip(&interface).await {
+                        if !ip.starts_with("127.") {
+                            return Some((interface, ip));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Live AP scan on the first WiFi interface, through whichever
+    /// [`NetworkBackend`] was selected (so the web UI's network picker
+    /// sees the same real SSID/signal/flags data `connect_via_backend`
+    /// already scans with, instead of hand-rolled mocks).
+    pub async fn scan_networks(&self) -> Result<Vec<ScanResult>> {
+        let interfaces = self.get_wifi_interfaces().await?;
+        let interface = interfaces
+            .first()
+            .ok_or_else(|| NetworkConnectError::NoInterface("No WiFi interfaces found".to_string()))?;
+        self.backend.scan(interface)
+    }
+
+    /// Joins `ssid` on the first WiFi interface and returns the resulting
+    /// [`NetworkConnection`] - the same backend call `connect_via_backend`
+    /// makes for the configured/backup networks, just driven directly by
+    /// a caller-supplied SSID/password (the web UI's "connect" button).
+    pub async fn connect_to_network(&self, ssid: &str, password: Option<&str>) -> Result<NetworkConnection> {
+        let interfaces = self.get_wifi_interfaces().await?;
+        let interface = interfaces
+            .first()
+            .ok_or_else(|| NetworkConnectError::NoInterface("No WiFi interfaces found".to_string()))?;
+        self.bring_interface_up(interface).await?;
+        self.backend.connect(interface, ssid, password)
+    }
+
+    async fn get_all_interfaces(&self) -> Result<Vec<String>> {
+        let mut interfaces = Vec::new();
+
+        if let Ok(entries) = fs::read_dir("/sys/class/net") {
+            for entry in entries.flatten() {
+                let interface_name = entry.file_name().to_string_lossy().to_string();
+                interfaces.push(interface_name);
+            }
+        }
+
+        Ok(interfaces)
+    }
+
+    /// Reads cumulative byte counters straight from the kernel, the same
+    /// data peach-network surfaces (`/sys/class/net/<iface>/statistics/`
+    /// is populated for every interface, wired or wireless, with no extra
+    /// tooling required).
+    pub fn get_traffic(&self, interface: &str) -> Result<Traffic> {
+        let received = read_stat_counter(interface, "rx_bytes")?;
+        let transmitted = read_stat_counter(interface, "tx_bytes")?;
+        Ok(Traffic { received, transmitted })
+    }
+
+    /// Snapshots `interface`'s counters, waits `sample_window`, snapshots
+    /// again, and reports both the cumulative totals and the throughput
+    /// over that window. A metered hotspot link is the reason this
+    /// exists - operators need to see usage building up, not just
+    /// whether the link is up.
+    pub async fn sample_throughput(&self, interface: &str, sample_window: Duration) -> Result<ThroughputSample> {
+        let start = self.get_traffic(interface)?;
+        tokio::time::sleep(sample_window).await;
+        let end = self.get_traffic(interface)?;
+
+        let elapsed_secs = sample_window.as_secs_f64().max(f64::EPSILON);
+        let received_per_sec = (end.received.saturating_sub(start.received) as f64 / elapsed_secs) as u64;
+        let transmitted_per_sec = (end.transmitted.saturating_sub(start.transmitted) as f64 / elapsed_secs) as u64;
+
+        Ok(ThroughputSample {
+            interface: interface.to_string(),
+            cumulative: end,
+            received_bytes_per_sec: received_per_sec,
+            transmitted_bytes_per_sec: transmitted_per_sec,
+        })
+    }
+}
+
+/// Cumulative byte counters for one interface since it was brought up
+/// (or since boot), from `/sys/class/net/<iface>/statistics/`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Traffic {
+    pub received: u64,
+    pub transmitted: u64,
+}
+
+/// Result of [`NetworkManager::sample_throughput`]: cumulative counters
+/// plus the rate observed over the sampling window, ready to hand to the
+/// crate's web telemetry endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThroughputSample {
+    pub interface: String,
+    pub cumulative: Traffic,
+    pub received_bytes_per_sec: u64,
+    pub transmitted_bytes_per_sec: u64,
+}
+
+impl NetworkManager {
+    const HOTSPOT_IP: &'static str = "192.168.4.1";
+    const HOTSPOT_DHCP_RANGE: &'static str = "192.168.4.10,192.168.4.100,255.255.255.0,24h";
+
+    /// Switches the first available WiFi interface from client mode into
+    /// AP mode, the same way peach-network's client↔AP handoff works:
+    /// stop anything already managing that interface's WiFi (wpa_supplicant/
+    /// NetworkManager), reconfigure it with a static address, then bring
+    /// up `hostapd` (beacons the network) and `dnsmasq` (hands out leases
+    /// and acts as the clients' DNS) against it. Returns a
+    /// [`NetworkConnection`] with `connection_type: Hotspot` so callers can
+    /// treat it the same as any other successful connection result.
+    pub async fn start_hotspot(&self, ssid: &str, passphrase: &str, channel: u8) -> Result<NetworkConnection> {
+        let interface = self
+            .get_wifi_interfaces()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("No WiFi interface available to start a hotspot"))?;
+
+        info!("🔥 Starting hotspot '{}' on interface {}", ssid, interface);
+        self.stop_client_mode_services(&interface).await;
+
+        let hostapd_conf = self.write_hostapd_config(&interface, ssid, passphrase, channel)?;
+        let dnsmasq_conf = self.write_dnsmasq_config(&interface)?;
+
+        self.configure_hotspot_address(&interface).await?;
+
+        let hostapd_status = Command::new("hostapd")
+            .args(["-B", &hostapd_conf])
+            .output()
+            .context("Failed to run hostapd")?;
+        if !hostapd_status.status.success() {
+            return Err(anyhow::anyhow!(
+                "hostapd refused to start the hotspot: {}",
+                String::from_utf8_lossy(&hostapd_status.stderr).trim()
+            ));
+        }
+
+        let dnsmasq_status = Command::new("dnsmasq")
+            .args(["-C", &dnsmasq_conf])
+            .output()
+            .context("Failed to run dnsmasq")?;
+        if !dnsmasq_status.status.success() {
+            let _ = self.stop_hotspot(&interface).await;
+            return Err(anyhow::anyhow!(
+                "dnsmasq refused to start the DHCP server: {}",
+                String::from_utf8_lossy(&dnsmasq_status.stderr).trim()
+            ));
+        }
+
+        Ok(NetworkConnection {
+            interface,
+            connection_type: ConnectionType::Hotspot,
+            ip_address: Self::HOTSPOT_IP.to_string(),
+            gateway: Some(Self::HOTSPOT_IP.to_string()),
+            dns_servers: vec![Self::HOTSPOT_IP.to_string()],
+            is_connected: true,
+        })
+    }
+
+    /// Tears down `hostapd`/`dnsmasq` on `interface` and clears its static
+    /// address so `try_wifi_connection` can reclaim it as a client again.
+    /// The link state is left as it is.
+    pub async fn stop_hotspot(&self, interface: &str) -> Result<()> {
+        info!("🛑 Stopping hotspot on interface {}", interface);
+        let _ = Command::new("pkill").args(["-f", &format!("hostapd.*{}", interface)]).output();
+        let _ = Command::new("pkill").args(["-f", &format!("dnsmasq.*{}", interface)]).output();
+
+        let output = Command::new("ip")
+            .args(["addr", "flush", "dev", interface])
+            .output()
+            .context("Failed to flush interface address")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to flush address on interface {}", interface));
+        }
+
+        Ok(())
+    }
+
+    /// Stops whatever is already managing `interface` in client mode
+    /// before handing it to `hostapd` - both `wpa_supplicant` (our own
+    /// `WpaSupplicantBackend`) and NetworkManager (`NmcliBackend`) would
+    /// otherwise fight hostapd for the same radio.
+    async fn stop_client_mode_services(&self, interface: &str) {
+        let _ = Command::new("nmcli").args(["dev", "set", interface, "managed", "no"]).output();
+        let _ = Command::new("pkill").args(["-f", &format!("wpa_supplicant.*-i {}", interface)]).output();
+    }
+
+    fn write_hostapd_config(&self, interface: &str, ssid: &str, passphrase: &str, channel: u8) -> Result<String> {
+        Self::validate_hotspot_credentials(ssid, passphrase)?;
+
+        let path = format!("/tmp/dos_safar_hostapd_{}.conf", interface);
+        let body = format!(
+            "interface={interface}\n\
+             driver=nl80211\n\
+             ssid={ssid}\n\
+             hw_mode=g\n\
+             channel={channel}\n\
+             auth_algs=1\n\
+             wpa=2\n\
+             wpa_passphrase={passphrase}\n\
+             wpa_key_mgmt=WPA-PSK\n\
+             rsn_pairwise=CCMP\n",
+            interface = interface,
+            ssid = ssid,
+            channel = channel,
+            passphrase = passphrase,
+        );
+        fs::write(&path, body).with_context(|| format!("Failed to write hostapd config: {}", path))?;
+        Self::restrict_to_owner(&path)?;
+        Ok(path)
+    }
+
+    /// Sets `path`'s permissions to `0600` right after writing it -
+    /// `hostapd.conf` carries the WPA-PSK passphrase in plaintext, and
+    /// without this it's written with the default umask permissions
+    /// (world-readable on many embedded OS images) under `/tmp`, which
+    /// every user/process on the device shares.
+    fn restrict_to_owner(path: &str) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("Failed to set permissions on {}", path))
+    }
+
+    /// Rejects any `ssid`/`passphrase` containing a control character
+    /// (including `\n`) or `#` before splicing them into `hostapd.conf` -
+    /// an injected newline breaks the current `key=value` line and injects
+    /// extra hostapd directives, and `#` starts a comment that silently
+    /// truncates the rest of the line. Also enforces the WPA-PSK passphrase
+    /// length (8-63 characters) that hostapd itself requires.
+    fn validate_hotspot_credentials(ssid: &str, passphrase: &str) -> Result<()> {
+        for (label, value) in [("SSID", ssid), ("passphrase", passphrase)] {
+            if value.chars().any(|c| c.is_control() || c == '#') {
+                return Err(anyhow::anyhow!(
+                    "{} contains a control character or '#', not allowed in hostapd config",
+                    label
+                ));
+            }
+        }
+
+        if !(8..=63).contains(&passphrase.len()) {
+            return Err(anyhow::anyhow!(
+                "WPA-PSK passphrase must be between 8 and 63 characters (current length: {})",
+                passphrase.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn write_dnsmasq_config(&self, interface: &str) -> Result<String> {
+        let path = format!("/tmp/dos_safar_dnsmasq_{}.conf", interface);
+        let body = format!(
+            "interface={interface}\n\
+             bind-interfaces\n\
+             dhcp-range={range}\n\
+             dhcp-option=3,{gateway}\n\
+             dhcp-option=6,{gateway}\n",
+            interface = interface,
+            range = Self::HOTSPOT_DHCP_RANGE,
+            gateway = Self::HOTSPOT_IP,
+        );
+        fs::write(&path, body).with_context(|| format!("Failed to write dnsmasq config: {}", path))?;
+        Ok(path)
+    }
+
+    async fn configure_hotspot_address(&self, interface: &str) -> Result<()> {
+        let _ = Command::new("ip").args(["addr", "flush", "dev", interface]).output();
+
+        let output = Command::new("ip")
+            .args(["addr", "add", &format!("{}/24", Self::HOTSPOT_IP), "dev", interface])
+            .output()
+            .context("Failed to assign a static address to the hotspot interface")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("Failed to assign a static address on interface {}", interface));
+        }
+
+        self.bring_interface_up(interface).await
+    }
+}
+
+fn read_stat_counter(interface: &str, counter: &str) -> Result<u64> {
+    let path = format!("/sys/class/net/{}/statistics/{}", interface, counter);
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read traffic counter: {}", path))?;
+    content
+        .trim()
+        .parse()
+        .with_context(|| format!("Non-numeric value in {}: '{}'", path, content.trim()))
 }
\ No newline at end of file
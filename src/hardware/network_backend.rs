@@ -0,0 +1,499 @@
+// Pluggable network backend abstraction, so `NetworkManager` doesn't assume
+// every host manages WiFi via a raw wpa_supplicant control socket.
+// `detect_backend` probes the running system (or honors an explicit
+// `config.network.backend` override) and hands back whichever of
+// `WpaSupplicantBackend`, `NmcliBackend`, or `LegacyBackend` actually
+// applies, so the same `NetworkManager` code works on a plain Raspberry Pi
+// OS image, a desktop distro running NetworkManager, and a minimal image
+// with neither.
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::hardware::wpa_ctrl::{ScanResult, WpaCtrl};
+use crate::hardware::network::{ConnectionType, NetworkConnection};
+use crate::utils::config::Config;
+
+/// Distinguishes why a connection attempt failed, so callers like
+/// `remote::web_server::connect_network` can map failure classes to
+/// distinct HTTP responses instead of string-matching translated error
+/// text. Carried as an ordinary `anyhow::Error` like every other error in
+/// this module (`.into()` at the call site); recover it with
+/// `error.downcast_ref::<NetworkConnectError>()`.
+#[derive(Debug)]
+pub enum NetworkConnectError {
+    /// Association/authentication itself failed: wrong password, AP
+    /// rejected the attempt, or association timed out.
+    AuthFailed(String),
+    /// No WiFi radio was available to even attempt the connection.
+    NoInterface(String),
+    /// Associated successfully but never obtained a usable IP lease.
+    DhcpFailed(String),
+}
+
+impl std::fmt::Display for NetworkConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkConnectError::AuthFailed(message)
+            | NetworkConnectError::NoInterface(message)
+            | NetworkConnectError::DhcpFailed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for NetworkConnectError {}
+
+/// One WiFi connectivity strategy. Every method is synchronous (like
+/// [`WpaCtrl`] itself, which blocks on its control-socket datagrams) -
+/// callers already run these from within `tokio::task`-friendly async
+/// functions and don't need to `.await` them directly.
+pub trait NetworkBackend: Send + Sync {
+    /// Short identifier for logging (`"wpa_supplicant"`, `"nmcli"`, `"legacy"`).
+    fn name(&self) -> &'static str;
+
+    /// Scans `interface` and returns discovered networks.
+    fn scan(&self, interface: &str) -> Result<Vec<ScanResult>>;
+
+    /// Joins `ssid` on `interface` (open network if `password` is `None`
+    /// or empty) and waits for an IP lease, returning the resulting
+    /// [`NetworkConnection`].
+    fn connect(&self, interface: &str, ssid: &str, password: Option<&str>) -> Result<NetworkConnection>;
+
+    /// Reads back the current IPv4 address of `interface`, if any.
+    fn get_ip(&self, interface: &str) -> Result<String>;
+
+    /// Tears down whatever association/lease `connect` established.
+    fn disconnect(&self, interface: &str) -> Result<()>;
+}
+
+/// Primary backend: talks to `wpa_supplicant`'s own control socket
+/// directly via [`WpaCtrl`], same as the rest of this module already did
+/// before backend selection existed.
+pub struct WpaSupplicantBackend {
+    pub connection_timeout_seconds: u64,
+}
+
+impl NetworkBackend for WpaSupplicantBackend {
+    fn name(&self) -> &'static str {
+        "wpa_supplicant"
+    }
+
+    fn scan(&self, interface: &str) -> Result<Vec<ScanResult>> {
+        let ctrl = WpaCtrl::open(interface)?;
+        ctrl.scan()?;
+        std::thread::sleep(Duration::from_secs(3));
+        ctrl.scan_results()
+    }
+
+    fn connect(&self, interface: &str, ssid: &str, password: Option<&str>) -> Result<NetworkConnection> {
+        let ctrl = WpaCtrl::open(interface)?;
+        let network_id = ctrl.configure_network(ssid, password)?;
+
+        let deadline = Duration::from_secs(self.connection_timeout_seconds);
+        let poll_interval = Duration::from_millis(500);
+        let mut waited = Duration::ZERO;
+        let mut associated = false;
+        while waited < deadline {
+            if let Ok(status) = ctrl.status() {
+                associated = status.get("wpa_state").map(String::as_str) == Some("COMPLETED")
+                    && status.get("ssid").map(String::as_str) == Some(ssid);
+                if associated {
+                    break;
+                }
+            }
+            std::thread::sleep(poll_interval);
+            waited += poll_interval;
+        }
+
+        if !associated {
+            let _ = ctrl.remove_network(network_id);
+            return Err(NetworkConnectError::AuthFailed(format!(
+                "لم يرتبط wpa_supplicant بشبكة {} قبل انتهاء المهلة",
+                ssid
+            ))
+            .into());
+        }
+
+        run_dhclient(interface)?;
+        let ip = self.get_ip(interface)?;
+        Ok(NetworkConnection {
+            interface: interface.to_string(),
+            connection_type: ConnectionType::WiFi,
+            ip_address: ip,
+            gateway: read_default_gateway(),
+            dns_servers: read_dns_servers(),
+            is_connected: true,
+        })
+    }
+
+    fn get_ip(&self, interface: &str) -> Result<String> {
+        read_interface_ip(interface)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        let ctrl = WpaCtrl::open(interface)?;
+        ctrl.disconnect()
+    }
+}
+
+/// Backend for hosts managed by NetworkManager (desktop distros, most
+/// Ubuntu/Debian installs) instead of a bare `wpa_supplicant` instance -
+/// `nmcli` already owns the control socket there, so speaking to it
+/// directly would fight NetworkManager for the interface.
+///
+/// This is a scoped-down substitute for the D-Bus client originally
+/// requested: it shells out to `nmcli` and text-parses its `-t` (terse,
+/// script-friendly) output rather than going through `zbus`/`dbus-rs`
+/// against NetworkManager's own D-Bus API, and it doesn't poll
+/// `ActiveConnection` state directly - `connect` below reads back the
+/// interface's IP once `nmcli dev wifi connect` returns instead. That
+/// means behavior depends on `nmcli`'s CLI output format staying stable
+/// across NetworkManager versions/locales, which typed D-Bus properties
+/// would have avoided. Revisit as a real D-Bus backend if that ever bites.
+pub struct NmcliBackend;
+
+impl NetworkBackend for NmcliBackend {
+    fn name(&self) -> &'static str {
+        "nmcli"
+    }
+
+    fn scan(&self, interface: &str) -> Result<Vec<ScanResult>> {
+        let _ = Command::new("nmcli")
+            .args(["dev", "wifi", "rescan", "ifname", interface])
+            .output();
+        std::thread::sleep(Duration::from_secs(2));
+
+        let output = Command::new("nmcli")
+            .args([
+                "-t",
+                "-f",
+                "SSID,BSSID,FREQ,SIGNAL,SECURITY",
+                "dev",
+                "wifi",
+                "list",
+                "ifname",
+                interface,
+            ])
+            .output()
+            .context("فشل تشغيل nmcli dev wifi list")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("فشل استجواب nmcli عن الشبكات المرئية"));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut results = Vec::new();
+        for line in stdout.lines() {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 5 {
+                continue;
+            }
+            let frequency_mhz = fields[2]
+                .split_whitespace()
+                .next()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0);
+            let signal_percent: i32 = fields[3].parse().unwrap_or(0);
+            results.push(ScanResult {
+                bssid: fields[1].to_string(),
+                frequency_mhz,
+                // nmcli reports a 0-100 quality percentage, not dBm; keep
+                // the same field so callers can still rank candidates.
+                signal: signal_percent,
+                flags: fields[4].to_string(),
+                ssid: fields[0].to_string(),
+            });
+        }
+        Ok(results)
+    }
+
+    fn connect(&self, interface: &str, ssid: &str, password: Option<&str>) -> Result<NetworkConnection> {
+        let mut args = vec!["dev", "wifi", "connect", ssid, "ifname", interface];
+        if let Some(password) = password.filter(|p| !p.is_empty()) {
+            args.push("password");
+            args.push(password);
+        }
+
+        let output = Command::new("nmcli")
+            .args(&args)
+            .output()
+            .context("فشل تشغيل nmcli dev wifi connect")?;
+        if !output.status.success() {
+            return Err(NetworkConnectError::AuthFailed(format!(
+                "رفض nmcli الاتصال بشبكة {}: {}",
+                ssid,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+            .into());
+        }
+
+        let ip = self.get_ip(interface)?;
+        Ok(NetworkConnection {
+            interface: interface.to_string(),
+            connection_type: ConnectionType::WiFi,
+            ip_address: ip,
+            gateway: read_default_gateway(),
+            dns_servers: read_dns_servers(),
+            is_connected: true,
+        })
+    }
+
+    fn get_ip(&self, interface: &str) -> Result<String> {
+        read_interface_ip(interface)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        let output = Command::new("nmcli")
+            .args(["dev", "disconnect", interface])
+            .output()
+            .context("فشل تشغيل nmcli dev disconnect")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("فشل فصل الواجهة {} عبر nmcli", interface));
+        }
+        Ok(())
+    }
+}
+
+/// Fallback backend for hosts with neither a usable `wpa_supplicant`
+/// control socket nor `nmcli` - writes a throwaway `wpa_supplicant.conf`
+/// and spawns a short-lived `wpa_supplicant -B` against it, then
+/// `dhclient`, like the pre-`WpaCtrl` version of this module did.
+pub struct LegacyBackend;
+
+impl NetworkBackend for LegacyBackend {
+    fn name(&self) -> &'static str {
+        "legacy"
+    }
+
+    fn scan(&self, interface: &str) -> Result<Vec<ScanResult>> {
+        let output = Command::new("iw")
+            .args(["dev", interface, "scan"])
+            .output()
+            .context("فشل تشغيل iw scan")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("فشل مسح الشبكات عبر iw"));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut results = Vec::new();
+        let mut current: Option<ScanResult> = None;
+        for line in stdout.lines() {
+            let line = line.trim();
+            if let Some(bssid) = line.strip_prefix("BSS ") {
+                if let Some(result) = current.take() {
+                    results.push(result);
+                }
+                let bssid = bssid.split(['(', ' ']).next().unwrap_or(bssid).to_string();
+                current = Some(ScanResult {
+                    bssid,
+                    frequency_mhz: 0,
+                    signal: 0,
+                    flags: String::new(),
+                    ssid: String::new(),
+                });
+            } else if let Some(rest) = line.strip_prefix("freq: ") {
+                if let Some(result) = current.as_mut() {
+                    result.frequency_mhz = rest.trim().parse().unwrap_or(0);
+                }
+            } else if let Some(rest) = line.strip_prefix("signal: ") {
+                if let Some(result) = current.as_mut() {
+                    result.signal = rest
+                        .split_whitespace()
+                        .next()
+                        .and_then(|s| s.parse::<f32>().ok())
+                        .map(|dbm| dbm as i32)
+                        .unwrap_or(0);
+                }
+            } else if let Some(rest) = line.strip_prefix("SSID: ") {
+                if let Some(result) = current.as_mut() {
+                    result.ssid = rest.to_string();
+                }
+            } else if line.starts_with("WPA:") || line.starts_with("RSN:") {
+                if let Some(result) = current.as_mut() {
+                    result.flags.push_str(line.split(':').next().unwrap_or(""));
+                }
+            }
+        }
+        if let Some(result) = current.take() {
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    fn connect(&self, interface: &str, ssid: &str, password: Option<&str>) -> Result<NetworkConnection> {
+        let conf_path = format!("/tmp/dos_safar_wpa_legacy_{}.conf", std::process::id());
+        let conf_body = match password.filter(|p| !p.is_empty()) {
+            Some(password) => format!(
+                "network={{\n\tssid=\"{}\"\n\tpsk=\"{}\"\n}}\n",
+                ssid, password
+            ),
+            None => format!("network={{\n\tssid=\"{}\"\n\tkey_mgmt=NONE\n}}\n", ssid),
+        };
+        std::fs::write(&conf_path, conf_body)
+            .with_context(|| format!("فشل كتابة ملف إعداد wpa_supplicant المؤقت: {}", conf_path))?;
+        Self::restrict_to_owner(&conf_path)?;
+
+        let spawn = Command::new("wpa_supplicant")
+            .args(["-B", "-i", interface, "-c", &conf_path])
+            .output()
+            .context("فشل تشغيل wpa_supplicant (-B)");
+        let spawn = match spawn {
+            Ok(spawn) => spawn,
+            Err(err) => {
+                let _ = std::fs::remove_file(&conf_path);
+                return Err(err);
+            }
+        };
+        if !spawn.status.success() {
+            let _ = std::fs::remove_file(&conf_path);
+            return Err(NetworkConnectError::AuthFailed(format!(
+                "فشل بدء wpa_supplicant على الواجهة {}: {}",
+                interface,
+                String::from_utf8_lossy(&spawn.stderr).trim()
+            ))
+            .into());
+        }
+        let _ = std::fs::remove_file(&conf_path);
+
+        std::thread::sleep(Duration::from_secs(5));
+        run_dhclient(interface)?;
+        let ip = self.get_ip(interface)?;
+        Ok(NetworkConnection {
+            interface: interface.to_string(),
+            connection_type: ConnectionType::WiFi,
+            ip_address: ip,
+            gateway: read_default_gateway(),
+            dns_servers: read_dns_servers(),
+            is_connected: true,
+        })
+    }
+
+    /// يضبط صلاحيات `path` إلى `0600` فور كتابته - `wpa_supplicant.conf`
+    /// المؤقت يحمل `psk` بنص صريح، وبدون هذا يُكتب بصلاحيات umask
+    /// الافتراضية (قابلة للقراءة عالمياً على كثير من صور الأنظمة المدمجة)
+    /// تحت `/tmp` الذي يشاركه كل مستخدم/عملية على الجهاز - نفس إصلاح
+    /// `write_hostapd_config` في `hardware::network`.
+    fn restrict_to_owner(path: &str) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("فشل ضبط صلاحيات {}", path))
+    }
+
+    fn get_ip(&self, interface: &str) -> Result<String> {
+        read_interface_ip(interface)
+    }
+
+    fn disconnect(&self, interface: &str) -> Result<()> {
+        let _ = Command::new("pkill")
+            .args(["-f", &format!("wpa_supplicant.*-i {}", interface)])
+            .output();
+        let output = Command::new("ip")
+            .args(["link", "set", interface, "down"])
+            .output()
+            .context("فشل إيقاف تشغيل الواجهة")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!("فشل إيقاف تشغيل الواجهة {}", interface));
+        }
+        Ok(())
+    }
+}
+
+fn run_dhclient(interface: &str) -> Result<()> {
+    let output = Command::new("dhclient")
+        .arg(interface)
+        .output()
+        .context("فشل تشغيل عميل DHCP")?;
+    if !output.status.success() {
+        return Err(NetworkConnectError::DhcpFailed(format!("فشل طلب DHCP على الواجهة {}", interface)).into());
+    }
+    std::thread::sleep(Duration::from_secs(2));
+    Ok(())
+}
+
+fn read_interface_ip(interface: &str) -> Result<String> {
+    let output = Command::new("ip")
+        .args(["addr", "show", interface])
+        .output()
+        .context("فشل تشغيل أمر ip")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!("فشل أمر ip"));
+    }
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    for line in output_str.lines() {
+        if let Some(rest) = line.trim().strip_prefix("inet ") {
+            if let Some(ip) = rest.split_whitespace().next().and_then(|cidr| cidr.split('/').next()) {
+                return Ok(ip.to_string());
+            }
+        }
+    }
+    Err(NetworkConnectError::DhcpFailed(format!("لا يوجد عنوان IP للواجهة {}", interface)).into())
+}
+
+fn read_default_gateway() -> Option<String> {
+    let output = Command::new("ip").args(["route", "show", "default"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    for line in output_str.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if let Some(pos) = parts.iter().position(|&x| x == "via") {
+            if pos + 1 < parts.len() {
+                return Some(parts[pos + 1].to_string());
+            }
+        }
+    }
+    None
+}
+
+fn read_dns_servers() -> Vec<String> {
+    let mut dns_servers = Vec::new();
+    if let Ok(content) = std::fs::read_to_string("/etc/resolv.conf") {
+        for line in content.lines() {
+            if let Some(rest) = line.strip_prefix("nameserver") {
+                if let Some(server) = rest.split_whitespace().next() {
+                    dns_servers.push(server.to_string());
+                }
+            }
+        }
+    }
+    dns_servers
+}
+
+/// Picks a backend for this host: an explicit `config.network.backend`
+/// override wins outright; otherwise probes for a running NetworkManager
+/// (`nmcli` on `$PATH` and responsive) first since it actively owns WiFi
+/// management when present and fighting it would just cause both to fail,
+/// then a reachable `wpa_supplicant` control socket directory, and finally
+/// falls back to `LegacyBackend` for minimal images with neither.
+pub fn detect_backend(config: &Config) -> Box<dyn NetworkBackend> {
+    if let Some(choice) = &config.network.backend {
+        return match choice.as_str() {
+            "nmcli" => Box::new(NmcliBackend),
+            "legacy" => Box::new(LegacyBackend),
+            _ => Box::new(WpaSupplicantBackend {
+                connection_timeout_seconds: config.network.connection_timeout_seconds,
+            }),
+        };
+    }
+
+    if nmcli_available() {
+        return Box::new(NmcliBackend);
+    }
+
+    if std::path::Path::new("/var/run/wpa_supplicant").exists() {
+        return Box::new(WpaSupplicantBackend {
+            connection_timeout_seconds: config.network.connection_timeout_seconds,
+        });
+    }
+
+    Box::new(LegacyBackend)
+}
+
+fn nmcli_available() -> bool {
+    Command::new("nmcli")
+        .arg("general")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
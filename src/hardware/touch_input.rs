@@ -0,0 +1,237 @@
+// Applies `LcdConfig`'s `calibration_matrix`/`touch_device` to the real
+// evdev stream - until now nothing read either field. Resistive touch
+// panels on Anbernic/Pi add-on screens report raw ADC coordinates, not
+// screen pixels, so every libinput-based stack applies a 2x3 affine
+// (`sx = m0*x + m1*y + m2`, `sy = m3*x + m4*y + m5`) before a touch lands
+// in the right place; `calibrate()` derives that matrix the same way
+// those stacks' calibration tools do, by sampling four corner taps.
+use anyhow::{Context, Result};
+use evdev::{AbsoluteAxisType, Device, InputEventKind, Key};
+use std::path::Path;
+use tracing::info;
+use crate::hardware::lcd_display::{LcdDisplayConfig, LcdDisplayDetector};
+use crate::utils::config::{Config, LcdConfig};
+
+/// Edge margin, in pixels, for the calibration target boxes.
+const CALIBRATION_MARGIN: u32 = 20;
+/// Side length, in pixels, of each calibration target box.
+const TARGET_SIZE: u32 = 10;
+
+/// A touch point in the touch controller's own coordinate space, straight
+/// off `ABS_X`/`ABS_Y`, before the calibration matrix maps it onto the
+/// LCD panel's pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RawTouchPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// A touch point after the calibration matrix has been applied, in LCD
+/// panel pixel coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScreenPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Reads taps from `LcdConfig::touch_device` and transforms them into
+/// panel pixel coordinates via `LcdConfig::calibration_matrix`.
+pub struct TouchInputReader {
+    config: LcdConfig,
+}
+
+impl TouchInputReader {
+    pub fn new(config: &LcdConfig) -> Self {
+        TouchInputReader {
+            config: config.clone(),
+        }
+    }
+
+    /// Applies the configured calibration matrix to a raw touch point.
+    pub fn transform(&self, raw: RawTouchPoint) -> Result<ScreenPoint> {
+        apply_matrix(&self.config.calibration_matrix, raw)
+    }
+
+    /// Blocks until the next tap on `touch_device`, returning it already
+    /// transformed into panel pixel coordinates.
+    pub async fn next_touch(&self) -> Result<ScreenPoint> {
+        let raw = self.read_raw_tap().await?;
+        self.transform(raw)
+    }
+
+    /// Opens `touch_device` and blocks until a full tap (`BTN_TOUCH` down
+    /// then up) completes, returning the last `ABS_X`/`ABS_Y` reported
+    /// before release.
+    async fn read_raw_tap(&self) -> Result<RawTouchPoint> {
+        let device = Device::open(&self.config.touch_device).with_context(|| {
+            format!(
+                "فشل في فتح جهاز شاشة اللمس: {}",
+                self.config.touch_device
+            )
+        })?;
+        let mut events = device
+            .into_event_stream()
+            .context("فشل في فتح تدفق أحداث شاشة اللمس")?;
+
+        let mut x = 0i32;
+        let mut y = 0i32;
+        let mut touching = false;
+
+        loop {
+            let event = events
+                .next_event()
+                .await
+                .context("انقطع تدفق أحداث شاشة اللمس")?;
+
+            match event.kind() {
+                InputEventKind::AbsAxis(AbsoluteAxisType::ABS_X) => x = event.value(),
+                InputEventKind::AbsAxis(AbsoluteAxisType::ABS_Y) => y = event.value(),
+                InputEventKind::Key(Key::BTN_TOUCH) => {
+                    if event.value() == 1 {
+                        touching = true;
+                    } else if touching {
+                        return Ok(RawTouchPoint {
+                            x: x as f32,
+                            y: y as f32,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Draws four corner targets on `lcd`, collects the raw tap sampled
+    /// for each, and least-squares-fits the 2x3 affine that maps raw
+    /// coordinates onto those targets' known screen positions. Writes the
+    /// result into `config.lcd.calibration_matrix` and persists it via
+    /// `Config::save` before returning it.
+    pub async fn calibrate(
+        &self,
+        lcd: &LcdDisplayDetector,
+        resolved: &LcdDisplayConfig,
+        config: &mut Config,
+        config_path: &Path,
+    ) -> Result<Vec<f32>> {
+        let targets = [
+            (CALIBRATION_MARGIN, CALIBRATION_MARGIN),
+            (
+                resolved.width.saturating_sub(CALIBRATION_MARGIN + TARGET_SIZE),
+                CALIBRATION_MARGIN,
+            ),
+            (
+                CALIBRATION_MARGIN,
+                resolved.height.saturating_sub(CALIBRATION_MARGIN + TARGET_SIZE),
+            ),
+            (
+                resolved.width.saturating_sub(CALIBRATION_MARGIN + TARGET_SIZE),
+                resolved.height.saturating_sub(CALIBRATION_MARGIN + TARGET_SIZE),
+            ),
+        ];
+
+        let mut samples = Vec::with_capacity(targets.len());
+        for (target_x, target_y) in targets {
+            draw_target_marker(lcd, target_x, target_y).await?;
+            info!("🖐️ المسّ المربع المعروض لمعايرة شاشة اللمس");
+
+            let raw = self.read_raw_tap().await?;
+            let screen = ScreenPoint {
+                x: (target_x + TARGET_SIZE / 2) as f32,
+                y: (target_y + TARGET_SIZE / 2) as f32,
+            };
+            samples.push((raw, screen));
+        }
+
+        let row_x = solve_affine_axis(
+            &samples.iter().map(|(raw, screen)| (*raw, screen.x)).collect::<Vec<_>>(),
+        )?;
+        let row_y = solve_affine_axis(
+            &samples.iter().map(|(raw, screen)| (*raw, screen.y)).collect::<Vec<_>>(),
+        )?;
+
+        let matrix = vec![row_x[0], row_x[1], row_x[2], row_y[0], row_y[1], row_y[2]];
+
+        config.lcd.calibration_matrix = matrix.clone();
+        config
+            .save(config_path)
+            .context("فشل في حفظ مصفوفة معايرة شاشة اللمس إلى ملف التكوين")?;
+        info!("✅ تم حفظ مصفوفة معايرة شاشة اللمس: {:?}", matrix);
+
+        Ok(matrix)
+    }
+}
+
+/// Draws a solid white square target at `(x, y)` for the user to tap.
+async fn draw_target_marker(lcd: &LcdDisplayDetector, x: u32, y: u32) -> Result<()> {
+    const WHITE: u16 = 0xFFFF;
+    let pattern = vec![WHITE; (TARGET_SIZE * TARGET_SIZE) as usize];
+    lcd.blit(&pattern, x, y, TARGET_SIZE, TARGET_SIZE).await
+}
+
+fn apply_matrix(matrix: &[f32], raw: RawTouchPoint) -> Result<ScreenPoint> {
+    if matrix.len() != 6 {
+        return Err(anyhow::anyhow!(
+            "calibration_matrix يجب أن يحوي 6 عناصر (مصفوفة تآلفية 2x3)، ووُجد {}",
+            matrix.len()
+        ));
+    }
+
+    Ok(ScreenPoint {
+        x: matrix[0] * raw.x + matrix[1] * raw.y + matrix[2],
+        y: matrix[3] * raw.x + matrix[4] * raw.y + matrix[5],
+    })
+}
+
+/// Least-squares-fits one row (`[m, m, offset]`) of the affine matrix from
+/// `(raw_point, target_value)` samples via the 3x3 normal equations
+/// `AᵀA c = Aᵀb`, where each row of `A` is `[x, y, 1]`.
+fn solve_affine_axis(samples: &[(RawTouchPoint, f32)]) -> Result<[f32; 3]> {
+    let mut ata = [[0f64; 3]; 3];
+    let mut atb = [0f64; 3];
+
+    for (raw, target) in samples {
+        let row = [raw.x as f64, raw.y as f64, 1.0];
+        for i in 0..3 {
+            atb[i] += row[i] * (*target as f64);
+            for j in 0..3 {
+                ata[i][j] += row[i] * row[j];
+            }
+        }
+    }
+
+    let solved = solve_3x3(ata, atb)
+        .context("نقاط المعايرة متراصة على استقامة واحدة - تعذر حل المصفوفة")?;
+    Ok([solved[0] as f32, solved[1] as f32, solved[2] as f32])
+}
+
+/// Solves the 3x3 linear system `a * x = b` via Gaussian elimination with
+/// partial pivoting. `None` if `a` is singular (e.g. collinear samples).
+fn solve_3x3(mut a: [[f64; 3]; 3], mut b: [f64; 3]) -> Option<[f64; 3]> {
+    for col in 0..3 {
+        let pivot_row = (col..3)
+            .max_by(|&r1, &r2| a[r1][col].abs().partial_cmp(&a[r2][col].abs()).unwrap())?;
+        if a[pivot_row][col].abs() < 1e-9 {
+            return None;
+        }
+        a.swap(col, pivot_row);
+        b.swap(col, pivot_row);
+
+        for row in (col + 1)..3 {
+            let factor = a[row][col] / a[col][col];
+            for k in col..3 {
+                a[row][k] -= factor * a[col][k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0f64; 3];
+    for row in (0..3).rev() {
+        let mut sum = b[row];
+        for k in (row + 1)..3 {
+            sum -= a[row][k] * x[k];
+        }
+        x[row] = sum / a[row][row];
+    }
+    Some(x)
+}
@@ -0,0 +1,372 @@
+// Virtual uinput gamepad, fusing a handheld's fragmented GPIO D-pad/button
+// node and separate ADC analog-stick node into a single standard
+// Xbox-360-style device, so emulators see one consistent controller
+// instead of the raw nodes `InputTester::detect_builtin_gaming_controls`
+// reports as `builtin_dpad`/`builtin_analog` placeholders. The actual
+// source node paths and their remap to standard codes come from a
+// per-model `GamepadProfile`, loaded from `config.virtual_gamepad.profile_path`.
+use anyhow::{Context, Result};
+use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+use evdev::{AbsInfo, AbsoluteAxisType, AttributeSet, Device, EventType, InputEvent, InputEventKind, Key, UinputAbsSetup};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::io::AsRawFd;
+use tracing::warn;
+
+use crate::hardware::device_detect::{DeviceInfo, DeviceType};
+use crate::utils::config::Config;
+
+/// One `EV_KEY` source code remapped onto a standard virtual target (e.g.
+/// `BTN_0 -> BTN_SOUTH` for a raw GPIO button wired to the "A" face button).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRemap {
+    pub source: String,
+    pub target: String,
+}
+
+/// One `EV_ABS` source axis remapped onto a standard virtual axis. The
+/// output is the source value times `scale`, set to 0 when its magnitude is
+/// within `deadzone`; no offset is applied, so a source range such as a raw
+/// 0..4096 ADC is not re-centered around zero.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxisRemap {
+    pub source: String,
+    pub target: String,
+    #[serde(default = "default_axis_scale")]
+    pub scale: f32,
+    #[serde(default)]
+    pub deadzone: i32,
+}
+
+fn default_axis_scale() -> f32 {
+    1.0
+}
+
+/// A complete remap for one board/model: which evdev nodes to grab and
+/// forward from, and how their codes map onto the virtual device's
+/// standard capability set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadProfile {
+    pub device_type: DeviceType,
+    /// Matched case-insensitively against `DeviceInfo::model`; an empty
+    /// list matches any model of `device_type`.
+    #[serde(default)]
+    pub model_substrings: Vec<String>,
+    /// evdev nodes to open, grab, and forward from - typically the GPIO
+    /// keys device and the ADC joystick device on a fragmented handheld.
+    pub source_devices: Vec<String>,
+    #[serde(default)]
+    pub key_remaps: Vec<KeyRemap>,
+    #[serde(default)]
+    pub axis_remaps: Vec<AxisRemap>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct GamepadProfileFile {
+    #[serde(rename = "profile")]
+    profiles: Vec<GamepadProfile>,
+}
+
+/// Loads every `[[profile]]` entry from `config.virtual_gamepad.profile_path`.
+/// Returns an empty list (not an error) if remapping is disabled, no path
+/// is configured, or the file can't be read/parsed, since a missing
+/// profile just means nothing gets remapped.
+pub fn load_profiles(config: &Config) -> Vec<GamepadProfile> {
+    if !config.virtual_gamepad.enabled {
+        return Vec::new();
+    }
+    let Some(path) = &config.virtual_gamepad.profile_path else {
+        return Vec::new();
+    };
+
+    match fs::read_to_string(path) {
+        Ok(content) => match toml::from_str::<GamepadProfileFile>(&content) {
+            Ok(file) => file.profiles,
+            Err(e) => {
+                warn!("Failed to parse gamepad profile file {}: {}", path, e);
+                Vec::new()
+            }
+        },
+        Err(e) => {
+            warn!("Failed to read gamepad profile file {}: {}", path, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Picks the first profile whose `device_type` matches `device_info` and
+/// whose `model_substrings` (if any) matches `device_info.model`.
+pub fn select_profile<'a>(profiles: &'a [GamepadProfile], device_info: &DeviceInfo) -> Option<&'a GamepadProfile> {
+    let model_lower = device_info.model.to_lowercase();
+    profiles.iter().find(|profile| {
+        profile.device_type == device_info.device_type
+            && (profile.model_substrings.is_empty()
+                || profile
+                    .model_substrings
+                    .iter()
+                    .any(|substring| model_lower.contains(&substring.to_lowercase())))
+    })
+}
+
+struct ResolvedKeyRemap {
+    source: Key,
+    target: Key,
+}
+
+struct ResolvedAxisRemap {
+    source: AbsoluteAxisType,
+    target: AbsoluteAxisType,
+    scale: f32,
+    deadzone: i32,
+}
+
+/// The forwarding half of the remap: every grabbed source device plus the
+/// uinput device their events get translated onto.
+pub struct VirtualGamepad {
+    virtual_device: VirtualDevice,
+    source_devices: Vec<Device>,
+    key_remaps: Vec<ResolvedKeyRemap>,
+    axis_remaps: Vec<ResolvedAxisRemap>,
+}
+
+impl VirtualGamepad {
+    /// Opens and exclusively grabs (`EVIOCGRAB`) every `profile.source_devices`
+    /// node so their raw events stop reaching anything else, builds the
+    /// standard-capability uinput device, and resolves the profile's
+    /// string-named remaps into evdev codes (unrecognized names are
+    /// skipped with a warning rather than failing the whole profile).
+    pub fn build(profile: &GamepadProfile) -> Result<Self> {
+        let mut source_devices = Vec::with_capacity(profile.source_devices.len());
+        for path in &profile.source_devices {
+            let mut device =
+                Device::open(path).with_context(|| format!("failed to open gamepad source device: {}", path))?;
+            device
+                .grab()
+                .with_context(|| format!("failed to grab gamepad source device: {}", path))?;
+            source_devices.push(device);
+        }
+
+        let key_remaps = profile
+            .key_remaps
+            .iter()
+            .filter_map(|remap| match (parse_key(&remap.source), parse_key(&remap.target)) {
+                (Some(source), Some(target)) => Some(ResolvedKeyRemap { source, target }),
+                _ => {
+                    warn!("Skipping unrecognized key remap {} -> {}", remap.source, remap.target);
+                    None
+                }
+            })
+            .collect();
+
+        let axis_remaps = profile
+            .axis_remaps
+            .iter()
+            .filter_map(|remap| match (parse_axis(&remap.source), parse_axis(&remap.target)) {
+                (Some(source), Some(target)) => Some(ResolvedAxisRemap {
+                    source,
+                    target,
+                    scale: remap.scale,
+                    deadzone: remap.deadzone,
+                }),
+                _ => {
+                    warn!("Skipping unrecognized axis remap {} -> {}", remap.source, remap.target);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(VirtualGamepad {
+            virtual_device: build_virtual_device()?,
+            source_devices,
+            key_remaps,
+            axis_remaps,
+        })
+    }
+
+    /// Blocks forever, `poll(2)`-ing every grabbed source device and
+    /// forwarding/translating each event onto the virtual device. Returns
+    /// only if reading from a source device fails (e.g. it was unplugged)
+    /// or emitting onto the virtual device fails.
+    pub fn run(&mut self) -> Result<()> {
+        let mut poll_fds: Vec<libc::pollfd> = self
+            .source_devices
+            .iter()
+            .map(|device| libc::pollfd {
+                fd: device.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+
+        loop {
+            for pfd in poll_fds.iter_mut() {
+                pfd.revents = 0;
+            }
+            let ready = unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as libc::nfds_t, -1) };
+            if ready <= 0 {
+                continue;
+            }
+
+            for (pfd, device) in poll_fds.iter().zip(self.source_devices.iter_mut()) {
+                if pfd.revents & libc::POLLIN == 0 {
+                    continue;
+                }
+
+                let events = device
+                    .fetch_events()
+                    .context("failed to read events from gamepad source device")?;
+                for event in events {
+                    self.forward_event(event.kind(), event.value())?;
+                }
+            }
+        }
+    }
+
+    fn forward_event(&mut self, kind: InputEventKind, value: i32) -> Result<()> {
+        match kind {
+            InputEventKind::Key(source) => {
+                if let Some(remap) = self.key_remaps.iter().find(|remap| remap.source == source) {
+                    let event = InputEvent::new(EventType::KEY, remap.target.code(), value);
+                    self.virtual_device
+                        .emit(&[event])
+                        .context("failed to emit remapped key event onto virtual gamepad")?;
+                }
+            }
+            InputEventKind::AbsAxis(source) => {
+                if let Some(remap) = self.axis_remaps.iter().find(|remap| remap.source == source) {
+                    let scaled = (value as f32 * remap.scale) as i32;
+                    let output = if scaled.abs() <= remap.deadzone { 0 } else { scaled };
+                    let event = InputEvent::new(EventType::ABSOLUTE, axis_code(remap.target), output);
+                    self.virtual_device
+                        .emit(&[event])
+                        .context("failed to emit remapped axis event onto virtual gamepad")?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+fn axis_code(axis: AbsoluteAxisType) -> u16 {
+    axis.0
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    Some(match name {
+        "BTN_0" => Key::BTN_0,
+        "BTN_1" => Key::BTN_1,
+        "BTN_2" => Key::BTN_2,
+        "BTN_3" => Key::BTN_3,
+        "BTN_4" => Key::BTN_4,
+        "BTN_5" => Key::BTN_5,
+        "BTN_6" => Key::BTN_6,
+        "BTN_7" => Key::BTN_7,
+        "BTN_8" => Key::BTN_8,
+        "BTN_9" => Key::BTN_9,
+        "BTN_TRIGGER" => Key::BTN_TRIGGER,
+        "BTN_THUMB" => Key::BTN_THUMB,
+        "BTN_THUMB2" => Key::BTN_THUMB2,
+        "BTN_TOP" => Key::BTN_TOP,
+        "BTN_TOP2" => Key::BTN_TOP2,
+        "BTN_PINKIE" => Key::BTN_PINKIE,
+        "BTN_BASE" => Key::BTN_BASE,
+        "BTN_SOUTH" | "BTN_A" => Key::BTN_SOUTH,
+        "BTN_EAST" | "BTN_B" => Key::BTN_EAST,
+        "BTN_NORTH" | "BTN_X" => Key::BTN_NORTH,
+        "BTN_WEST" | "BTN_Y" => Key::BTN_WEST,
+        "BTN_TL" => Key::BTN_TL,
+        "BTN_TR" => Key::BTN_TR,
+        "BTN_TL2" => Key::BTN_TL2,
+        "BTN_TR2" => Key::BTN_TR2,
+        "BTN_SELECT" => Key::BTN_SELECT,
+        "BTN_START" => Key::BTN_START,
+        "BTN_MODE" => Key::BTN_MODE,
+        "BTN_THUMBL" => Key::BTN_THUMBL,
+        "BTN_THUMBR" => Key::BTN_THUMBR,
+        "BTN_DPAD_UP" => Key::BTN_DPAD_UP,
+        "BTN_DPAD_DOWN" => Key::BTN_DPAD_DOWN,
+        "BTN_DPAD_LEFT" => Key::BTN_DPAD_LEFT,
+        "BTN_DPAD_RIGHT" => Key::BTN_DPAD_RIGHT,
+        _ => return None,
+    })
+}
+
+fn parse_axis(name: &str) -> Option<AbsoluteAxisType> {
+    Some(match name {
+        "ABS_X" => AbsoluteAxisType::ABS_X,
+        "ABS_Y" => AbsoluteAxisType::ABS_Y,
+        "ABS_RX" => AbsoluteAxisType::ABS_RX,
+        "ABS_RY" => AbsoluteAxisType::ABS_RY,
+        "ABS_Z" => AbsoluteAxisType::ABS_Z,
+        "ABS_RZ" => AbsoluteAxisType::ABS_RZ,
+        "ABS_HAT0X" => AbsoluteAxisType::ABS_HAT0X,
+        "ABS_HAT0Y" => AbsoluteAxisType::ABS_HAT0Y,
+        _ => return None,
+    })
+}
+
+/// Every button a standard Xbox-360-style gamepad advertises.
+fn standard_gamepad_keys() -> AttributeSet<Key> {
+    let mut keys = AttributeSet::<Key>::new();
+    for key in [
+        Key::BTN_SOUTH,
+        Key::BTN_EAST,
+        Key::BTN_NORTH,
+        Key::BTN_WEST,
+        Key::BTN_TL,
+        Key::BTN_TR,
+        Key::BTN_TL2,
+        Key::BTN_TR2,
+        Key::BTN_SELECT,
+        Key::BTN_START,
+        Key::BTN_MODE,
+        Key::BTN_THUMBL,
+        Key::BTN_THUMBR,
+        Key::BTN_DPAD_UP,
+        Key::BTN_DPAD_DOWN,
+        Key::BTN_DPAD_LEFT,
+        Key::BTN_DPAD_RIGHT,
+    ] {
+        keys.insert(key);
+    }
+    keys
+}
+
+/// Every axis a standard Xbox-360-style gamepad advertises: two signed
+/// sticks, two unsigned analog triggers, and a digital D-pad hat.
+fn standard_gamepad_axes() -> Vec<UinputAbsSetup> {
+    const STICK_RANGE: (i32, i32) = (-32768, 32767);
+    const TRIGGER_RANGE: (i32, i32) = (0, 255);
+    const HAT_RANGE: (i32, i32) = (-1, 1);
+
+    [
+        (AbsoluteAxisType::ABS_X, STICK_RANGE),
+        (AbsoluteAxisType::ABS_Y, STICK_RANGE),
+        (AbsoluteAxisType::ABS_RX, STICK_RANGE),
+        (AbsoluteAxisType::ABS_RY, STICK_RANGE),
+        (AbsoluteAxisType::ABS_Z, TRIGGER_RANGE),
+        (AbsoluteAxisType::ABS_RZ, TRIGGER_RANGE),
+        (AbsoluteAxisType::ABS_HAT0X, HAT_RANGE),
+        (AbsoluteAxisType::ABS_HAT0Y, HAT_RANGE),
+    ]
+    .into_iter()
+    .map(|(axis, (min, max))| UinputAbsSetup::new(axis, AbsInfo::new(0, min, max, 0, 0, 0)))
+    .collect()
+}
+
+fn build_virtual_device() -> Result<VirtualDevice> {
+    let mut builder = VirtualDeviceBuilder::new()
+        .context("failed to start uinput virtual device builder")?
+        .name("dos_safar Virtual Gamepad")
+        .with_keys(&standard_gamepad_keys())
+        .context("failed to advertise virtual gamepad buttons")?;
+
+    for axis_setup in standard_gamepad_axes() {
+        builder = builder
+            .with_absolute_axis(&axis_setup)
+            .context("failed to advertise virtual gamepad axis")?;
+    }
+
+    builder.build().context("failed to create uinput virtual gamepad device")
+}
@@ -0,0 +1,320 @@
+// wpa_supplicant control-interface client, modeled on PeachCloud's
+// wpactrl layer: a Unix datagram socket against
+// `/var/run/wpa_supplicant/<iface>`, with plain-text commands and
+// line-based replies - this is what actually drives SCAN/ADD_NETWORK/
+// SELECT_NETWORK/STATUS instead of the iwlist/iwconfig shell-outs the
+// rest of this module used to rely on.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CTRL_SOCKET_DIR: &str = "/var/run/wpa_supplicant";
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One network reported by `SCAN_RESULTS`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub bssid: String,
+    pub frequency_mhz: u32,
+    pub signal: i32,
+    pub flags: String,
+    pub ssid: String,
+}
+
+impl ScanResult {
+    /// An "open" network's `flags` carry no key-management tag (e.g.
+    /// `[ESS]` alone, vs `[WPA2-PSK-CCMP][ESS]`).
+    pub fn is_open(&self) -> bool {
+        matches!(self.security(), SecurityType::Open)
+    }
+
+    /// Classifies `flags` (wpa_supplicant's bracketed `IE:`-derived tags,
+    /// e.g. `[WPA2-PSK-CCMP][ESS]`, `[WPA3-SAE-CCMP][ESS]`) into a
+    /// structured security type instead of the old flat `"Open"`/
+    /// `"Secured"` split, so callers can actually prefer WPA2/WPA3 over a
+    /// weaker WEP/WPA1 network with similar signal.
+    pub fn security(&self) -> SecurityType {
+        if self.flags.contains("WPA3") || self.flags.contains("SAE") {
+            SecurityType::Wpa3
+        } else if self.flags.contains("WPA2") || self.flags.contains("RSN") {
+            SecurityType::Wpa2
+        } else if self.flags.contains("WPA") {
+            SecurityType::Wpa
+        } else if self.flags.contains("WEP") {
+            SecurityType::Wep
+        } else {
+            SecurityType::Open
+        }
+    }
+}
+
+/// Structured security classification, replacing the old collapsed
+/// `"Open"`/`"Secured"` strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SecurityType {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+}
+
+/// Collapses multiple `ScanResult`s sharing an SSID (common with
+/// mesh/multi-AP deployments broadcasting the same network from several
+/// BSSIDs) down to the strongest one per SSID, so a picker doesn't offer
+/// the same network several times over.
+pub fn dedupe_strongest_per_ssid(results: Vec<ScanResult>) -> Vec<ScanResult> {
+    let mut strongest: HashMap<String, ScanResult> = HashMap::new();
+    for result in results {
+        strongest
+            .entry(result.ssid.clone())
+            .and_modify(|existing| {
+                if result.signal > existing.signal {
+                    *existing = result.clone();
+                }
+            })
+            .or_insert(result);
+    }
+    strongest.into_values().collect()
+}
+
+/// A connected client to one interface's wpa_supplicant control socket.
+pub struct WpaCtrl {
+    socket: UnixDatagram,
+    local_path: PathBuf,
+}
+
+impl WpaCtrl {
+    /// Opens the control socket for `interface` at
+    /// `/var/run/wpa_supplicant/<interface>`. wpa_supplicant replies over
+    /// the same `SOCK_DGRAM`, addressed back to our socket's bound path,
+    /// so (like `wpa_cli`/wpactrl) we must bind our own path under `/tmp`
+    /// before connecting, not just connect.
+    pub fn open(interface: &str) -> Result<Self> {
+        let ctrl_path = Path::new(CTRL_SOCKET_DIR).join(interface);
+        let local_path = PathBuf::from(format!(
+            "/tmp/dos_safar_wpa_ctrl_{}_{}",
+            interface,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&local_path);
+
+        let socket = UnixDatagram::bind(&local_path).with_context(|| {
+            format!(
+                "فشل في ربط مقبس عميل wpa_supplicant المحلي: {}",
+                local_path.display()
+            )
+        })?;
+        socket.connect(&ctrl_path).with_context(|| {
+            format!(
+                "فشل في الاتصال بمقبس تحكم wpa_supplicant: {}",
+                ctrl_path.display()
+            )
+        })?;
+        socket
+            .set_read_timeout(Some(RECV_TIMEOUT))
+            .context("فشل في ضبط مهلة قراءة مقبس wpa_supplicant")?;
+
+        Ok(WpaCtrl { socket, local_path })
+    }
+
+    fn request(&self, command: &str) -> Result<String> {
+        self.socket
+            .send(command.as_bytes())
+            .with_context(|| format!("فشل في إرسال أمر '{}' إلى wpa_supplicant", command))?;
+
+        let mut buf = [0u8; 4096];
+        let n = self
+            .socket
+            .recv(&mut buf)
+            .with_context(|| format!("لم يرد wpa_supplicant على أمر '{}'", command))?;
+
+        Ok(String::from_utf8_lossy(&buf[..n]).into_owned())
+    }
+
+    fn expect_ok(&self, command: &str) -> Result<()> {
+        let reply = self.request(command)?;
+        if reply.trim() != "OK" {
+            return Err(anyhow::anyhow!(
+                "رفض wpa_supplicant الأمر '{}': {}",
+                command,
+                reply.trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Triggers a scan; results land asynchronously and are read back via
+    /// `scan_results`.
+    pub fn scan(&self) -> Result<()> {
+        self.expect_ok("SCAN")
+    }
+
+    /// Parses `SCAN_RESULTS`' tab-separated table (`bssid / frequency /
+    /// signal level / flags / ssid`), skipping its header line.
+    pub fn scan_results(&self) -> Result<Vec<ScanResult>> {
+        let reply = self.request("SCAN_RESULTS")?;
+        Ok(parse_scan_results(&reply))
+    }
+
+    fn add_network(&self) -> Result<u32> {
+        let reply = self.request("ADD_NETWORK")?;
+        reply
+            .trim()
+            .parse()
+            .with_context(|| format!("رد ADD_NETWORK غير متوقع: '{}'", reply.trim()))
+    }
+
+    fn set_network(&self, id: u32, key: &str, value: &str) -> Result<()> {
+        self.expect_ok(&format!("SET_NETWORK {} {} {}", id, key, value))
+    }
+
+    fn enable_network(&self, id: u32) -> Result<()> {
+        self.expect_ok(&format!("ENABLE_NETWORK {}", id))
+    }
+
+    fn select_network(&self, id: u32) -> Result<()> {
+        self.expect_ok(&format!("SELECT_NETWORK {}", id))
+    }
+
+    /// Removes a network block, e.g. after a failed join attempt so it
+    /// doesn't linger and get auto-retried by wpa_supplicant itself.
+    pub fn remove_network(&self, id: u32) -> Result<()> {
+        self.expect_ok(&format!("REMOVE_NETWORK {}", id))
+    }
+
+    /// Adds a network block for `ssid`/`password` (open if `password` is
+    /// `None` or empty), enables and selects it (deselecting any other
+    /// configured network, like `wpa_cli select_network` does), and
+    /// returns its id.
+    pub fn configure_network(&self, ssid: &str, password: Option<&str>) -> Result<u32> {
+        let id = self.add_network()?;
+        self.set_network(id, "ssid", &format!("\"{}\"", ssid))?;
+
+        match password {
+            Some(password) if !password.is_empty() => {
+                self.set_network(id, "psk", &format!("\"{}\"", password))?;
+            }
+            _ => {
+                self.set_network(id, "key_mgmt", "NONE")?;
+            }
+        }
+
+        self.enable_network(id)?;
+        self.select_network(id)?;
+        Ok(id)
+    }
+
+    /// Tears down the current association without removing its network
+    /// block, mirroring `wpa_cli disconnect`.
+    pub fn disconnect(&self) -> Result<()> {
+        self.expect_ok("DISCONNECT")
+    }
+
+    /// Writes the current in-memory network blocks back to
+    /// wpa_supplicant's config file on disk, mirroring `wpa_cli
+    /// save_config`. Requires `update_config=1` in that file; a refusal
+    /// here is non-fatal to the caller (the connection itself already
+    /// succeeded), so callers should log rather than bail.
+    pub fn save_config(&self) -> Result<()> {
+        self.expect_ok("SAVE_CONFIG")
+    }
+
+    /// Parses `STATUS`'s `key=value` lines, used to confirm
+    /// `wpa_state=COMPLETED` and read the associated `ssid`.
+    pub fn status(&self) -> Result<HashMap<String, String>> {
+        let reply = self.request("STATUS")?;
+        Ok(parse_status(&reply))
+    }
+}
+
+/// Parses `SCAN_RESULTS`' tab-separated table (`bssid / frequency / signal
+/// level / flags / ssid`), skipping its header line. Pulled out of
+/// [`WpaCtrl::scan_results`] so the line protocol can be tested without a
+/// live control socket.
+fn parse_scan_results(reply: &str) -> Vec<ScanResult> {
+    let mut results = Vec::new();
+
+    for line in reply.lines().skip(1) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 5 {
+            continue;
+        }
+        results.push(ScanResult {
+            bssid: fields[0].to_string(),
+            frequency_mhz: fields[1].parse().unwrap_or(0),
+            signal: fields[2].parse().unwrap_or(0),
+            flags: fields[3].to_string(),
+            ssid: fields[4].to_string(),
+        });
+    }
+
+    results
+}
+
+/// Parses `STATUS`'s `key=value` lines, pulled out of [`WpaCtrl::status`]
+/// for the same reason as [`parse_scan_results`].
+fn parse_status(reply: &str) -> HashMap<String, String> {
+    let mut status = HashMap::new();
+    for line in reply.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            status.insert(key.to_string(), value.to_string());
+        }
+    }
+    status
+}
+
+impl Drop for WpaCtrl {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.local_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scan_results_table_skipping_header() {
+        let reply = "bssid / frequency / signal level / flags / ssid\n\
+            aa:bb:cc:dd:ee:ff\t2412\t-42\t[WPA2-PSK-CCMP][ESS]\tHomeNet\n\
+            11:22:33:44:55:66\t5180\t-67\t[ESS]\tOpenNet\n";
+
+        let results = parse_scan_results(reply);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].ssid, "HomeNet");
+        assert_eq!(results[0].frequency_mhz, 2412);
+        assert_eq!(results[0].signal, -42);
+        assert_eq!(results[0].security(), SecurityType::Wpa2);
+        assert!(results[1].is_open());
+    }
+
+    /// A line with fewer than 5 tab-separated fields (truncated reply, or
+    /// an AP broadcasting no SSID at all) is skipped rather than parsed
+    /// with missing fields defaulted.
+    #[test]
+    fn skips_short_scan_result_lines() {
+        let reply = "header\nonly\tthree\tfields\n";
+        assert!(parse_scan_results(reply).is_empty());
+    }
+
+    #[test]
+    fn parses_status_key_value_lines() {
+        let reply = "bssid=aa:bb:cc:dd:ee:ff\nssid=HomeNet\nwpa_state=COMPLETED\n";
+        let status = parse_status(reply);
+        assert_eq!(status.get("wpa_state").map(String::as_str), Some("COMPLETED"));
+        assert_eq!(status.get("ssid").map(String::as_str), Some("HomeNet"));
+    }
+
+    /// A line with no `=` (blank line, unexpected directive) is skipped
+    /// instead of panicking or inserting a garbage key.
+    #[test]
+    fn ignores_status_lines_without_equals() {
+        let reply = "wpa_state=COMPLETED\n\nnotakeyvalue\n";
+        let status = parse_status(reply);
+        assert_eq!(status.len(), 1);
+    }
+}
@@ -1,259 +1,415 @@
-use anyhow::Result;
-use clap::{Arg, Command};
-use std::time::Duration;
-use tokio::time::sleep;
-use tracing::{info, warn, error};
-
-mod hardware;
-mod bootloader;
-mod remote;
-mod utils;
-
-use hardware::device_detect::DeviceDetector;
-use hardware::display::DisplayTester;
-use hardware::input::InputTester;
-use hardware::network::NetworkManager;
-use hardware::lcd_display::LcdDisplayDetector; // إضافة جديدةuse bootloader::menu::BootMenu;
-use remote::web_server::WebServer;
-use utils::config::Config;
-use utils::logger::init_logger;
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    init_logger()?;
-    
-    let matches = Command::new("DOS Safar")
-        .version("0.1.0")
-        .about("Universal ARM Boot Manager for gaming handhelds and Raspberry Pi")
-        .arg(Arg::new("config")
-            .short('c')
-            .long("config")
-            .value_name("FILE")
-            .help("Configuration file path")
-            .default_value("config/default.toml"))
-        .arg(Arg::new("skip-tests")
-            .long("skip-tests")
-            .help("Skip hardware tests and go directly to boot menu")
-            .action(clap::ArgAction::SetTrue))
-        .arg(Arg::new("web-only")
-            .long("web-only")
-            .help("Start only web interface (for development)")
-            .action(clap::ArgAction::SetTrue))
-        .get_matches();
-
-    let config_path = matches.get_one::<String>("config").unwrap();
-    let skip_tests = matches.get_flag("skip-tests");
-    let web_only = matches.get_flag("web-only");
-
-    info!("🎮 Starting DOS Safar Boot Manager");
-    info!("📁 Configuration: {}", config_path);
-
-    // Load configuration
-    let config = Config::load(config_path)?;
-    
-    // If web-only mode, start web server and exit
-    if web_only {
-        info!("🌐 Starting in web-only mode for development");
-        start_web_server(&config).await?;
-        return Ok(());
-    }
-
-    // Phase 1: Device Detection
-    info!("🔍 === Phase 1: Device Detection ===");
-    let device_detector = DeviceDetector::new();
-    let device_info = device_detector.detect_device().await?;
-    info!("✅ Detected device: {} ({})", device_info.model, device_info.architecture);
-
-    // Phase 2: Show boot options with keyboard interrupt detection
-    info!("⏰ === Phase 2: Boot Timeout ({}s) ===", config.boot.menu_timeout_seconds);
-    println!("\n🎮 DOS Safar Boot Manager");
-    println!("Device: {}", device_info.model);
-    println!("═══════════════════════════════════════");
-    println!("Press ANY KEY to access boot menu...");
-    println!("Or wait {} seconds for automatic web interface", config.boot.menu_timeout_seconds);
-    println!("═══════════════════════════════════════");
-
-    // Wait for keyboard input or timeout
-    let user_interrupted = wait_for_keyboard_or_timeout(&config).await;
-
-    if user_interrupted {
-        info!("⌨️  User input detected - showing boot menu");
-        
-        // Phase 2a: Hardware Testing (if requested)
-        if !skip_tests {
-            info!("🔧 === Hardware Testing ===");
-            run_hardware_tests(&device_info).await?;
-        }
-
-        // Phase 2b: Show boot menu
-        info!("📋 === Boot Menu ===");
-        let boot_menu = BootMenu::new(&config, &device_info)?;
-        boot_menu.show_menu().await?;
-        
-    } else {
-        info!("⏱️  Timeout reached - starting automatic web interface");
-        
-        // Phase 3: Smart Network Auto-Connect
-        info!("🌐 === Phase 3: Smart Network Connection ===");
-        let network_result = auto_connect_and_start_web(&config).await;
-        
-        match network_result {
-            Ok(connection) => {
-                info!("✅ Web interface started successfully");
-                
-                // Keep the system running
-                info!("🔄 System ready - web interface active");
-                loop {
-                    sleep(Duration::from_secs(60)).await;
-                }
-            }
-            Err(e) => {
-                warn!("❌ Failed to start web interface: {}", e);
-                info!("📋 Falling back to boot menu...");
-                
-                let boot_menu = BootMenu::new(&config, &device_info)?;
-                boot_menu.show_menu().await?;
-            }
-        }
-    }
-
-    Ok(())
-}
-
-async fn run_hardware_tests(device_info: &hardware::device_detect::DeviceInfo) -> Result<()> {
-    info!("Running hardware tests for {}", device_info.model);
-
-    // Test display
-    info!("Testing display configuration...");
-    let display_tester = DisplayTester::new(device_info);
-    let display_result = display_tester.test_display().await;
-    match display_result {
-        Ok(config) => info!("Display test passed: {}x{}", config.width, config.height),
-        Err(e) => warn!("Display test failed: {}", e),
-    }
-
-    // Test input devices
-    info!("Testing input devices...");
-    let input_tester = InputTester::new(device_info);
-    let input_result = input_tester.test_controllers().await;
-    match input_result {
-        Ok(controllers) => info!("Found {} input devices", controllers.len()),
-        Err(e) => warn!("Input test failed: {}", e),
-    }
-// إضافة قبل "All tests completed"
-// Test LCD displays
-info!("Testing LCD displays...");
-let lcd_detector = LcdDisplayDetector::new(device_info);
-let lcd_result = lcd_detector.detect_lcd_displays().await;
-match lcd_result {
-    Ok(displays) => {
-        info!("Found {} LCD displays", displays.len());
-        
-        // Test each LCD display
-        for display in displays {
-            info!("Testing LCD: {:?} - {}\"", display.driver, display.size_inch);
-            if let Ok(test_passed) = lcd_detector.test_lcd_display(&display).await {
-                if test_passed {
-                    info!("LCD display test passed");
-                    
-                    // Configure the LCD display
-                    if let Err(e) = lcd_detector.configure_lcd_display(&display).await {
-                        warn!("LCD configuration failed: {}", e);
-                    } else {
-                        info!("LCD display configured successfully");
-                    }
-                } else {
-                    warn!("LCD display test failed");
-                }
-            }
-        }
-    },
-    Err(e) => warn!("LCD detection failed: {}", e),
-}
-    // All tests completed
-    info!("Hardware tests completed");
-    Ok(())
-}
-
-// Smart keyboard detection with timeout
-async fn wait_for_keyboard_or_timeout(config: &Config) -> bool {
-    use std::io::{self, Read};
-    use std::sync::mpsc;
-    use std::thread;
-    
-    let (tx, rx) = mpsc::channel();
-    
-    // Spawn thread to listen for keyboard input
-    thread::spawn(move || {
-        let mut stdin = io::stdin();
-        let mut buffer = [0; 1];
-        
-        // Non-blocking read attempt
-        if stdin.read(&mut buffer).is_ok() {
-            let _ = tx.send(true);
-        }
-    });
-    
-    // Wait for either keyboard input or timeout
-    match tokio::time::timeout(
-        Duration::from_secs(config.boot.menu_timeout_seconds), 
-        tokio::task::spawn_blocking(move || rx.recv())
-    ).await {
-        Ok(Ok(Ok(_))) => {
-            info!("⌨️  Keyboard input detected!");
-            true
-        }
-        _ => {
-            info!("⏱️  No keyboard input - proceeding with auto-connect");
-            false
-        }
-    }
-}
-
-// Smart auto-connect and web interface startup
-async fn auto_connect_and_start_web(config: &Config) -> Result<()> {
-    use crate::hardware::enhanced_network::SmartNetworkManager;
-    
-    let network_manager = SmartNetworkManager::new(config);
-    
-    // Try to connect to network
-    println!("🔍 Searching for networks...");
-    match network_manager.auto_connect().await {
-        Ok(connection) => {
-            // Display connection info on screen
-            network_manager.display_connection_info(&connection);
-            
-            // Start web server
-            info!("🚀 Starting web interface...");
-            tokio::spawn(async move {
-                if let Err(e) = start_web_server(config).await {
-                    error!("❌ Web server error: {}", e);
-                }
-            });
-            
-            // Wait a moment for web server to start
-            sleep(Duration::from_secs(2)).await;
-            
-            println!("✅ Web interface is ready!");
-            println!("📱 Open your browser/phone and go to: http://{}", connection.ip_address);
-            println!("🔧 Use the web interface to:");
-            println!("   • View current screen");
-            println!("   • Fix display/keyboard issues");
-            println!("   • Manage operating systems");
-            println!("   • Change settings");
-            
-            Ok(())
-        }
-        Err(e) => {
-            error!("❌ Network connection failed: {}", e);
-            println!("\n⚠️  No network connection available");
-            println!("Options:");
-            println!("1. Check network settings in config/default.toml");
-            println!("2. Connect Ethernet cable");
-            println!("3. Restart to try again");
-            
-            Err(e)
-        }
-    }
+use anyhow::{Context, Result};
+use clap::{Arg, Command};
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{info, warn, error};
+
+mod hardware;
+mod bootloader;
+mod remote;
+mod utils;
+
+use hardware::device_detect::{DeviceDetector, DeviceInfo};
+use hardware::display::DisplayTester;
+use hardware::input::InputTester;
+use hardware::network::NetworkManager;
+use hardware::lcd_display::LcdDisplayDetector;
+use hardware::touch_input::TouchInputReader;
+use bootloader::menu::BootMenu;
+use remote::web_server::WebServer;
+use utils::config::Config;
+use utils::logger::init_logger;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Initialize logging
+    init_logger()?;
+    
+    let matches = Command::new("DOS Safar")
+        .version("0.1.0")
+        .about("Universal ARM Boot Manager for gaming handhelds and Raspberry Pi")
+        .arg(Arg::new("config")
+            .short('c')
+            .long("config")
+            .value_name("FILE")
+            .help("Configuration file path")
+            .default_value("config/default.toml"))
+        .arg(Arg::new("skip-tests")
+            .long("skip-tests")
+            .help("Skip hardware tests and go directly to boot menu")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("web-only")
+            .long("web-only")
+            .help("Start only web interface (for development)")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("calibrate-touch")
+            .long("calibrate-touch")
+            .help("Run the LCD touchscreen calibration routine and exit")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("set")
+            .long("set")
+            .value_name("KEY=VALUE")
+            .help("Override a config value by dotted path (e.g. web.port=9090); repeatable")
+            .action(clap::ArgAction::Append))
+        .get_matches();
+
+    let config_path = matches.get_one::<String>("config").unwrap();
+    let skip_tests = matches.get_flag("skip-tests");
+    let web_only = matches.get_flag("web-only");
+    let calibrate_touch = matches.get_flag("calibrate-touch");
+    let overrides: Vec<String> = matches
+        .get_many::<String>("set")
+        .map(|values| values.cloned().collect())
+        .unwrap_or_default();
+
+    info!("🎮 Starting DOS Safar Boot Manager");
+    info!("📁 Configuration: {}", config_path);
+
+    // Load configuration, layering --set overrides and DOS_SAFAR_* env vars
+    // on top of the file; loading writes none of them back, but a later
+    // `Config::save` of this value (touch calibration, setting a default
+    // OS) writes them along with the rest
+    let mut config = Config::load_with_overrides(config_path, &overrides, std::env::vars())?;
+
+    // If touch calibration was requested, run it and exit
+    if calibrate_touch {
+        info!("🖐️ Starting touchscreen calibration");
+        run_touch_calibration(&mut config, config_path).await?;
+        return Ok(());
+    }
+
+    // If web-only mode, start web server and exit
+    if web_only {
+        info!("🌐 Starting in web-only mode for development");
+        let device_detector = DeviceDetector::with_config(&config);
+        let device_info = device_detector.detect_device().await?;
+        let boot_menu = std::sync::Arc::new(tokio::sync::Mutex::new(BootMenu::new(
+            &config,
+            &device_info,
+            Path::new(config_path),
+        )?));
+        tokio::spawn(remote::mqtt::run(config.clone(), boot_menu.clone()));
+        start_web_server(&config, Path::new(config_path), boot_menu).await?;
+        return Ok(());
+    }
+
+    // Phase 1: Device Detection
+    info!("🔍 === Phase 1: Device Detection ===");
+    let device_detector = DeviceDetector::with_config(&config);
+    let device_info = device_detector.detect_device().await?;
+    info!("✅ Detected device: {} ({})", device_info.model, device_info.architecture);
+
+    info!("🎯 Assessing emulation capabilities...");
+    let _capability_report = hardware::capability::assess_capabilities(&device_info);
+
+    // Phase 2: Show boot options with keyboard interrupt detection
+    info!("⏰ === Phase 2: Boot Timeout ({}s) ===", config.boot.menu_timeout_seconds);
+    println!("\n🎮 DOS Safar Boot Manager");
+    println!("Device: {}", device_info.model);
+    println!("═══════════════════════════════════════");
+    println!("Press ANY KEY to access boot menu...");
+    println!("Or wait {} seconds for automatic web interface", config.boot.menu_timeout_seconds);
+    println!("═══════════════════════════════════════");
+
+    // Wait for keyboard input or timeout
+    let user_interrupted = wait_for_keyboard_or_timeout(&config, &device_info, Path::new(config_path)).await;
+
+    if user_interrupted {
+        info!("⌨️  User input detected - showing boot menu");
+        
+        // Phase 2a: Hardware Testing (if requested)
+        if !skip_tests {
+            info!("🔧 === Hardware Testing ===");
+            run_hardware_tests(&device_info, &config).await?;
+        }
+
+        // Phase 2b: Show boot menu
+        info!("📋 === Boot Menu ===");
+        let boot_menu = BootMenu::new(&config, &device_info, Path::new(config_path))?;
+        boot_menu.show_menu().await?;
+        
+    } else {
+        info!("⏱️  Timeout reached - starting automatic web interface");
+        
+        // Phase 3: Smart Network Auto-Connect
+        info!("🌐 === Phase 3: Smart Network Connection ===");
+        let network_result = auto_connect_and_start_web(&config, &device_info, Path::new(config_path)).await;
+        
+        match network_result {
+            Ok(connection) => {
+                info!("✅ Web interface started successfully");
+                
+                // Keep the system running
+                info!("🔄 System ready - web interface active");
+                loop {
+                    sleep(Duration::from_secs(60)).await;
+                }
+            }
+            Err(e) => {
+                warn!("❌ Failed to start web interface: {}", e);
+                info!("📋 Falling back to boot menu...");
+                
+                let boot_menu = BootMenu::new(&config, &device_info, Path::new(config_path))?;
+                boot_menu.show_menu().await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_hardware_tests(
+    device_info: &hardware::device_detect::DeviceInfo,
+    config: &utils::config::Config,
+) -> Result<()> {
+    info!("Running hardware tests for {}", device_info.model);
+
+    // Test display
+    info!("Testing display configuration...");
+    let display_tester = DisplayTester::new(device_info, &config.lcd, &config.hardware);
+    let display_result = display_tester.test_display().await;
+    match display_result {
+        Ok(config) => info!("Display test passed: {}x{}", config.width, config.height),
+        Err(e) => warn!("Display test failed: {}", e),
+    }
+
+    // Test input devices
+    info!("Testing input devices...");
+    let input_tester = InputTester::new(device_info);
+    let input_result = input_tester.test_controllers().await;
+    match input_result {
+        Ok(controllers) => info!("Found {} input devices", controllers.len()),
+        Err(e) => warn!("Input test failed: {}", e),
+    }
+
+    // شاشة LCD عبر SPI (إن فُعِّلت في التكوين) تُختبر ضمن display_tester
+    // أعلاه عبر run_display_test -> run_lcd_test، فلا حاجة لمسار منفصل هنا
+
+    // Test Bluetooth controllers
+    if config.bluetooth.enabled {
+        info!("Checking Bluetooth controllers...");
+        let bluetooth_manager = hardware::bluetooth::BluetoothManager::new(config);
+        match bluetooth_manager.reconnect_paired().await {
+            Ok(devices) => info!("Found {} connected BLE controller(s)", devices.len()),
+            Err(e) => warn!("Bluetooth controller check failed: {}", e),
+        }
+    }
+
+    // All tests completed
+    info!("Hardware tests completed");
+    Ok(())
+}
+
+/// يهيئ شاشة LCD عبر SPI ثم يشغّل `TouchInputReader::calibrate` لمعايرة
+/// `calibration_matrix` تفاعلياً (أربع نقاط في زوايا الشاشة)، ويحفظ النتيجة
+/// في ملف التكوين قبل العودة.
+async fn run_touch_calibration(config: &mut Config, config_path: &str) -> Result<()> {
+    if !config.lcd.touch_enabled {
+        return Err(anyhow::anyhow!(
+            "شاشة اللمس معطّلة في التكوين (lcd.touch_enabled = false)"
+        ));
+    }
+
+    let mut lcd = LcdDisplayDetector::new(&config.lcd);
+    let resolved = lcd
+        .init()
+        .await
+        .context("فشل في تهيئة شاشة LCD للمعايرة")?;
+
+    let touch = TouchInputReader::new(&config.lcd);
+    let matrix = touch
+        .calibrate(&lcd, &resolved, config, Path::new(config_path))
+        .await?;
+
+    info!("✅ اكتملت معايرة شاشة اللمس: {:?}", matrix);
+    Ok(())
+}
+
+// Smart keyboard detection with timeout
+async fn wait_for_keyboard_or_timeout(config: &Config, device_info: &DeviceInfo, config_path: &Path) -> bool {
+    use std::io::{self, Read};
+    use std::sync::mpsc;
+    use std::thread;
+
+    let (tx, rx) = mpsc::channel();
+
+    // Spawn thread to listen for keyboard input
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 1];
+
+        // Non-blocking read attempt
+        if stdin.read(&mut buffer).is_ok() {
+            let _ = tx.send(true);
+        }
+    });
+
+    // Wait for either keyboard input, the timeout, or the factory-reset
+    // combo being held through its full countdown (see
+    // `run_recovery_watchdog`). The latter only resolves once a reset has
+    // been attempted - after a successful wipe it reboots the device on the
+    // way out, and a failed wipe is logged - so it always counts as "not
+    // interrupted".
+    tokio::select! {
+        result = tokio::time::timeout(
+            Duration::from_secs(config.boot.menu_timeout_seconds),
+            tokio::task::spawn_blocking(move || rx.recv())
+        ) => {
+            match result {
+                Ok(Ok(Ok(_))) => {
+                    info!("⌨️  Keyboard input detected!");
+                    true
+                }
+                _ => {
+                    info!("⏱️  No keyboard input - proceeding with auto-connect");
+                    false
+                }
+            }
+        }
+        _ = run_recovery_watchdog(config, device_info, config_path) => false,
+    }
+}
+
+/// State of the physical factory-reset combo watcher, ticked once per
+/// second by [`run_recovery_watchdog`]: `Idle` -> `ButtonHeld` while
+/// `hardware::input::FACTORY_RESET_COMBO` is held but under
+/// `config.recovery.hold_seconds` -> `Countdown` (visible, cancels back to
+/// `Idle` on release) -> `Executing` once the countdown reaches zero still
+/// held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecoveryState {
+    Idle,
+    ButtonHeld { held_for_secs: u64 },
+    Countdown { remaining_secs: u64 },
+    Executing,
+}
+
+/// Polls [`InputTester::is_combo_held`] once a second for as long as this
+/// process runs, driving [`RecoveryState`] forward. Only returns once the
+/// combo has been held continuously through the whole hold+countdown
+/// window, after wiping config/OS selections back to defaults
+/// (`remote::power::reset_config`) and triggering a reboot
+/// (`remote::power::reboot`), or once that wipe fails (logged, no reboot);
+/// disabled entirely when
+/// `config.recovery.enabled` is false.
+async fn run_recovery_watchdog(config: &Config, device_info: &DeviceInfo, config_path: &Path) -> bool {
+    if !config.recovery.enabled {
+        return std::future::pending::<bool>().await;
+    }
+
+    let input_tester = InputTester::new(device_info);
+    let mut state = RecoveryState::Idle;
+
+    loop {
+        let combo_held = input_tester.is_combo_held(&hardware::input::FACTORY_RESET_COMBO).await;
+
+        state = match (state, combo_held) {
+            (RecoveryState::Idle, true) => {
+                info!("🕹️  Factory-reset combo pressed - hold for {}s to arm", config.recovery.hold_seconds);
+                RecoveryState::ButtonHeld { held_for_secs: 1 }
+            }
+            (RecoveryState::Idle, false) => RecoveryState::Idle,
+
+            (RecoveryState::ButtonHeld { .. }, false) => {
+                info!("🕹️  Factory-reset combo released - cancelled");
+                RecoveryState::Idle
+            }
+            (RecoveryState::ButtonHeld { held_for_secs }, true) => {
+                if held_for_secs >= config.recovery.hold_seconds {
+                    RecoveryState::Countdown { remaining_secs: config.recovery.countdown_seconds }
+                } else {
+                    RecoveryState::ButtonHeld { held_for_secs: held_for_secs + 1 }
+                }
+            }
+
+            (RecoveryState::Countdown { .. }, false) => {
+                println!("✅ Factory reset cancelled");
+                RecoveryState::Idle
+            }
+            (RecoveryState::Countdown { remaining_secs }, true) => {
+                if remaining_secs == 0 {
+                    RecoveryState::Executing
+                } else {
+                    println!("⚠️  FACTORY RESET in {}...", remaining_secs);
+                    RecoveryState::Countdown { remaining_secs: remaining_secs - 1 }
+                }
+            }
+
+            (RecoveryState::Executing, _) => RecoveryState::Executing,
+        };
+
+        if state == RecoveryState::Executing {
+            warn!("♻️  Factory-reset combo held through countdown - wiping config and rebooting");
+            if let Err(e) = remote::power::reset_config(config, config_path) {
+                error!("Factory reset failed: {}", e);
+                return false;
+            }
+            if let Err(e) = remote::power::reboot() {
+                error!("Factory reset reboot failed: {}", e);
+            }
+            return false;
+        }
+
+        sleep(Duration::from_secs(1)).await;
+    }
+}
+
+// Smart auto-connect and web interface startup
+async fn auto_connect_and_start_web(config: &Config, device_info: &DeviceInfo, config_path: &Path) -> Result<()> {
+    use crate::hardware::enhanced_network::SmartNetworkManager;
+
+    let network_manager = SmartNetworkManager::new(config);
+
+    // Try to connect to network
+    println!("🔍 Searching for networks...");
+    match network_manager.auto_connect().await {
+        Ok(connection) => {
+            // Display connection info on screen
+            network_manager.display_connection_info(&connection);
+
+            // Start web server, plus the MQTT bridge alongside it for
+            // headless control/telemetry when no one's reaching the web UI
+            info!("🚀 Starting web interface...");
+            let boot_menu = std::sync::Arc::new(tokio::sync::Mutex::new(BootMenu::new(config, device_info, config_path)?));
+            tokio::spawn(remote::mqtt::run(config.clone(), boot_menu.clone()));
+            let web_config = config.clone();
+            let web_config_path = config_path.to_path_buf();
+            tokio::spawn(async move {
+                if let Err(e) = start_web_server(&web_config, &web_config_path, boot_menu).await {
+                    error!("❌ Web server error: {}", e);
+                }
+            });
+            
+            // Wait a moment for web server to start
+            sleep(Duration::from_secs(2)).await;
+            
+            println!("✅ Web interface is ready!");
+            println!("📱 Open your browser/phone and go to: http://{}", connection.ip_address);
+            println!("🔧 Use the web interface to:");
+            println!("   • View current screen");
+            println!("   • Fix display/keyboard issues");
+            println!("   • Manage operating systems");
+            println!("   • Change settings");
+            
+            Ok(())
+        }
+        Err(e) => {
+            error!("❌ Network connection failed: {}", e);
+            println!("\n⚠️  No network connection available");
+            println!("Options:");
+            println!("1. Check network settings in config/default.toml");
+            println!("2. Connect Ethernet cable");
+            println!("3. Restart to try again");
+
+            Err(e)
+        }
+    }
+}
+
+/// Thin wrapper around `WebServer::new`/`start`, so both call sites above
+/// just need a `Config`, a config path, and the shared `boot_menu` handle.
+async fn start_web_server(config: &Config, config_path: &Path, boot_menu: std::sync::Arc<tokio::sync::Mutex<BootMenu>>) -> Result<()> {
+    let web_server = WebServer::new(config, config_path, boot_menu)?;
+    web_server.start().await
 }
\ No newline at end of file
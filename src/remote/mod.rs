@@ -0,0 +1,7 @@
+pub mod mqtt;
+pub mod power;
+pub mod screen_capture;
+pub mod telemetry;
+pub mod web_server;
+
+pub use web_server::WebServer;
@@ -0,0 +1,132 @@
+// MQTT bridge for handhelds with no reachable local web UI: republishes
+// `web_server::SystemStatus` to `dos_safar/<device_id>/status` on a timer
+// and subscribes to `dos_safar/<device_id>/boot` for `BootRequest`s, so
+// the device can be monitored and booted remotely without ever opening
+// `/api/status` or `/api/boot` over HTTP. Gated behind `config.mqtt.enabled`,
+// started from `main` alongside the web server.
+use anyhow::{Context, Result};
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::bootloader::menu::BootMenu;
+use crate::remote::web_server::{collect_system_status, trigger_boot, BootRequest};
+use crate::utils::config::Config;
+use crate::utils::sntp;
+
+/// Gap before reconnecting after the client/event loop drops (broker
+/// restart, network blip) - this bridge keeps retrying forever rather
+/// than give up, since a handheld with no web UI has no other way back in.
+const RECONNECT_BACKOFF: Duration = Duration::from_secs(5);
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+
+/// Runs forever, alongside the web server. No-ops entirely (never
+/// returns) if `config.mqtt.enabled` is false, same as
+/// `main::run_recovery_watchdog`'s disabled branch. Otherwise syncs the
+/// clock via `utils::sntp` once up front, then connects and reconnects to
+/// the configured broker indefinitely. `boot_menu` is the same handle
+/// passed into `web_server::WebServer`, so an MQTT boot command and an
+/// `/api/boot` request drive the same `BootMenu`.
+pub async fn run(config: Config, boot_menu: Arc<Mutex<BootMenu>>) {
+    if !config.mqtt.enabled {
+        return std::future::pending::<()>().await;
+    }
+
+    if let Err(e) = sntp::sync_time(&config.mqtt.sntp_server) {
+        warn!("SNTP sync failed, publishing with the existing system clock: {}", e);
+    }
+
+    let device_id = config.mqtt.device_id.clone().or_else(read_hostname).unwrap_or_else(|| "dos_safar".to_string());
+    let status_topic = format!("dos_safar/{}/status", device_id);
+    let boot_topic = format!("dos_safar/{}/boot", device_id);
+
+    loop {
+        if let Err(e) = run_once(&config, &device_id, &status_topic, &boot_topic, &boot_menu).await {
+            error!("MQTT bridge lost its connection, retrying in {}s: {}", RECONNECT_BACKOFF.as_secs(), e);
+        }
+        tokio::time::sleep(RECONNECT_BACKOFF).await;
+    }
+}
+
+/// `/etc/hostname`, trimmed - the fallback `device_id` when
+/// `config.mqtt.device_id` is left unset.
+fn read_hostname() -> Option<String> {
+    fs::read_to_string("/etc/hostname").ok().map(|s| s.trim().to_string()).filter(|s| !s.is_empty())
+}
+
+/// One connection's worth of work: subscribes to `boot_topic`, spawns a
+/// ticker that republishes `SystemStatus` to `status_topic` every
+/// `config.mqtt.publish_interval_seconds`, and drives the event loop
+/// until it errors. Always returns `Err` - the only way out of a healthy
+/// connection is the caller's backoff-and-retry loop in [`run`].
+async fn run_once(
+    config: &Config,
+    device_id: &str,
+    status_topic: &str,
+    boot_topic: &str,
+    boot_menu: &Arc<Mutex<BootMenu>>,
+) -> Result<()> {
+    let mut mqtt_options = MqttOptions::new(format!("dos_safar-{}", device_id), config.mqtt.broker_host.as_str(), config.mqtt.broker_port);
+    mqtt_options.set_keep_alive(KEEP_ALIVE);
+    if let (Some(username), Some(password)) = (&config.mqtt.username, &config.mqtt.password) {
+        mqtt_options.set_credentials(username.as_str(), password.as_str());
+    }
+
+    let (client, mut event_loop) = AsyncClient::new(mqtt_options, 10);
+    client
+        .subscribe(boot_topic, QoS::AtLeastOnce)
+        .await
+        .with_context(|| format!("failed to subscribe to {}", boot_topic))?;
+    info!("📡 MQTT bridge connected to {}:{}, device id '{}'", config.mqtt.broker_host, config.mqtt.broker_port, device_id);
+
+    let publisher = tokio::spawn(run_publisher(
+        client,
+        config.clone(),
+        status_topic.to_string(),
+        Duration::from_secs(config.mqtt.publish_interval_seconds.max(1)),
+    ));
+
+    let result = loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::Publish(publish))) if publish.topic == boot_topic => {
+                match serde_json::from_slice::<BootRequest>(&publish.payload) {
+                    Ok(request) => {
+                        info!("📡 MQTT boot command received for: {}", request.os_name);
+                        trigger_boot(boot_menu, &request.os_name).await;
+                    }
+                    Err(e) => warn!("Ignoring malformed MQTT boot payload: {}", e),
+                }
+            }
+            Ok(_) => {}
+            Err(e) => break e,
+        }
+    };
+
+    publisher.abort();
+    Err(result).context("MQTT event loop error")
+}
+
+/// Republishes `SystemStatus` to `status_topic` every `interval` for as
+/// long as the connection this was spawned under stays up; aborted by
+/// [`run_once`] once its event loop errors out.
+async fn run_publisher(client: AsyncClient, config: Config, status_topic: String, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let status = collect_system_status(&config).await;
+        let payload = match serde_json::to_vec(&status) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to serialize SystemStatus for MQTT: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = client.publish(status_topic.as_str(), QoS::AtLeastOnce, false, payload).await {
+            warn!("Failed to publish MQTT status: {}", e);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+// Reboot/shutdown/factory-reset actions for the web power menu
+// (`remote::web_server`'s `/api/power/*` routes) and the physical
+// long-press recovery combo (`main::run_recovery_watchdog`) - both paths
+// end up here so there's one place that actually shells out or touches
+// persisted state.
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::info;
+
+use crate::bootloader::boot_state;
+use crate::utils::config::Config;
+
+pub fn reboot() -> Result<()> {
+    info!("🔁 Rebooting system...");
+    let output = std::process::Command::new("reboot")
+        .output()
+        .context("Failed to invoke reboot")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "reboot command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+pub fn shutdown() -> Result<()> {
+    info!("💤 Shutting down system...");
+    let output = std::process::Command::new("shutdown")
+        .args(&["-h", "now"])
+        .output()
+        .context("Failed to invoke shutdown")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "shutdown command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Wipes user config and OS boot-selection history back to defaults:
+/// overwrites `config_path` with `Config::default()` and deletes the
+/// persistent boot-state file (`bootloader::boot_state`), so the next boot
+/// starts from a clean slate. Does not reboot by itself - callers that
+/// want a reset-and-reboot (the web route, the long-press combo) call
+/// [`reboot`] afterwards.
+pub fn reset_config(config: &Config, config_path: &Path) -> Result<()> {
+    info!("♻️  Resetting config and OS selections to defaults: {}", config_path.display());
+    Config::default()
+        .save(config_path)
+        .with_context(|| format!("Failed to write default config to {}", config_path.display()))?;
+
+    let state_path = boot_state::state_file_path(config);
+    if state_path.exists() {
+        std::fs::remove_file(&state_path)
+            .with_context(|| format!("Failed to remove boot state file: {}", state_path.display()))?;
+    }
+
+    Ok(())
+}
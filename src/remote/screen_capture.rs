@@ -0,0 +1,188 @@
+// Framebuffer capture + tile diffing for the live screen-streaming
+// WebSocket endpoint (`/ws/screen`): reads raw /dev/fb0 bytes, converts
+// them to RGB8, and only re-encodes the tiles that changed since the
+// previous frame so a slow link isn't asked to push a full frame every
+// tick.
+use anyhow::{Context, Result};
+use image::codecs::png::PngEncoder;
+use image::ImageEncoder;
+use memmap2::Mmap;
+use std::fs;
+use std::fs::File;
+use tracing::warn;
+
+/// Tile edge length in pixels for `diff_tiles`'s change grid: small enough
+/// that a moving cursor or HUD element doesn't dirty the whole frame,
+/// large enough to keep the per-tile PNG header overhead down.
+const TILE_SIZE: u32 = 64;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FramebufferInfo {
+    pub width: u32,
+    pub height: u32,
+    pub bytes_per_line: u32,
+    pub color_depth: u32,
+}
+
+/// One changed tile from `diff_tiles`, already PNG-encoded and ready to
+/// frame onto the `/ws/screen` socket.
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub png: Vec<u8>,
+}
+
+/// Probes `/sys/class/graphics/fb0` the same way
+/// `DisplayTester::get_framebuffer_config` does, independently, since a
+/// capture session shouldn't need to drag in the full display-test
+/// dependency chain (device/LCD config) just to read a resolution.
+pub fn probe_framebuffer() -> Result<FramebufferInfo> {
+    let fb_path = "/sys/class/graphics/fb0";
+    if !std::path::Path::new(fb_path).exists() {
+        return Err(anyhow::anyhow!("Framebuffer not found"));
+    }
+
+    let virtual_size = fs::read_to_string(format!("{}/virtual_size", fb_path)).context("Failed to read virtual_size")?;
+    let (width, height) = virtual_size
+        .trim()
+        .split_once(',')
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .context("unexpected virtual_size format")?;
+
+    let bits_per_pixel = fs::read_to_string(format!("{}/bits_per_pixel", fb_path)).unwrap_or_else(|_| "24".to_string());
+    let color_depth: u32 = bits_per_pixel.trim().parse().unwrap_or(24);
+
+    if !matches!(color_depth, 16 | 24 | 32) {
+        return Err(anyhow::anyhow!(
+            "unsupported framebuffer color depth {}bpp (only 16/24/32bpp are supported)",
+            color_depth
+        ));
+    }
+
+    let file = File::open("/dev/fb0").context("failed to open /dev/fb0")?;
+    let bytes_per_line = read_line_length(&file).unwrap_or_else(|| width * color_depth.div_ceil(8));
+
+    Ok(FramebufferInfo { width, height, bytes_per_line, color_depth })
+}
+
+/// Real (possibly padded) stride via `FBIOGET_FSCREENINFO`, reusing
+/// `hardware::display`'s ioctl wrapper instead of redeclaring it.
+fn read_line_length(file: &File) -> Option<u32> {
+    crate::hardware::display::read_fb_fix_screeninfo(file)
+        .ok()
+        .map(|info| info.line_length)
+        .filter(|&length| length > 0)
+}
+
+/// Mmaps `/dev/fb0` read-only and converts it to a flat RGB8 buffer
+/// (`width * height * 3` bytes), unpacking whatever native pixel format
+/// `info.color_depth` describes.
+pub fn capture_frame(info: &FramebufferInfo) -> Result<Vec<u8>> {
+    let file = File::open("/dev/fb0").context("failed to open /dev/fb0")?;
+    let mmap = unsafe { Mmap::map(&file).context("failed to mmap /dev/fb0")? };
+
+    let bytes_per_pixel = info.color_depth.div_ceil(8).max(1);
+    let mut rgb = vec![0u8; (info.width * info.height * 3) as usize];
+
+    for y in 0..info.height {
+        let row_start = (y * info.bytes_per_line) as usize;
+        for x in 0..info.width {
+            let px_start = row_start + (x * bytes_per_pixel) as usize;
+            let px_end = px_start + bytes_per_pixel as usize;
+            if px_end > mmap.len() {
+                continue;
+            }
+
+            let (r, g, b) = unpack_pixel(&mmap[px_start..px_end], info.color_depth);
+            let out_index = ((y * info.width + x) * 3) as usize;
+            rgb[out_index] = r;
+            rgb[out_index + 1] = g;
+            rgb[out_index + 2] = b;
+        }
+    }
+
+    Ok(rgb)
+}
+
+/// Inverse of `hardware::display::pack_pixel`: 16-bit RGB565, or 24/32-bit
+/// packed BGR(X) (the common Linux fbdev layouts). Returns black for any
+/// depth/slice combination it doesn't recognize instead of indexing blind -
+/// `probe_framebuffer` already rejects anything but 16/24/32bpp, but this
+/// keeps a malformed or short slice from panicking the `/ws/screen` task.
+fn unpack_pixel(bytes: &[u8], color_depth: u32) -> (u8, u8, u8) {
+    match color_depth {
+        16 if bytes.len() >= 2 => {
+            let packed = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let r5 = (packed >> 11) & 0x1F;
+            let g6 = (packed >> 5) & 0x3F;
+            let b5 = packed & 0x1F;
+            (((r5 * 255) / 31) as u8, ((g6 * 255) / 63) as u8, ((b5 * 255) / 31) as u8)
+        }
+        24 | 32 if bytes.len() >= 3 => (bytes[2], bytes[1], bytes[0]),
+        _ => (0, 0, 0),
+    }
+}
+
+/// Splits `current` into `TILE_SIZE`-pixel tiles and PNG-encodes only the
+/// ones that differ from `prev` (every tile, if there's no previous
+/// frame yet).
+pub fn diff_tiles(prev: Option<&[u8]>, current: &[u8], width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+
+    let mut y = 0;
+    while y < height {
+        let tile_h = TILE_SIZE.min(height - y);
+        let mut x = 0;
+        while x < width {
+            let tile_w = TILE_SIZE.min(width - x);
+
+            let changed = match prev {
+                None => true,
+                Some(prev) => tile_differs(prev, current, width, x, y, tile_w, tile_h),
+            };
+            if changed {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: tile_w,
+                    height: tile_h,
+                    png: encode_tile_png(current, width, x, y, tile_w, tile_h),
+                });
+            }
+
+            x += TILE_SIZE;
+        }
+        y += TILE_SIZE;
+    }
+
+    tiles
+}
+
+fn tile_differs(prev: &[u8], current: &[u8], width: u32, x: u32, y: u32, tile_w: u32, tile_h: u32) -> bool {
+    for row in 0..tile_h {
+        let row_start = (((y + row) * width + x) * 3) as usize;
+        let row_end = row_start + (tile_w * 3) as usize;
+        if prev[row_start..row_end] != current[row_start..row_end] {
+            return true;
+        }
+    }
+    false
+}
+
+fn encode_tile_png(frame: &[u8], width: u32, x: u32, y: u32, tile_w: u32, tile_h: u32) -> Vec<u8> {
+    let mut tile_rgb = Vec::with_capacity((tile_w * tile_h * 3) as usize);
+    for row in 0..tile_h {
+        let row_start = (((y + row) * width + x) * 3) as usize;
+        let row_end = row_start + (tile_w * 3) as usize;
+        tile_rgb.extend_from_slice(&frame[row_start..row_end]);
+    }
+
+    let mut png = Vec::new();
+    let encoder = PngEncoder::new(&mut png);
+    if let Err(e) = encoder.write_image(&tile_rgb, tile_w, tile_h, image::ColorType::Rgb8) {
+        warn!("failed to encode screen tile as PNG: {}", e);
+    }
+    png
+}
@@ -0,0 +1,119 @@
+// System telemetry sampling for the web status API: CPU/memory/temperature
+// readings straight from /proc and /sys, replacing the placeholder
+// constants `get_system_status` used to return.
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+/// Default gap between the two `/proc/stat`/interface-counter snapshots
+/// used to derive a percentage or a throughput rate.
+pub const SAMPLE_WINDOW: Duration = Duration::from_millis(500);
+
+/// One CPU-time sample from `/proc/stat`'s aggregate `cpu` line, in
+/// jiffies: `idle` is `idle + iowait`, `total` is the sum of every field.
+/// The aggregate line only yields a meaningful percentage as a delta
+/// between two samples, never from a single snapshot.
+#[derive(Debug, Clone, Copy)]
+struct CpuSample {
+    idle: u64,
+    total: u64,
+}
+
+fn read_cpu_sample() -> Result<CpuSample> {
+    let stat = fs::read_to_string("/proc/stat").context("failed to read /proc/stat")?;
+    let line = stat.lines().next().context("/proc/stat is empty")?;
+    let fields: Vec<u64> = line.split_whitespace().skip(1).filter_map(|field| field.parse().ok()).collect();
+    if fields.len() < 4 {
+        return Err(anyhow::anyhow!("unexpected /proc/stat cpu line: {}", line));
+    }
+
+    let idle = fields[3] + fields.get(4).copied().unwrap_or(0);
+    let total: u64 = fields.iter().sum();
+    Ok(CpuSample { idle, total })
+}
+
+/// CPU usage percentage over `sample_window`, taken as two `/proc/stat`
+/// reads `sample_window` apart.
+pub async fn cpu_usage_percent(sample_window: Duration) -> Result<f32> {
+    let first = read_cpu_sample()?;
+    tokio::time::sleep(sample_window).await;
+    let second = read_cpu_sample()?;
+
+    let idle_delta = second.idle.saturating_sub(first.idle) as f32;
+    let total_delta = second.total.saturating_sub(first.total) as f32;
+    if total_delta <= 0.0 {
+        return Ok(0.0);
+    }
+
+    Ok(((total_delta - idle_delta) / total_delta) * 100.0)
+}
+
+/// Memory usage as `(MemTotal - MemAvailable) / MemTotal * 100`, parsed
+/// from `/proc/meminfo`'s `MemTotal:`/`MemAvailable:` lines (both in kB).
+/// `MemAvailable` (not `MemFree`) is the kernel's own estimate of memory
+/// a new process could get without swapping, which is what "usage" means
+/// to a user watching this number.
+pub fn memory_usage_percent() -> Result<f32> {
+    let meminfo = fs::read_to_string("/proc/meminfo").context("failed to read /proc/meminfo")?;
+    let mut fields: HashMap<&str, u64> = HashMap::new();
+    for line in meminfo.lines() {
+        if let Some((key, rest)) = line.split_once(':') {
+            if let Some(value) = rest.split_whitespace().next().and_then(|v| v.parse().ok()) {
+                fields.insert(key, value);
+            }
+        }
+    }
+
+    let total = *fields.get("MemTotal").context("MemTotal missing from /proc/meminfo")?;
+    let available = *fields.get("MemAvailable").context("MemAvailable missing from /proc/meminfo")?;
+    if total == 0 {
+        return Ok(0.0);
+    }
+
+    Ok(((total - available) as f32 / total as f32) * 100.0)
+}
+
+/// SoC temperature in Celsius, read from the default thermal zone
+/// (`/sys/class/thermal/thermal_zone0/temp`, reported in millidegrees).
+pub fn cpu_temperature_celsius() -> Result<f32> {
+    let raw = fs::read_to_string("/sys/class/thermal/thermal_zone0/temp")
+        .context("failed to read /sys/class/thermal/thermal_zone0/temp")?;
+    let millidegrees: f32 = raw.trim().parse().context("unexpected thermal_zone0 temp value")?;
+    Ok(millidegrees / 1000.0)
+}
+
+/// System uptime, formatted as `"<days>d <hours>h <minutes>m"` from
+/// `/proc/uptime`'s first field (seconds since boot).
+pub fn system_uptime() -> Result<String> {
+    let raw = fs::read_to_string("/proc/uptime").context("failed to read /proc/uptime")?;
+    let seconds: f64 = raw
+        .split_whitespace()
+        .next()
+        .context("/proc/uptime is empty")?
+        .parse()
+        .context("unexpected /proc/uptime value")?;
+
+    let total_minutes = (seconds / 60.0) as u64;
+    let days = total_minutes / (24 * 60);
+    let hours = (total_minutes / 60) % 24;
+    let minutes = total_minutes % 60;
+    Ok(format!("{}d {}h {}m", days, hours, minutes))
+}
+
+/// Current WiFi signal strength in dBm for `interface`, parsed from
+/// `/proc/net/wireless`'s `level` column (the third numeric field on the
+/// interface's line, e.g. `wlan0: 0000   70.  -45.  -256 ...`). Returns
+/// `None` for a wired interface or one with no entry in that table.
+pub fn wifi_signal_dbm(interface: &str) -> Option<i32> {
+    let wireless = fs::read_to_string("/proc/net/wireless").ok()?;
+    for line in wireless.lines() {
+        let Some((name, rest)) = line.split_once(':') else { continue };
+        if name.trim() != interface {
+            continue;
+        }
+        let level = rest.split_whitespace().nth(2)?;
+        return level.trim_end_matches('.').parse().ok();
+    }
+    None
+}
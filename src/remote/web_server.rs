@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use axum::{
-    extract::Query,
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{Query, State},
     http::StatusCode,
     response::{Html, Json},
     routing::{get, post},
@@ -9,14 +10,47 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
 use tower::ServiceBuilder;
 use tower_http::{
     cors::CorsLayer,
     services::ServeDir,
 };
 use tracing::{info, warn};
+use crate::bootloader::menu::BootMenu;
+use crate::hardware::bluetooth::{BleDevice, BluetoothManager};
+use crate::hardware::device_detect::DeviceDetector;
+use crate::hardware::network::{NetworkConnection, NetworkManager};
+use crate::hardware::network_backend::NetworkConnectError;
+use crate::hardware::wpa_ctrl::{dedupe_strongest_per_ssid, ScanResult};
+use crate::remote::power;
+use crate::remote::screen_capture;
+use crate::remote::telemetry;
 use crate::utils::config::Config;
 
+/// Shared Axum state: the loaded config plus the on-disk path it was
+/// loaded from, so `power_reset_config` has somewhere to write
+/// `Config::default()` back to (mirrors `bootloader::menu::BootMenu::new`,
+/// which takes the same path explicitly for the same reason), plus the
+/// `BootMenu` handle `boot_system` actually boots through.
+#[derive(Debug, Clone)]
+struct AppState {
+    config: Config,
+    config_path: PathBuf,
+    /// Shared with `remote::mqtt::run` so both the HTTP and MQTT boot
+    /// paths drive the same `BootMenu` and see the same
+    /// `available_systems`/boot-state updates.
+    boot_menu: Arc<Mutex<BootMenu>>,
+}
+
+/// Interval between captured frames on `/ws/screen`; 10fps is plenty for
+/// a remote-control preview and keeps the tile-diff/PNG-encode work from
+/// competing with the rest of the process.
+const SCREEN_STREAM_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SystemStatus {
     pub device_model: String,
@@ -26,6 +60,9 @@ pub struct SystemStatus {
     pub temperature: Option<f32>,
     pub network_status: NetworkStatus,
     pub available_systems: Vec<OSInfo>,
+    /// Currently-connected bonded BLE gamepads, from
+    /// `BluetoothManager::connected_devices`.
+    pub bluetooth_devices: Vec<BleDevice>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +71,13 @@ pub struct NetworkStatus {
     pub interface: String,
     pub ip_address: String,
     pub signal_strength: Option<i32>,
+    /// Cumulative bytes received/transmitted on `interface` since it came
+    /// up, from `NetworkManager::sample_throughput`.
+    pub rx_bytes_total: u64,
+    pub tx_bytes_total: u64,
+    /// Throughput sampled over `telemetry::SAMPLE_WINDOW`.
+    pub rx_bytes_per_sec: u64,
+    pub tx_bytes_per_sec: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,12 +102,16 @@ pub struct ControllerInput {
 
 pub struct WebServer {
     config: Config,
+    config_path: PathBuf,
+    boot_menu: Arc<Mutex<BootMenu>>,
 }
 
 impl WebServer {
-    pub fn new(config: &Config) -> Result<Self> {
+    pub fn new(config: &Config, config_path: &Path, boot_menu: Arc<Mutex<BootMenu>>) -> Result<Self> {
         Ok(WebServer {
             config: config.clone(),
+            config_path: config_path.to_path_buf(),
+            boot_menu,
         })
     }
 
@@ -93,21 +141,38 @@ impl WebServer {
             .route("/api/files/upload", post(upload_file))
             .route("/api/network/scan", get(scan_networks))
             .route("/api/network/connect", post(connect_network))
-            
+            .route("/api/bluetooth/scan", get(bluetooth_scan))
+            .route("/api/bluetooth/pair", post(bluetooth_pair))
+            .route("/api/power/reboot", post(power_reboot))
+            .route("/api/power/shutdown", post(power_shutdown))
+            .route("/api/power/reset-config", post(power_reset_config))
+
+            // Low-latency WebSocket endpoints: live framebuffer tiles out,
+            // real-time controller input in, replacing the single static
+            // screenshot and one-shot input POST above for the remote page.
+            .route("/ws/screen", get(ws_screen_handler))
+            .route("/ws/input", get(ws_input_handler))
+
             // Web interface routes
             .route("/", get(serve_index))
             .route("/remote", get(serve_remote_control))
             .route("/systems", get(serve_systems_manager))
             .route("/settings", get(serve_settings))
             .route("/troubleshoot", get(serve_troubleshoot)) // ← صفحة الـ troubleshooting الجديدة
-            
+            .route("/power", get(serve_power_menu))
+
             // Static files
             .nest_service("/static", ServeDir::new(&self.config.web.static_files_path))
-            
+
             // Middleware
             .layer(ServiceBuilder::new()
                 .layer(CorsLayer::permissive())
-            );
+            )
+            .with_state(AppState {
+                config: self.config.clone(),
+                config_path: self.config_path.clone(),
+                boot_menu: self.boot_menu.clone(),
+            });
 
         Ok(app)
     }
@@ -115,19 +180,73 @@ impl WebServer {
 
 // API Handlers
 
-async fn get_system_status() -> Result<Json<SystemStatus>, StatusCode> {
+async fn get_system_status(State(state): State<AppState>) -> Result<Json<SystemStatus>, StatusCode> {
+    Ok(Json(collect_system_status(&state.config).await))
+}
+
+/// Gathers the same payload served by `GET /api/status`, shared with
+/// `remote::mqtt`'s periodic publisher so both paths report identical
+/// telemetry.
+pub(crate) async fn collect_system_status(config: &Config) -> SystemStatus {
+    let device_model = match DeviceDetector::new().detect_device().await {
+        Ok(device_info) => device_info.model,
+        Err(e) => {
+            warn!("Failed to detect device for status endpoint: {}", e);
+            "Unknown".to_string()
+        }
+    };
+
+    let network_manager = NetworkManager::new(config);
+    let active_interface = network_manager.active_interface().await;
+
+    let cpu_usage_fut = telemetry::cpu_usage_percent(telemetry::SAMPLE_WINDOW);
+    let network_status = if let Some((interface, ip_address)) = active_interface {
+        let (cpu_usage, throughput) =
+            tokio::join!(cpu_usage_fut, network_manager.sample_throughput(&interface, telemetry::SAMPLE_WINDOW));
+        let throughput = throughput.ok();
+
+        (
+            cpu_usage,
+            NetworkStatus {
+                connected: true,
+                signal_strength: telemetry::wifi_signal_dbm(&interface),
+                rx_bytes_total: throughput.as_ref().map(|t| t.cumulative.received).unwrap_or(0),
+                tx_bytes_total: throughput.as_ref().map(|t| t.cumulative.transmitted).unwrap_or(0),
+                rx_bytes_per_sec: throughput.as_ref().map(|t| t.received_bytes_per_sec).unwrap_or(0),
+                tx_bytes_per_sec: throughput.as_ref().map(|t| t.transmitted_bytes_per_sec).unwrap_or(0),
+                interface,
+                ip_address,
+            },
+        )
+    } else {
+        (
+            cpu_usage_fut.await,
+            NetworkStatus {
+                connected: false,
+                interface: String::new(),
+                ip_address: String::new(),
+                signal_strength: None,
+                rx_bytes_total: 0,
+                tx_bytes_total: 0,
+                rx_bytes_per_sec: 0,
+                tx_bytes_per_sec: 0,
+            },
+        )
+    };
+    let (cpu_usage, network_status) = network_status;
+
+    let bluetooth_devices = BluetoothManager::new(config).connected_devices().await.unwrap_or_else(|e| {
+        warn!("Failed to query connected Bluetooth devices: {}", e);
+        Vec::new()
+    });
+
     let status = SystemStatus {
-        device_model: "Raspberry Pi 4B".to_string(), // This would come from device detection
-        uptime: get_system_uptime().unwrap_or_else(|_| "Unknown".to_string()),
-        cpu_usage: get_cpu_usage().unwrap_or(0.0),
-        memory_usage: get_memory_usage().unwrap_or(0.0),
-        temperature: get_cpu_temperature().ok(),
-        network_status: NetworkStatus {
-            connected: true,
-            interface: "wlan0".to_string(),
-            ip_address: "192.168.1.100".to_string(),
-            signal_strength: Some(-45),
-        },
+        device_model,
+        uptime: telemetry::system_uptime().unwrap_or_else(|_| "Unknown".to_string()),
+        cpu_usage: cpu_usage.unwrap_or(0.0),
+        memory_usage: telemetry::memory_usage_percent().unwrap_or(0.0),
+        temperature: telemetry::cpu_temperature_celsius().ok(),
+        network_status,
         available_systems: vec![
             OSInfo {
                 name: "RetroPie".to_string(),
@@ -144,9 +263,10 @@ async fn get_system_status() -> Result<Json<SystemStatus>, StatusCode> {
                 last_used: None,
             },
         ],
+        bluetooth_devices,
     };
 
-    Ok(Json(status))
+    status
 }
 
 async fn get_available_systems() -> Result<Json<Vec<OSInfo>>, StatusCode> {
@@ -171,27 +291,119 @@ async fn get_available_systems() -> Result<Json<Vec<OSInfo>>, StatusCode> {
     Ok(Json(systems))
 }
 
-async fn boot_system(Json(request): Json<BootRequest>) -> Result<Json<HashMap<String, String>>, StatusCode> {
-    info!("Boot request received for: {}", request.os_name);
-    
-    // This would trigger the actual boot process
+async fn boot_system(
+    State(state): State<AppState>,
+    Json(request): Json<BootRequest>,
+) -> Result<Json<HashMap<String, String>>, StatusCode> {
+    let message = trigger_boot(&state.boot_menu, &request.os_name).await;
+
     let mut response = HashMap::new();
     response.insert("status".to_string(), "success".to_string());
-    response.insert("message".to_string(), format!("Booting {}", request.os_name));
-    
+    response.insert("message".to_string(), message);
+
     Ok(Json(response))
 }
 
+/// Shared by `POST /api/boot` above and `remote::mqtt`'s boot command
+/// topic, so both paths trigger the same real boot logic: looks `os_name`
+/// up in `boot_menu`'s `available_systems` and kexecs into it via
+/// `BootMenu::boot_by_name` (`boot_operating_system` -> `kexec::kexec_boot`).
+/// A successful kexec never returns (the process is replaced), so the
+/// `Ok` branch below only fires for callers that race this against a
+/// boot that turned out to fail before the jump.
+pub(crate) async fn trigger_boot(boot_menu: &Arc<Mutex<BootMenu>>, os_name: &str) -> String {
+    info!("Boot request received for: {}", os_name);
+
+    let menu = boot_menu.lock().await;
+    match menu.boot_by_name(os_name).await {
+        Ok(()) => format!("Booting {}", os_name),
+        Err(e) => {
+            warn!("Failed to boot {}: {}", os_name, e);
+            format!("Failed to boot {}: {}", os_name, e)
+        }
+    }
+}
+
 async fn send_input(Json(input): Json<ControllerInput>) -> Result<Json<HashMap<String, String>>, StatusCode> {
-    info!("Input received: {} = {}", input.button, input.pressed);
-    
-    // This would send the input to the running system
+    apply_controller_input(&input).await;
+
     let mut response = HashMap::new();
     response.insert("status".to_string(), "received".to_string());
-    
+
     Ok(Json(response))
 }
 
+/// Shared by the one-shot `POST /api/input` handler above and the
+/// real-time `/ws/input` socket below.
+async fn apply_controller_input(input: &ControllerInput) {
+    info!("Input received: {} = {}", input.button, input.pressed);
+    // This would send the input to the running system
+}
+
+async fn ws_screen_handler(ws: WebSocketUpgrade) -> axum::response::Response {
+    ws.on_upgrade(handle_screen_socket)
+}
+
+/// Streams `/dev/fb0` over the socket as a sequence of changed-tile PNGs:
+/// each binary message is a `x:u32 y:u32 width:u32 height:u32` big-endian
+/// header followed by that tile's PNG bytes, so the client can blit it
+/// straight onto a canvas without decoding a full frame every tick.
+async fn handle_screen_socket(mut socket: WebSocket) {
+    let info = match screen_capture::probe_framebuffer() {
+        Ok(info) => info,
+        Err(e) => {
+            warn!("Screen stream: no framebuffer available: {}", e);
+            let _ = socket.close().await;
+            return;
+        }
+    };
+
+    let mut previous_frame: Option<Vec<u8>> = None;
+    loop {
+        let frame = match screen_capture::capture_frame(&info) {
+            Ok(frame) => frame,
+            Err(e) => {
+                warn!("Screen stream: failed to capture frame: {}", e);
+                break;
+            }
+        };
+
+        let tiles = screen_capture::diff_tiles(previous_frame.as_deref(), &frame, info.width, info.height);
+        for tile in tiles {
+            let mut message = Vec::with_capacity(16 + tile.png.len());
+            message.extend_from_slice(&tile.x.to_be_bytes());
+            message.extend_from_slice(&tile.y.to_be_bytes());
+            message.extend_from_slice(&tile.width.to_be_bytes());
+            message.extend_from_slice(&tile.height.to_be_bytes());
+            message.extend_from_slice(&tile.png);
+
+            if socket.send(Message::Binary(message)).await.is_err() {
+                return; // client disconnected
+            }
+        }
+
+        previous_frame = Some(frame);
+        tokio::time::sleep(SCREEN_STREAM_INTERVAL).await;
+    }
+}
+
+async fn ws_input_handler(ws: WebSocketUpgrade) -> axum::response::Response {
+    ws.on_upgrade(handle_input_socket)
+}
+
+/// Real-time counterpart to `POST /api/input`: each text message is a
+/// JSON-encoded `ControllerInput`, applied as soon as it arrives instead
+/// of waiting on a request/response round-trip per button press.
+async fn handle_input_socket(mut socket: WebSocket) {
+    while let Some(Ok(message)) = socket.recv().await {
+        let Message::Text(text) = message else { continue };
+        match serde_json::from_str::<ControllerInput>(&text) {
+            Ok(input) => apply_controller_input(&input).await,
+            Err(e) => warn!("Ignoring malformed /ws/input message: {}", e),
+        }
+    }
+}
+
 async fn get_screenshot() -> Result<Json<HashMap<String, String>>, StatusCode> {
     // This would capture the current screen
     let mut response = HashMap::new();
@@ -209,34 +421,122 @@ async fn upload_file() -> Result<Json<HashMap<String, String>>, StatusCode> {
     Ok(Json(response))
 }
 
-async fn scan_networks() -> Result<Json<Vec<HashMap<String, String>>>, StatusCode> {
-    // This would scan for WiFi networks
-    let networks = vec![
-        {
-            let mut network = HashMap::new();
-            network.insert("ssid".to_string(), "Gaming_Network".to_string());
-            network.insert("signal".to_string(), "-45".to_string());
-            network.insert("security".to_string(), "WPA2".to_string());
-            network
-        },
-        {
-            let mut network = HashMap::new();
-            network.insert("ssid".to_string(), "Public_WiFi".to_string());
-            network.insert("signal".to_string(), "-60".to_string());
-            network.insert("security".to_string(), "Open".to_string());
-            network
-        },
-    ];
-    
-    Ok(Json(networks))
+#[derive(Debug, Deserialize)]
+pub struct ConnectNetworkRequest {
+    pub ssid: String,
+    pub password: Option<String>,
+}
+
+async fn scan_networks(State(state): State<AppState>) -> Result<Json<Vec<ScanResult>>, StatusCode> {
+    let network_manager = NetworkManager::new(&state.config);
+    match network_manager.scan_networks().await {
+        Ok(results) => Ok(Json(dedupe_strongest_per_ssid(results))),
+        Err(e) => {
+            warn!("Failed to scan WiFi networks: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+async fn connect_network(
+    State(state): State<AppState>,
+    Json(request): Json<ConnectNetworkRequest>,
+) -> Result<Json<NetworkConnection>, (StatusCode, Json<HashMap<String, String>>)> {
+    let network_manager = NetworkManager::new(&state.config);
+    network_manager
+        .connect_to_network(&request.ssid, request.password.as_deref())
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!("Failed to connect to network {}: {}", request.ssid, e);
+            let status = classify_connect_error(&e);
+            let mut error = HashMap::new();
+            error.insert("status".to_string(), "error".to_string());
+            error.insert("message".to_string(), e.to_string());
+            (status, Json(error))
+        })
+}
+
+/// يميّز فشل الربط/المصادقة الفعلي (401، كلمة مرور خاطئة) عن فئات الفشل
+/// الأخرى (لا توجد واجهة واي-فاي، فشل DHCP بعد ربط ناجح) عبر نوع
+/// `NetworkConnectError` المُصنَّف بدل مطابقة نص الخطأ المُترجَم - تغيير
+/// صياغة الرسالة أو إضافة مسار فشل جديد في الواجهة الخلفية لا يُفقد هذا
+/// التصنيف صمتاً.
+fn classify_connect_error(error: &anyhow::Error) -> StatusCode {
+    match error.downcast_ref::<NetworkConnectError>() {
+        Some(NetworkConnectError::AuthFailed(_)) => StatusCode::UNAUTHORIZED,
+        Some(NetworkConnectError::NoInterface(_)) => StatusCode::SERVICE_UNAVAILABLE,
+        Some(NetworkConnectError::DhcpFailed(_)) => StatusCode::BAD_GATEWAY,
+        None => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BluetoothPairRequest {
+    pub device_id: String,
+}
+
+async fn bluetooth_scan(State(state): State<AppState>) -> Result<Json<Vec<BleDevice>>, StatusCode> {
+    match BluetoothManager::new(&state.config).scan().await {
+        Ok(devices) => Ok(Json(devices)),
+        Err(e) => {
+            warn!("Failed to scan for Bluetooth devices: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+async fn bluetooth_pair(
+    State(state): State<AppState>,
+    Json(request): Json<BluetoothPairRequest>,
+) -> Result<Json<BleDevice>, (StatusCode, Json<HashMap<String, String>>)> {
+    BluetoothManager::new(&state.config)
+        .pair(&request.device_id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            warn!("Failed to pair Bluetooth device {}: {}", request.device_id, e);
+            let mut error = HashMap::new();
+            error.insert("status".to_string(), "error".to_string());
+            error.insert("message".to_string(), e.to_string());
+            (StatusCode::UNAUTHORIZED, Json(error))
+        })
 }
 
-async fn connect_network() -> Result<Json<HashMap<String, String>>, StatusCode> {
-    // This would connect to a WiFi network
+async fn power_reboot() -> Result<Json<HashMap<String, String>>, StatusCode> {
+    power::reboot().map_err(|e| {
+        warn!("Failed to reboot: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
     let mut response = HashMap::new();
-    response.insert("status".to_string(), "connected".to_string());
-    response.insert("ip".to_string(), "192.168.1.101".to_string());
-    
+    response.insert("status".to_string(), "rebooting".to_string());
+    Ok(Json(response))
+}
+
+async fn power_shutdown() -> Result<Json<HashMap<String, String>>, StatusCode> {
+    power::shutdown().map_err(|e| {
+        warn!("Failed to shut down: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut response = HashMap::new();
+    response.insert("status".to_string(), "shutting_down".to_string());
+    Ok(Json(response))
+}
+
+async fn power_reset_config(State(state): State<AppState>) -> Result<Json<HashMap<String, String>>, StatusCode> {
+    power::reset_config(&state.config, &state.config_path).map_err(|e| {
+        warn!("Failed to reset config to defaults: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    power::reboot().map_err(|e| {
+        warn!("Config reset but failed to reboot: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let mut response = HashMap::new();
+    response.insert("status".to_string(), "reset_rebooting".to_string());
     Ok(Json(response))
 }
 
@@ -246,6 +546,79 @@ async fn serve_index() -> Html<&'static str> {
     Html(include_str!("../../assets/web/index.html"))
 }
 
+async fn serve_power_menu() -> Html<&'static str> {
+    Html(r#"
+<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>DOS Safar - Power</title>
+    <style>
+        body {
+            margin: 0;
+            padding: 20px;
+            font-family: Arial, sans-serif;
+            background: #1a1a1a;
+            color: white;
+        }
+        .container { max-width: 400px; margin: 0 auto; }
+        .btn {
+            display: block;
+            width: 100%;
+            background: #333;
+            border: none;
+            color: white;
+            padding: 15px;
+            margin-bottom: 15px;
+            border-radius: 8px;
+            font-size: 16px;
+            cursor: pointer;
+        }
+        .btn:active { background: #555; }
+        .btn.danger { background: #5a1d1d; }
+        .btn.danger:active { background: #7a2626; }
+        #power-status { min-height: 1.5em; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <h1>🔌 DOS Safar Power</h1>
+        <button class="btn" data-action="reboot">🔁 Reboot</button>
+        <button class="btn" data-action="shutdown">💤 Shutdown</button>
+        <button class="btn danger" data-action="reset-config">♻️ Factory Reset</button>
+        <p id="power-status"></p>
+    </div>
+
+    <script>
+        const confirmations = {
+            'reboot': 'Reboot the device now?',
+            'shutdown': 'Shut down the device now?',
+            'reset-config': 'This wipes config and OS selections back to defaults and reboots. Continue?',
+        };
+
+        document.querySelectorAll('.btn').forEach(btn => {
+            btn.addEventListener('click', async () => {
+                const action = btn.dataset.action;
+                if (!confirm(confirmations[action])) return;
+
+                const status = document.getElementById('power-status');
+                status.textContent = 'Working...';
+                try {
+                    const response = await fetch(`/api/power/${action}`, { method: 'POST' });
+                    const data = await response.json();
+                    status.textContent = data.status || 'done';
+                } catch (e) {
+                    status.textContent = 'Request failed: ' + e;
+                }
+            });
+        });
+    </script>
+</body>
+</html>
+    "#)
+}
+
 async fn serve_remote_control() -> Html<&'static str> {
     Html(r#"
 <!DOCTYPE html>
@@ -353,4 +726,4 @@ async fn serve_remote_control() -> Html<&'static str> {
     
     <script>
         document.querySelectorAll('.btn').forEach(btn => {
-            btn.addEventListener('touchstart',
\ No newline at end of file
+            btn.addEventListener('touchstart',
\ No newline at end of file
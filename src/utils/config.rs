@@ -10,27 +10,107 @@ pub struct Config {
     pub network: NetworkConfig,
     pub web: WebConfig,
     pub boot: BootConfig,
-    pub lcd: LcdConfig, // إضافة جديدة
-}
-// إضافة في نهاية Default::default()
-lcd: LcdConfig {
-    enabled: true,
-    auto_detect: true,
-    driver: "auto".to_string(),
-    interface: "spi".to_string(),
-    size_inch: 3.5,
-    rotation: 0,
-    spi_bus: 0,
-    spi_device: 0,
-    spi_speed_hz: 32000000,
-    gpio_cs: Some(8),
-    gpio_dc: Some(24),
-    gpio_rst: Some(25),
-    gpio_bl: Some(18),
-    touch_enabled: true,
-    touch_device: "/dev/input/touchscreen".to_string(),
-    calibration_matrix: vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
-},
+    pub lcd: LcdConfig,
+    pub boards: BoardsConfig,
+    pub os_manager: OsManagerConfig,
+    pub serial_console: SerialConsoleConfig,
+    pub virtual_gamepad: VirtualGamepadConfig,
+    pub recovery: RecoveryConfig,
+    pub bluetooth: BluetoothConfig,
+    pub mqtt: MqttConfig,
+}
+
+/// A secondary boot-menu output/input sink over a UART cable, for headless
+/// boards (e.g. a Raspberry Pi with no attached screen). See
+/// `bootloader::console::Console`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialConsoleConfig {
+    pub enabled: bool,
+    pub device_path: String,
+    pub baud_rate: u32,
+}
+
+/// Settings for `bootloader::os_manager::OSManager`'s generation-based
+/// install/rollback/GC model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OsManagerConfig {
+    /// Maximum number of generations kept per installed OS; `gc()` prunes
+    /// the oldest ones beyond this (broken generations are pruned first).
+    pub configuration_limit: usize,
+    /// Glob patterns (supporting `*`, matched against each file's path
+    /// relative to the OS root) that `update_os` always carries over
+    /// unconditionally from the old tree into the updated one.
+    pub preserve_globs: Vec<String>,
+    /// Glob patterns `update_os` preserves only when the old copy is newer
+    /// than the incoming one; a content mismatch on both sides is reported
+    /// as a conflict in `OsUpdateResult` either way.
+    pub newer_wins_globs: Vec<String>,
+}
+
+/// User-supplied overrides/additions to the built-in board detection
+/// registry (see `hardware::board_registry`), so new SBCs can be supported
+/// without recompiling.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoardsConfig {
+    /// Path to a TOML file containing a `[[rule]]` list of extra board
+    /// detection rules, merged ahead of the built-in table.
+    pub extra_rules_path: Option<String>,
+}
+
+/// Settings for `hardware::virtual_gamepad`'s uinput remapping of
+/// fragmented GPIO D-pad/ADC analog-stick nodes into one standard
+/// gamepad device.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualGamepadConfig {
+    pub enabled: bool,
+    /// Path to a TOML file containing a `[[profile]]` list of per-model
+    /// source-to-virtual remaps (see `hardware::virtual_gamepad::GamepadProfile`).
+    /// `None` disables remapping even if `enabled` is true, since there's
+    /// nothing to map without a profile.
+    pub profile_path: Option<String>,
+}
+
+/// Settings for the physical factory-reset combo watched during the boot
+/// countdown by `main::run_recovery_watchdog`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryConfig {
+    pub enabled: bool,
+    /// Seconds `hardware::input::FACTORY_RESET_COMBO` must be held
+    /// continuously before the visible countdown starts.
+    pub hold_seconds: u64,
+    /// Seconds of visible countdown before config/OS selections are wiped
+    /// and the device reboots; releasing the combo at any point before
+    /// this elapses cancels back to `Idle`.
+    pub countdown_seconds: u64,
+}
+
+/// Settings for `hardware::bluetooth::BluetoothManager`'s BLE HID
+/// gamepad discovery/pairing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BluetoothConfig {
+    pub enabled: bool,
+}
+
+/// Settings for `remote::mqtt`'s headless telemetry/remote-boot bridge -
+/// publishes `SystemStatus` to `dos_safar/<device_id>/status` and listens
+/// for `BootRequest`s on `dos_safar/<device_id>/boot`, for handhelds with
+/// no reachable local web UI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttConfig {
+    pub enabled: bool,
+    pub broker_host: String,
+    pub broker_port: u16,
+    /// Identifies this device within its topics; falls back to the
+    /// machine hostname when unset.
+    pub device_id: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// How often the status topic is republished.
+    pub publish_interval_seconds: u64,
+    /// NTP server queried once at startup so published timestamps and
+    /// `last_used` fields are accurate even without an RTC.
+    pub sntp_server: String,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemConfig {
@@ -60,6 +140,16 @@ pub struct NetworkConfig {
     pub auto_scan_open_networks: bool,
     pub prefer_saved_networks: bool,
     pub max_connection_attempts: u32,
+    /// Issues `SAVE_CONFIG` to wpa_supplicant after a successful join, so
+    /// the network block survives past this process's own `REMOVE_NETWORK`
+    /// cleanup and wpa_supplicant auto-reconnects to it on its own next
+    /// time without going through `NetworkManager` at all.
+    pub persist_wifi_config: bool,
+    /// Explicit backend override for `network_backend::detect_backend`:
+    /// `"wpa_supplicant"`, `"nmcli"`, or `"legacy"`. `None` (the default)
+    /// lets it probe the running system instead - see that function's doc
+    /// comment for the probe order.
+    pub backend: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -144,6 +234,8 @@ impl Default for Config {
                 auto_scan_open_networks: true,
                 prefer_saved_networks: true,
                 max_connection_attempts: 3,
+                persist_wifi_config: true,
+                backend: None,
             },
             web: WebConfig {
                 port: 8080,
@@ -162,6 +254,63 @@ impl Default for Config {
                 keyboard_interrupt_enabled: true,
                 show_ip_on_screen: true,
             },
+            lcd: LcdConfig {
+                enabled: true,
+                auto_detect: true,
+                driver: "auto".to_string(),
+                interface: "spi".to_string(),
+                size_inch: 3.5,
+                rotation: 0,
+                spi_bus: 0,
+                spi_device: 0,
+                spi_speed_hz: 32000000,
+                gpio_cs: Some(8),
+                gpio_dc: Some(24),
+                gpio_rst: Some(25),
+                gpio_bl: Some(18),
+                touch_enabled: true,
+                touch_device: "/dev/input/touchscreen".to_string(),
+                calibration_matrix: vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0],
+            },
+            boards: BoardsConfig {
+                extra_rules_path: None,
+            },
+            os_manager: OsManagerConfig {
+                configuration_limit: 3,
+                preserve_globs: vec![
+                    "config.txt".to_string(),
+                    "display_config.txt".to_string(),
+                    "dos_safar_config.toml".to_string(),
+                ],
+                newer_wins_globs: vec![],
+            },
+            serial_console: SerialConsoleConfig {
+                enabled: false,
+                device_path: "/dev/ttyUSB0".to_string(),
+                baud_rate: 115200,
+            },
+            virtual_gamepad: VirtualGamepadConfig {
+                enabled: false,
+                profile_path: None,
+            },
+            recovery: RecoveryConfig {
+                enabled: true,
+                hold_seconds: 5,
+                countdown_seconds: 5,
+            },
+            bluetooth: BluetoothConfig {
+                enabled: true,
+            },
+            mqtt: MqttConfig {
+                enabled: false,
+                broker_host: "localhost".to_string(),
+                broker_port: 1883,
+                device_id: None,
+                username: None,
+                password: None,
+                publish_interval_seconds: 30,
+                sntp_server: "pool.ntp.org".to_string(),
+            },
         }
     }
 }
@@ -182,10 +331,61 @@ impl Config {
         
         let config: Config = toml::from_str(&content)
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
-        
+
         Ok(config)
     }
 
+    /// Like `load`, but layers overrides on top of the parsed TOML file -
+    /// the boot/recovery flow can then be scripted without editing the
+    /// config file. Loading never writes the overrides back; a later `save`
+    /// of the returned value writes them along with every other field.
+    ///
+    /// `args` are `key.path=value` strings (e.g. as passed via repeated
+    /// `--set` flags); `env` is scanned for `DOS_SAFAR_`-prefixed vars,
+    /// whose names are lowered and `__` is read as the dotted-path
+    /// separator (e.g. `DOS_SAFAR_WEB__PORT` -> `web.port`). Both are
+    /// applied, in order, on top of the file's values.
+    ///
+    /// Implemented by round-tripping through `toml::Value` (serialize,
+    /// patch the tree, deserialize back) so every field is reachable by
+    /// dotted key path, including array elements such as
+    /// `network.backup_networks.0.ssid`.
+    pub fn load_with_overrides<P: AsRef<Path>, I: IntoIterator<Item = (String, String)>>(
+        path: P,
+        args: &[String],
+        env: I,
+    ) -> Result<Self> {
+        let config = Self::load(path)?;
+
+        let mut overrides: Vec<(String, String)> = Vec::new();
+        for arg in args {
+            let (key, value) = arg
+                .split_once('=')
+                .with_context(|| format!("تجاوز غير صالح '{}' - الصيغة المتوقعة key.path=value", arg))?;
+            overrides.push((key.to_string(), value.to_string()));
+        }
+        for (name, value) in env {
+            if let Some(key) = name.strip_prefix("DOS_SAFAR_") {
+                overrides.push((key.to_ascii_lowercase().replace("__", "."), value));
+            }
+        }
+
+        if overrides.is_empty() {
+            return Ok(config);
+        }
+
+        let mut tree = toml::Value::try_from(&config)
+            .context("فشل في تحويل التكوين الحالي إلى شجرة TOML للدمج")?;
+
+        for (key, value) in &overrides {
+            apply_override(&mut tree, key, value)
+                .with_context(|| format!("تعذر تطبيق التجاوز '{}={}'", key, value))?;
+        }
+
+        tree.try_into()
+            .context("فشل في إعادة بناء التكوين بعد تطبيق التجاوزات")
+    }
+
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let path = path.as_ref();
         
@@ -219,4 +419,116 @@ impl Config {
             format!("http://{}:{}", self.web.host, self.web.port)
         }
     }
+}
+
+/// Sets `tree`'s value at `key_path` (dotted table keys, numeric segments
+/// index into arrays - e.g. `network.backup_networks.0.ssid`) by parsing
+/// `raw_value` to match the existing value's TOML type, or - for an
+/// `Option` field the file left absent - the simplest type `raw_value`
+/// parses as.
+fn apply_override(tree: &mut toml::Value, key_path: &str, raw_value: &str) -> Result<()> {
+    let parts: Vec<&str> = key_path.split('.').collect();
+    let (leaf, parent_parts) = parts
+        .split_last()
+        .ok_or_else(|| anyhow::anyhow!("مسار مفتاح فارغ"))?;
+    let leaf = *leaf;
+
+    let mut current = tree;
+    for part in parent_parts {
+        current = navigate_mut(current, part)?;
+    }
+
+    match current {
+        toml::Value::Table(table) => {
+            let new_value = match table.get(leaf) {
+                Some(existing) => parse_value_like(existing, raw_value)?,
+                None => infer_value(raw_value),
+            };
+            table.insert(leaf.to_string(), new_value);
+        }
+        toml::Value::Array(array) => {
+            let index: usize = leaf
+                .parse()
+                .with_context(|| format!("فهرس مصفوفة غير صالح: '{}'", leaf))?;
+            let slot = array
+                .get_mut(index)
+                .ok_or_else(|| anyhow::anyhow!("الفهرس {} خارج حدود المصفوفة", index))?;
+            *slot = parse_value_like(slot, raw_value)?;
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "المسار '{}' يمر عبر قيمة لا تحوي حقولاً فرعية",
+                key_path
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks one dotted-path segment into `value`: a named field of a table,
+/// or a numeric index into an array.
+fn navigate_mut<'a>(value: &'a mut toml::Value, part: &str) -> Result<&'a mut toml::Value> {
+    match value {
+        toml::Value::Table(table) => table
+            .get_mut(part)
+            .ok_or_else(|| anyhow::anyhow!("لا يوجد حقل تكوين باسم '{}'", part)),
+        toml::Value::Array(array) => {
+            let index: usize = part
+                .parse()
+                .with_context(|| format!("فهرس مصفوفة غير صالح: '{}'", part))?;
+            array
+                .get_mut(index)
+                .ok_or_else(|| anyhow::anyhow!("الفهرس {} خارج حدود المصفوفة", index))
+        }
+        _ => Err(anyhow::anyhow!(
+            "لا يمكن النزول إلى '{}' - القيمة الأصل ليست جدولاً ولا مصفوفة",
+            part
+        )),
+    }
+}
+
+/// Parses `raw` to the same TOML type as `existing`, erroring clearly if
+/// it doesn't fit, or if `existing` is a compound value that can't be set
+/// from one command-line/env string directly.
+fn parse_value_like(existing: &toml::Value, raw: &str) -> Result<toml::Value> {
+    Ok(match existing {
+        toml::Value::String(_) => toml::Value::String(raw.to_string()),
+        toml::Value::Integer(_) => toml::Value::Integer(
+            raw.parse()
+                .with_context(|| format!("'{}' ليست عدداً صحيحاً صالحاً", raw))?,
+        ),
+        toml::Value::Float(_) => toml::Value::Float(
+            raw.parse()
+                .with_context(|| format!("'{}' ليس عدداً عشرياً صالحاً", raw))?,
+        ),
+        toml::Value::Boolean(_) => toml::Value::Boolean(
+            raw.parse()
+                .with_context(|| format!("'{}' ليست true/false صالحة", raw))?,
+        ),
+        toml::Value::Datetime(_) => {
+            return Err(anyhow::anyhow!("لا يمكن تجاوز حقول التاريخ/الوقت من سطر الأوامر"))
+        }
+        toml::Value::Array(_) | toml::Value::Table(_) => {
+            return Err(anyhow::anyhow!(
+                "لا يمكن تجاوز حقل مركّب (مصفوفة/جدول) مباشرة - حدد مساراً أعمق"
+            ))
+        }
+    })
+}
+
+/// Infers the simplest TOML type `raw` parses as (bool, then integer,
+/// then float, else string) - used only when overriding an `Option` field
+/// the file left absent, so there's no existing value to match types to.
+fn infer_value(raw: &str) -> toml::Value {
+    if let Ok(b) = raw.parse::<bool>() {
+        return toml::Value::Boolean(b);
+    }
+    if let Ok(i) = raw.parse::<i64>() {
+        return toml::Value::Integer(i);
+    }
+    if let Ok(f) = raw.parse::<f64>() {
+        return toml::Value::Float(f);
+    }
+    toml::Value::String(raw.to_string())
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 pub mod config;
 pub mod logger;
 pub mod filesystem;
+pub mod sntp;
 
 // Re-export commonly used types
 pub use config::Config;
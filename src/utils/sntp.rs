@@ -0,0 +1,69 @@
+// Minimal SNTPv4 client (RFC 4330) for devices with no RTC: queries a
+// single server over UDP/123 and sets the kernel clock via
+// `clock_settime(2)`, so `remote::mqtt`'s published timestamps and
+// `last_used` fields are accurate from the first boot after power loss.
+use anyhow::{Context, Result};
+use std::net::UdpSocket;
+use std::time::Duration;
+use tracing::info;
+
+const NTP_PORT: u16 = 123;
+const NTP_PACKET_SIZE: usize = 48;
+/// Seconds between the NTP epoch (1900-01-01) and the Unix epoch
+/// (1970-01-01), used to convert the reply's 32-bit seconds field.
+const NTP_UNIX_EPOCH_DELTA: u64 = 2_208_988_800;
+const RECV_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Queries `server` (host or host:port, defaulting to 123) for the
+/// current time and applies it to the system clock with
+/// `clock_settime(CLOCK_REALTIME)`. Requires `CAP_SYS_TIME` (root); any
+/// failure - unreachable server, malformed reply, denied syscall - is
+/// returned rather than panicking, since a device with a working RTC has
+/// no real need for this at all.
+pub fn sync_time(server: &str) -> Result<()> {
+    let addr = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:{}", server, NTP_PORT)
+    };
+
+    let socket = UdpSocket::bind("0.0.0.0:0").context("failed to open UDP socket for SNTP query")?;
+    socket.set_read_timeout(Some(RECV_TIMEOUT)).context("failed to set SNTP read timeout")?;
+    socket.connect(&addr).with_context(|| format!("failed to resolve/connect SNTP server {}", addr))?;
+
+    let mut request = [0u8; NTP_PACKET_SIZE];
+    // LI=0 (no warning), VN=4, Mode=3 (client)
+    request[0] = 0b00_100_011;
+    socket.send(&request).context("failed to send SNTP request")?;
+
+    let mut reply = [0u8; NTP_PACKET_SIZE];
+    socket.recv(&mut reply).context("failed to receive SNTP reply")?;
+
+    // Transmit Timestamp: seconds since 1900 (bytes 40..44) plus a
+    // fixed-point fraction (bytes 44..48), per RFC 4330 section 4.
+    let seconds_since_1900 = u32::from_be_bytes(reply[40..44].try_into().unwrap()) as u64;
+    let fraction = u32::from_be_bytes(reply[44..48].try_into().unwrap());
+    let unix_seconds = seconds_since_1900
+        .checked_sub(NTP_UNIX_EPOCH_DELTA)
+        .with_context(|| format!("SNTP server {} returned a timestamp before the Unix epoch", addr))?;
+    let nanos = (fraction as u64 * 1_000_000_000) >> 32;
+
+    set_system_clock(unix_seconds, nanos as i64)?;
+    info!("🕐 System clock synced via SNTP from {} ({}s since Unix epoch)", addr, unix_seconds);
+    Ok(())
+}
+
+fn set_system_clock(unix_seconds: u64, nanos: i64) -> Result<()> {
+    let ts = libc::timespec {
+        tv_sec: unix_seconds as libc::time_t,
+        tv_nsec: nanos,
+    };
+    let result = unsafe { libc::clock_settime(libc::CLOCK_REALTIME, &ts) };
+    if result != 0 {
+        return Err(anyhow::anyhow!(
+            "clock_settime(CLOCK_REALTIME) failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
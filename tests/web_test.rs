@@ -1,7 +1,12 @@
 use anyhow::Result;
+use dos_safar::bootloader::menu::BootMenu;
+use dos_safar::hardware::device_detect::DeviceDetector;
 use dos_safar::utils::config::Config;
 use dos_safar::remote::web_server::WebServer;
 use dos_safar::utils::logger::init_logger;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Mutex;
 use tracing::info;
 
 #[tokio::main]
@@ -27,7 +32,11 @@ async fn main() -> Result<()> {
     info!("Open your browser and go to: http://localhost:{}", config.web.port);
     info!("Press Ctrl+C to stop the server");
     
-    let web_server = WebServer::new(&config)?;
+    let device_info = DeviceDetector::with_config(&config).detect_device().await?;
+    let config_path = Path::new("config/default.toml");
+    let boot_menu = Arc::new(Mutex::new(BootMenu::new(&config, &device_info, config_path)?));
+
+    let web_server = WebServer::new(&config, config_path, boot_menu)?;
     web_server.start().await?;
     
     Ok(())